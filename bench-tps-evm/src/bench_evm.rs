@@ -292,6 +292,8 @@ fn generate_system_txs(
     source: &[&(Keypair, evm::SecretKey)],
     dest: &VecDeque<&(Keypair, evm::SecretKey)>,
     reclaim: bool,
+    nonce: u64,
+    gas_price: U256,
     blockhash: &Hash,
 ) -> Vec<(Transaction, u64)> {
     let pairs: Vec<_> = if !reclaim {
@@ -306,9 +308,9 @@ fn generate_system_txs(
             let tx_address = to.1.to_address();
 
             let tx_call = evm::UnsignedTransaction {
-                nonce: 0.into(),
-                gas_price: 0.into(),
-                gas_limit: 300000.into(),
+                nonce: nonce.into(),
+                gas_price,
+                gas_limit: 21000.into(),
                 action: evm::TransactionAction::Call(tx_address),
                 value: 1.into(),
                 input: vec![],
@@ -328,6 +330,7 @@ fn generate_system_txs(
         .collect()
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_txs(
     shared_txs: &SharedTransactions,
     blockhash: &Arc<RwLock<Hash>>,
@@ -335,6 +338,8 @@ fn generate_txs(
     dest: &VecDeque<&(Keypair, evm::SecretKey)>,
     threads: usize,
     reclaim: bool,
+    nonce: u64,
+    gas_price: U256,
 ) {
     let blockhash = *blockhash.read().unwrap();
     let tx_count = source.len();
@@ -344,7 +349,7 @@ fn generate_txs(
     );
     let signing_start = Instant::now();
 
-    let transactions = generate_system_txs(source, dest, reclaim, &blockhash);
+    let transactions = generate_system_txs(source, dest, reclaim, nonce, gas_price, &blockhash);
 
     let duration = signing_start.elapsed();
     let ns = duration.as_secs() * 1_000_000_000 + u64::from(duration.subsec_nanos());
@@ -410,6 +415,19 @@ where
     };
     info!("Initial transaction count {}", first_tx_count);
 
+    let gas_price = loop {
+        match client.get_fee_rate_governor() {
+            Ok(fee_rate_governor) => {
+                break evm::min_gas_price(fee_rate_governor.target_lamports_per_signature)
+            }
+            Err(err) => {
+                info!("Couldn't get fee rate governor: {:?}", err);
+                sleep(Duration::from_secs(1));
+            }
+        }
+    };
+    info!("Evm gas price {}", gas_price);
+
     let exit_signal = Arc::new(AtomicBool::new(false));
 
     // Setup a thread per validator to sample every period
@@ -463,6 +481,7 @@ where
         threads,
         duration,
         sustained,
+        gas_price,
     );
 
     // Stop the sampling threads so it will collect the stats
@@ -500,6 +519,7 @@ where
     r_maxes.first().unwrap().1.txs
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_chunked_transfers(
     recent_blockhash: Arc<RwLock<Hash>>,
     shared_txs: &SharedTransactions,
@@ -509,11 +529,14 @@ fn generate_chunked_transfers(
     threads: usize,
     duration: Duration,
     sustained: bool,
+    gas_price: U256,
 ) {
     // generate and send transactions for the specified duration
     let start = Instant::now();
     let keypair_chunks = source_keypair_chunks.len();
     let mut reclaim_lamports_back_to_source_account = false;
+    // Every evm key sends a single transaction per direction pass.
+    let mut nonce = 0;
     let mut chunk_index = 0;
     while start.elapsed() < duration {
         generate_txs(
@@ -523,6 +546,8 @@ fn generate_chunked_transfers(
             &dest_keypair_chunks[chunk_index],
             threads,
             reclaim_lamports_back_to_source_account,
+            nonce,
+            gas_price,
         );
 
         // In sustained mode, overlap the transfers with generation. This has higher average
@@ -549,6 +574,9 @@ fn generate_chunked_transfers(
 
         // Switch directions after transfering for each "chunk"
         if chunk_index == 0 {
+            if reclaim_lamports_back_to_source_account {
+                nonce += 1;
+            }
             reclaim_lamports_back_to_source_account = !reclaim_lamports_back_to_source_account;
         }
     }
@@ -1,4 +1,6 @@
 use crate::rpc_filter::RpcFilterType;
+use evm_rpc::{Hex, RPCTopicFilter};
+use evm_state::Address;
 use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_sdk::{
     clock::Epoch,
@@ -86,6 +88,16 @@ pub struct RpcTransactionLogsConfig {
     pub commitment: Option<CommitmentConfig>,
 }
 
+/// Parameters of `eth_subscribe`, address and topics are used only by logs subscription.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEvmSubscribeConfig {
+    pub address: Option<Hex<Address>>,
+    pub topics: Option<Vec<Option<RPCTopicFilter>>>,
+    #[serde(flatten)]
+    pub commitment: Option<CommitmentConfig>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum RpcTokenAccountsFilter {
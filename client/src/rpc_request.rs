@@ -76,6 +76,8 @@ pub enum RpcRequest {
     EthCall,
     EthEstimateGas,
     EthGetLogs,
+    EthChainId,
+    EthGasPrice,
 }
 
 impl fmt::Display for RpcRequest {
@@ -149,6 +151,8 @@ impl fmt::Display for RpcRequest {
             RpcRequest::EthCall => "eth_call",
             RpcRequest::EthEstimateGas => "eth_estimateGas",
             RpcRequest::EthGetLogs => "eth_getLogs",
+            RpcRequest::EthChainId => "eth_chainId",
+            RpcRequest::EthGasPrice => "eth_gasPrice",
         };
 
         write!(f, "{}", method)
@@ -160,6 +164,7 @@ pub const MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE: u64 = 10_000;
 pub const MAX_GET_CONFIRMED_BLOCKS_RANGE: u64 = 500_000;
 pub const MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT: usize = 1_000;
 pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+pub const MAX_EVM_LOGS_BLOCK_RANGE: u64 = 10_000;
 pub const NUM_LARGEST_ACCOUNTS: usize = 20;
 
 // Validators that are this number of slots behind are considered delinquent
@@ -23,7 +23,7 @@ use solana_perf::{
 };
 use solana_runtime::{
     accounts_db::ErrorCounters,
-    bank::{Bank, TransactionBalancesSet, TransactionCheckResult, TransactionExecutionResult},
+    bank::{Bank, TransactionCheckResult, TransactionExecutionResult},
     bank_utils,
     transaction_batch::TransactionBatch,
     vote_sender_types::ReplayVoteSender,
@@ -533,27 +533,18 @@ impl BankingStage {
 
         let mut mint_decimals: HashMap<Pubkey, u8> = HashMap::new();
 
-        let pre_balances = bank.collect_balances(batch);
         let pre_token_balances = if transaction_status_sender.is_some() {
             collect_token_balances(&bank, &batch, &mut mint_decimals)
         } else {
             vec![]
         };
 
-        let (
-            mut loaded_accounts,
-            results,
-            inner_instructions,
-            transaction_logs,
-            mut retryable_txs,
-            tx_count,
-            signature_count,
-            patch,
-        ) = bank.load_and_execute_transactions(
+        let executed = bank.load_and_execute_batch(
             batch,
             MAX_PROCESSING_AGE,
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
+            transaction_status_sender.is_some(),
         );
 
         load_execute_time.stop();
@@ -561,39 +552,39 @@ impl BankingStage {
         let freeze_lock = bank.freeze_lock();
 
         let mut record_time = Measure::start("record_time");
-        let (num_to_commit, retryable_record_txs) =
-            Self::record_transactions(bank.slot(), txs, &results, poh);
-        retryable_txs.extend(retryable_record_txs);
-        if num_to_commit.is_err() {
-            return (num_to_commit, retryable_txs);
-        }
-        record_time.stop();
-
         let mut commit_time = Measure::start("commit_time");
+        let mut num_to_commit = Ok(0);
+        let mut retryable_txs = vec![];
+        // Batch that accessed evm state changed after its execution is executed again,
+        // and transactions are recorded with evm changes of their final execution.
+        let committed = bank.record_and_commit_executed_batch(batch, executed, |executed| {
+            record_time = Measure::start("record_time");
+            let (record_result, retryable_record_txs) =
+                Self::record_transactions(bank.slot(), txs, executed.execution_results(), poh);
+            retryable_txs.extend_from_slice(executed.retryable_transactions());
+            retryable_txs.extend(retryable_record_txs);
+            record_time.stop();
+            commit_time = Measure::start("commit_time");
+
+            let commit = matches!(record_result, Ok(num_to_commit) if num_to_commit != 0);
+            num_to_commit = record_result;
+            commit
+        });
+        let num_to_commit = match num_to_commit {
+            Ok(num_to_commit) => num_to_commit,
+            Err(err) => return (Err(err), retryable_txs),
+        };
 
-        let num_to_commit = num_to_commit.unwrap();
-
-        if num_to_commit != 0 {
-            let tx_results = bank.commit_transactions(
-                txs,
-                None,
-                &mut loaded_accounts,
-                &results,
-                tx_count,
-                signature_count,
-                patch,
-            );
-
+        if let Some((tx_results, balances, inner_instructions, transaction_logs)) = committed {
             bank_utils::find_and_send_votes(txs, &tx_results, Some(gossip_vote_sender));
             if let Some(sender) = transaction_status_sender {
-                let post_balances = bank.collect_balances(batch);
                 let post_token_balances = collect_token_balances(&bank, &batch, &mut mint_decimals);
                 send_transaction_status_batch(
                     bank.clone(),
                     batch.transactions(),
                     batch.iteration_order_vec(),
                     tx_results.execution_results,
-                    TransactionBalancesSet::new(pre_balances, post_balances),
+                    balances,
                     TransactionTokenBalancesSet::new(pre_token_balances, post_token_balances),
                     inner_instructions,
                     transaction_logs,
@@ -0,0 +1,195 @@
+//! Installed EVM filters, that are polled by `eth_getFilterChanges`.
+
+use evm_state::{Address, H256};
+use solana_sdk::clock::Slot;
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+/// Filter is removed, if it wasn't polled during this time.
+pub const FILTER_TTL: Duration = Duration::from_secs(5 * 60);
+pub const MAX_FILTERS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterKind {
+    Logs {
+        address: Option<Address>,
+        /// Positional topics, `None` matches any topic, otherwise any of listed.
+        topics: Vec<Option<Vec<H256>>>,
+        /// Bounds of filter, `None` means latest block at the time of poll.
+        from_block: Option<Slot>,
+        to_block: Option<Slot>,
+    },
+    Blocks,
+    /// Cursor of pending transactions filter follows sequence numbers of pool insertions, not slots.
+    PendingTransactions,
+}
+
+#[derive(Debug)]
+struct Filter {
+    kind: FilterKind,
+    // First block, or pool insertion of pending transactions filter, that wasn't returned to client.
+    next_block: Slot,
+    last_poll: Instant,
+}
+
+#[derive(Debug)]
+pub struct EvmFilters {
+    filters: RwLock<HashMap<u64, Filter>>,
+    ttl: Duration,
+}
+
+impl Default for EvmFilters {
+    fn default() -> Self {
+        Self::new(FILTER_TTL)
+    }
+}
+
+impl EvmFilters {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            filters: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Install new filter, that will return changes after `current_slot`.
+    /// Returns `None` if there is too many active filters.
+    pub fn install(&self, kind: FilterKind, current_slot: Slot) -> Option<u64> {
+        let mut filters = self.filters.write().expect("Filters lock poisoned");
+        let now = Instant::now();
+        self.evict_expired(&mut filters, now);
+        if filters.len() >= MAX_FILTERS {
+            return None;
+        }
+
+        let mut id = rand::random();
+        while filters.contains_key(&id) {
+            id = rand::random();
+        }
+        filters.insert(
+            id,
+            Filter {
+                kind,
+                next_block: current_slot + 1,
+                last_poll: now,
+            },
+        );
+        Some(id)
+    }
+
+    pub fn uninstall(&self, id: u64) -> bool {
+        let mut filters = self.filters.write().expect("Filters lock poisoned");
+        self.evict_expired(&mut filters, Instant::now());
+        filters.remove(&id).is_some()
+    }
+
+    /// Returns filter, without moving its cursor.
+    pub fn get(&self, id: u64) -> Option<FilterKind> {
+        let mut filters = self.filters.write().expect("Filters lock poisoned");
+        let now = Instant::now();
+        self.evict_expired(&mut filters, now);
+        let filter = filters.get_mut(&id)?;
+        filter.last_poll = now;
+        Some(filter.kind.clone())
+    }
+
+    /// Returns filter and range of blocks that wasn't polled yet,
+    /// and moves cursor of filter after `current_slot`.
+    pub fn poll(&self, id: u64, current_slot: Slot) -> Option<(FilterKind, RangeInclusive<Slot>)> {
+        let mut filters = self.filters.write().expect("Filters lock poisoned");
+        let now = Instant::now();
+        self.evict_expired(&mut filters, now);
+        let filter = filters.get_mut(&id)?;
+        filter.last_poll = now;
+
+        let range = filter.next_block..=current_slot;
+        if !range.is_empty() {
+            filter.next_block = current_slot + 1;
+        }
+        Some((filter.kind.clone(), range))
+    }
+
+    fn evict_expired(&self, filters: &mut HashMap<u64, Filter>, now: Instant) {
+        let ttl = self.ttl;
+        filters.retain(|_, filter| now.duration_since(filter.last_poll) < ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn poll_moves_cursor() {
+        let filters = EvmFilters::default();
+        let id = filters.install(FilterKind::Blocks, 10).unwrap();
+
+        let (kind, range) = filters.poll(id, 10).unwrap();
+        assert_eq!(kind, FilterKind::Blocks);
+        assert!(range.is_empty());
+
+        assert_eq!(filters.poll(id, 13).unwrap().1, 11..=13);
+        assert!(filters.poll(id, 13).unwrap().1.is_empty());
+        assert_eq!(filters.poll(id, 14).unwrap().1, 14..=14);
+
+        // Cursor don't move back, if node returns to older bank.
+        assert!(filters.poll(id, 12).unwrap().1.is_empty());
+        assert_eq!(filters.poll(id, 15).unwrap().1, 15..=15);
+    }
+
+    #[test]
+    fn get_keeps_cursor() {
+        let filters = EvmFilters::default();
+        let kind = FilterKind::Logs {
+            address: Some(Address::repeat_byte(1)),
+            topics: vec![],
+            from_block: Some(1),
+            to_block: None,
+        };
+        let id = filters.install(kind.clone(), 10).unwrap();
+        assert_eq!(filters.get(id), Some(kind));
+        assert_eq!(filters.poll(id, 12).unwrap().1, 11..=12);
+    }
+
+    #[test]
+    fn uninstall() {
+        let filters = EvmFilters::default();
+        let id = filters.install(FilterKind::PendingTransactions, 0).unwrap();
+        let other = filters.install(FilterKind::Blocks, 0).unwrap();
+        assert_ne!(id, other);
+
+        assert!(filters.uninstall(id));
+        assert!(!filters.uninstall(id));
+        assert!(filters.poll(id, 1).is_none());
+        assert!(filters.get(id).is_none());
+        assert!(filters.poll(other, 1).is_some());
+    }
+
+    #[test]
+    fn expired_filters_are_evicted() {
+        let filters = EvmFilters::new(Duration::from_millis(100));
+        let expired = filters.install(FilterKind::Blocks, 0).unwrap();
+        let polled = filters.install(FilterKind::Blocks, 0).unwrap();
+
+        sleep(Duration::from_millis(60));
+        assert!(filters.poll(polled, 1).is_some());
+        sleep(Duration::from_millis(60));
+
+        assert!(filters.poll(expired, 1).is_none());
+        assert!(filters.poll(polled, 1).is_some());
+    }
+
+    #[test]
+    fn filters_limit() {
+        let filters = EvmFilters::default();
+        for _ in 0..MAX_FILTERS {
+            filters.install(FilterKind::Blocks, 0).unwrap();
+        }
+        assert!(filters.install(FilterKind::Blocks, 0).is_none());
+    }
+}
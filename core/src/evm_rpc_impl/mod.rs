@@ -1,36 +1,119 @@
 use crate::rpc::JsonRpcRequestProcessor;
 use evm_rpc::basic::BasicERPC;
+use evm_rpc::bridge::BridgeERPC;
 use evm_rpc::chain_mock::ChainMockERPC;
+use evm_rpc::debug::DebugERPC;
+use evm_rpc::filters::FilterERPC;
+use evm_rpc::txpool::TxPoolERPC;
 use evm_rpc::*;
 use evm_state::*;
 use sha3::{Digest, Keccak256};
+use solana_perf::packet::PACKET_DATA_SIZE;
+use solana_runtime::bank::Bank;
+use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::fee_calculator::FeeCalculator;
+use solana_sdk::message::Message;
+use solana_sdk::signature::{Keypair, Signer};
 use solana_transaction_status::UiTransactionEncoding;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::str::FromStr;
 
-const CHAIN_ID: u64 = 0x77;
+mod filters;
+use filters::{EvmFilters, FilterKind};
+mod pool;
+use pool::PoolContent;
+pub use pool::{EvmTxPool, EvmTxPoolService};
 
 const DEFAULT_COMITTMENT: Option<CommitmentConfig> = Some(CommitmentConfig {
     commitment: CommitmentLevel::Recent,
 });
 
-fn block_to_commitment(block: Option<String>) -> Option<CommitmentConfig> {
-    let commitment = match block?.as_str() {
-        "earliest" => CommitmentLevel::Root,
-        "latest" => CommitmentLevel::Single,
-        "pending" => CommitmentLevel::Single,
-        v => {
-            // Try to parse newest version of block commitment.
-            if let Ok(c) = serde_json::from_str::<CommitmentLevel>(v) {
-                c
-            } else {
-                // Probably user provide specific slot number, we didn't support bank from future, so just return default.
-                return None;
+/// Block requested by client: commitment level, block number or block hash.
+enum BlockId {
+    Commitment(Option<CommitmentConfig>),
+    Num(Slot),
+    Hash(H256),
+}
+
+impl BlockId {
+    fn parse(block: Option<String>, meta: &JsonRpcRequestProcessor) -> Result<Self, Error> {
+        let block = match block {
+            Some(block) => block,
+            None => return Ok(BlockId::Commitment(None)),
+        };
+        Ok(match block.as_str() {
+            "earliest" => BlockId::Num(meta.get_first_available_block()),
+            "latest" => BlockId::Commitment(Some(CommitmentConfig::single())),
+            "pending" => BlockId::Commitment(DEFAULT_COMITTMENT),
+            v if v.starts_with("0x") && v.len() == 2 + 2 * H256::len_bytes() => {
+                let hash = Hex::<H256>::from_hex(v).map_err(|_| Error::InvalidParams)?;
+                BlockId::Hash(hash.0)
             }
+            v if v.starts_with("0x") => {
+                let num = Hex::<u64>::from_hex(v).map_err(|_| Error::InvalidParams)?;
+                BlockId::Num(num.0)
+            }
+            v => {
+                // Try to parse newest version of block commitment.
+                let commitment = serde_json::from_value::<CommitmentLevel>(v.into())
+                    .map_err(|_| Error::InvalidParams)?;
+                BlockId::Commitment(Some(CommitmentConfig { commitment }))
+            }
+        })
+    }
+}
+
+/// Finds slot of block with `blockhash` on the fork of `bank`.
+fn slot_by_blockhash(bank: &Bank, blockhash: H256) -> Option<Slot> {
+    bank.evm_state
+        .read()
+        .expect("Evm lock poisoned")
+        .get_slot_by_block_hash(blockhash)
+}
+
+/// Hash of confirmed native block, that is used as hash of EVM block.
+fn confirmed_block_hash(meta: &JsonRpcRequestProcessor, slot: Slot) -> Result<H256, Error> {
+    let block_hash = meta
+        .get_confirmed_block_hash(slot)
+        .map_err(|_| Error::InvalidParams)?;
+    let block_hash = block_hash.ok_or(Error::InvalidParams)?;
+    let block_hash = solana_sdk::hash::Hash::from_str(&block_hash).unwrap();
+    Ok(H256::from_slice(&block_hash.0))
+}
+
+/// Runs `f` with bank and EVM state at requested block, and number of that block.
+/// Historical state is read from versions kept by EVM storage on the fork of recent bank.
+fn with_block_state<T>(
+    meta: &JsonRpcRequestProcessor,
+    block: Option<String>,
+    default_commitment: Option<CommitmentConfig>,
+    f: impl FnOnce(&Bank, Slot, &EvmState) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let (bank, slot) = match BlockId::parse(block, meta)? {
+        BlockId::Commitment(commitment) => {
+            let bank = meta.bank(commitment.or(default_commitment));
+            let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+            return f(&bank, bank.slot(), &evm_state);
+        }
+        BlockId::Num(slot) => (meta.bank(DEFAULT_COMITTMENT), slot),
+        BlockId::Hash(blockhash) => {
+            let bank = meta.bank(DEFAULT_COMITTMENT);
+            let slot = slot_by_blockhash(&bank, blockhash).ok_or(Error::NotFound)?;
+            (bank, slot)
         }
     };
-    Some(CommitmentConfig { commitment })
+    if slot > bank.slot() {
+        return Err(Error::NotFound);
+    }
+    let evm_state = bank
+        .evm_state
+        .read()
+        .expect("Evm lock poisoned")
+        .state_at_slot(slot)
+        .ok_or(Error::NotFound)?;
+    f(&bank, slot, &evm_state)
 }
 
 fn block_to_confirmed_num(block: Option<String>, meta: &JsonRpcRequestProcessor) -> Option<u64> {
@@ -43,16 +126,91 @@ fn block_to_confirmed_num(block: Option<String>, meta: &JsonRpcRequestProcessor)
     }
 }
 
+/// Builds EVM block from native block metadata and receipts stored in evm state.
+pub(crate) fn block_from_state(
+    evm_state: &EvmState,
+    block_num: u64,
+    block_hash: H256,
+    parent_hash: H256,
+    timestamp: u64,
+    full: bool,
+) -> RPCBlock {
+    let tx_hashes = evm_state.get_txs_in_block(block_num).unwrap_or_default();
+    let receipts: Vec<_> = tx_hashes
+        .iter()
+        .map(|tx_hash| {
+            evm_state
+                .get_tx_receipt_by_hash(*tx_hash)
+                .expect("Transaction exist")
+        })
+        .collect();
+    let state_root = evm_state.get_state_root(block_num).unwrap_or_default();
+    // Blooms are not stored for blocks, that were processed before they were introduced.
+    let logs_bloom = evm_state.get_logs_bloom(block_num).unwrap_or_else(|| {
+        receipts
+            .iter()
+            .fold(Bloom::default(), |mut bloom, receipt| {
+                bloom.accrue_bloom(&receipt.logs_bloom);
+                bloom
+            })
+    });
+    let transactions_root = transactions_root(receipts.iter().map(|receipt| &receipt.transaction));
+    let receipts_root = receipts_root(&receipts);
+    let gas_used = receipts
+        .iter()
+        .fold(Gas::zero(), |gas, receipt| gas + receipt.used_gas);
+    // Native block header is not stored in EVM state, so only transactions are counted.
+    let size: usize = receipts
+        .iter()
+        .map(|receipt| rlp::encode(&receipt.transaction).len())
+        .sum();
+    let transactions = if full {
+        let txs = receipts
+            .into_iter()
+            .filter_map(|receipt| RPCTransaction::new_from_receipt(receipt, block_hash).ok())
+            .collect();
+        Either::Right(txs)
+    } else {
+        let txs = tx_hashes.into_iter().map(Hex).collect();
+        Either::Left(txs)
+    };
+
+    RPCBlock {
+        number: U256::from(block_num).into(),
+        hash: block_hash.into(),
+        parent_hash: parent_hash.into(),
+        size: size.into(),
+        gas_limit: Gas::from(BLOCK_GAS_LIMIT).into(),
+        gas_used: gas_used.into(),
+        timestamp: Hex(timestamp),
+        transactions,
+
+        nonce: 0x7bb9369dcbaec019.into(),
+        sha3_uncles: H256::zero().into(),
+        logs_bloom: logs_bloom.into(),
+        transactions_root: transactions_root.into(),
+        state_root: state_root.into(),
+        receipts_root: receipts_root.into(),
+        miner: Address::zero().into(),
+        difficulty: U256::zero().into(),
+        total_difficulty: U256::zero().into(),
+        extra_data: b"Native chain data ommitted...".to_vec().into(),
+        uncles: vec![],
+    }
+}
+
 pub struct ChainMockERPCImpl;
 impl ChainMockERPC for ChainMockERPCImpl {
     type Metadata = JsonRpcRequestProcessor;
 
-    fn network_id(&self, _meta: Self::Metadata) -> Result<String, Error> {
-        Ok(format!("0x{:x}", CHAIN_ID))
+    fn network_id(&self, meta: Self::Metadata) -> Result<String, Error> {
+        let bank = meta.bank(None);
+        Ok(format!("{}", bank.evm_chain_id))
     }
 
-    fn chain_id(&self, _meta: Self::Metadata) -> Result<Hex<u64>, Error> {
-        Ok(Hex(CHAIN_ID))
+    fn chain_id(&self, meta: Self::Metadata) -> Result<Hex<u64>, Error> {
+        let bank = meta.bank(None);
+        Ok(Hex(bank.evm_chain_id))
     }
 
     // TODO: Add network info
@@ -96,31 +254,15 @@ impl ChainMockERPC for ChainMockERPCImpl {
 
     fn block_by_hash(
         &self,
-        _meta: Self::Metadata,
-        _block_hash: Hex<H256>,
-        _full: bool,
+        meta: Self::Metadata,
+        block_hash: Hex<H256>,
+        full: bool,
     ) -> Result<Option<RPCBlock>, Error> {
-        Ok(Some(RPCBlock {
-            number: U256::zero().into(),
-            hash: H256::zero().into(),
-            parent_hash: H256::zero().into(),
-            size: 0x100.into(),
-            gas_limit: Gas::zero().into(),
-            gas_used: Gas::zero().into(),
-            timestamp: 0.into(),
-            transactions: Either::Left(vec![]),
-            nonce: 0.into(),
-            sha3_uncles: H256::zero().into(),
-            logs_bloom: H256::zero().into(), // H2048
-            transactions_root: H256::zero().into(),
-            state_root: H256::zero().into(),
-            receipts_root: H256::zero().into(),
-            miner: Address::zero().into(),
-            difficulty: U256::zero().into(),
-            total_difficulty: U256::zero().into(),
-            extra_data: b"Native chain data ommitted...".to_vec().into(),
-            uncles: vec![],
-        }))
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        match slot_by_blockhash(&bank, block_hash.0) {
+            Some(block_num) => block_by_slot(&meta, block_num, full),
+            None => Ok(None),
+        }
     }
 
     fn block_by_number(
@@ -130,81 +272,33 @@ impl ChainMockERPC for ChainMockERPCImpl {
         full: bool,
     ) -> Result<Option<RPCBlock>, Error> {
         let num = block_to_confirmed_num(Some(block), &meta);
-        // TODO: Inline evm_state lookups, and request only solana headers.
         let block_num = num.unwrap_or(0);
         if block_num == 0 {
             return Ok(None);
         }
-        Ok(meta
-            .get_confirmed_block(block_num, UiTransactionEncoding::Binary.into())
-            .map_err(|_| Error::NotFound)?
-            .map(|block| {
-                let block_hash = solana_sdk::hash::Hash::from_str(&block.blockhash).unwrap();
-                let block_hash = H256::from_slice(&block_hash.0);
-                let parent_hash =
-                    solana_sdk::hash::Hash::from_str(&block.previous_blockhash).unwrap();
-                let bank = meta.bank(None);
-                let evm_lock = bank.evm_state.read().expect("Evm lock poisoned");
-                let tx_hashes = evm_lock.get_txs_in_block(block_num);
-                let transactions = if full {
-                    let txs = tx_hashes
-                        .iter()
-                        .flatten()
-                        .map(|tx_hash| {
-                            evm_lock
-                                .get_tx_receipt_by_hash(*tx_hash)
-                                .expect("Transaction exist")
-                        })
-                        .filter_map(|receipt| {
-                            RPCTransaction::new_from_receipt(receipt, block_hash.clone()).ok()
-                        })
-                        .collect();
-                    Either::Right(txs)
-                } else {
-                    let txs = tx_hashes.into_iter().flatten().map(Hex).collect();
-                    Either::Left(txs)
-                };
-                drop(evm_lock);
-
-                RPCBlock {
-                    number: U256::from(block_num).into(),
-                    hash: H256::from_slice(&block_hash.0).into(),
-                    parent_hash: H256::from_slice(&parent_hash.0).into(),
-                    size: 0x100.into(),
-                    gas_limit: Hex(0x10000.into()),
-                    gas_used: Gas::zero().into(),
-                    timestamp: Hex(block.block_time.unwrap_or(0) as u64),
-                    transactions,
-
-                    nonce: 0x7bb9369dcbaec019.into(),
-                    sha3_uncles: H256::zero().into(),
-                    logs_bloom: H256::zero().into(), // H2048
-                    transactions_root: H256::zero().into(),
-                    state_root: H256::zero().into(),
-                    receipts_root: H256::zero().into(),
-                    miner: Address::zero().into(),
-                    difficulty: U256::zero().into(),
-                    total_difficulty: U256::zero().into(),
-                    extra_data: b"Native chain data ommitted...".to_vec().into(),
-                    uncles: vec![],
-                }
-            }))
+        block_by_slot(&meta, block_num, full)
     }
 
     fn block_transaction_count_by_number(
         &self,
-        _meta: Self::Metadata,
-        _block: String,
+        meta: Self::Metadata,
+        block: String,
     ) -> Result<Option<Hex<usize>>, Error> {
-        Ok(None)
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        Ok(block_to_confirmed_num(Some(block), &meta)
+            .and_then(|block_num| block_transactions(&bank, block_num))
+            .map(|txs| Hex(txs.len())))
     }
 
     fn block_transaction_count_by_hash(
         &self,
-        _meta: Self::Metadata,
-        _block_hash: Hex<H256>,
+        meta: Self::Metadata,
+        block_hash: Hex<H256>,
     ) -> Result<Option<Hex<usize>>, Error> {
-        Err(Error::NotFound)
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        Ok(slot_by_blockhash(&bank, block_hash.0)
+            .and_then(|block_num| block_transactions(&bank, block_num))
+            .map(|txs| Hex(txs.len())))
     }
 
     fn uncle_by_block_hash_and_index(
@@ -243,21 +337,85 @@ impl ChainMockERPC for ChainMockERPCImpl {
 
     fn transaction_by_block_hash_and_index(
         &self,
-        _meta: Self::Metadata,
-        _block_hash: Hex<H256>,
-        _tx_id: Hex<U256>,
+        meta: Self::Metadata,
+        block_hash: Hex<H256>,
+        tx_id: Hex<U256>,
     ) -> Result<Option<RPCTransaction>, Error> {
-        Err(Error::NotFound)
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        match slot_by_blockhash(&bank, block_hash.0) {
+            Some(block_num) => transaction_by_index(&bank, block_num, block_hash.0, tx_id.0),
+            None => Ok(None),
+        }
     }
 
     fn transaction_by_block_number_and_index(
         &self,
-        _meta: Self::Metadata,
-        _block: String,
-        _tx_id: Hex<U256>,
+        meta: Self::Metadata,
+        block: String,
+        tx_id: Hex<U256>,
     ) -> Result<Option<RPCTransaction>, Error> {
-        Err(Error::NotFound)
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        let block_num = match block_to_confirmed_num(Some(block), &meta) {
+            Some(block_num) => block_num,
+            None => return Ok(None),
+        };
+        let block_hash = confirmed_block_hash(&meta, block_num)?;
+        transaction_by_index(&bank, block_num, block_hash, tx_id.0)
+    }
+}
+
+/// EVM block built from confirmed native block, `None` if block is skipped or not confirmed yet.
+fn block_by_slot(
+    meta: &JsonRpcRequestProcessor,
+    block_num: Slot,
+    full: bool,
+) -> Result<Option<RPCBlock>, Error> {
+    // TODO: Inline evm_state lookups, and request only solana headers.
+    Ok(meta
+        .get_confirmed_block(block_num, UiTransactionEncoding::Binary.into())
+        .map_err(|_| Error::NotFound)?
+        .map(|block| {
+            let block_hash = solana_sdk::hash::Hash::from_str(&block.blockhash).unwrap();
+            let parent_hash = solana_sdk::hash::Hash::from_str(&block.previous_blockhash).unwrap();
+            let bank = meta.bank(None);
+            let evm_lock = bank.evm_state.read().expect("Evm lock poisoned");
+            block_from_state(
+                &evm_lock,
+                block_num,
+                H256::from_slice(&block_hash.0),
+                H256::from_slice(&parent_hash.0),
+                block.block_time.unwrap_or(0) as u64,
+                full,
+            )
+        }))
+}
+
+/// Hashes of transactions in block, `None` if block is not processed by `bank` yet.
+fn block_transactions(bank: &Bank, block_num: Slot) -> Option<Vec<H256>> {
+    if block_num > bank.slot() {
+        return None;
     }
+    let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+    Some(evm_state.get_txs_in_block(block_num).unwrap_or_default())
+}
+
+fn transaction_by_index(
+    bank: &Bank,
+    block_num: Slot,
+    block_hash: H256,
+    index: U256,
+) -> Result<Option<RPCTransaction>, Error> {
+    let tx_hash = match block_transactions(bank, block_num) {
+        Some(txs) if index < txs.len().into() => txs[index.as_usize()],
+        _ => return Ok(None),
+    };
+    let receipt = bank
+        .evm_state
+        .read()
+        .expect("Evm lock poisoned")
+        .get_tx_receipt_by_hash(tx_hash)
+        .ok_or(Error::NotFound)?;
+    Ok(Some(RPCTransaction::new_from_receipt(receipt, block_hash)?))
 }
 
 pub struct BasicERPCImpl;
@@ -276,10 +434,10 @@ impl BasicERPC for BasicERPCImpl {
         address: Hex<Address>,
         block: Option<String>,
     ) -> Result<Hex<U256>, Error> {
-        let bank = meta.bank(block_to_commitment(block));
-        let evm_state = bank.evm_state.read().unwrap();
-        let account = evm_state.get_account(address.0).unwrap_or_default();
-        Ok(Hex(account.balance))
+        with_block_state(&meta, block, None, |_, _, evm_state| {
+            let account = evm_state.get_account(address.0).unwrap_or_default();
+            Ok(Hex(account.balance))
+        })
     }
 
     fn storage_at(
@@ -289,11 +447,11 @@ impl BasicERPC for BasicERPCImpl {
         data: Hex<H256>,
         block: Option<String>,
     ) -> Result<Hex<H256>, Error> {
-        let bank = meta.bank(block_to_commitment(block));
-        let evm_state = bank.evm_state.read().unwrap();
-        Ok(Hex(evm_state
-            .get_storage(address.0, data.0)
-            .unwrap_or_default()))
+        with_block_state(&meta, block, None, |_, _, evm_state| {
+            Ok(Hex(evm_state
+                .get_storage(address.0, data.0)
+                .unwrap_or_default()))
+        })
     }
 
     fn transaction_count(
@@ -302,10 +460,16 @@ impl BasicERPC for BasicERPCImpl {
         address: Hex<Address>,
         block: Option<String>,
     ) -> Result<Hex<U256>, Error> {
-        let bank = meta.bank(block_to_commitment(block));
-        let evm_state = bank.evm_state.read().unwrap();
-        let account = evm_state.get_account(address.0).unwrap_or_default();
-        Ok(Hex(account.nonce))
+        let is_pending = block.as_deref() == Some("pending");
+        let nonce = with_block_state(&meta, block, None, |_, _, evm_state| {
+            let account = evm_state.get_account(address.0).unwrap_or_default();
+            Ok(account.nonce)
+        })?;
+        // Count transactions, that wait in pool to be executed in a row.
+        if is_pending {
+            return Ok(Hex(meta.evm_tx_pool().pending_nonce(address.0, nonce)));
+        }
+        Ok(Hex(nonce))
     }
 
     fn code(
@@ -314,10 +478,10 @@ impl BasicERPC for BasicERPCImpl {
         address: Hex<Address>,
         block: Option<String>,
     ) -> Result<Bytes, Error> {
-        let bank = meta.bank(block_to_commitment(block));
-        let evm_state = bank.evm_state.read().unwrap();
-        let account = evm_state.get_account(address.0).unwrap_or_default();
-        Ok(Bytes(account.code))
+        with_block_state(&meta, block, None, |_, _, evm_state| {
+            let account = evm_state.get_account(address.0).unwrap_or_default();
+            Ok(Bytes(account.code))
+        })
     }
 
     fn transaction_by_hash(
@@ -331,16 +495,14 @@ impl BasicERPC for BasicERPCImpl {
 
         Ok(match receipt {
             Some(receipt) => {
-                let block_hash = meta
-                    .get_confirmed_block_hash(receipt.block_number)
-                    .map_err(|_| Error::InvalidParams)?;
-                let block_hash = block_hash.ok_or(Error::InvalidParams)?;
-                let block_hash = solana_sdk::hash::Hash::from_str(&block_hash).unwrap();
-                let block_hash = H256::from_slice(&block_hash.0);
+                let block_hash = confirmed_block_hash(&meta, receipt.block_number)?;
 
                 Some(RPCTransaction::new_from_receipt(receipt, block_hash)?)
             }
-            None => None,
+            None => match meta.evm_tx_pool().get(tx_hash.0) {
+                Some(pooled) => Some(RPCTransaction::new_pending(&pooled.tx)?),
+                None => None,
+            },
         })
     }
 
@@ -354,12 +516,7 @@ impl BasicERPC for BasicERPCImpl {
         let receipt = evm_state.get_tx_receipt_by_hash(tx_hash.0);
         Ok(match receipt {
             Some(receipt) => {
-                let block_hash = meta
-                    .get_confirmed_block_hash(receipt.block_number)
-                    .map_err(|_| Error::InvalidParams)?;
-                let block_hash = block_hash.ok_or(Error::InvalidParams)?;
-                let block_hash = solana_sdk::hash::Hash::from_str(&block_hash).unwrap();
-                let block_hash = H256::from_slice(&block_hash.0);
+                let block_hash = confirmed_block_hash(&meta, receipt.block_number)?;
                 Some(RPCReceipt::new_from_receipt(receipt, block_hash)?)
             }
             None => None,
@@ -372,8 +529,12 @@ impl BasicERPC for BasicERPCImpl {
         tx: RPCTransaction,
         block: Option<String>,
     ) -> Result<Bytes, Error> {
-        let result = call(meta, tx, block)?;
-        Ok(Bytes(result.1))
+        let (exit_reason, data, _) =
+            with_block_state(&meta, block, DEFAULT_COMITTMENT, |bank, slot, evm_state| {
+                let gas_limit = tx_gas_limit(&tx)?;
+                Ok(call(bank, slot, evm_state, &tx, gas_limit))
+            })?;
+        check_exit_reason(exit_reason, data)
     }
 
     fn estimate_gas(
@@ -382,8 +543,10 @@ impl BasicERPC for BasicERPCImpl {
         tx: RPCTransaction,
         block: Option<String>,
     ) -> Result<Hex<Gas>, Error> {
-        let result = call(meta, tx, block)?;
-        Ok(Hex(result.2.into()))
+        let gas = with_block_state(&meta, block, DEFAULT_COMITTMENT, |bank, slot, evm_state| {
+            estimate_gas(bank, slot, evm_state, &tx)
+        })?;
+        Ok(Hex(gas.into()))
     }
 
     fn logs(&self, meta: Self::Metadata, log_filter: RPCLogFilter) -> Result<Vec<RPCLog>, Error> {
@@ -396,53 +559,643 @@ impl BasicERPC for BasicERPCImpl {
 
         let filter = LogFilter {
             address: log_filter.address.map(|k| k.0),
-            topics: vec![],
+            topics: RPCTopicFilter::into_log_filter_topics(log_filter.topics)?,
             from_block: from,
             to_block: to,
         };
+        get_logs(&meta, &evm_lock, filter)
+    }
+}
 
-        Ok(evm_lock
-            .get_logs(filter)
-            .into_iter()
-            .map(|l| l.into())
-            .collect())
+fn get_logs(
+    meta: &JsonRpcRequestProcessor,
+    evm_state: &EvmState,
+    filter: LogFilter,
+) -> Result<Vec<RPCLog>, Error> {
+    let max_range = meta.max_evm_logs_block_range();
+    if filter.to_block.saturating_sub(filter.from_block) >= max_range {
+        return Err(Error::BlockRangeTooLarge(max_range));
     }
+    Ok(evm_state
+        .get_logs(filter)
+        .into_iter()
+        .map(|l| l.into())
+        .collect())
 }
 
-fn call(
-    meta: JsonRpcRequestProcessor,
-    tx: RPCTransaction,
-    _block: Option<String>,
-) -> Result<(evm_state::ExitReason, Vec<u8>, u64), Error> {
-    let caller = tx.from.map(|a| a.0).unwrap_or_default();
-
-    let value = tx.value.map(|a| a.0).unwrap_or_else(|| 0.into());
-    let input = tx.data.map(|a| a.0).unwrap_or_else(Vec::new);
-    let gas_limit: u64 = tx
-        .gas
-        .map(|a| a.0)
-        .unwrap_or_else(|| 300000000.into())
-        .try_into()
-        .map_err(|_| Error::InvalidParams)?;
+/// Block number of filter bound, `None` if filter should follow latest block.
+fn block_to_filter_bound(block: Option<String>, meta: &JsonRpcRequestProcessor) -> Option<u64> {
+    if block.as_deref() == Some("latest") {
+        return None;
+    }
+    block_to_confirmed_num(block, meta)
+}
+
+#[derive(Default)]
+pub struct FilterERPCImpl {
+    filters: EvmFilters,
+}
 
+impl FilterERPCImpl {
+    fn install(&self, kind: FilterKind, current_slot: u64) -> Result<Hex<u64>, Error> {
+        self.filters
+            .install(kind, current_slot)
+            .map(Hex)
+            .ok_or(Error::InvalidParams)
+    }
+}
+
+impl FilterERPC for FilterERPCImpl {
+    type Metadata = JsonRpcRequestProcessor;
+
+    fn new_filter(
+        &self,
+        meta: Self::Metadata,
+        log_filter: RPCLogFilter,
+    ) -> Result<Hex<u64>, Error> {
+        let kind = FilterKind::Logs {
+            address: log_filter.address.map(|k| k.0),
+            topics: RPCTopicFilter::into_log_filter_topics(log_filter.topics)?,
+            from_block: block_to_filter_bound(log_filter.from_block, &meta),
+            to_block: block_to_filter_bound(log_filter.to_block, &meta),
+        };
+        self.install(kind, meta.get_slot(None))
+    }
+
+    fn new_block_filter(&self, meta: Self::Metadata) -> Result<Hex<u64>, Error> {
+        self.install(FilterKind::Blocks, meta.get_slot(None))
+    }
+
+    fn new_pending_transaction_filter(&self, meta: Self::Metadata) -> Result<Hex<u64>, Error> {
+        self.install(
+            FilterKind::PendingTransactions,
+            meta.evm_tx_pool().last_inserted(),
+        )
+    }
+
+    fn uninstall_filter(&self, _meta: Self::Metadata, id: Hex<u64>) -> Result<bool, Error> {
+        Ok(self.filters.uninstall(id.0))
+    }
+
+    fn filter_changes(
+        &self,
+        meta: Self::Metadata,
+        id: Hex<u64>,
+    ) -> Result<Either<Vec<Hex<H256>>, Vec<RPCLog>>, Error> {
+        let kind = self.filters.get(id.0).ok_or(Error::NotFound)?;
+        // Pending transactions are taken from pool insertions, while blocks and logs from rooted bank.
+        let bank = meta.bank(None);
+        let current = match kind {
+            FilterKind::PendingTransactions => meta.evm_tx_pool().last_inserted(),
+            _ => bank.slot(),
+        };
+        let (kind, range) = self.filters.poll(id.0, current).ok_or(Error::NotFound)?;
+        if range.is_empty() {
+            return Ok(match kind {
+                FilterKind::Logs { .. } => Either::Right(vec![]),
+                _ => Either::Left(vec![]),
+            });
+        }
+
+        match kind {
+            FilterKind::Logs {
+                address,
+                topics,
+                from_block,
+                to_block,
+            } => {
+                let from = std::cmp::max(*range.start(), from_block.unwrap_or(0));
+                let to = std::cmp::min(*range.end(), to_block.unwrap_or(u64::MAX));
+                if from > to {
+                    return Ok(Either::Right(vec![]));
+                }
+                let filter = LogFilter {
+                    address,
+                    topics,
+                    from_block: from,
+                    to_block: to,
+                };
+                let evm_lock = bank.evm_state.read().expect("Evm lock poisoned");
+                Ok(Either::Right(get_logs(&meta, &evm_lock, filter)?))
+            }
+            FilterKind::Blocks => {
+                let blocks = meta
+                    .get_confirmed_blocks(*range.start(), Some(*range.end()))
+                    .map_err(|_| Error::InvalidParams)?;
+                let mut hashes = vec![];
+                for block in blocks {
+                    let block_hash = meta
+                        .get_confirmed_block_hash(block)
+                        .map_err(|_| Error::InvalidParams)?;
+                    if let Some(block_hash) = block_hash {
+                        let block_hash = solana_sdk::hash::Hash::from_str(&block_hash).unwrap();
+                        hashes.push(Hex(H256::from_slice(&block_hash.0)));
+                    }
+                }
+                Ok(Either::Left(hashes))
+            }
+            FilterKind::PendingTransactions => Ok(Either::Left(
+                meta.evm_tx_pool()
+                    .inserted_in(range)
+                    .into_iter()
+                    .map(Hex)
+                    .collect(),
+            )),
+        }
+    }
+
+    fn filter_logs(&self, meta: Self::Metadata, id: Hex<u64>) -> Result<Vec<RPCLog>, Error> {
+        let (address, topics, from_block, to_block) = match self.filters.get(id.0) {
+            Some(FilterKind::Logs {
+                address,
+                topics,
+                from_block,
+                to_block,
+            }) => (address, topics, from_block, to_block),
+            Some(_) => return Err(Error::InvalidParams),
+            None => return Err(Error::NotFound),
+        };
+        let bank = meta.bank(None);
+        let slot = bank.slot();
+        let filter = LogFilter {
+            address,
+            topics,
+            from_block: from_block.unwrap_or(slot),
+            to_block: to_block.unwrap_or(slot),
+        };
+
+        let evm_lock = bank.evm_state.read().expect("Evm lock poisoned");
+        get_logs(&meta, &evm_lock, filter)
+    }
+}
+
+/// Current nonces of `senders` in EVM state of `bank`.
+fn state_nonces(bank: &Bank, senders: Vec<Address>) -> HashMap<Address, U256> {
+    let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+    senders
+        .into_iter()
+        .map(|sender| {
+            let account = evm_state.get_account(sender).unwrap_or_default();
+            (sender, account.nonce)
+        })
+        .collect()
+}
+
+/// Native fee of relayed transaction is paid by node, so gas price should be high enough,
+/// that the cheapest EVM transaction costs as much as native signature.
+fn check_gas_price(tx: &Transaction, fee_calculator: &FeeCalculator) -> Result<(), Error> {
+    let min_gas_price = evm_state::min_gas_price(fee_calculator.lamports_per_signature);
+    if tx.gas_price < min_gas_price {
+        return Err(Error::TransactionRejected(format!(
+            "Gas price is too low: {}, min {}",
+            tx.gas_price, min_gas_price
+        )));
+    }
+    Ok(())
+}
+
+/// Wraps EVM transaction into native transaction, that should fit into single packet.
+fn native_transaction(
+    bank: &Bank,
+    fee_payer: &Keypair,
+    tx: Transaction,
+    blockhash: solana_sdk::hash::Hash,
+) -> Result<solana_sdk::transaction::Transaction, Error> {
+    let ix = if bank.evm_features().readonly_state_account {
+        solana_evm_loader_program::send_raw_tx_readonly_state(fee_payer.pubkey(), tx)
+    } else {
+        solana_evm_loader_program::send_raw_tx(fee_payer.pubkey(), tx)
+    };
+    let message = Message::new(&[ix], Some(&fee_payer.pubkey()));
+    let transaction = solana_sdk::transaction::Transaction::new(&[fee_payer], message, blockhash);
+    let tx_size = bincode::serialized_size(&transaction).unwrap();
+    if tx_size > PACKET_DATA_SIZE as u64 {
+        return Err(Error::TransactionRejected(format!(
+            "Transaction is too large: {} bytes, max {} bytes",
+            tx_size, PACKET_DATA_SIZE
+        )));
+    }
+    Ok(transaction)
+}
+
+/// Wraps EVM transaction into native transaction paid by `fee_payer`, checks it on `bank`
+/// and sends to leaders. Returns last slot, when native transaction can be processed.
+fn relay_transaction(
+    meta: &JsonRpcRequestProcessor,
+    bank: &Bank,
+    fee_payer: &Keypair,
+    tx: Transaction,
+) -> Result<Slot, Error> {
+    let (blockhash, fee_calculator) = bank.last_blockhash_with_fee_calculator();
+    check_gas_price(&tx, &fee_calculator)?;
+    let transaction = native_transaction(bank, fee_payer, tx, blockhash)?;
+
+    if let (Err(err), _logs) = bank.simulate_transaction(transaction.clone()) {
+        return Err(Error::TransactionRejected(format!(
+            "Transaction simulation failed: {}",
+            err
+        )));
+    }
+    let last_valid_slot = bank.get_blockhash_last_valid_slot(&blockhash).unwrap_or(0);
+    let wire_transaction = bincode::serialize(&transaction).unwrap();
+    crate::rpc::_send_transaction(
+        meta.clone(),
+        transaction,
+        wire_transaction,
+        last_valid_slot,
+        None,
+    )
+    .map_err(|err| Error::TransactionRejected(err.message))?;
+    Ok(last_valid_slot)
+}
+
+/// Relays pooled transactions, whose nonce became next for their sender.
+fn relay_pooled_transactions(meta: &JsonRpcRequestProcessor) {
+    let fee_payer = match meta.evm_fee_payer() {
+        Some(fee_payer) => fee_payer,
+        None => return,
+    };
+    let pool = meta.evm_tx_pool();
     let bank = meta.bank(DEFAULT_COMITTMENT);
-    let evm_state = bank
-        .evm_state
-        .read()
-        .expect("meta bank EVM state was poisoned");
+    let nonces = state_nonces(&bank, pool.senders());
+    for pooled in pool.ready(&nonces, bank.slot()) {
+        match relay_transaction(meta, &bank, fee_payer, pooled.tx) {
+            Ok(last_valid_slot) => pool.mark_relayed(pooled.hash, last_valid_slot),
+            Err(err) => {
+                warn!(
+                    "Dropping pooled EVM transaction {} of {}: {:?}",
+                    pooled.hash, pooled.sender, err
+                );
+                pool.remove(pooled.hash);
+            }
+        }
+    }
+}
 
-    let evm_state = evm_state.clone(); // TODO: revise
+pub struct BridgeERPCImpl;
+impl BridgeERPC for BridgeERPCImpl {
+    type Metadata = JsonRpcRequestProcessor;
 
-    let mut executor =
-        evm_state::Executor::with_config(evm_state, Config::istanbul(), gas_limit, bank.slot());
+    // Node doesn't keep EVM keys, transactions should be signed by client.
+    fn accounts(&self, _meta: Self::Metadata) -> Result<Vec<Hex<Address>>, Error> {
+        Ok(vec![])
+    }
 
-    let result = if let Some(address) = tx.to {
+    fn sign(
+        &self,
+        _meta: Self::Metadata,
+        _address: Hex<Address>,
+        _data: Bytes,
+    ) -> Result<Bytes, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn sign_transaction(
+        &self,
+        _meta: Self::Metadata,
+        _tx: RPCTransaction,
+    ) -> Result<RPCSignedTransaction, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn sign_typed_data(
+        &self,
+        _meta: Self::Metadata,
+        _address: Hex<Address>,
+        _data: serde_json::Value,
+    ) -> Result<Bytes, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn send_transaction(
+        &self,
+        _meta: Self::Metadata,
+        _tx: RPCTransaction,
+    ) -> Result<Hex<H256>, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn send_raw_transaction(&self, meta: Self::Metadata, tx: Bytes) -> Result<Hex<H256>, Error> {
+        let tx: Transaction = rlp::decode(&tx.0)?;
+        let sender = tx.caller().map_err(|_| Error::ECDSAError)?;
+        let hash = tx.tx_id_hash();
+        debug!("send_raw_transaction tx_hash = {}", hash);
+
+        let fee_payer = meta.evm_fee_payer().ok_or_else(|| {
+            Error::TransactionRejected("Node is not configured to relay EVM transactions".into())
+        })?;
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        check_gas_price(&tx, &bank.last_blockhash_with_fee_calculator().1)?;
+        // Pooled transaction should fit into packet, when it will be relayed.
+        native_transaction(&bank, fee_payer, tx.clone(), bank.last_blockhash())?;
+        let state_account = bank
+            .evm_state
+            .read()
+            .expect("Evm lock poisoned")
+            .get_account(sender)
+            .unwrap_or_default();
+        let nonce = tx.nonce;
+        let pool = meta.evm_tx_pool();
+        pool.insert(
+            sender,
+            hash,
+            tx.clone(),
+            state_account.nonce,
+            state_account.balance,
+        )
+        .map_err(|err| Error::TransactionRejected(err.to_string()))?;
+
+        // Transactions with future nonce are relayed by pool service later.
+        if nonce == state_account.nonce {
+            match relay_transaction(&meta, &bank, fee_payer, tx) {
+                Ok(last_valid_slot) => pool.mark_relayed(hash, last_valid_slot),
+                Err(err) => {
+                    pool.remove(hash);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(Hex(hash))
+    }
+
+    fn gas_price(&self, meta: Self::Metadata) -> Result<Hex<Gas>, Error> {
+        Ok(Hex(meta.bank(None).evm_min_gas_price()))
+    }
+
+    fn compilers(&self, _meta: Self::Metadata) -> Result<Vec<String>, Error> {
+        Err(Error::NotFound)
+    }
+}
+
+pub struct TxPoolERPCImpl;
+impl TxPoolERPC for TxPoolERPCImpl {
+    type Metadata = JsonRpcRequestProcessor;
+
+    fn content(&self, meta: Self::Metadata) -> Result<RPCTxPoolContent, Error> {
+        let pool = meta.evm_tx_pool();
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        let (pending, queued) = pool.content(&state_nonces(&bank, pool.senders()));
+        let into_rpc = |content: PoolContent| {
+            content
+                .into_iter()
+                .map(|(sender, txs)| {
+                    let txs = txs
+                        .into_iter()
+                        .map(|(nonce, tx)| {
+                            Ok((nonce.to_string(), RPCTransaction::new_pending(&tx)?))
+                        })
+                        .collect::<Result<HashMap<_, _>, Error>>()?;
+                    Ok((Hex(sender), txs))
+                })
+                .collect::<Result<HashMap<_, _>, Error>>()
+        };
+        Ok(RPCTxPoolContent {
+            pending: into_rpc(pending)?,
+            queued: into_rpc(queued)?,
+        })
+    }
+
+    fn status(&self, meta: Self::Metadata) -> Result<RPCTxPoolStatus, Error> {
+        let pool = meta.evm_tx_pool();
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        let (pending, queued) = pool.content(&state_nonces(&bank, pool.senders()));
+        let count =
+            |content: &PoolContent| -> usize { content.values().map(|txs| txs.len()).sum() };
+        Ok(RPCTxPoolStatus {
+            pending: Hex(count(&pending)),
+            queued: Hex(count(&queued)),
+        })
+    }
+}
+
+pub struct DebugERPCImpl;
+impl DebugERPC for DebugERPCImpl {
+    type Metadata = JsonRpcRequestProcessor;
+
+    fn trace_transaction(
+        &self,
+        meta: Self::Metadata,
+        tx_hash: Hex<H256>,
+        config: Option<RPCTraceConfig>,
+    ) -> Result<Either<RPCTrace, RPCCallFrame>, Error> {
+        let config = config.unwrap_or_default();
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        let receipt = bank
+            .evm_state
+            .read()
+            .expect("Evm lock poisoned")
+            .get_tx_receipt_by_hash(tx_hash.0)
+            .ok_or(Error::NotFound)?;
+        let traces = trace_block(&bank, receipt.block_number, Some(receipt.index), &config)?;
+        let (tx, trace) = traces.into_iter().next().ok_or(Error::NotFound)?;
+        config.format_trace(&tx, trace)
+    }
+
+    fn trace_block_by_number(
+        &self,
+        meta: Self::Metadata,
+        block: String,
+        config: Option<RPCTraceConfig>,
+    ) -> Result<Vec<RPCBlockTrace>, Error> {
+        let config = config.unwrap_or_default();
+        let block_num = block_to_confirmed_num(Some(block), &meta).ok_or(Error::InvalidParams)?;
+        let bank = meta.bank(DEFAULT_COMITTMENT);
+        trace_block(&bank, block_num, None, &config)?
+            .into_iter()
+            .map(|(tx, trace)| {
+                Ok(RPCBlockTrace {
+                    tx_hash: Hex(tx.tx_id_hash()),
+                    result: config.format_trace(&tx, trace)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Re-execute transactions of block on top of state before it, and trace them.
+/// If `target` index is set, only this transaction is traced, and transactions after it are not executed.
+/// Native accounts are restored from receipts, as they were seen by transactions.
+fn trace_block(
+    bank: &solana_runtime::bank::Bank,
+    block_num: u64,
+    target: Option<u64>,
+    config: &RPCTraceConfig,
+) -> Result<
+    Vec<(
+        evm_state::transactions::Transaction,
+        evm_state::tracing::Trace,
+    )>,
+    Error,
+> {
+    let (state, receipts) = {
+        let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+        let state = evm_state
+            .state_before_slot(block_num)
+            .ok_or(Error::NotFound)?;
+        let receipts = evm_state
+            .get_txs_in_block(block_num)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tx_hash| {
+                evm_state
+                    .get_tx_receipt_by_hash(tx_hash)
+                    .ok_or(Error::NotFound)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (state, receipts)
+    };
+
+    let mut executor = evm_state::Executor::with_config(
+        state,
+        Config::istanbul(),
+        u64::max_value(),
+        bank.evm_chain_id,
+        block_num,
+    );
+    executor.set_features(bank.evm_features_at(block_num));
+    executor.set_epoch(bank.epoch_schedule().get_epoch(block_num));
+
+    let mut traces = vec![];
+    for (index, receipt) in receipts.into_iter().enumerate() {
+        let tx = receipt.transaction;
+        match target {
+            Some(target) if target != index as u64 => {
+                executor
+                    .transaction_execute_with_native_accounts(tx, receipt.native_accounts)
+                    .map_err(|_| Error::CallError)?;
+            }
+            _ => {
+                let trace = executor
+                    .transaction_trace_with_native_accounts(
+                        tx.clone(),
+                        receipt.native_accounts,
+                        config.trace_config(),
+                    )
+                    .map_err(|_| Error::CallError)?;
+                traces.push((tx, trace));
+                if target.is_some() {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(traces)
+}
+
+/// Gas limit of `eth_call`, if it wasn't provided in transaction.
+const DEFAULT_CALL_GAS_LIMIT: u64 = 300_000_000;
+
+fn tx_gas_limit(tx: &RPCTransaction) -> Result<u64, Error> {
+    tx.gas
+        .as_ref()
+        .map(|gas| gas.0)
+        .unwrap_or_else(|| DEFAULT_CALL_GAS_LIMIT.into())
+        .try_into()
+        .map_err(|_| Error::InvalidParams)
+}
+
+/// Executes transaction on top of `evm_state` without committing it.
+/// Returns exit reason, returned data and used gas.
+fn call(
+    bank: &Bank,
+    slot: Slot,
+    evm_state: &EvmState,
+    tx: &RPCTransaction,
+    gas_limit: u64,
+) -> (evm_state::ExitReason, Vec<u8>, u64) {
+    let caller = tx.from.as_ref().map(|a| a.0).unwrap_or_default();
+    let value = tx.value.as_ref().map(|a| a.0).unwrap_or_else(|| 0.into());
+    let input = tx.data.as_ref().map(|a| a.0.clone()).unwrap_or_default();
+
+    let mut executor = evm_state::Executor::with_config(
+        evm_state.clone(),
+        Config::istanbul(),
+        gas_limit,
+        bank.evm_chain_id,
+        slot,
+    );
+    executor.set_features(bank.evm_features_at(slot));
+    executor.set_epoch(bank.epoch_schedule().get_epoch(slot));
+
+    if let Some(address) = &tx.to {
         let address = address.0;
-        executor.with_executor(|e| e.transact_call(caller, address, value, input, gas_limit))
+        executor.with_executor(|e| {
+            let (exit_reason, data) = e.transact_call(caller, address, value, input, gas_limit);
+            (exit_reason, data, e.used_gas())
+        })
+    } else {
+        executor.with_executor(|e| {
+            let exit_reason = e.transact_create(caller, value, input, gas_limit);
+            (exit_reason, vec![], e.used_gas())
+        })
+    }
+}
+
+fn check_exit_reason(exit_reason: evm_state::ExitReason, data: Vec<u8>) -> Result<Bytes, Error> {
+    match exit_reason {
+        ExitReason::Succeed(_) => Ok(Bytes(data)),
+        ExitReason::Revert(_) => Err(Error::Revert(data)),
+        reason => Err(Error::ExecutionError(reason)),
+    }
+}
+
+/// Gas that is charged before execution: base cost of call or create, and cost of data.
+fn intrinsic_gas(tx: &RPCTransaction, config: &Config) -> u64 {
+    let data = tx
+        .data
+        .as_ref()
+        .map(|data| data.0.as_slice())
+        .unwrap_or_default();
+    let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+    let base = if tx.to.is_some() {
+        config.gas_transaction_call
     } else {
-        executor.with_executor(|e| (e.transact_create(caller, value, input, gas_limit), vec![]))
+        config.gas_transaction_create
+    };
+    base + zero_bytes * config.gas_transaction_zero_data
+        + non_zero_bytes * config.gas_transaction_non_zero_data
+}
+
+/// Finds the lowest gas limit, that transaction succeeds with, using binary search
+/// between intrinsic gas and gas limit of transaction. Gas limit defaults to block gas limit,
+/// capped by gas, that sender can pay for at gas price of transaction.
+fn estimate_gas(
+    bank: &Bank,
+    slot: Slot,
+    evm_state: &EvmState,
+    tx: &RPCTransaction,
+) -> Result<u64, Error> {
+    let mut cap = match tx.gas {
+        Some(_) => tx_gas_limit(tx)?,
+        None => BLOCK_GAS_LIMIT,
     };
+    if let Some(gas_price) = tx.gas_price.as_ref().filter(|price| !price.0.is_zero()) {
+        let caller = tx.from.as_ref().map(|a| a.0).unwrap_or_default();
+        let value = tx.value.as_ref().map(|a| a.0).unwrap_or_default();
+        let balance = evm_state.get_account(caller).unwrap_or_default().balance;
+        let allowance = balance.saturating_sub(value) / gas_price.0;
+        if allowance < cap.into() {
+            cap = allowance.as_u64();
+        }
+    }
 
-    let gas_used = executor.used_gas();
-    Ok((result.0, result.1, gas_used))
+    let (exit_reason, data, _) = call(bank, slot, evm_state, tx, cap);
+    if let ExitReason::Error(ExitError::OutOfGas) = exit_reason {
+        return Err(Error::GasLimitExceeded(cap));
+    }
+    check_exit_reason(exit_reason, data)?;
+
+    // Transaction always fails with gas limit lower than intrinsic gas.
+    let mut lo = intrinsic_gas(tx, &Config::istanbul()).saturating_sub(1);
+    let mut hi = cap;
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        if call(bank, slot, evm_state, tx, mid).0.is_succeed() {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Ok(hi)
 }
@@ -0,0 +1,585 @@
+//! Pool of EVM transactions, that wait until their nonce become next for the sender.
+
+use crate::{rpc::JsonRpcRequestProcessor, rpc_subscriptions::RpcSubscriptions};
+use evm_state::{Address, Transaction, H256, U256};
+use solana_sdk::clock::Slot;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// Transaction is removed, if it wasn't executed during this time.
+pub const POOL_TX_TTL: Duration = Duration::from_secs(3 * 60 * 60);
+pub const MAX_POOL_TRANSACTIONS: usize = 10_000;
+/// Limit of pooled transactions per sender, so single sender can't take the whole pool.
+pub const MAX_SENDER_TRANSACTIONS: usize = 64;
+/// Minimal increase of gas price in percents, that allows to replace transaction with the same nonce.
+pub const PRICE_BUMP_PERCENT: u64 = 10;
+const RELAY_INTERVAL: Duration = Duration::from_millis(400);
+/// Value of `relayed_until` for transaction, that is being relayed right now.
+const RESERVED: Slot = Slot::MAX;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    #[error("already known")]
+    AlreadyKnown,
+    #[error("nonce too low")]
+    NonceTooLow,
+    #[error("transaction with the same nonce is already relayed")]
+    NonceAlreadyPooled,
+    #[error("replacement transaction underpriced")]
+    ReplacementUnderpriced,
+    #[error("insufficient funds for gas * price")]
+    InsufficientFunds,
+    #[error("too many transactions of sender")]
+    SenderLimit,
+    #[error("txpool is full")]
+    PoolFull,
+}
+
+#[derive(Debug, Clone)]
+pub struct PooledTransaction {
+    pub sender: Address,
+    pub hash: H256,
+    pub tx: Transaction,
+    inserted: Instant,
+    /// Last slot, when relayed native transaction can be processed, `None` if it wasn't relayed.
+    relayed_until: Option<Slot>,
+}
+
+#[derive(Debug, Default)]
+struct PoolInner {
+    by_sender: HashMap<Address, BTreeMap<U256, PooledTransaction>>,
+    by_hash: HashMap<H256, (Address, U256)>,
+    // Hashes of the last inserted transactions, and sequence number of the last one.
+    inserted: VecDeque<H256>,
+    last_inserted: u64,
+}
+
+impl PoolInner {
+    fn remove(&mut self, hash: H256) -> Option<PooledTransaction> {
+        let (sender, nonce) = self.by_hash.remove(&hash)?;
+        let txs = self.by_sender.get_mut(&sender)?;
+        let removed = txs.remove(&nonce);
+        if txs.is_empty() {
+            self.by_sender.remove(&sender);
+        }
+        removed
+    }
+
+    /// Removes transactions with nonce below current nonce of sender.
+    fn prune_executed(&mut self, sender: Address, state_nonce: U256) {
+        let stale: Vec<_> = match self.by_sender.get(&sender) {
+            Some(txs) => txs.range(..state_nonce).map(|(_, tx)| tx.hash).collect(),
+            None => return,
+        };
+        for hash in stale {
+            self.remove(hash);
+        }
+    }
+
+    fn evict_expired(&mut self, now: Instant, ttl: Duration) {
+        let expired: Vec<_> = self
+            .by_sender
+            .values()
+            .flat_map(|txs| txs.values())
+            .filter(|tx| now.duration_since(tx.inserted) >= ttl)
+            .map(|tx| tx.hash)
+            .collect();
+        for hash in expired {
+            self.remove(hash);
+        }
+    }
+}
+
+/// Pending and queued transactions by sender and nonce.
+pub type PoolContent = HashMap<Address, BTreeMap<U256, Transaction>>;
+
+pub struct EvmTxPool {
+    inner: RwLock<PoolInner>,
+    ttl: Duration,
+    /// Subscriptions of pending transactions, notified on every insertion.
+    subscriptions: Option<Arc<RpcSubscriptions>>,
+}
+
+impl Default for EvmTxPool {
+    fn default() -> Self {
+        Self::new(POOL_TX_TTL)
+    }
+}
+
+impl EvmTxPool {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            inner: RwLock::new(PoolInner::default()),
+            ttl,
+            subscriptions: None,
+        }
+    }
+
+    pub fn new_with_subscriptions(subscriptions: Arc<RpcSubscriptions>) -> Self {
+        Self {
+            subscriptions: Some(subscriptions),
+            ..Self::default()
+        }
+    }
+
+    /// Adds transaction of `sender`, replacing transaction with the same nonce,
+    /// if it wasn't relayed yet and gas price of new one is high enough.
+    /// `state_balance` of sender should cover gas of transaction.
+    /// Transaction with next nonce of sender should be relayed by caller.
+    pub fn insert(
+        &self,
+        sender: Address,
+        hash: H256,
+        tx: Transaction,
+        state_nonce: U256,
+        state_balance: U256,
+    ) -> Result<(), PoolError> {
+        let mut inner = self.inner.write().expect("Pool lock poisoned");
+        let now = Instant::now();
+        inner.evict_expired(now, self.ttl);
+        inner.prune_executed(sender, state_nonce);
+
+        if inner.by_hash.contains_key(&hash) {
+            return Err(PoolError::AlreadyKnown);
+        }
+        if tx.nonce < state_nonce {
+            return Err(PoolError::NonceTooLow);
+        }
+        if tx.gas_limit.saturating_mul(tx.gas_price) > state_balance {
+            return Err(PoolError::InsufficientFunds);
+        }
+        let sender_txs = inner.by_sender.get(&sender);
+        let sender_len = sender_txs.map_or(0, BTreeMap::len);
+        let replaced = sender_txs
+            .and_then(|txs| txs.get(&tx.nonce))
+            .map(|old| (old.hash, old.tx.gas_price, old.relayed_until));
+        match replaced {
+            Some((_, _, Some(_))) => return Err(PoolError::NonceAlreadyPooled),
+            Some((old_hash, old_price, None)) => {
+                let min_price = old_price.saturating_mul((100 + PRICE_BUMP_PERCENT).into()) / 100;
+                if tx.gas_price < min_price {
+                    return Err(PoolError::ReplacementUnderpriced);
+                }
+                inner.remove(old_hash);
+            }
+            None if sender_len >= MAX_SENDER_TRANSACTIONS => return Err(PoolError::SenderLimit),
+            None if inner.by_hash.len() >= MAX_POOL_TRANSACTIONS => {
+                return Err(PoolError::PoolFull)
+            }
+            None => {}
+        }
+
+        let nonce = tx.nonce;
+        inner.by_hash.insert(hash, (sender, nonce));
+        inner.by_sender.entry(sender).or_default().insert(
+            nonce,
+            PooledTransaction {
+                sender,
+                hash,
+                tx,
+                inserted: now,
+                relayed_until: if nonce == state_nonce {
+                    Some(RESERVED)
+                } else {
+                    None
+                },
+            },
+        );
+        inner.last_inserted += 1;
+        inner.inserted.push_back(hash);
+        if inner.inserted.len() > MAX_POOL_TRANSACTIONS {
+            inner.inserted.pop_front();
+        }
+        drop(inner);
+
+        if let Some(subscriptions) = &self.subscriptions {
+            subscriptions.notify_evm_pending_transaction(hash);
+        }
+        Ok(())
+    }
+
+    /// Sequence number of the last inserted transaction, starting from one.
+    pub fn last_inserted(&self) -> u64 {
+        self.inner.read().expect("Pool lock poisoned").last_inserted
+    }
+
+    /// Hashes of transactions, that were inserted with sequence numbers in `range`.
+    /// Only the last `MAX_POOL_TRANSACTIONS` insertions are remembered.
+    pub fn inserted_in(&self, range: RangeInclusive<u64>) -> Vec<H256> {
+        let inner = self.inner.read().expect("Pool lock poisoned");
+        let first = inner.last_inserted + 1 - inner.inserted.len() as u64;
+        let start = std::cmp::max(*range.start(), first);
+        let end = std::cmp::min(*range.end(), inner.last_inserted);
+        (start..=end)
+            .map(|seq| inner.inserted[(seq - first) as usize])
+            .collect()
+    }
+
+    pub fn remove(&self, hash: H256) -> Option<PooledTransaction> {
+        self.inner.write().expect("Pool lock poisoned").remove(hash)
+    }
+
+    pub fn get(&self, hash: H256) -> Option<PooledTransaction> {
+        let inner = self.inner.read().expect("Pool lock poisoned");
+        let (sender, nonce) = inner.by_hash.get(&hash)?;
+        inner.by_sender.get(sender)?.get(nonce).cloned()
+    }
+
+    pub fn senders(&self) -> Vec<Address> {
+        let inner = self.inner.read().expect("Pool lock poisoned");
+        inner.by_sender.keys().copied().collect()
+    }
+
+    /// Remembers that transaction was relayed, and can be executed until `last_valid_slot`.
+    pub fn mark_relayed(&self, hash: H256, last_valid_slot: Slot) {
+        let mut inner = self.inner.write().expect("Pool lock poisoned");
+        if let Some((sender, nonce)) = inner.by_hash.get(&hash).copied() {
+            if let Some(tx) = inner
+                .by_sender
+                .get_mut(&sender)
+                .and_then(|txs| txs.get_mut(&nonce))
+            {
+                tx.relayed_until = Some(last_valid_slot);
+            }
+        }
+    }
+
+    /// Returns transactions with next nonce of their senders, that wasn't relayed yet,
+    /// or their relayed native transaction has expired before `slot`.
+    /// Returned transactions are reserved until `mark_relayed` or `remove`.
+    /// Executed and expired transactions are removed from pool.
+    pub fn ready(
+        &self,
+        state_nonces: &HashMap<Address, U256>,
+        slot: Slot,
+    ) -> Vec<PooledTransaction> {
+        let mut inner = self.inner.write().expect("Pool lock poisoned");
+        inner.evict_expired(Instant::now(), self.ttl);
+        for (sender, nonce) in state_nonces {
+            inner.prune_executed(*sender, *nonce);
+        }
+
+        inner
+            .by_sender
+            .iter_mut()
+            .filter_map(|(sender, txs)| {
+                let nonce = state_nonces.get(sender).copied().unwrap_or_default();
+                txs.get_mut(&nonce)
+            })
+            .filter(|tx| tx.relayed_until.map_or(true, |until| until < slot))
+            .map(|tx| {
+                tx.relayed_until = Some(RESERVED);
+                tx.clone()
+            })
+            .collect()
+    }
+
+    /// Nonce after the last transaction of `sender`, that can be executed in a row.
+    pub fn pending_nonce(&self, sender: Address, state_nonce: U256) -> U256 {
+        let inner = self.inner.read().expect("Pool lock poisoned");
+        let mut nonce = state_nonce;
+        if let Some(txs) = inner.by_sender.get(&sender) {
+            while txs.contains_key(&nonce) {
+                nonce += U256::one();
+            }
+        }
+        nonce
+    }
+
+    /// Splits pool into pending transactions, that can be executed in a row,
+    /// and queued ones, that wait for missing nonces.
+    pub fn content(&self, state_nonces: &HashMap<Address, U256>) -> (PoolContent, PoolContent) {
+        let inner = self.inner.read().expect("Pool lock poisoned");
+        let mut pending = PoolContent::new();
+        let mut queued = PoolContent::new();
+        for (sender, txs) in &inner.by_sender {
+            let mut next_nonce = state_nonces.get(sender).copied().unwrap_or_default();
+            for (nonce, tx) in txs.range(next_nonce..) {
+                let content = if *nonce == next_nonce {
+                    next_nonce += U256::one();
+                    &mut pending
+                } else {
+                    &mut queued
+                };
+                content
+                    .entry(*sender)
+                    .or_default()
+                    .insert(*nonce, tx.tx.clone());
+            }
+        }
+        (pending, queued)
+    }
+}
+
+/// Periodically relays pooled transactions, whose nonce became next for their sender.
+pub struct EvmTxPoolService {
+    thread: JoinHandle<()>,
+}
+
+impl EvmTxPoolService {
+    pub fn new(meta: JsonRpcRequestProcessor, exit: Arc<AtomicBool>) -> Self {
+        let thread = Builder::new()
+            .name("evm-tx-pool".to_string())
+            .spawn(move || loop {
+                if exit.load(Ordering::Relaxed) {
+                    break;
+                }
+                super::relay_pooled_transactions(&meta);
+                sleep(RELAY_INTERVAL);
+            })
+            .unwrap();
+        Self { thread }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evm_state::{TransactionAction, TransactionSignature};
+
+    fn tx(nonce: u64, gas_price: u64) -> Transaction {
+        Transaction {
+            nonce: nonce.into(),
+            gas_price: gas_price.into(),
+            gas_limit: 21000.into(),
+            action: TransactionAction::Call(Address::repeat_byte(2)),
+            value: 0.into(),
+            signature: TransactionSignature {
+                v: 27,
+                r: H256::repeat_byte(1),
+                s: H256::repeat_byte(1),
+            },
+            input: vec![],
+        }
+    }
+
+    fn hash(id: u8) -> H256 {
+        H256::repeat_byte(id)
+    }
+
+    fn nonces(sender: Address, nonce: u64) -> HashMap<Address, U256> {
+        vec![(sender, nonce.into())].into_iter().collect()
+    }
+
+    #[test]
+    fn future_nonce_waits_for_gap() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        pool.insert(sender, hash(2), tx(2, 1), 0.into(), U256::MAX)
+            .unwrap();
+        pool.insert(sender, hash(1), tx(1, 1), 0.into(), U256::MAX)
+            .unwrap();
+        assert!(pool.ready(&nonces(sender, 0), 0).is_empty());
+        assert_eq!(pool.pending_nonce(sender, 0.into()), 0.into());
+
+        pool.insert(sender, hash(0), tx(0, 1), 0.into(), U256::MAX)
+            .unwrap();
+        assert_eq!(pool.pending_nonce(sender, 0.into()), 3.into());
+        // Transaction with next nonce is relayed by caller of insert.
+        assert!(pool.ready(&nonces(sender, 0), 0).is_empty());
+
+        // Relayed transaction is not returned, until it's native transaction expires.
+        pool.mark_relayed(hash(0), 10);
+        assert!(pool.ready(&nonces(sender, 0), 10).is_empty());
+        let ready = pool.ready(&nonces(sender, 0), 11);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].hash, hash(0));
+        assert!(pool.ready(&nonces(sender, 0), 11).is_empty());
+
+        // Executed transaction is removed.
+        let ready = pool.ready(&nonces(sender, 1), 11);
+        assert_eq!(ready[0].hash, hash(1));
+        assert!(pool.get(hash(0)).is_none());
+        assert!(pool.get(hash(2)).is_some());
+    }
+
+    #[test]
+    fn insert_checks_nonce_and_duplicates() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        assert_eq!(
+            pool.insert(sender, hash(1), tx(1, 1), 2.into(), U256::MAX),
+            Err(PoolError::NonceTooLow)
+        );
+        pool.insert(sender, hash(2), tx(2, 1), 2.into(), U256::MAX)
+            .unwrap();
+        assert_eq!(
+            pool.insert(sender, hash(2), tx(2, 1), 2.into(), U256::MAX),
+            Err(PoolError::AlreadyKnown)
+        );
+    }
+
+    #[test]
+    fn replace_by_fee() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        pool.insert(sender, hash(1), tx(1, 100), 0.into(), U256::MAX)
+            .unwrap();
+        assert_eq!(
+            pool.insert(sender, hash(2), tx(1, 109), 0.into(), U256::MAX),
+            Err(PoolError::ReplacementUnderpriced)
+        );
+        pool.insert(sender, hash(3), tx(1, 110), 0.into(), U256::MAX)
+            .unwrap();
+        assert!(pool.get(hash(1)).is_none());
+        assert_eq!(pool.get(hash(3)).unwrap().tx.gas_price, 110.into());
+        assert_eq!(pool.ready(&nonces(sender, 1), 0)[0].hash, hash(3));
+    }
+
+    #[test]
+    fn relayed_transaction_is_not_replaced() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        // Transaction with next nonce is reserved for relay by caller.
+        pool.insert(sender, hash(1), tx(0, 100), 0.into(), U256::MAX)
+            .unwrap();
+        assert_eq!(
+            pool.insert(sender, hash(2), tx(0, 200), 0.into(), U256::MAX),
+            Err(PoolError::NonceAlreadyPooled)
+        );
+        pool.mark_relayed(hash(1), 10);
+        assert_eq!(
+            pool.insert(sender, hash(2), tx(0, 200), 0.into(), U256::MAX),
+            Err(PoolError::NonceAlreadyPooled)
+        );
+        assert_eq!(pool.get(hash(1)).unwrap().tx.gas_price, 100.into());
+
+        // Nonce is free again, after pooled transaction was dropped.
+        pool.remove(hash(1));
+        pool.insert(sender, hash(2), tx(0, 200), 0.into(), U256::MAX)
+            .unwrap();
+    }
+
+    #[test]
+    fn insert_checks_balance() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        // Gas limit of test transaction is 21000.
+        assert_eq!(
+            pool.insert(sender, hash(1), tx(0, 2), 0.into(), 41_999.into()),
+            Err(PoolError::InsufficientFunds)
+        );
+        pool.insert(sender, hash(1), tx(0, 2), 0.into(), 42_000.into())
+            .unwrap();
+    }
+
+    #[test]
+    fn sender_limit() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        for nonce in 0..MAX_SENDER_TRANSACTIONS as u64 {
+            let hash = H256::from_low_u64_be(nonce + 1);
+            pool.insert(sender, hash, tx(nonce, 1), 0.into(), U256::MAX)
+                .unwrap();
+        }
+        let nonce = MAX_SENDER_TRANSACTIONS as u64;
+        assert_eq!(
+            pool.insert(sender, H256::zero(), tx(nonce, 1), 0.into(), U256::MAX),
+            Err(PoolError::SenderLimit)
+        );
+        // Replacement doesn't count against limit.
+        pool.insert(sender, H256::zero(), tx(nonce - 1, 2), 0.into(), U256::MAX)
+            .unwrap();
+        // Other senders are not affected.
+        pool.insert(
+            Address::repeat_byte(2),
+            hash(1),
+            tx(0, 1),
+            0.into(),
+            U256::MAX,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn inserted_hashes_by_sequence() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        assert_eq!(pool.last_inserted(), 0);
+        assert!(pool.inserted_in(1..=0).is_empty());
+
+        pool.insert(sender, hash(1), tx(1, 100), 0.into(), U256::MAX)
+            .unwrap();
+        pool.insert(sender, hash(2), tx(2, 100), 0.into(), U256::MAX)
+            .unwrap();
+        // Rejected transaction is not inserted, while replacement is.
+        assert!(pool
+            .insert(sender, hash(3), tx(1, 100), 0.into(), U256::MAX)
+            .is_err());
+        pool.insert(sender, hash(4), tx(1, 200), 0.into(), U256::MAX)
+            .unwrap();
+
+        assert_eq!(pool.last_inserted(), 3);
+        assert_eq!(pool.inserted_in(1..=3), vec![hash(1), hash(2), hash(4)]);
+        assert_eq!(pool.inserted_in(3..=10), vec![hash(4)]);
+        assert!(pool.inserted_in(4..=3).is_empty());
+    }
+
+    #[test]
+    fn content_splits_pending_and_queued() {
+        let pool = EvmTxPool::default();
+        let sender = Address::repeat_byte(1);
+        let other = Address::repeat_byte(3);
+        pool.insert(sender, hash(1), tx(5, 1), 5.into(), U256::MAX)
+            .unwrap();
+        pool.insert(sender, hash(2), tx(6, 1), 5.into(), U256::MAX)
+            .unwrap();
+        pool.insert(sender, hash(3), tx(8, 1), 5.into(), U256::MAX)
+            .unwrap();
+        pool.insert(other, hash(4), tx(1, 1), 0.into(), U256::MAX)
+            .unwrap();
+
+        let (pending, queued) = pool.content(&nonces(sender, 5));
+        let pending_nonces: Vec<_> = pending[&sender].keys().copied().collect();
+        assert_eq!(pending_nonces, vec![5.into(), 6.into()]);
+        let queued_nonces: Vec<_> = queued[&sender].keys().copied().collect();
+        assert_eq!(queued_nonces, vec![8.into()]);
+        // Sender without known nonce starts from zero.
+        assert!(!pending.contains_key(&other));
+        assert!(queued[&other].contains_key(&1.into()));
+    }
+
+    #[test]
+    fn expired_transactions_are_evicted() {
+        let pool = EvmTxPool::new(Duration::from_millis(50));
+        let sender = Address::repeat_byte(1);
+        pool.insert(sender, hash(1), tx(1, 1), 0.into(), U256::MAX)
+            .unwrap();
+        sleep(Duration::from_millis(60));
+        assert!(pool.ready(&HashMap::new(), 0).is_empty());
+        assert!(pool.get(hash(1)).is_none());
+        assert!(pool.senders().is_empty());
+    }
+
+    #[test]
+    fn pool_limit() {
+        let pool = EvmTxPool::default();
+        for id in 0..MAX_POOL_TRANSACTIONS as u64 {
+            let sender = Address::from_low_u64_be(id + 1);
+            let hash = H256::from_low_u64_be(id + 1);
+            pool.insert(sender, hash, tx(0, 1), 0.into(), U256::MAX)
+                .unwrap();
+        }
+        let sender = Address::from_low_u64_be(1);
+        assert_eq!(
+            pool.insert(sender, H256::zero(), tx(1, 1), 0.into(), U256::MAX),
+            Err(PoolError::PoolFull)
+        );
+        // Executed transactions free space.
+        pool.insert(sender, H256::zero(), tx(1, 1), 1.into(), U256::MAX)
+            .unwrap();
+    }
+}
@@ -3,6 +3,7 @@
 use crate::{
     cluster_info::ClusterInfo,
     contact_info::ContactInfo,
+    evm_rpc_impl::EvmTxPool,
     non_circulating_supply::calculate_non_circulating_supply,
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
     rpc_health::*,
@@ -25,8 +26,8 @@ use solana_client::{
     rpc_custom_error::RpcCustomError,
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
     rpc_request::{
-        TokenAccountsFilter, DELINQUENT_VALIDATOR_SLOT_DISTANCE, MAX_GET_CONFIRMED_BLOCKS_RANGE,
-        MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT,
+        TokenAccountsFilter, DELINQUENT_VALIDATOR_SLOT_DISTANCE, MAX_EVM_LOGS_BLOCK_RANGE,
+        MAX_GET_CONFIRMED_BLOCKS_RANGE, MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT,
         MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE,
         MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS, NUM_LARGEST_ACCOUNTS,
     },
@@ -54,7 +55,7 @@ use solana_sdk::{
     epoch_schedule::EpochSchedule,
     hash::Hash,
     pubkey::Pubkey,
-    signature::Signature,
+    signature::{read_keypair_file, Keypair, Signature},
     stake_history::StakeHistory,
     system_instruction,
     sysvar::stake_history,
@@ -73,6 +74,7 @@ use std::{
     cmp::{max, min},
     collections::{HashMap, HashSet},
     net::SocketAddr,
+    path::PathBuf,
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -111,6 +113,9 @@ pub struct JsonRpcConfig {
     pub enable_bigtable_ledger_storage: bool,
     pub enable_bigtable_ledger_upload: bool,
     pub max_multiple_accounts: Option<usize>,
+    pub max_evm_logs_block_range: Option<u64>,
+    /// Keypair file of account, that pays fees for EVM transactions sent with `eth_sendRawTransaction`.
+    pub evm_fee_payer_keypair: Option<PathBuf>,
     pub account_indexes: HashSet<AccountIndex>,
     pub rpc_threads: usize,
 }
@@ -129,6 +134,8 @@ pub struct JsonRpcRequestProcessor {
     runtime_handle: runtime::Handle,
     bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
     optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
+    evm_fee_payer: Option<Arc<Keypair>>,
+    evm_tx_pool: Arc<EvmTxPool>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
 
@@ -208,8 +215,14 @@ impl JsonRpcRequestProcessor {
         runtime: &runtime::Runtime,
         bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
+        evm_tx_pool: Arc<EvmTxPool>,
     ) -> (Self, Receiver<TransactionInfo>) {
         let (sender, receiver) = channel();
+        let evm_fee_payer = config.evm_fee_payer_keypair.as_ref().map(|path| {
+            Arc::new(read_keypair_file(path).unwrap_or_else(|err| {
+                panic!("Unable to read EVM fee payer keypair {:?}: {}", path, err)
+            }))
+        });
         (
             Self {
                 config,
@@ -224,6 +237,8 @@ impl JsonRpcRequestProcessor {
                 runtime_handle: runtime.handle().clone(),
                 bigtable_ledger_storage,
                 optimistically_confirmed_bank,
+                evm_fee_payer,
+                evm_tx_pool,
             },
             receiver,
         )
@@ -262,6 +277,8 @@ impl JsonRpcRequestProcessor {
             optimistically_confirmed_bank: Arc::new(RwLock::new(OptimisticallyConfirmedBank {
                 bank: bank.clone(),
             })),
+            evm_fee_payer: None,
+            evm_tx_pool: Arc::new(EvmTxPool::default()),
         }
     }
 
@@ -1067,6 +1084,22 @@ impl JsonRpcRequestProcessor {
         slot
     }
 
+    /// Account, that signs and pays fees for native transactions wrapping raw EVM transactions.
+    pub(crate) fn evm_fee_payer(&self) -> Option<&Keypair> {
+        self.evm_fee_payer.as_deref()
+    }
+
+    pub(crate) fn evm_tx_pool(&self) -> &EvmTxPool {
+        &self.evm_tx_pool
+    }
+
+    /// Maximum number of blocks, that can be scanned by single EVM logs request.
+    pub fn max_evm_logs_block_range(&self) -> u64 {
+        self.config
+            .max_evm_logs_block_range
+            .unwrap_or(MAX_EVM_LOGS_BLOCK_RANGE)
+    }
+
     pub fn get_stake_activation(
         &self,
         pubkey: &Pubkey,
@@ -2012,7 +2045,7 @@ pub trait RpcSol {
     ) -> Result<RpcResponse<Vec<RpcKeyedAccount>>>;
 }
 
-fn _send_transaction(
+pub(crate) fn _send_transaction(
     meta: JsonRpcRequestProcessor,
     transaction: Transaction,
     wire_transaction: Vec<u8>,
@@ -3084,6 +3117,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(EvmTxPool::default()),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
 
@@ -4484,6 +4518,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(EvmTxPool::default()),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
 
@@ -4680,6 +4715,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(EvmTxPool::default()),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(request_processor.validator_exit(), false);
@@ -4712,6 +4748,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(EvmTxPool::default()),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(request_processor.validator_exit(), true);
@@ -4803,6 +4840,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(EvmTxPool::default()),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(
@@ -6031,6 +6069,7 @@ pub mod tests {
             &runtime::Runtime::new().unwrap(),
             None,
             optimistically_confirmed_bank.clone(),
+            Arc::new(EvmTxPool::default()),
         );
 
         let mut io = MetaIoHandler::default();
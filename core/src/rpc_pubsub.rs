@@ -1,14 +1,15 @@
 //! The `pubsub` module implements a threaded subscription service on client RPC request
 
-use crate::rpc_subscriptions::{RpcSubscriptions, RpcVote};
+use crate::rpc_subscriptions::{EvmSubscriptionKind, RpcSubscriptions, RpcVote};
+use evm_rpc::{RPCSubscriptionKind, RPCSubscriptionResult, RPCTopicFilter};
 use jsonrpc_core::{Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, Session, SubscriptionId};
 use solana_account_decoder::UiAccount;
 use solana_client::{
     rpc_config::{
-        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig,
-        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+        RpcAccountInfoConfig, RpcEvmSubscribeConfig, RpcProgramAccountsConfig,
+        RpcSignatureSubscribeConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
     },
     rpc_response::{
         Response as RpcResponse, RpcKeyedAccount, RpcLogsResponse, RpcSignatureResult, SlotInfo,
@@ -162,6 +163,24 @@ pub trait RpcSolPubSub {
         name = "rootUnsubscribe"
     )]
     fn root_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+
+    // Get EVM block headers, logs or pending transaction hashes
+    #[pubsub(subscription = "eth_subscription", subscribe, name = "eth_subscribe")]
+    fn eth_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<RPCSubscriptionResult>,
+        kind: RPCSubscriptionKind,
+        config: Option<RpcEvmSubscribeConfig>,
+    );
+
+    // Unsubscribe from EVM notification subscription.
+    #[pubsub(
+        subscription = "eth_subscription",
+        unsubscribe,
+        name = "eth_unsubscribe"
+    )]
+    fn eth_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
 }
 
 pub struct RpcSolPubSubImpl {
@@ -477,6 +496,56 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
             })
         }
     }
+
+    fn eth_subscribe(
+        &self,
+        _meta: Self::Metadata,
+        subscriber: Subscriber<RPCSubscriptionResult>,
+        kind: RPCSubscriptionKind,
+        config: Option<RpcEvmSubscribeConfig>,
+    ) {
+        info!("eth_subscribe: kind={:?}", kind);
+        if let Err(err) = self.check_subscription_count() {
+            subscriber.reject(err).unwrap_or_default();
+            return;
+        }
+        let config = config.unwrap_or_default();
+        let kind = match kind {
+            RPCSubscriptionKind::NewHeads => EvmSubscriptionKind::NewHeads,
+            RPCSubscriptionKind::NewPendingTransactions => EvmSubscriptionKind::PendingTransactions,
+            RPCSubscriptionKind::Logs => {
+                match RPCTopicFilter::into_log_filter_topics(config.topics) {
+                    Ok(topics) => EvmSubscriptionKind::Logs {
+                        address: config.address.map(|address| address.0),
+                        topics,
+                    },
+                    Err(err) => {
+                        subscriber.reject(err.into()).unwrap_or_default();
+                        return;
+                    }
+                }
+            }
+        };
+
+        let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
+        let sub_id = SubscriptionId::Number(id as u64);
+        info!("eth_subscribe: id={:?}", sub_id);
+        self.subscriptions
+            .add_evm_subscription(kind, config.commitment, sub_id, subscriber);
+    }
+
+    fn eth_unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        info!("eth_unsubscribe: id={:?}", id);
+        if self.subscriptions.remove_evm_subscription(&id) {
+            Ok(true)
+        } else {
+            Err(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid Request: Subscription id does not exist".into(),
+                data: None,
+            })
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1222,4 +1291,52 @@ mod tests {
             .vote_unsubscribe(Some(session), SubscriptionId::Number(0))
             .is_ok());
     }
+
+    #[test]
+    #[serial]
+    fn test_eth_subscribe_new_heads() {
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let bank0 = bank_forks.read().unwrap().get(0).unwrap().clone();
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        bank_forks.write().unwrap().insert(bank1);
+
+        let rpc = RpcSolPubSubImpl::default_with_bank_forks(bank_forks);
+        let session = create_session();
+        let (subscriber, _id_receiver, receiver) = Subscriber::new_test("eth_subscription");
+        rpc.eth_subscribe(
+            session,
+            subscriber,
+            RPCSubscriptionKind::NewHeads,
+            Some(RpcEvmSubscribeConfig {
+                commitment: Some(CommitmentConfig::recent()),
+                ..RpcEvmSubscribeConfig::default()
+            }),
+        );
+
+        rpc.subscriptions.notify_subscribers(CommitmentSlots {
+            slot: 1,
+            ..CommitmentSlots::default()
+        });
+        let (response, _) = robust_poll_or_panic(receiver);
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["method"], "eth_subscription");
+        assert_eq!(response["params"]["subscription"], 0);
+        assert_eq!(response["params"]["result"]["number"], "0x1");
+        assert_eq!(
+            response["params"]["result"]["parentHash"],
+            serde_json::to_value(evm_rpc::Hex(evm_state::H256::from_slice(
+                bank0.last_blockhash().as_ref()
+            )))
+            .unwrap()
+        );
+
+        assert!(rpc
+            .eth_unsubscribe(None, SubscriptionId::Number(0))
+            .unwrap());
+        assert!(rpc
+            .eth_unsubscribe(None, SubscriptionId::Number(0))
+            .is_err());
+    }
 }
@@ -3,6 +3,7 @@
 use crate::{
     bigtable_upload_service::BigTableUploadService,
     cluster_info::ClusterInfo,
+    evm_rpc_impl::{EvmTxPool, EvmTxPoolService},
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
     poh_recorder::PohRecorder,
     rpc::*,
@@ -271,6 +272,7 @@ impl JsonRpcService {
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
         send_transaction_retry_ms: u64,
         send_transaction_leader_forward_count: u64,
+        evm_tx_pool: Arc<EvmTxPool>,
     ) -> Self {
         info!("rpc bound to {:?}", rpc_addr);
         info!("rpc configuration: {:?}", config);
@@ -292,6 +294,7 @@ impl JsonRpcService {
             .expect("Runtime");
 
         let exit_bigtable_ledger_upload_service = Arc::new(AtomicBool::new(false));
+        let exit_evm_tx_pool_service = Arc::new(AtomicBool::new(false));
 
         let (bigtable_ledger_storage, _bigtable_ledger_upload_service) =
             if config.enable_bigtable_ledger_storage || config.enable_bigtable_ledger_upload {
@@ -340,6 +343,7 @@ impl JsonRpcService {
             &runtime,
             bigtable_ledger_storage,
             optimistically_confirmed_bank,
+            evm_tx_pool,
         );
 
         let leader_info =
@@ -352,6 +356,8 @@ impl JsonRpcService {
             send_transaction_retry_ms,
             send_transaction_leader_forward_count,
         ));
+        let _evm_tx_pool_service =
+            EvmTxPoolService::new(request_processor.clone(), exit_evm_tx_pool_service.clone());
 
         #[cfg(test)]
         let test_request_processor = request_processor.clone();
@@ -369,6 +375,14 @@ impl JsonRpcService {
                 io.extend_with(ether_basic.to_delegate());
                 let chain_mock = super::evm_rpc_impl::ChainMockERPCImpl;
                 io.extend_with(chain_mock.to_delegate());
+                let debug = super::evm_rpc_impl::DebugERPCImpl;
+                io.extend_with(debug.to_delegate());
+                let filters = super::evm_rpc_impl::FilterERPCImpl::default();
+                io.extend_with(filters.to_delegate());
+                let bridge = super::evm_rpc_impl::BridgeERPCImpl;
+                io.extend_with(bridge.to_delegate());
+                let txpool = super::evm_rpc_impl::TxPoolERPCImpl;
+                io.extend_with(txpool.to_delegate());
 
                 let request_middleware = RpcRequestMiddleware::new(
                     ledger_path,
@@ -403,6 +417,7 @@ impl JsonRpcService {
                 close_handle_sender.send(server.close_handle()).unwrap();
                 server.wait();
                 exit_bigtable_ledger_upload_service.store(true, Ordering::Relaxed);
+                exit_evm_tx_pool_service.store(true, Ordering::Relaxed);
             })
             .unwrap();
 
@@ -488,6 +503,7 @@ mod tests {
             optimistically_confirmed_bank,
             1000,
             1,
+            Arc::new(EvmTxPool::default()),
         );
         let thread = rpc_service.thread_hdl.thread();
         assert_eq!(thread.name().unwrap(), "solana-jsonrpc");
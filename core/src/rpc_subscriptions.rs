@@ -1,10 +1,13 @@
 //! The `pubsub` module implements a threaded subscription service on client RPC request
 
 use crate::{
+    evm_rpc_impl::block_from_state,
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
     rpc::{get_parsed_token_account, get_parsed_token_accounts},
 };
 use core::hash::Hash;
+use evm_rpc::{Hex, RPCSubscriptionResult};
+use evm_state::{Address, LogFilter, H256};
 use jsonrpc_core::futures::Future;
 use jsonrpc_pubsub::{
     typed::{Sink, Subscriber},
@@ -29,11 +32,12 @@ use solana_runtime::{
     commitment::{BlockCommitmentCache, CommitmentSlots},
 };
 use solana_sdk::{
-    account::Account,
+    account::{from_account, Account},
     clock::{Slot, UnixTimestamp},
     commitment_config::{CommitmentConfig, CommitmentLevel},
     pubkey::Pubkey,
     signature::Signature,
+    sysvar::{self, recent_blockhashes::RecentBlockhashes},
     transaction,
 };
 use solana_vote_program::vote_state::Vote;
@@ -91,6 +95,7 @@ enum NotificationEntry {
     Bank(CommitmentSlots),
     Gossip(Slot),
     SignaturesReceived((Slot, Vec<Signature>)),
+    EvmPendingTransaction(H256),
 }
 
 impl std::fmt::Debug for NotificationEntry {
@@ -106,6 +111,9 @@ impl std::fmt::Debug for NotificationEntry {
                 write!(f, "SignaturesReceived({:?})", slot_signatures)
             }
             NotificationEntry::Gossip(slot) => write!(f, "Gossip({:?})", slot),
+            NotificationEntry::EvmPendingTransaction(hash) => {
+                write!(f, "EvmPendingTransaction({:?})", hash)
+            }
         }
     }
 }
@@ -121,6 +129,18 @@ struct ProgramConfig {
     filters: Vec<RpcFilterType>,
     encoding: Option<UiAccountEncoding>,
 }
+
+/// Kind of `eth_subscribe` subscription.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EvmSubscriptionKind {
+    NewHeads,
+    Logs {
+        address: Option<Address>,
+        /// Positional topics, `None` matches any topic, otherwise any of listed.
+        topics: Vec<Option<Vec<H256>>>,
+    },
+    PendingTransactions,
+}
 type RpcAccountSubscriptions = RwLock<
     HashMap<
         Pubkey,
@@ -145,6 +165,12 @@ type RpcSignatureSubscriptions = RwLock<
         HashMap<SubscriptionId, SubscriptionData<Response<RpcSignatureResult>, bool>>,
     >,
 >;
+type RpcEvmSubscriptions = RwLock<
+    HashMap<
+        EvmSubscriptionKind,
+        HashMap<SubscriptionId, SubscriptionData<RPCSubscriptionResult, ()>>,
+    >,
+>;
 type RpcSlotSubscriptions = RwLock<HashMap<SubscriptionId, Sink<SlotInfo>>>;
 type RpcVoteSubscriptions = RwLock<HashMap<SubscriptionId, Sink<RpcVote>>>;
 type RpcRootSubscriptions = RwLock<HashMap<SubscriptionId, Sink<Slot>>>;
@@ -197,6 +223,17 @@ where
     found
 }
 
+fn commitment_slot(commitment: &CommitmentConfig, commitment_slots: &CommitmentSlots) -> Slot {
+    match commitment.commitment {
+        CommitmentLevel::Max => commitment_slots.highest_confirmed_root,
+        CommitmentLevel::Recent => commitment_slots.slot,
+        CommitmentLevel::Root => commitment_slots.root,
+        CommitmentLevel::Single | CommitmentLevel::SingleGossip => {
+            commitment_slots.highest_confirmed_slot
+        }
+    }
+}
+
 #[allow(clippy::type_complexity)]
 fn check_commitment_and_notify<K, S, B, F, X, T>(
     subscriptions: &HashMap<K, HashMap<SubscriptionId, SubscriptionData<Response<S>, T>>>,
@@ -227,14 +264,7 @@ where
             },
         ) in hashmap.iter()
         {
-            let slot = match commitment.commitment {
-                CommitmentLevel::Max => commitment_slots.highest_confirmed_root,
-                CommitmentLevel::Recent => commitment_slots.slot,
-                CommitmentLevel::Root => commitment_slots.root,
-                CommitmentLevel::Single | CommitmentLevel::SingleGossip => {
-                    commitment_slots.highest_confirmed_slot
-                }
-            };
+            let slot = commitment_slot(commitment, commitment_slots);
             if let Some(bank) = bank_forks.read().unwrap().get(slot).cloned() {
                 let results = bank_method(&bank, hashmap_key);
                 let mut w_last_notified_slot = last_notified_slot.write().unwrap();
@@ -371,6 +401,73 @@ fn filter_logs_results(
     }
 }
 
+// Parent of rooted bank is dropped, so its blockhash is taken from `RecentBlockhashes` sysvar.
+fn parent_blockhash(bank: &Bank) -> solana_sdk::hash::Hash {
+    if let Some(parent) = bank.parent() {
+        return parent.last_blockhash();
+    }
+    let last_blockhash = bank.last_blockhash();
+    bank.get_account(&sysvar::recent_blockhashes::id())
+        .and_then(|account| from_account::<RecentBlockhashes>(&account))
+        .and_then(|blockhashes| {
+            blockhashes
+                .iter()
+                .map(|entry| entry.blockhash)
+                .find(|blockhash| *blockhash != last_blockhash)
+        })
+        .unwrap_or_default()
+}
+
+fn evm_block_header(bank: &Bank) -> RPCSubscriptionResult {
+    let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+    RPCSubscriptionResult::Header(block_from_state(
+        &evm_state,
+        bank.slot(),
+        H256::from_slice(bank.last_blockhash().as_ref()),
+        H256::from_slice(parent_blockhash(bank).as_ref()),
+        bank.clock().unix_timestamp as u64,
+        false,
+    ))
+}
+
+/// Returns EVM notifications for blocks in range (`last_notified_slot`, `bank.slot()`].
+fn filter_evm_results(
+    kind: &EvmSubscriptionKind,
+    bank: &Arc<Bank>,
+    last_notified_slot: Slot,
+) -> Vec<RPCSubscriptionResult> {
+    let from_block = last_notified_slot + 1;
+    match kind {
+        EvmSubscriptionKind::NewHeads => {
+            // Skipped slots are not in ancestors, and ancestors of rooted banks are unavailable.
+            let mut banks: Vec<_> = bank
+                .parents()
+                .into_iter()
+                .take_while(|parent| parent.slot() >= from_block)
+                .collect();
+            banks.reverse();
+            banks.push(bank.clone());
+            banks.iter().map(|bank| evm_block_header(bank)).collect()
+        }
+        EvmSubscriptionKind::Logs { address, topics } => {
+            let evm_state = bank.evm_state.read().expect("Evm lock poisoned");
+            let filter = LogFilter {
+                address: *address,
+                topics: topics.clone(),
+                from_block,
+                to_block: bank.slot(),
+            };
+            evm_state
+                .get_logs(filter)
+                .into_iter()
+                .map(|log| RPCSubscriptionResult::Log(log.into()))
+                .collect()
+        }
+        // Pending transactions are notified on insertion into pool, not by blocks.
+        EvmSubscriptionKind::PendingTransactions => vec![],
+    }
+}
+
 fn total_nested_subscriptions<K, L, V>(
     subscription_map: &RwLock<HashMap<K, HashMap<L, V>>>,
 ) -> usize {
@@ -391,6 +488,8 @@ struct Subscriptions {
     gossip_logs_subscriptions: Arc<RpcLogsSubscriptions>,
     gossip_program_subscriptions: Arc<RpcProgramSubscriptions>,
     gossip_signature_subscriptions: Arc<RpcSignatureSubscriptions>,
+    evm_subscriptions: Arc<RpcEvmSubscriptions>,
+    gossip_evm_subscriptions: Arc<RpcEvmSubscriptions>,
     slot_subscriptions: Arc<RpcSlotSubscriptions>,
     vote_subscriptions: Arc<RpcVoteSubscriptions>,
     root_subscriptions: Arc<RpcRootSubscriptions>,
@@ -407,6 +506,8 @@ impl Subscriptions {
         total += total_nested_subscriptions(&self.gossip_logs_subscriptions);
         total += total_nested_subscriptions(&self.gossip_program_subscriptions);
         total += total_nested_subscriptions(&self.gossip_signature_subscriptions);
+        total += total_nested_subscriptions(&self.evm_subscriptions);
+        total += total_nested_subscriptions(&self.gossip_evm_subscriptions);
         total += self.slot_subscriptions.read().unwrap().len();
         total += self.vote_subscriptions.read().unwrap().len();
         total += self.root_subscriptions.read().unwrap().len();
@@ -470,6 +571,8 @@ impl RpcSubscriptions {
         let gossip_logs_subscriptions = Arc::new(RpcLogsSubscriptions::default());
         let gossip_program_subscriptions = Arc::new(RpcProgramSubscriptions::default());
         let gossip_signature_subscriptions = Arc::new(RpcSignatureSubscriptions::default());
+        let evm_subscriptions = Arc::new(RpcEvmSubscriptions::default());
+        let gossip_evm_subscriptions = Arc::new(RpcEvmSubscriptions::default());
         let slot_subscriptions = Arc::new(RpcSlotSubscriptions::default());
         let vote_subscriptions = Arc::new(RpcVoteSubscriptions::default());
         let root_subscriptions = Arc::new(RpcRootSubscriptions::default());
@@ -487,6 +590,8 @@ impl RpcSubscriptions {
             gossip_logs_subscriptions,
             gossip_program_subscriptions,
             gossip_signature_subscriptions,
+            evm_subscriptions,
+            gossip_evm_subscriptions,
             slot_subscriptions,
             vote_subscriptions,
             root_subscriptions,
@@ -626,19 +731,8 @@ impl RpcSubscriptions {
         self.subscriptions.total()
     }
 
-    pub fn add_account_subscription(
-        &self,
-        pubkey: Pubkey,
-        config: Option<RpcAccountInfoConfig>,
-        sub_id: SubscriptionId,
-        subscriber: Subscriber<Response<UiAccount>>,
-    ) {
-        let config = config.unwrap_or_default();
-        let commitment = config
-            .commitment
-            .unwrap_or_else(CommitmentConfig::single_gossip);
-
-        let slot = match commitment.commitment {
+    fn current_slot(&self, commitment: &CommitmentConfig) -> Slot {
+        match commitment.commitment {
             CommitmentLevel::Max => self
                 .block_commitment_cache
                 .read()
@@ -657,7 +751,22 @@ impl RpcSubscriptions {
                 .unwrap()
                 .bank
                 .slot(),
-        };
+        }
+    }
+
+    pub fn add_account_subscription(
+        &self,
+        pubkey: Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+        sub_id: SubscriptionId,
+        subscriber: Subscriber<Response<UiAccount>>,
+    ) {
+        let config = config.unwrap_or_default();
+        let commitment = config
+            .commitment
+            .unwrap_or_else(CommitmentConfig::single_gossip);
+
+        let slot = self.current_slot(&commitment);
         let last_notified_slot = if let Some((_account, slot)) = self
             .bank_forks
             .read()
@@ -909,6 +1018,49 @@ impl RpcSubscriptions {
         }
     }
 
+    pub fn add_evm_subscription(
+        &self,
+        kind: EvmSubscriptionKind,
+        commitment: Option<CommitmentConfig>,
+        sub_id: SubscriptionId,
+        subscriber: Subscriber<RPCSubscriptionResult>,
+    ) {
+        let commitment = commitment.unwrap_or_else(CommitmentConfig::single_gossip);
+        // Notify only about blocks, that will be confirmed after subscription.
+        let last_notified_slot = self.current_slot(&commitment);
+
+        let mut subscriptions = if commitment.commitment == CommitmentLevel::SingleGossip {
+            self.subscriptions.gossip_evm_subscriptions.write().unwrap()
+        } else {
+            self.subscriptions.evm_subscriptions.write().unwrap()
+        };
+
+        add_subscription(
+            &mut subscriptions,
+            kind,
+            commitment,
+            sub_id,
+            subscriber,
+            last_notified_slot,
+            None,
+        );
+    }
+
+    pub fn remove_evm_subscription(&self, id: &SubscriptionId) -> bool {
+        let mut subscriptions = self.subscriptions.evm_subscriptions.write().unwrap();
+        if remove_subscription(&mut subscriptions, id) {
+            true
+        } else {
+            let mut subscriptions = self.subscriptions.gossip_evm_subscriptions.write().unwrap();
+            remove_subscription(&mut subscriptions, id)
+        }
+    }
+
+    /// Notify pending transactions subscribers of transaction inserted into EVM pool.
+    pub fn notify_evm_pending_transaction(&self, hash: H256) {
+        self.enqueue_notification(NotificationEntry::EvmPendingTransaction(hash));
+    }
+
     /// Notify subscribers of changes to any accounts or new signatures since
     /// the bank's last checkpoint.
     pub fn notify_subscribers(&self, commitment_slots: CommitmentSlots) {
@@ -1071,6 +1223,12 @@ impl RpcSubscriptions {
                             &commitment_slots,
                             &notifier,
                             "bank",
+                        );
+                        RpcSubscriptions::notify_evm(
+                            &subscriptions.evm_subscriptions,
+                            &bank_forks,
+                            &commitment_slots,
+                            &notifier,
                         )
                     }
                     NotificationEntry::Gossip(slot) => {
@@ -1088,6 +1246,18 @@ impl RpcSubscriptions {
                             &notifier,
                         )
                     }
+                    NotificationEntry::EvmPendingTransaction(hash) => {
+                        for evm_subscriptions in &[
+                            &subscriptions.evm_subscriptions,
+                            &subscriptions.gossip_evm_subscriptions,
+                        ] {
+                            RpcSubscriptions::notify_evm_pending_transaction_subscribers(
+                                evm_subscriptions,
+                                hash,
+                                &notifier,
+                            );
+                        }
+                    }
                 },
                 Err(RecvTimeoutError::Timeout) => {
                     // not a problem - try reading again
@@ -1120,6 +1290,59 @@ impl RpcSubscriptions {
             &notifier,
             "gossip",
         );
+        RpcSubscriptions::notify_evm(
+            &subscriptions.gossip_evm_subscriptions,
+            bank_forks,
+            &commitment_slots,
+            notifier,
+        );
+    }
+
+    fn notify_evm_pending_transaction_subscribers(
+        evm_subscriptions: &Arc<RpcEvmSubscriptions>,
+        hash: H256,
+        notifier: &RpcNotifier,
+    ) {
+        let subscriptions = evm_subscriptions.read().unwrap();
+        if let Some(hashmap) = subscriptions.get(&EvmSubscriptionKind::PendingTransactions) {
+            for SubscriptionData { sink, .. } in hashmap.values() {
+                inc_new_counter_info!("rpc-subscription-notify-evm", 1);
+                notifier.notify(RPCSubscriptionResult::TransactionHash(Hex(hash)), sink);
+            }
+        }
+    }
+
+    fn notify_evm(
+        evm_subscriptions: &Arc<RpcEvmSubscriptions>,
+        bank_forks: &Arc<RwLock<BankForks>>,
+        commitment_slots: &CommitmentSlots,
+        notifier: &RpcNotifier,
+    ) {
+        let subscriptions = evm_subscriptions.read().unwrap();
+        for (kind, hashmap) in subscriptions.iter() {
+            for SubscriptionData {
+                sink,
+                commitment,
+                last_notified_slot,
+                ..
+            } in hashmap.values()
+            {
+                let slot = commitment_slot(commitment, commitment_slots);
+                let mut w_last_notified_slot = last_notified_slot.write().unwrap();
+                if slot <= *w_last_notified_slot {
+                    continue;
+                }
+                let bank = match bank_forks.read().unwrap().get(slot).cloned() {
+                    Some(bank) => bank,
+                    None => continue,
+                };
+                for result in filter_evm_results(kind, &bank, *w_last_notified_slot) {
+                    inc_new_counter_info!("rpc-subscription-notify-evm", 1);
+                    notifier.notify(result, sink);
+                }
+                *w_last_notified_slot = slot;
+            }
+        }
     }
 
     fn notify_accounts_logs_programs_signatures(
@@ -1887,6 +2110,44 @@ pub(crate) mod tests {
             .contains_key(&sub_id));
     }
 
+    #[test]
+    #[serial]
+    fn test_check_evm_pending_transaction_subscribe() {
+        let (subscriber, _id_receiver, transport_receiver) =
+            Subscriber::new_test("eth_subscription");
+        let sub_id = SubscriptionId::Number(0);
+        let exit = Arc::new(AtomicBool::new(false));
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let optimistically_confirmed_bank =
+            OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks);
+        let subscriptions = RpcSubscriptions::new(
+            &exit,
+            bank_forks,
+            Arc::new(RwLock::new(BlockCommitmentCache::new_for_tests())),
+            optimistically_confirmed_bank,
+        );
+        subscriptions.add_evm_subscription(
+            EvmSubscriptionKind::PendingTransactions,
+            None,
+            sub_id.clone(),
+            subscriber,
+        );
+
+        let hash = H256::repeat_byte(0x11);
+        subscriptions.notify_evm_pending_transaction(hash);
+
+        let (response, _) = robust_poll_or_panic(transport_receiver);
+        let expected = format!(
+            r#"{{"jsonrpc":"2.0","method":"eth_subscription","params":{{"result":{},"subscription":0}}}}"#,
+            serde_json::to_string(&RPCSubscriptionResult::TransactionHash(Hex(hash))).unwrap()
+        );
+        assert_eq!(expected, response);
+
+        assert!(subscriptions.remove_evm_subscription(&sub_id));
+    }
+
     #[test]
     #[serial]
     fn test_add_and_remove_subscription() {
@@ -35,9 +35,11 @@ use solana_runtime::{
     },
     bank_forks::{BankForks, SnapshotConfig},
     commitment::BlockCommitmentCache,
+    evm_state_pruner_service::EvmStatePrunerService,
     vote_sender_types::ReplayVoteSender,
 };
 use solana_sdk::{
+    clock::Slot,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
 };
@@ -60,6 +62,7 @@ pub struct Tvu {
     replay_stage: ReplayStage,
     ledger_cleanup_service: Option<LedgerCleanupService>,
     accounts_background_service: AccountsBackgroundService,
+    evm_state_pruner_service: Option<EvmStatePrunerService>,
     accounts_hash_verifier: AccountsHashVerifier,
 }
 
@@ -79,6 +82,8 @@ pub struct TvuConfig {
     pub repair_validators: Option<HashSet<Pubkey>>,
     pub accounts_hash_fault_injection_slots: u64,
     pub accounts_db_caching_enabled: bool,
+    /// Number of rooted slots, which evm state is kept, `None` for archive mode.
+    pub evm_state_retention: Option<Slot>,
 }
 
 impl Tvu {
@@ -276,6 +281,10 @@ impl Tvu {
             tvu_config.accounts_db_caching_enabled,
         );
 
+        let evm_state_pruner_service = tvu_config
+            .evm_state_retention
+            .map(|retention| EvmStatePrunerService::new(bank_forks.clone(), retention, &exit));
+
         Tvu {
             fetch_stage,
             sigverify_stage,
@@ -283,6 +292,7 @@ impl Tvu {
             replay_stage,
             ledger_cleanup_service,
             accounts_background_service,
+            evm_state_pruner_service,
             accounts_hash_verifier,
         }
     }
@@ -295,6 +305,9 @@ impl Tvu {
             self.ledger_cleanup_service.unwrap().join()?;
         }
         self.accounts_background_service.join()?;
+        if let Some(evm_state_pruner_service) = self.evm_state_pruner_service {
+            evm_state_pruner_service.join()?;
+        }
         self.replay_stage.join()?;
         self.accounts_hash_verifier.join()?;
         Ok(())
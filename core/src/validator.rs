@@ -11,6 +11,7 @@ use crate::{
     completed_data_sets_service::CompletedDataSetsService,
     consensus::{reconcile_blockstore_roots_with_tower, Tower},
     contact_info::ContactInfo,
+    evm_rpc_impl::EvmTxPool,
     gossip_service::GossipService,
     optimistically_confirmed_bank_tracker::{
         OptimisticallyConfirmedBank, OptimisticallyConfirmedBankTracker,
@@ -49,6 +50,7 @@ use solana_runtime::{
     bank::Bank,
     bank_forks::{BankForks, SnapshotConfig},
     commitment::BlockCommitmentCache,
+    evm_state_pruner_service::DEFAULT_EVM_STATE_RETENTION,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
 };
 use solana_sdk::{
@@ -120,6 +122,7 @@ pub struct ValidatorConfig {
     pub poh_pinned_cpu_core: usize,
     pub account_indexes: HashSet<AccountIndex>,
     pub accounts_db_caching_enabled: bool,
+    pub evm_state_retention: Option<Slot>, // None = archive
 }
 
 impl Default for ValidatorConfig {
@@ -166,6 +169,7 @@ impl Default for ValidatorConfig {
             poh_pinned_cpu_core: poh_service::DEFAULT_PINNED_CPU_CORE,
             account_indexes: HashSet::new(),
             accounts_db_caching_enabled: false,
+            evm_state_retention: Some(DEFAULT_EVM_STATE_RETENTION),
         }
     }
 }
@@ -397,6 +401,7 @@ impl Validator {
             optimistically_confirmed_bank.clone(),
             config.pubsub_config.enable_vote_subscription,
         ));
+        let evm_tx_pool = Arc::new(EvmTxPool::new_with_subscriptions(subscriptions.clone()));
 
         let (completed_data_sets_sender, completed_data_sets_receiver) =
             bounded(MAX_COMPLETED_DATA_SETS_IN_CHANNEL);
@@ -469,6 +474,7 @@ impl Validator {
                         optimistically_confirmed_bank.clone(),
                         config.send_transaction_retry_ms,
                         config.send_transaction_leader_forward_count,
+                        evm_tx_pool,
                     ),
                     pubsub_service: PubSubService::new(
                         config.pubsub_config.clone(),
@@ -632,6 +638,7 @@ impl Validator {
                 repair_validators: config.repair_validators.clone(),
                 accounts_hash_fault_injection_slots: config.accounts_hash_fault_injection_slots,
                 accounts_db_caching_enabled: config.accounts_db_caching_enabled,
+                evm_state_retention: config.evm_state_retention,
             },
         );
 
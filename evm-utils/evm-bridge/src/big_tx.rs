@@ -0,0 +1,147 @@
+//! Upload of evm transactions, that doesn't fit into single native transaction.
+//!
+//! Transaction is RLP encoded, written by chunks into storage allocated in evm state, and then executed.
+//! Allocation, that can't be executed, is reclaimed or expires after `BIG_TX_LIFETIME` slots.
+
+use log::*;
+use solana_client::{
+    rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig,
+    rpc_request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+};
+use solana_evm_loader_program::scope::*;
+use solana_sdk::{
+    clock::DEFAULT_TICKS_PER_SECOND,
+    instruction::Instruction,
+    signature::{Keypair, Signature, Signer},
+};
+use std::{error::Error, thread::sleep, time::Duration};
+
+const SEND_RETRIES: usize = 5;
+const STATUS_RETRIES: usize = 15;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+pub fn deploy_big_tx(payer: &Keypair, rpc_client: &RpcClient, tx: &evm::Transaction) -> Result<()> {
+    let pubkey = payer.pubkey();
+    let tx_bytes = rlp::encode(tx).to_vec();
+    let seed = evm::H256::random();
+
+    trace!("Allocating big transaction storage, seed = {}", seed);
+    let ix = solana_evm_loader_program::big_tx_allocate(&pubkey, seed, tx_bytes.len() as u64);
+    send_and_confirm(rpc_client, payer, ix)?;
+
+    let result = write_chunks(rpc_client, payer, seed, &tx_bytes).and_then(|()| {
+        trace!("Executing big transaction, seed = {}", seed);
+        let ix = solana_evm_loader_program::big_tx_execute(&pubkey, seed);
+        send_and_confirm(rpc_client, payer, ix)
+    });
+
+    if result.is_err() {
+        let ix = solana_evm_loader_program::big_tx_reclaim(&pubkey, seed);
+        if let Err(e) = send_and_confirm(rpc_client, payer, ix) {
+            warn!(
+                "Unable to reclaim big transaction storage, it will expire later: {}",
+                e
+            );
+        }
+    }
+    result
+}
+
+fn send_and_confirm(rpc_client: &RpcClient, payer: &Keypair, ix: Instruction) -> Result<()> {
+    let (blockhash, _fee_calculator) = rpc_client.get_recent_blockhash()?;
+    let tx = solana::Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    rpc_client.send_and_confirm_transaction(&tx)?;
+    Ok(())
+}
+
+/// Writes chunks of transaction, until each of them is landed.
+/// Chunks, that are lost or failed, are sent again with new blockhash.
+fn write_chunks(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    seed: evm::H256,
+    tx_bytes: &[u8],
+) -> Result<()> {
+    let mut pending: Vec<Instruction> = tx_bytes
+        .chunks(evm::TX_MTU as usize)
+        .enumerate()
+        .map(|(i, chunk)| {
+            solana_evm_loader_program::big_tx_write(
+                &payer.pubkey(),
+                seed,
+                i as u64 * evm::TX_MTU,
+                chunk.to_vec(),
+            )
+        })
+        .collect();
+
+    for _ in 0..SEND_RETRIES {
+        let (blockhash, _fee_calculator) = rpc_client.get_recent_blockhash()?;
+
+        let mut failed = vec![];
+        let mut in_flight: Vec<(Instruction, Signature)> = vec![];
+        for ix in pending.drain(..) {
+            // Delay ~1 tick between write transactions, they all lock evm state account.
+            sleep(Duration::from_millis(1000 / DEFAULT_TICKS_PER_SECOND));
+
+            let tx = solana::Transaction::new_signed_with_payer(
+                &[ix.clone()],
+                Some(&payer.pubkey()),
+                &[payer],
+                blockhash,
+            );
+            let config = RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..RpcSendTransactionConfig::default()
+            };
+            match rpc_client.send_transaction_with_config(&tx, config) {
+                Ok(signature) => in_flight.push((ix, signature)),
+                Err(e) => {
+                    debug!("Unable to send chunk: {}", e);
+                    failed.push(ix)
+                }
+            }
+        }
+
+        for _ in 0..STATUS_RETRIES {
+            if in_flight.is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(500));
+
+            let signatures: Vec<_> = in_flight.iter().map(|(_, signature)| *signature).collect();
+            let mut statuses = Vec::with_capacity(signatures.len());
+            for signatures in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+                statuses.extend(rpc_client.get_signature_statuses(signatures)?.value);
+            }
+
+            let mut not_landed = vec![];
+            for ((ix, signature), status) in in_flight.into_iter().zip(statuses) {
+                match status {
+                    Some(status) if status.err.is_none() => {}
+                    Some(status) => {
+                        debug!("Chunk {} failed: {:?}", signature, status.err);
+                        failed.push(ix);
+                    }
+                    None => not_landed.push((ix, signature)),
+                }
+            }
+            in_flight = not_landed;
+        }
+
+        // Writes are idempotent, so chunk can be sent again, even if previous attempt lands later.
+        pending = failed;
+        pending.extend(in_flight.into_iter().map(|(ix, _)| ix));
+        if pending.is_empty() {
+            return Ok(());
+        }
+        debug!("{} chunks are not written, retrying", pending.len());
+    }
+    Err(format!("{} chunks of transaction are not written", pending.len()).into())
+}
@@ -0,0 +1,345 @@
+//! Hashing of EIP-712 typed structured data, as done by `eth_signTypedData_v4`.
+
+use evm_state::{H256, U256};
+use primitive_types::H160 as Address;
+use serde::Deserialize;
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+#[derive(Debug, Error)]
+pub enum Eip712Error {
+    #[error("unknown type {0}")]
+    UnknownType(String),
+    #[error("invalid value of {field}: {reason}")]
+    InvalidValue { field: String, reason: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberType {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<MemberType>>,
+    pub primary_type: String,
+    pub domain: Value,
+    #[serde(default)]
+    pub message: Value,
+}
+
+fn keccak(data: &[u8]) -> H256 {
+    H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+fn invalid(field: &str, reason: impl Into<String>) -> Eip712Error {
+    Eip712Error::InvalidValue {
+        field: field.into(),
+        reason: reason.into(),
+    }
+}
+
+/// Type of array items, if `type_` is array.
+fn array_item_type(type_: &str) -> Option<&str> {
+    if type_.ends_with(']') {
+        type_.rfind('[').map(|pos| &type_[..pos])
+    } else {
+        None
+    }
+}
+
+fn parse_bytes(field: &str, value: &Value) -> Result<Vec<u8>, Eip712Error> {
+    let value = value
+        .as_str()
+        .ok_or_else(|| invalid(field, "expected hex string"))?;
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| invalid(field, "expected hex string"))
+}
+
+fn parse_int(field: &str, value: &Value) -> Result<U256, Eip712Error> {
+    let (negative, abs) = match value {
+        Value::Number(num) => match (num.as_u64(), num.as_i64()) {
+            (Some(num), _) => (false, U256::from(num)),
+            (None, Some(num)) => (true, U256::from(num.wrapping_neg() as u64)),
+            _ => return Err(invalid(field, "expected integer")),
+        },
+        Value::String(num) => {
+            let (negative, num) = match num.strip_prefix('-') {
+                Some(num) => (true, num),
+                None => (false, num.as_str()),
+            };
+            let abs = match num.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16).ok(),
+                None => U256::from_dec_str(num).ok(),
+            };
+            let abs = abs.ok_or_else(|| invalid(field, "expected integer"))?;
+            (negative, abs)
+        }
+        _ => return Err(invalid(field, "expected integer")),
+    };
+    // Negative numbers are encoded in two's complement.
+    Ok(if negative {
+        (!abs).overflowing_add(U256::one()).0
+    } else {
+        abs
+    })
+}
+
+impl TypedData {
+    /// Hash, that is signed: `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`.
+    pub fn hash(&self) -> Result<H256, Eip712Error> {
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(self.hash_struct(DOMAIN_TYPE, &self.domain)?.as_bytes());
+        if self.primary_type != DOMAIN_TYPE {
+            data.extend_from_slice(
+                self.hash_struct(&self.primary_type, &self.message)?
+                    .as_bytes(),
+            );
+        }
+        Ok(keccak(&data))
+    }
+
+    fn struct_type(&self, type_: &str) -> Result<&[MemberType], Eip712Error> {
+        self.types
+            .get(type_)
+            .map(Vec::as_slice)
+            .ok_or_else(|| Eip712Error::UnknownType(type_.into()))
+    }
+
+    fn collect_dependencies(&self, type_: &str, deps: &mut BTreeSet<String>) {
+        let type_ = array_item_type(type_).unwrap_or(type_);
+        if deps.contains(type_) {
+            return;
+        }
+        if let Some(members) = self.types.get(type_) {
+            deps.insert(type_.to_string());
+            for member in members {
+                self.collect_dependencies(&member.type_, deps);
+            }
+        }
+    }
+
+    /// Encoding of type and its dependencies sorted by name, like `Mail(Person from)Person(string name)`.
+    pub fn encode_type(&self, type_: &str) -> Result<String, Eip712Error> {
+        let mut deps = BTreeSet::new();
+        self.collect_dependencies(type_, &mut deps);
+        deps.remove(type_);
+
+        let mut encoded = String::new();
+        for type_ in std::iter::once(type_).chain(deps.iter().map(String::as_str)) {
+            let members: Vec<_> = self
+                .struct_type(type_)?
+                .iter()
+                .map(|member| format!("{} {}", member.type_, member.name))
+                .collect();
+            encoded.push_str(&format!("{}({})", type_, members.join(",")));
+        }
+        Ok(encoded)
+    }
+
+    pub fn hash_struct(&self, type_: &str, value: &Value) -> Result<H256, Eip712Error> {
+        let type_hash = keccak(self.encode_type(type_)?.as_bytes());
+        let mut data = type_hash.as_bytes().to_vec();
+        for member in self.struct_type(type_)? {
+            let field = value.get(&member.name).unwrap_or(&Value::Null);
+            data.extend_from_slice(
+                self.encode_field(&member.name, &member.type_, field)?
+                    .as_bytes(),
+            );
+        }
+        Ok(keccak(&data))
+    }
+
+    fn encode_field(&self, name: &str, type_: &str, value: &Value) -> Result<H256, Eip712Error> {
+        if let Some(item_type) = array_item_type(type_) {
+            let items = value
+                .as_array()
+                .ok_or_else(|| invalid(name, "expected array"))?;
+            let mut data = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                data.extend_from_slice(self.encode_field(name, item_type, item)?.as_bytes());
+            }
+            return Ok(keccak(&data));
+        }
+        if self.types.contains_key(type_) {
+            if value.is_null() {
+                return Ok(H256::zero());
+            }
+            return self.hash_struct(type_, value);
+        }
+
+        let mut word = [0; 32];
+        match type_ {
+            "string" => {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| invalid(name, "expected string"))?;
+                return Ok(keccak(value.as_bytes()));
+            }
+            "bytes" => return Ok(keccak(&parse_bytes(name, value)?)),
+            "bool" => {
+                let value = value
+                    .as_bool()
+                    .ok_or_else(|| invalid(name, "expected bool"))?;
+                word[31] = value as u8;
+            }
+            "address" => {
+                let address = parse_bytes(name, value)?;
+                if address.len() != Address::len_bytes() {
+                    return Err(invalid(name, "expected address"));
+                }
+                word[12..].copy_from_slice(&address);
+            }
+            _ if type_.starts_with("bytes") => {
+                let len: usize = type_["bytes".len()..]
+                    .parse()
+                    .map_err(|_| Eip712Error::UnknownType(type_.into()))?;
+                let bytes = parse_bytes(name, value)?;
+                if len == 0 || len > 32 || bytes.len() > len {
+                    return Err(invalid(name, format!("expected {}", type_)));
+                }
+                word[..bytes.len()].copy_from_slice(&bytes);
+            }
+            _ if type_.starts_with("uint") || type_.starts_with("int") => {
+                parse_int(name, value)?.to_big_endian(&mut word);
+            }
+            _ => return Err(Eip712Error::UnknownType(type_.into())),
+        }
+        Ok(H256(word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evm_state::{FromKey, SecretKey};
+    use serde_json::json;
+
+    // Example from EIP-712.
+    fn mail() -> TypedData {
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn mail_example() {
+        let data = mail();
+        assert_eq!(
+            data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            data.hash_struct(DOMAIN_TYPE, &data.domain).unwrap(),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+                .parse()
+                .unwrap()
+        );
+        let hash = data.hash().unwrap();
+        assert_eq!(
+            hash,
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+                .parse()
+                .unwrap()
+        );
+
+        let secret_key = SecretKey::from_slice(&keccak(b"cow")[..]).unwrap();
+        assert_eq!(
+            secret_key.to_address(),
+            "cd2a3d9f938e13cd947ec05abc7fe734df8dd826".parse().unwrap()
+        );
+        let signature = crate::keystore::sign_hash(&secret_key, hash);
+        assert_eq!(
+            hex::encode(signature),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+             07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562\
+             1c"
+        );
+    }
+
+    #[test]
+    fn arrays_and_integers() {
+        let data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "Batch": [
+                    { "name": "ids", "type": "uint256[]" },
+                    { "name": "delta", "type": "int8" },
+                    { "name": "tag", "type": "bytes4" }
+                ]
+            },
+            "primaryType": "Batch",
+            "domain": { "name": "Test" },
+            "message": { "ids": [1, "0x2", "3"], "delta": -1, "tag": "0x01020304" }
+        }))
+        .unwrap();
+        assert_eq!(
+            data.encode_field("ids", "uint256[]", &data.message["ids"])
+                .unwrap(),
+            keccak(
+                &[
+                    H256::from_low_u64_be(1).as_bytes(),
+                    H256::from_low_u64_be(2).as_bytes(),
+                    H256::from_low_u64_be(3).as_bytes()
+                ]
+                .concat()
+            )
+        );
+        assert_eq!(
+            data.encode_field("delta", "int8", &data.message["delta"])
+                .unwrap(),
+            H256::repeat_byte(0xff)
+        );
+        let mut tag = [0; 32];
+        tag[..4].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(
+            data.encode_field("tag", "bytes4", &data.message["tag"])
+                .unwrap(),
+            H256(tag)
+        );
+        assert!(data.hash().is_ok());
+        assert!(matches!(
+            data.encode_field("x", "uint256", &json!("abc")),
+            Err(Eip712Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            data.hash_struct("Unknown", &json!({})),
+            Err(Eip712Error::UnknownType(_))
+        ));
+    }
+}
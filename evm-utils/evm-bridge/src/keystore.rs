@@ -0,0 +1,548 @@
+//! Accounts of bridge, that are loaded from Ethereum JSON keystore (version 3) files.
+
+use evm_state::{FromKey, SecretKey, H256, SECP256K1};
+use log::*;
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    pkcs5,
+    symm::{self, Cipher},
+};
+use primitive_types::H160 as Address;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+const KEYSTORE_VERSION: u32 = 3;
+const CIPHER: &str = "aes-128-ctr";
+const DKLEN: u32 = 32;
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("unknown account")]
+    UnknownAccount,
+    #[error("authentication needed: password or unlock")]
+    Locked,
+    #[error("could not decrypt key with given password")]
+    InvalidPassword,
+    #[error("keystore directory is not configured")]
+    NoKeystore,
+    #[error("unsupported keystore: {0}")]
+    Unsupported(String),
+    #[error("invalid key file: {0}")]
+    InvalidKeyFile(String),
+    #[error("crypto error: {0}")]
+    Crypto(#[from] ErrorStack),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KdfParams {
+    Scrypt {
+        dklen: u32,
+        n: u64,
+        r: u64,
+        p: u64,
+        salt: String,
+    },
+    Pbkdf2 {
+        c: u32,
+        dklen: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptoParams {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    pub ciphertext: String,
+    pub kdf: String,
+    pub kdfparams: KdfParams,
+    pub mac: String,
+}
+
+/// Secret key encrypted with password, as stored by geth and other clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(alias = "Crypto")]
+    pub crypto: CryptoParams,
+    pub id: String,
+    pub version: u32,
+}
+
+/// Parameters of scrypt, that are used for new key files.
+#[derive(Debug, Clone, Copy)]
+pub struct ScryptParams {
+    pub n: u64,
+    pub r: u64,
+    pub p: u64,
+}
+
+impl ScryptParams {
+    pub const STANDARD: Self = Self {
+        n: 1 << 18,
+        r: 8,
+        p: 1,
+    };
+    pub const LIGHT: Self = Self {
+        n: 1 << 12,
+        r: 8,
+        p: 6,
+    };
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, KeystoreError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|_| KeystoreError::InvalidKeyFile(format!("{} is not a hex", field)))
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>, KeystoreError> {
+    let mut bytes = vec![0; len];
+    openssl::rand::rand_bytes(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn key_mac(derived_key: &[u8], ciphertext: &[u8]) -> H256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived_key[16..32]);
+    hasher.update(ciphertext);
+    H256::from_slice(hasher.finalize().as_slice())
+}
+
+impl KdfParams {
+    fn derive_key(&self, password: &str) -> Result<Vec<u8>, KeystoreError> {
+        let (dklen, salt) = match self {
+            KdfParams::Scrypt { dklen, salt, .. } | KdfParams::Pbkdf2 { dklen, salt, .. } => {
+                (*dklen, decode_hex("salt", salt)?)
+            }
+        };
+        if dklen < DKLEN {
+            return Err(KeystoreError::Unsupported(format!("dklen {}", dklen)));
+        }
+        let mut key = vec![0; dklen as usize];
+        match self {
+            KdfParams::Scrypt { n, r, p, .. } => {
+                // Memory, that is used by scrypt, with some reserve for openssl.
+                let maxmem = 128u64
+                    .checked_mul(*r)
+                    .and_then(|mem| mem.checked_mul(n.checked_add(*p)?.checked_add(2)?))
+                    .and_then(|mem| mem.checked_add(1 << 20))
+                    .ok_or_else(|| KeystoreError::Unsupported("scrypt params".into()))?;
+                pkcs5::scrypt(password.as_bytes(), &salt, *n, *r, *p, maxmem, &mut key)?;
+            }
+            KdfParams::Pbkdf2 { c, prf, .. } => {
+                if prf != "hmac-sha256" {
+                    return Err(KeystoreError::Unsupported(format!("prf {}", prf)));
+                }
+                pkcs5::pbkdf2_hmac(
+                    password.as_bytes(),
+                    &salt,
+                    *c as usize,
+                    MessageDigest::sha256(),
+                    &mut key,
+                )?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl KeyFile {
+    pub fn encrypt(
+        secret_key: &SecretKey,
+        password: &str,
+        params: ScryptParams,
+    ) -> Result<Self, KeystoreError> {
+        let kdfparams = KdfParams::Scrypt {
+            dklen: DKLEN,
+            n: params.n,
+            r: params.r,
+            p: params.p,
+            salt: hex::encode(random_bytes(32)?),
+        };
+        let derived_key = kdfparams.derive_key(password)?;
+        let iv = random_bytes(16)?;
+        let ciphertext = symm::encrypt(
+            Cipher::aes_128_ctr(),
+            &derived_key[..16],
+            Some(&iv),
+            &secret_key[..],
+        )?;
+
+        let id = random_bytes(16)?;
+        let id = format!(
+            "{}-{}-{}-{}-{}",
+            hex::encode(&id[0..4]),
+            hex::encode(&id[4..6]),
+            hex::encode(&id[6..8]),
+            hex::encode(&id[8..10]),
+            hex::encode(&id[10..16])
+        );
+        Ok(Self {
+            address: Some(hex::encode(secret_key.to_address())),
+            crypto: CryptoParams {
+                cipher: CIPHER.into(),
+                cipherparams: CipherParams {
+                    iv: hex::encode(iv),
+                },
+                mac: hex::encode(key_mac(&derived_key, &ciphertext)),
+                ciphertext: hex::encode(ciphertext),
+                kdf: "scrypt".into(),
+                kdfparams,
+            },
+            id,
+            version: KEYSTORE_VERSION,
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::Unsupported(format!(
+                "version {}",
+                self.version
+            )));
+        }
+        let crypto = &self.crypto;
+        if crypto.cipher != CIPHER {
+            return Err(KeystoreError::Unsupported(format!(
+                "cipher {}",
+                crypto.cipher
+            )));
+        }
+        match (crypto.kdf.as_str(), &crypto.kdfparams) {
+            ("scrypt", KdfParams::Scrypt { .. }) | ("pbkdf2", KdfParams::Pbkdf2 { .. }) => {}
+            (kdf, _) => return Err(KeystoreError::Unsupported(format!("kdf {}", kdf))),
+        }
+
+        let derived_key = crypto.kdfparams.derive_key(password)?;
+        let ciphertext = decode_hex("ciphertext", &crypto.ciphertext)?;
+        let mac = decode_hex("mac", &crypto.mac)?;
+        if key_mac(&derived_key, &ciphertext).as_bytes() != mac.as_slice() {
+            return Err(KeystoreError::InvalidPassword);
+        }
+        let iv = decode_hex("iv", &crypto.cipherparams.iv)?;
+        let secret = symm::decrypt(
+            Cipher::aes_128_ctr(),
+            &derived_key[..16],
+            Some(&iv),
+            &ciphertext,
+        )?;
+        SecretKey::from_slice(&secret)
+            .map_err(|_| KeystoreError::InvalidKeyFile("invalid secret key".into()))
+    }
+
+    pub fn address(&self) -> Result<Address, KeystoreError> {
+        let address = self
+            .address
+            .as_ref()
+            .ok_or_else(|| KeystoreError::InvalidKeyFile("address is missing".into()))?;
+        let address = decode_hex("address", address)?;
+        if address.len() != Address::len_bytes() {
+            return Err(KeystoreError::InvalidKeyFile("invalid address".into()));
+        }
+        Ok(Address::from_slice(&address))
+    }
+}
+
+struct UnlockedKey {
+    secret_key: SecretKey,
+    until: Option<Instant>,
+}
+
+/// Accounts from keystore directory, that can be unlocked to sign transactions and messages.
+pub struct Accounts {
+    keystore: Option<PathBuf>,
+    scrypt_params: ScryptParams,
+    key_files: RwLock<HashMap<Address, KeyFile>>,
+    unlocked: RwLock<HashMap<Address, UnlockedKey>>,
+}
+
+impl Accounts {
+    /// Loads key files from `keystore` directory, creating it if needed.
+    /// Files that can't be parsed are skipped.
+    pub fn load(
+        keystore: Option<&Path>,
+        scrypt_params: ScryptParams,
+    ) -> Result<Self, KeystoreError> {
+        let mut key_files = HashMap::new();
+        if let Some(keystore) = keystore {
+            fs::create_dir_all(keystore)?;
+            for entry in fs::read_dir(keystore)? {
+                let path = entry?.path();
+                if !path.is_file() {
+                    continue;
+                }
+                let key_file = fs::read(&path)
+                    .map_err(KeystoreError::from)
+                    .and_then(|data| Ok(serde_json::from_slice::<KeyFile>(&data)?))
+                    .and_then(|key_file| Ok((key_file.address()?, key_file)));
+                match key_file {
+                    Ok((address, key_file)) => {
+                        info!("Loaded key file of account {:?}", address);
+                        key_files.insert(address, key_file);
+                    }
+                    Err(err) => warn!("Skipping key file {:?}: {}", path, err),
+                }
+            }
+        }
+        Ok(Self {
+            keystore: keystore.map(Path::to_path_buf),
+            scrypt_params,
+            key_files: RwLock::new(key_files),
+            unlocked: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        let key_files = self.key_files.read().expect("Keystore lock poisoned");
+        let mut addresses: Vec<_> = key_files.keys().copied().collect();
+        addresses.sort();
+        addresses
+    }
+
+    /// Creates new account encrypted with `password` and saves it into keystore.
+    pub fn new_account(&self, password: &str) -> Result<Address, KeystoreError> {
+        let keystore = self.keystore.as_ref().ok_or(KeystoreError::NoKeystore)?;
+        let secret_key = loop {
+            if let Ok(secret_key) = SecretKey::from_slice(&random_bytes(32)?) {
+                break secret_key;
+            }
+        };
+        let address = secret_key.to_address();
+        let key_file = KeyFile::encrypt(&secret_key, password, self.scrypt_params)?;
+
+        let file_name = format!(
+            "UTC--{}--{}",
+            chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S%.9fZ"),
+            hex::encode(address)
+        );
+        fs::write(keystore.join(file_name), serde_json::to_vec(&key_file)?)?;
+        self.key_files
+            .write()
+            .expect("Keystore lock poisoned")
+            .insert(address, key_file);
+        Ok(address)
+    }
+
+    /// Decrypts key of `address`, without unlocking it.
+    pub fn decrypt(&self, address: Address, password: &str) -> Result<SecretKey, KeystoreError> {
+        let key_file = self
+            .key_files
+            .read()
+            .expect("Keystore lock poisoned")
+            .get(&address)
+            .cloned()
+            .ok_or(KeystoreError::UnknownAccount)?;
+        let secret_key = key_file.decrypt(password)?;
+        if secret_key.to_address() != address {
+            return Err(KeystoreError::InvalidKeyFile("address mismatch".into()));
+        }
+        Ok(secret_key)
+    }
+
+    /// Unlocks account for `duration`, or until restart if it's `None`.
+    pub fn unlock(
+        &self,
+        address: Address,
+        password: &str,
+        duration: Option<Duration>,
+    ) -> Result<(), KeystoreError> {
+        let secret_key = self.decrypt(address, password)?;
+        self.unlocked
+            .write()
+            .expect("Keystore lock poisoned")
+            .insert(
+                address,
+                UnlockedKey {
+                    secret_key,
+                    until: duration.map(|duration| Instant::now() + duration),
+                },
+            );
+        Ok(())
+    }
+
+    /// Returns key of unlocked account.
+    pub fn secret_key(&self, address: Address) -> Result<SecretKey, KeystoreError> {
+        let mut unlocked = self.unlocked.write().expect("Keystore lock poisoned");
+        match unlocked.get(&address) {
+            Some(key) if key.until.map_or(true, |until| Instant::now() < until) => {
+                return Ok(key.secret_key)
+            }
+            Some(_) => {
+                unlocked.remove(&address);
+            }
+            None => {}
+        }
+        if self
+            .key_files
+            .read()
+            .expect("Keystore lock poisoned")
+            .contains_key(&address)
+        {
+            Err(KeystoreError::Locked)
+        } else {
+            Err(KeystoreError::UnknownAccount)
+        }
+    }
+}
+
+/// Hash of message with `"\x19Ethereum Signed Message:\n"` prefix, that is signed by `eth_sign`.
+pub fn personal_message_hash(data: &[u8]) -> H256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", data.len()).as_bytes());
+    hasher.update(data);
+    H256::from_slice(hasher.finalize().as_slice())
+}
+
+/// Signs `hash`, returns 65 bytes of signature `r || s || v`, where `v` is 27 or 28.
+pub fn sign_hash(secret_key: &SecretKey, hash: H256) -> Vec<u8> {
+    // Hash is always MESSAGE_SIZE bytes.
+    let message = secp256k1::Message::from_slice(hash.as_bytes()).unwrap();
+    let (recovery_id, signature) = SECP256K1
+        .sign_recoverable(&message, secret_key)
+        .serialize_compact();
+    let mut signature = signature.to_vec();
+    signature.push(27 + recovery_id.to_i32() as u8);
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SECRET: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    fn test_key_file(
+        kdf: &str,
+        kdfparams: KdfParams,
+        ciphertext: &str,
+        iv: &str,
+        mac: &str,
+    ) -> KeyFile {
+        KeyFile {
+            address: None,
+            crypto: CryptoParams {
+                cipher: CIPHER.into(),
+                cipherparams: CipherParams { iv: iv.into() },
+                ciphertext: ciphertext.into(),
+                kdf: kdf.into(),
+                kdfparams,
+                mac: mac.into(),
+            },
+            id: "3198bc9c-6672-5ab3-d995-4942343ae5b6".into(),
+            version: 3,
+        }
+    }
+
+    // Test vectors from Web3 Secret Storage Definition.
+    #[test]
+    fn decrypt_pbkdf2() {
+        let key_file = test_key_file(
+            "pbkdf2",
+            KdfParams::Pbkdf2 {
+                c: 262144,
+                dklen: 32,
+                prf: "hmac-sha256".into(),
+                salt: "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd".into(),
+            },
+            "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "6087dab2f9fdbbfaddc31a909735c1e6",
+            "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2",
+        );
+        let secret_key = key_file.decrypt("testpassword").unwrap();
+        assert_eq!(hex::encode(&secret_key[..]), TEST_SECRET);
+        assert!(matches!(
+            key_file.decrypt("wrong"),
+            Err(KeystoreError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn key_file_json_roundtrip() {
+        let secret_key = SecretKey::from_slice(&hex::decode(TEST_SECRET).unwrap()).unwrap();
+        let key_file = KeyFile::encrypt(&secret_key, "password", ScryptParams::LIGHT).unwrap();
+        let json = serde_json::to_string(&key_file).unwrap();
+        let key_file: KeyFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(key_file.address().unwrap(), secret_key.to_address());
+        assert_eq!(key_file.decrypt("password").unwrap(), secret_key);
+    }
+
+    #[test]
+    fn new_account_and_unlock() {
+        let keystore = std::env::temp_dir().join(format!(
+            "evm-bridge-keystore-{}",
+            hex::encode(random_bytes(8).unwrap())
+        ));
+        let accounts = Accounts::load(Some(&keystore), ScryptParams::LIGHT).unwrap();
+        let address = accounts.new_account("password").unwrap();
+        assert_eq!(accounts.addresses(), vec![address]);
+        assert!(matches!(
+            accounts.secret_key(address),
+            Err(KeystoreError::Locked)
+        ));
+        assert!(matches!(
+            accounts.secret_key(Address::zero()),
+            Err(KeystoreError::UnknownAccount)
+        ));
+        assert!(matches!(
+            accounts.unlock(address, "wrong", None),
+            Err(KeystoreError::InvalidPassword)
+        ));
+
+        // Accounts are loaded from keystore after restart.
+        let accounts = Accounts::load(Some(&keystore), ScryptParams::LIGHT).unwrap();
+        assert_eq!(accounts.addresses(), vec![address]);
+        accounts
+            .unlock(address, "password", Some(Duration::from_millis(50)))
+            .unwrap();
+        assert_eq!(accounts.secret_key(address).unwrap().to_address(), address);
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(matches!(
+            accounts.secret_key(address),
+            Err(KeystoreError::Locked)
+        ));
+
+        fs::remove_dir_all(keystore).unwrap();
+    }
+
+    #[test]
+    fn sign_personal_message() {
+        let secret_key = SecretKey::from_slice(&hex::decode(TEST_SECRET).unwrap()).unwrap();
+        let hash = personal_message_hash(b"hello");
+        let signature = sign_hash(&secret_key, hash);
+        assert_eq!(signature.len(), 65);
+        assert!(signature[64] == 27 || signature[64] == 28);
+
+        let recovery_id =
+            secp256k1::recovery::RecoveryId::from_i32(signature[64] as i32 - 27).unwrap();
+        let signature =
+            secp256k1::recovery::RecoverableSignature::from_compact(&signature[..64], recovery_id)
+                .unwrap();
+        let message = secp256k1::Message::from_slice(hash.as_bytes()).unwrap();
+        let public_key = SECP256K1.recover(&message, &signature).unwrap();
+        assert_eq!(
+            evm_state::addr_from_public_key(&public_key),
+            secret_key.to_address()
+        );
+    }
+}
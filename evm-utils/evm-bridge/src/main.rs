@@ -1,14 +1,17 @@
+mod big_tx;
+mod eip712;
+mod keystore;
+
 use log::*;
 
-use solana_sdk::{clock::DEFAULT_TICKS_PER_SECOND, commitment_config::CommitmentLevel};
 use std::sync::Arc;
-use std::thread::sleep;
 use std::time::Duration;
-use std::{collections::HashMap, net::SocketAddr};
+use std::{net::SocketAddr, path::PathBuf};
 
 use evm_rpc::basic::BasicERPC;
 use evm_rpc::bridge::BridgeERPC;
 use evm_rpc::chain_mock::ChainMockERPC;
+use evm_rpc::personal::PersonalERPC;
 use evm_rpc::*;
 use evm_state::*;
 use sha3::{Digest, Keccak256};
@@ -26,8 +29,7 @@ use solana_sdk::{
     epoch_info::EpochInfo,
     epoch_schedule::EpochSchedule,
     message::Message,
-    signature::{Signature, Signer},
-    signers::Signers,
+    signature::Signer,
     transaction,
 };
 use solana_transaction_status::{
@@ -35,8 +37,8 @@ use solana_transaction_status::{
 };
 
 use solana_client::{
-    client_error::Result as ClientResult, rpc_client::RpcClient, rpc_config::*,
-    rpc_request::RpcRequest, rpc_response::Response as RpcResponse, rpc_response::*,
+    rpc_client::RpcClient, rpc_config::*, rpc_request::RpcRequest,
+    rpc_response::Response as RpcResponse, rpc_response::*,
 };
 
 use solana_core::rpc::RpcSol;
@@ -45,44 +47,71 @@ use std::result::Result as StdResult;
 type EvmResult<T> = StdResult<T, evm_rpc::Error>;
 type FutureEvmResult<T> = EvmResult<T>;
 
-const CHAIN_ID: u64 = 0x77;
+/// Accounts are unlocked for this time by `personal_unlockAccount` without duration.
+const DEFAULT_UNLOCK_DURATION: Duration = Duration::from_secs(300);
 
 pub struct EvmBridge {
+    evm_chain_id: u64,
     key: solana_sdk::signature::Keypair,
-    accounts: HashMap<evm_state::Address, evm_state::SecretKey>,
+    accounts: keystore::Accounts,
+    /// Allows to decrypt accounts by password received over HTTP.
+    allow_insecure_unlock: bool,
     rpc_client: RpcClient,
 }
 
 impl EvmBridge {
-    fn new(keypath: &str, evm_keys: Vec<SecretKey>, addr: String) -> Self {
-        let accounts = evm_keys
-            .into_iter()
-            .map(|secret_key| {
-                let public_key =
-                    evm_state::PublicKey::from_secret_key(&evm_state::SECP256K1, &secret_key);
-                let public_key = evm_state::addr_from_public_key(&public_key);
-                (public_key, secret_key)
-            })
-            .collect();
-
+    fn new(
+        keypath: &str,
+        accounts: keystore::Accounts,
+        allow_insecure_unlock: bool,
+        addr: String,
+    ) -> Self {
         info!("Trying to create rpc client with addr: {}", addr);
         let rpc_client = RpcClient::new(addr);
 
+        let evm_chain_id: Hex<u64> = rpc_client
+            .send(RpcRequest::EthChainId, json!([]))
+            .expect("Unable to get evm chain id from rpc");
+        info!("Using evm chain id: {}", evm_chain_id.0);
+
         info!("Loading keypair from: {}", keypath);
         Self {
+            evm_chain_id: evm_chain_id.0,
             key: solana_sdk::signature::read_keypair_file(&keypath).unwrap(),
             accounts,
+            allow_insecure_unlock,
             rpc_client,
         }
     }
 
+    fn check_insecure_unlock(&self) -> EvmResult<()> {
+        if self.allow_insecure_unlock {
+            Ok(())
+        } else {
+            Err(evm_rpc::Error::AccountError(
+                "account unlock with HTTP access is forbidden, use --allow-insecure-unlock"
+                    .to_string(),
+            ))
+        }
+    }
+
+    fn min_gas_price(&self) -> EvmResult<Gas> {
+        self.rpc_client
+            .send::<Hex<Gas>>(RpcRequest::EthGasPrice, json!([]))
+            .map(|gas_price| gas_price.0)
+            .map_err(|err| {
+                error!("Err = {}", err);
+                evm_rpc::Error::NotFound
+            })
+    }
+
     fn send_tx(&self, tx: evm::Transaction) -> FutureEvmResult<Hex<H256>> {
-        let hash = tx.signing_hash();
+        let hash = tx.tx_id_hash();
         let bytes = bincode::serialize(&tx).unwrap();
 
         if bytes.len() > evm::TX_MTU as usize {
             debug!("Sending tx = {}, by chunks", hash);
-            match deploy_big_tx(&self.key, &self.rpc_client, &tx) {
+            match big_tx::deploy_big_tx(&self.key, &self.rpc_client, &tx) {
                 Ok(_tx) => return Ok(Hex(hash)),
                 Err(e) => {
                     error!("Error creating big tx = {}", e);
@@ -108,7 +137,10 @@ impl EvmBridge {
         let (blockhash, _fee_calculator, _) = self
             .rpc_client
             .get_recent_blockhash_with_commitment(CommitmentConfig::default())
-            .unwrap()
+            .map_err(|err| {
+                error!("Err = {}", err);
+                evm_rpc::Error::NotFound
+            })?
             .value;
 
         send_raw_tx.sign(&vec![&self.key], blockhash);
@@ -122,6 +154,48 @@ impl EvmBridge {
                 evm_rpc::Error::InvalidParams
             })
     }
+
+    fn secret_key(&self, address: Address) -> EvmResult<SecretKey> {
+        self.accounts
+            .secret_key(address)
+            .map_err(|err| evm_rpc::Error::AccountError(err.to_string()))
+    }
+
+    /// Fills missing fields of transaction and signs it by unlocked account `tx.from`.
+    fn sign_transaction(&self, tx: RPCTransaction) -> EvmResult<evm::Transaction> {
+        let address = tx.from.map(|a| a.0).ok_or(evm_rpc::Error::InvalidParams)?;
+
+        debug!("sign_transaction from = {}", address);
+
+        let secret_key = self.secret_key(address)?;
+        let nonce = match tx.nonce {
+            Some(nonce) => nonce.0,
+            None => self
+                .rpc_client
+                .get_evm_transaction_count(&address)
+                .map_err(|err| {
+                    error!("Err = {}", err);
+                    evm_rpc::Error::NotFound
+                })?,
+        };
+        let gas_price = match tx.gas_price {
+            Some(gas_price) => gas_price.0,
+            None => self.min_gas_price()?,
+        };
+        let tx_create = evm::UnsignedTransaction {
+            nonce,
+            gas_price,
+            gas_limit: tx.gas.map(|a| a.0).unwrap_or_else(|| 30000000.into()),
+            action: tx
+                .to
+                .map(|a| evm::TransactionAction::Call(a.0))
+                .unwrap_or(evm::TransactionAction::Create),
+            value: tx.value.map(|a| a.0).unwrap_or_else(|| 0.into()),
+            input: tx.data.map(|a| a.0).unwrap_or_default(),
+        };
+
+        Ok(tx_create.sign(&secret_key, Some(self.evm_chain_id)))
+    }
 }
 
 macro_rules! proxy_evm_rpc {
@@ -143,16 +217,44 @@ impl BridgeERPC for BridgeERPCImpl {
     type Metadata = Arc<EvmBridge>;
 
     fn accounts(&self, meta: Self::Metadata) -> EvmResult<Vec<Hex<Address>>> {
-        Ok(meta.accounts.iter().map(|(k, _)| Hex(*k)).collect())
+        Ok(meta.accounts.addresses().into_iter().map(Hex).collect())
     }
 
-    fn sign(
+    fn sign(&self, meta: Self::Metadata, address: Hex<Address>, data: Bytes) -> EvmResult<Bytes> {
+        let secret_key = meta.secret_key(address.0)?;
+        let hash = keystore::personal_message_hash(&data.0);
+        Ok(Bytes(keystore::sign_hash(&secret_key, hash)))
+    }
+
+    fn sign_transaction(
         &self,
-        _meta: Self::Metadata,
-        _address: Hex<Address>,
-        _data: Bytes,
+        meta: Self::Metadata,
+        tx: RPCTransaction,
+    ) -> EvmResult<RPCSignedTransaction> {
+        let tx = meta.sign_transaction(tx)?;
+        Ok(RPCSignedTransaction {
+            raw: Bytes(rlp::encode(&tx).to_vec()),
+            tx: RPCTransaction::new_pending(&tx)?,
+        })
+    }
+
+    fn sign_typed_data(
+        &self,
+        meta: Self::Metadata,
+        address: Hex<Address>,
+        data: serde_json::Value,
     ) -> EvmResult<Bytes> {
-        Err(evm_rpc::Error::NotFound)
+        let data: eip712::TypedData = match data {
+            serde_json::Value::String(data) => serde_json::from_str(&data),
+            data => serde_json::from_value(data),
+        }
+        .map_err(|_| evm_rpc::Error::InvalidParams)?;
+        let hash = data.hash().map_err(|err| {
+            debug!("Invalid typed data: {}", err);
+            evm_rpc::Error::InvalidParams
+        })?;
+        let secret_key = meta.secret_key(address.0)?;
+        Ok(Bytes(keystore::sign_hash(&secret_key, hash)))
     }
 
     fn send_transaction(
@@ -160,30 +262,7 @@ impl BridgeERPC for BridgeERPCImpl {
         meta: Self::Metadata,
         tx: RPCTransaction,
     ) -> FutureEvmResult<Hex<H256>> {
-        let address = tx.from.map(|a| a.0).unwrap_or_default();
-
-        debug!("send_transaction from = {}", address);
-
-        let secret_key = meta.accounts.get(&address).unwrap();
-        let nonce = tx
-            .nonce
-            .map(|a| a.0)
-            .or_else(|| meta.rpc_client.get_evm_transaction_count(&address).ok())
-            .unwrap_or_default();
-        let tx_create = evm::UnsignedTransaction {
-            nonce,
-            gas_price: tx.gas_price.map(|a| a.0).unwrap_or_else(|| 0.into()),
-            gas_limit: tx.gas.map(|a| a.0).unwrap_or_else(|| 30000000.into()),
-            action: tx
-                .to
-                .map(|a| evm::TransactionAction::Call(a.0))
-                .unwrap_or(evm::TransactionAction::Create),
-            value: tx.value.map(|a| a.0).unwrap_or_else(|| 0.into()),
-            input: tx.data.map(|a| a.0).unwrap_or_default(),
-        };
-
-        let tx = tx_create.sign(&secret_key, CHAIN_ID.into());
-
+        let tx = meta.sign_transaction(tx)?;
         meta.send_tx(tx)
     }
 
@@ -194,16 +273,13 @@ impl BridgeERPC for BridgeERPCImpl {
     ) -> FutureEvmResult<Hex<H256>> {
         debug!("send_raw_transaction");
 
-        let tx: evm::Transaction = rlp::decode(&bytes.0).unwrap();
-        let unsigned_tx: evm::UnsignedTransaction = tx.clone().into();
-        let hash = unsigned_tx.signing_hash(CHAIN_ID.into());
-        debug!("loaded tx_hash = {}", hash);
+        let tx: evm::Transaction = rlp::decode(&bytes.0)?;
+        debug!("loaded tx_hash = {}", tx.tx_id_hash());
         meta.send_tx(tx)
     }
 
-    fn gas_price(&self, _meta: Self::Metadata) -> EvmResult<Hex<Gas>> {
-        //TODO: Add gas logic
-        Ok(Hex(1.into()))
+    fn gas_price(&self, meta: Self::Metadata) -> EvmResult<Hex<Gas>> {
+        meta.min_gas_price().map(Hex)
     }
 
     fn compilers(&self, _meta: Self::Metadata) -> EvmResult<Vec<String>> {
@@ -211,16 +287,68 @@ impl BridgeERPC for BridgeERPCImpl {
     }
 }
 
+pub struct PersonalERPCImpl;
+
+impl PersonalERPC for PersonalERPCImpl {
+    type Metadata = Arc<EvmBridge>;
+
+    fn new_account(&self, meta: Self::Metadata, password: String) -> EvmResult<Hex<Address>> {
+        meta.accounts
+            .new_account(&password)
+            .map(Hex)
+            .map_err(|err| evm_rpc::Error::AccountError(err.to_string()))
+    }
+
+    fn unlock_account(
+        &self,
+        meta: Self::Metadata,
+        address: Hex<Address>,
+        password: String,
+        duration: Option<u64>,
+    ) -> EvmResult<bool> {
+        meta.check_insecure_unlock()?;
+        let duration = match duration {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(DEFAULT_UNLOCK_DURATION),
+        };
+        meta.accounts
+            .unlock(address.0, &password, duration)
+            .map(|_| true)
+            .map_err(|err| evm_rpc::Error::AccountError(err.to_string()))
+    }
+
+    fn sign(
+        &self,
+        meta: Self::Metadata,
+        data: Bytes,
+        address: Hex<Address>,
+        password: Option<String>,
+    ) -> EvmResult<Bytes> {
+        let secret_key = match password {
+            Some(password) => {
+                meta.check_insecure_unlock()?;
+                meta.accounts
+                    .decrypt(address.0, &password)
+                    .map_err(|err| evm_rpc::Error::AccountError(err.to_string()))?
+            }
+            None => meta.secret_key(address.0)?,
+        };
+        let hash = keystore::personal_message_hash(&data.0);
+        Ok(Bytes(keystore::sign_hash(&secret_key, hash)))
+    }
+}
+
 pub struct ChainMockERPCProxy;
 impl ChainMockERPC for ChainMockERPCProxy {
     type Metadata = Arc<EvmBridge>;
 
-    fn network_id(&self, _meta: Self::Metadata) -> EvmResult<String> {
-        Ok(format!("{}", CHAIN_ID))
+    fn network_id(&self, meta: Self::Metadata) -> EvmResult<String> {
+        Ok(format!("{}", meta.evm_chain_id))
     }
 
-    fn chain_id(&self, _meta: Self::Metadata) -> EvmResult<Hex<u64>> {
-        Ok(Hex(CHAIN_ID))
+    fn chain_id(&self, meta: Self::Metadata) -> EvmResult<Hex<u64>> {
+        Ok(Hex(meta.evm_chain_id))
     }
 
     // TODO: Add network info
@@ -279,7 +407,7 @@ impl ChainMockERPC for ChainMockERPCProxy {
             transactions: Either::Left(vec![]),
             nonce: 0.into(),
             sha3_uncles: H256::zero().into(),
-            logs_bloom: H256::zero().into(), // H2048
+            logs_bloom: Bloom::zero().into(),
             transactions_root: H256::zero().into(),
             state_root: H256::zero().into(),
             receipts_root: H256::zero().into(),
@@ -942,6 +1070,12 @@ impl RpcSol for RpcSolProxy {
 #[derive(Debug, structopt::StructOpt)]
 struct Args {
     keyfile: Option<String>,
+    /// Directory with Ethereum JSON key files of accounts, that can be unlocked to sign transactions.
+    #[structopt(long)]
+    keystore: Option<PathBuf>,
+    /// Allow unlocking of accounts by password, that is sent over HTTP.
+    #[structopt(long)]
+    allow_insecure_unlock: bool,
     #[structopt(default_value = "http://127.0.0.1:8899")]
     rpc_address: String,
     #[structopt(default_value = "127.0.0.1:8545")]
@@ -954,8 +1088,6 @@ use jsonrpc_http_server::*;
 use jsonrpc_core::middleware::Middleware;
 use jsonrpc_core::middleware::{NoopCallFuture, NoopFuture};
 
-const SECRET_KEY_DUMMY: [u8; 32] = [1; 32];
-
 struct LoggingMiddleware;
 impl<M: jsonrpc_core::Metadata> Middleware<M> for LoggingMiddleware {
     type Future = NoopFuture;
@@ -983,9 +1115,12 @@ fn main(args: Args) -> std::result::Result<(), Box<dyn std::error::Error>> {
         .unwrap_or_else(|| solana_cli_config::Config::default().keypair_path);
     let server_path = args.rpc_address;
     let binding_address = args.binding_address;
+    let accounts =
+        keystore::Accounts::load(args.keystore.as_deref(), keystore::ScryptParams::STANDARD)?;
     let meta = EvmBridge::new(
         &keyfile_path,
-        vec![evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap()],
+        accounts,
+        args.allow_insecure_unlock,
         server_path,
     );
     let meta = Arc::new(meta);
@@ -999,6 +1134,8 @@ fn main(args: Args) -> std::result::Result<(), Box<dyn std::error::Error>> {
     io.extend_with(ether_basic.to_delegate());
     let ether_mock = ChainMockERPCProxy;
     io.extend_with(ether_mock.to_delegate());
+    let ether_personal = PersonalERPCImpl;
+    io.extend_with(ether_personal.to_delegate());
 
     info!("Creating server with: {}", binding_address);
     let server =
@@ -1016,168 +1153,3 @@ fn main(args: Args) -> std::result::Result<(), Box<dyn std::error::Error>> {
     server.wait();
     Ok(())
 }
-
-pub fn log_instruction_custom_error(
-    result: ClientResult<Signature>,
-) -> StdResult<Signature, Box<dyn std::error::Error>> {
-    match result {
-        Err(err) => {
-            format!("Error processing transaction = {}", err);
-            return Err("Error processing transaction".into());
-        }
-        Ok(sig) => Ok(sig),
-    }
-}
-
-fn send_and_confirm_transactions<T: Signers>(
-    rpc_client: &RpcClient,
-    mut transactions: Vec<solana::Transaction>,
-    signer_keys: &T,
-) -> StdResult<(), Box<dyn std::error::Error>> {
-    const SEND_RETRIES: usize = 5;
-    const STATUS_RETRIES: usize = 15;
-
-    for _ in 0..SEND_RETRIES {
-        // Send all transactions
-        let mut transactions_signatures = transactions
-            .drain(..)
-            .map(|transaction| {
-                if cfg!(not(test)) {
-                    // Delay ~1 tick between write transactions in an attempt to reduce AccountInUse errors
-                    // when all the write transactions modify the same program account (eg, deploying a
-                    // new program)
-                    sleep(Duration::from_millis(1000 / DEFAULT_TICKS_PER_SECOND));
-                }
-
-                let signature = rpc_client
-                    .send_transaction_with_config(
-                        &transaction,
-                        RpcSendTransactionConfig {
-                            skip_preflight: true,
-                            ..RpcSendTransactionConfig::default()
-                        },
-                    )
-                    .ok();
-                (transaction, signature)
-            })
-            .collect::<Vec<_>>();
-
-        for _ in 0..STATUS_RETRIES {
-            // Collect statuses for all the transactions, drop those that are confirmed
-
-            if cfg!(not(test)) {
-                // Retry twice a second
-                sleep(Duration::from_millis(500));
-            }
-
-            transactions_signatures.retain(|(_transaction, signature)| {
-                signature
-                    .and_then(|signature| rpc_client.get_signature_statuses(&[signature]).ok())
-                    .map(|RpcResponse { context: _, value }| match &value[0] {
-                        Some(transaction_status)
-                            if matches!(transaction_status.confirmations, Some(0)) =>
-                        {
-                            false
-                        }
-                        None => false,
-                        _ => true,
-                    })
-                    .unwrap_or(false)
-            });
-
-            if transactions_signatures.is_empty() {
-                return Ok(());
-            }
-        }
-
-        // Re-sign any failed transactions with a new blockhash and retry
-        let (blockhash, _fee_calculator) = rpc_client
-            .get_new_blockhash(&transactions_signatures[0].0.message().recent_blockhash)?;
-
-        for (mut transaction, _) in transactions_signatures {
-            transaction.try_sign(signer_keys, blockhash)?;
-            transactions.push(transaction);
-        }
-    }
-    Err("Transactions failed".into())
-}
-
-fn deploy_big_tx(
-    keypair: &solana_sdk::signature::Keypair,
-    rpc_client: &RpcClient,
-    tx: &evm::Transaction,
-) -> StdResult<(), Box<dyn std::error::Error>> {
-    let pubkey = keypair.pubkey();
-
-    let tx_bytes = bincode::serialize(&tx)?;
-
-    let seed = H256::random();
-    let (blockhash, _fee_calculator, _) = rpc_client
-        .get_recent_blockhash_with_commitment(CommitmentConfig::max())?
-        .value;
-
-    let ix = solana_evm_loader_program::big_tx_allocate(&pubkey, seed, tx_bytes.len() as u64);
-    let message = Message::new(&[ix], Some(&pubkey));
-
-    let signers = [keypair];
-    let create_account_tx = solana::Transaction::new(&signers, message, blockhash);
-
-    error!(
-        "transaction signature = {}",
-        create_account_tx.signatures[0]
-    );
-    let mut write_messages = vec![];
-    for (chunk, i) in tx_bytes.chunks(evm_state::TX_MTU as usize).zip(0..) {
-        let instruction = solana_evm_loader_program::big_tx_write(
-            &pubkey,
-            seed,
-            (i * evm_state::TX_MTU) as u64,
-            chunk.to_vec(),
-        );
-        let message = Message::new(&[instruction], Some(&pubkey));
-        write_messages.push(message);
-    }
-
-    let instruction = solana_evm_loader_program::big_tx_execute(&pubkey, seed);
-    let finalize_message = Message::new(&[instruction], Some(&signers[0].pubkey()));
-
-    trace!("Creating program account");
-    let result = rpc_client.send_and_confirm_transaction(&create_account_tx);
-    log_instruction_custom_error(result)?;
-
-    let (blockhash, _fee_calculator) = rpc_client.get_new_blockhash(&blockhash)?;
-
-    let mut write_transactions = vec![];
-    for message in write_messages.into_iter() {
-        let mut tx = solana::Transaction::new_unsigned(message);
-        tx.try_sign(&signers, blockhash)?;
-        write_transactions.push(tx);
-    }
-
-    trace!("Writing transaction data");
-    let result = send_and_confirm_transactions(&rpc_client, write_transactions, &signers);
-    if let Err(e) = result {
-        format!("Error sending write data = {}", e);
-        return Err("Error sending write data".into());
-    }
-    let (blockhash, _fee_calculator, _) = rpc_client
-        .get_recent_blockhash_with_commitment(CommitmentConfig::recent())?
-        .value;
-
-    let mut finalize_tx = solana::Transaction::new_unsigned(finalize_message);
-    finalize_tx.try_sign(&signers, blockhash)?;
-
-    trace!("Finalizing program account");
-    let result = rpc_client.send_transaction_with_config(
-        &finalize_tx,
-        RpcSendTransactionConfig {
-            skip_preflight: false,
-            preflight_commitment: Some(CommitmentLevel::Recent),
-            ..Default::default()
-        },
-    );
-
-    log_instruction_custom_error(result)?;
-
-    Ok(())
-}
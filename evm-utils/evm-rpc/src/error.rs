@@ -1,5 +1,8 @@
+use evm_state::ExitReason;
 use hex::FromHexError;
+use jsonrpc_core::ErrorCode;
 use rlp::DecoderError;
+use std::convert::TryFrom;
 use std::num::ParseIntError;
 
 #[derive(Debug)]
@@ -13,6 +16,47 @@ pub enum Error {
     RlpError,
     CallError,
     UnknownSourceMapJump,
+    /// Execution was reverted, contains returned data.
+    Revert(Vec<u8>),
+    /// Execution was halted with error.
+    ExecutionError(ExitReason),
+    /// Execution can't succeed within provided gas limit.
+    GasLimitExceeded(u64),
+    /// Logs were requested for range of blocks bigger than allowed maximum.
+    BlockRangeTooLarge(u64),
+    /// Transaction was not accepted by node, contains reason.
+    TransactionRejected(String),
+    /// Account can't be used for signing, contains reason.
+    AccountError(String),
+}
+
+/// Selector of `Error(string)`, that is used by solidity `revert` and `require`.
+const REVERT_REASON_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// JSON-RPC error code of reverted execution, the same as in geth.
+const REVERT_ERROR_CODE: i64 = 3;
+const SERVER_ERROR_CODE: i64 = -32000;
+
+/// Decodes reason of `revert("reason")` from ABI-encoded `Error(string)` call.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 4 || data[..4] != REVERT_REASON_SELECTOR {
+        return None;
+    }
+    let data = &data[4..];
+    let word = |offset: usize| -> Option<usize> {
+        let word = data.get(offset..offset.checked_add(32)?)?;
+        // Offsets and lengths bigger than usize can't point into data anyway.
+        if word[..24].iter().any(|b| *b != 0) {
+            return None;
+        }
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&word[24..]);
+        usize::try_from(u64::from_be_bytes(bytes)).ok()
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let reason = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(reason.to_vec()).ok()
 }
 
 impl From<DecoderError> for Error {
@@ -41,6 +85,113 @@ impl From<secp256k1::Error> for Error {
 
 impl Into<jsonrpc_core::Error> for Error {
     fn into(self) -> jsonrpc_core::Error {
-        jsonrpc_core::Error::invalid_request()
+        let (code, message, data) = match self {
+            Error::InvalidParams => (ErrorCode::InvalidParams, "Invalid params".into(), None),
+            Error::HexError => (ErrorCode::InvalidParams, "Invalid hex value".into(), None),
+            Error::IntError => (ErrorCode::InvalidParams, "Invalid integer".into(), None),
+            Error::ECDSAError => (ErrorCode::InvalidParams, "Invalid signature".into(), None),
+            Error::RlpError => (ErrorCode::InvalidParams, "Invalid rlp".into(), None),
+            Error::UnsupportedTrieQuery => (
+                ErrorCode::InvalidParams,
+                "Unsupported trie query".into(),
+                None,
+            ),
+            Error::UnknownSourceMapJump => (
+                ErrorCode::InvalidParams,
+                "Unknown source map jump".into(),
+                None,
+            ),
+            Error::NotFound => (
+                ErrorCode::ServerError(SERVER_ERROR_CODE),
+                "Not found".into(),
+                None,
+            ),
+            Error::CallError => (
+                ErrorCode::ServerError(SERVER_ERROR_CODE),
+                "Call failed".into(),
+                None,
+            ),
+            Error::Revert(data) => {
+                let message = match decode_revert_reason(&data) {
+                    Some(reason) => format!("execution reverted: {}", reason),
+                    None => "execution reverted".into(),
+                };
+                let data = format!("0x{}", hex::encode(data));
+                (
+                    ErrorCode::ServerError(REVERT_ERROR_CODE),
+                    message,
+                    Some(data.into()),
+                )
+            }
+            Error::ExecutionError(reason) => (
+                ErrorCode::ServerError(SERVER_ERROR_CODE),
+                format!("execution failed: {:?}", reason),
+                None,
+            ),
+            Error::GasLimitExceeded(gas_limit) => (
+                ErrorCode::ServerError(SERVER_ERROR_CODE),
+                format!("gas required exceeds allowance ({})", gas_limit),
+                None,
+            ),
+            Error::BlockRangeTooLarge(max) => (
+                ErrorCode::InvalidParams,
+                format!("block range is too large, max {} blocks", max),
+                None,
+            ),
+            Error::TransactionRejected(reason) | Error::AccountError(reason) => {
+                (ErrorCode::ServerError(SERVER_ERROR_CODE), reason, None)
+            }
+        };
+        jsonrpc_core::Error {
+            code,
+            message,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revert_reason() {
+        // revert("Not enough Ether provided.")
+        let data = hex::decode(
+            "08c379a0\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             000000000000000000000000000000000000000000000000000000000000001a\
+             4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+        )
+        .unwrap();
+        assert_eq!(
+            decode_revert_reason(&data).as_deref(),
+            Some("Not enough Ether provided.")
+        );
+
+        let error: jsonrpc_core::Error = Error::Revert(data).into();
+        assert_eq!(error.code, ErrorCode::ServerError(3));
+        assert_eq!(
+            error.message,
+            "execution reverted: Not enough Ether provided."
+        );
+
+        // Custom errors and truncated data are reported without reason.
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]), None);
+        assert_eq!(decode_revert_reason(&data_truncated()), None);
+        let error: jsonrpc_core::Error = Error::Revert(vec![0xde, 0xad]).into();
+        assert_eq!(error.message, "execution reverted");
+        assert_eq!(error.data, Some("0xdead".into()));
+    }
+
+    fn data_truncated() -> Vec<u8> {
+        let mut data = REVERT_REASON_SELECTOR.to_vec();
+        data.extend_from_slice(&[0; 31]);
+        data.push(0x20);
+        data.extend_from_slice(&[0; 31]);
+        data.push(0x40);
+        data.extend_from_slice(b"short");
+        data
     }
 }
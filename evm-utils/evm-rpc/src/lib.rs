@@ -65,7 +65,7 @@ pub struct RPCBlock {
     pub nonce: Hex<u64>,
 
     pub sha3_uncles: Hex<H256>,
-    pub logs_bloom: Hex<H256>, // H2048
+    pub logs_bloom: Hex<Bloom>,
 
     pub miner: Hex<Address>,
     pub difficulty: Hex<U256>,
@@ -92,6 +92,27 @@ pub struct RPCTransaction {
     pub transaction_index: Option<Hex<usize>>,
 }
 
+/// Pooled transactions by sender and decimal nonce.
+/// `pending` can be executed in a row, `queued` wait for missing nonces.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RPCTxPoolContent {
+    pub pending: HashMap<Hex<Address>, HashMap<String, RPCTransaction>>,
+    pub queued: HashMap<Hex<Address>, HashMap<String, RPCTransaction>>,
+}
+
+/// Result of `eth_signTransaction`, raw rlp-encoded transaction and its fields.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RPCSignedTransaction {
+    pub raw: Bytes,
+    pub tx: RPCTransaction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RPCTxPoolStatus {
+    pub pending: Hex<usize>,
+    pub queued: Hex<usize>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RPCReceipt {
@@ -104,12 +125,34 @@ pub struct RPCReceipt {
     pub contract_address: Option<Hex<Address>>,
     pub to: Option<Hex<Address>>,
     pub logs: Vec<RPCLog>,
+    pub logs_bloom: Hex<Bloom>,
     pub status: Hex<usize>,
+    /// Output of reverted transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<Bytes>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RPCSubscriptionKind {
+    NewHeads,
+    Logs,
+    NewPendingTransactions,
+}
+
+/// Payload of `eth_subscription` notification.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum RPCSubscriptionResult {
+    Header(RPCBlock),
+    Log(RPCLog),
+    TransactionHash(Hex<H256>),
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RPCTrace {
-    pub gas: Hex<Gas>,
+    pub gas: u64,
+    pub failed: bool,
     pub return_value: Bytes,
     pub struct_logs: Vec<RPCStep>,
 }
@@ -123,6 +166,9 @@ pub struct RPCTraceConfig {
     pub disable_stack: bool,
     #[serde(default)]
     pub disable_storage: bool,
+    /// Name of tracer, only "callTracer" is supported, struct logs are returned if not set.
+    #[serde(default)]
+    pub tracer: Option<String>,
     #[serde(default)]
     pub breakpoints: Option<RPCBreakpointConfig>,
 }
@@ -144,26 +190,44 @@ pub struct RPCSourceMapConfig {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RPCBlockTrace {
-    pub struct_logs: Vec<RPCStep>,
+    pub tx_hash: Hex<H256>,
+    pub result: Either<RPCTrace, RPCCallFrame>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RPCStep {
-    pub depth: usize,
-    pub error: String,
-    pub gas: Hex<Gas>,
-    pub gas_cost: Hex<Gas>,
-    pub op: u8,
     pub pc: usize,
-    pub opcode_pc: usize,
-    pub code_hash: Hex<H256>,
-    pub address: Hex<Address>,
-    pub breakpoint_index: Option<usize>,
-    pub breakpoint: Option<String>,
-    pub memory: Option<Vec<Bytes>>,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stack: Option<Vec<Hex<U256>>>,
-    pub storage: Option<HashMap<Hex<U256>, Hex<U256>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RPCCallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: Hex<Address>,
+    pub to: Hex<Address>,
+    pub value: Hex<U256>,
+    pub gas: Hex<u64>,
+    pub gas_used: Hex<u64>,
+    pub input: Bytes,
+    pub output: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<RPCCallFrame>,
 }
 
 // #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -186,6 +250,10 @@ pub struct RPCDumpAccountBasic {
 pub use basic::BasicERPC;
 pub use bridge::BridgeERPC;
 pub use chain_mock::ChainMockERPC;
+pub use debug::DebugERPC;
+pub use filters::FilterERPC;
+pub use personal::PersonalERPC;
+pub use txpool::TxPoolERPC;
 
 pub mod basic {
     use super::*;
@@ -405,6 +473,22 @@ pub mod bridge {
             data: Bytes,
         ) -> Result<Bytes, Error>;
 
+        #[rpc(meta, name = "eth_signTransaction")]
+        fn sign_transaction(
+            &self,
+            meta: Self::Metadata,
+            tx: RPCTransaction,
+        ) -> Result<RPCSignedTransaction, Error>;
+
+        /// Signs EIP-712 typed data, that can be passed as object or as json string.
+        #[rpc(meta, name = "eth_signTypedData_v4")]
+        fn sign_typed_data(
+            &self,
+            meta: Self::Metadata,
+            address: Hex<Address>,
+            data: serde_json::Value,
+        ) -> Result<Bytes, Error>;
+
         #[rpc(meta, name = "eth_sendTransaction")]
         fn send_transaction(
             &self,
@@ -424,36 +508,123 @@ pub mod bridge {
     }
 }
 
-// #[rpc]
-// pub trait FilterRPC {
-//     #[rpc(meta, name = "eth_newFilter")]
-//     fn new_filter(&self, RPCLogFilter) -> Result<String, Error>;
-//     #[rpc(meta, name = "eth_newBlockFilter")]
-//     fn new_block_filter(&self) -> Result<String, Error>;
-//     #[rpc(meta, name = "eth_newPendingTransactionFilter")]
-//     fn new_pending_transaction_filter(&self) -> Result<String, Error>;
-//     #[rpc(meta, name = "eth_uninstallFilter")]
-//     fn uninstall_filter(&self, String) -> Result<bool, Error>;
-
-//     #[rpc(meta, name = "eth_getFilterChanges")]
-//     fn filter_changes(&self, String) -> Result<Either<Vec<String>, Vec<RPCLog>>, Error>;
-//     #[rpc(meta, name = "eth_getFilterLogs")]
-//     fn filter_logs(&self, String) -> Result<Vec<RPCLog>, Error>;
-// }
+pub mod txpool {
+    use super::*;
+
+    #[rpc]
+    pub trait TxPoolERPC {
+        type Metadata;
+
+        #[rpc(meta, name = "txpool_content")]
+        fn content(&self, meta: Self::Metadata) -> Result<RPCTxPoolContent, Error>;
+
+        #[rpc(meta, name = "txpool_status")]
+        fn status(&self, meta: Self::Metadata) -> Result<RPCTxPoolStatus, Error>;
+    }
+}
+
+pub mod personal {
+    use super::*;
+
+    #[rpc]
+    pub trait PersonalERPC {
+        type Metadata;
+
+        #[rpc(meta, name = "personal_newAccount")]
+        fn new_account(
+            &self,
+            meta: Self::Metadata,
+            password: String,
+        ) -> Result<Hex<Address>, Error>;
+
+        /// Unlocks account for `duration` seconds, zero duration unlocks it until restart.
+        #[rpc(meta, name = "personal_unlockAccount")]
+        fn unlock_account(
+            &self,
+            meta: Self::Metadata,
+            address: Hex<Address>,
+            password: String,
+            duration: Option<u64>,
+        ) -> Result<bool, Error>;
+
+        #[rpc(meta, name = "personal_sign")]
+        fn sign(
+            &self,
+            meta: Self::Metadata,
+            data: Bytes,
+            address: Hex<Address>,
+            password: Option<String>,
+        ) -> Result<Bytes, Error>;
+    }
+}
+
+pub mod debug {
+    use super::*;
+
+    #[rpc]
+    pub trait DebugERPC {
+        type Metadata;
+
+        #[rpc(meta, name = "debug_traceTransaction")]
+        fn trace_transaction(
+            &self,
+            meta: Self::Metadata,
+            tx_hash: Hex<H256>,
+            config: Option<RPCTraceConfig>,
+        ) -> Result<Either<RPCTrace, RPCCallFrame>, Error>;
+
+        #[rpc(meta, name = "debug_traceBlockByNumber")]
+        fn trace_block_by_number(
+            &self,
+            meta: Self::Metadata,
+            block: String,
+            config: Option<RPCTraceConfig>,
+        ) -> Result<Vec<RPCBlockTrace>, Error>;
+    }
+}
+
+pub mod filters {
+    use super::*;
+
+    #[rpc]
+    pub trait FilterERPC {
+        type Metadata;
+
+        #[rpc(meta, name = "eth_newFilter")]
+        fn new_filter(
+            &self,
+            meta: Self::Metadata,
+            log_filter: RPCLogFilter,
+        ) -> Result<Hex<u64>, Error>;
+
+        #[rpc(meta, name = "eth_newBlockFilter")]
+        fn new_block_filter(&self, meta: Self::Metadata) -> Result<Hex<u64>, Error>;
+
+        #[rpc(meta, name = "eth_newPendingTransactionFilter")]
+        fn new_pending_transaction_filter(&self, meta: Self::Metadata) -> Result<Hex<u64>, Error>;
+
+        #[rpc(meta, name = "eth_uninstallFilter")]
+        fn uninstall_filter(&self, meta: Self::Metadata, id: Hex<u64>) -> Result<bool, Error>;
+
+        #[rpc(meta, name = "eth_getFilterChanges")]
+        fn filter_changes(
+            &self,
+            meta: Self::Metadata,
+            id: Hex<u64>,
+        ) -> Result<Either<Vec<Hex<H256>>, Vec<RPCLog>>, Error>;
+
+        #[rpc(meta, name = "eth_getFilterLogs")]
+        fn filter_logs(&self, meta: Self::Metadata, id: Hex<u64>) -> Result<Vec<RPCLog>, Error>;
+    }
+}
 
 // #[rpc]
 // pub trait DebugRPC {
 //     #[rpc(name = "debug_getBlockRlp")]
 //     fn block_rlp(&self, usize) -> Result<Bytes, Error>;
-//     #[rpc(name = "debug_traceTransaction")]
-//     fn trace_transaction(&self, Hex<H256>, Option<RPCTraceConfig>)
-//                             -> Result<RPCTrace, Error>;
 //     #[rpc(name = "debug_traceBlock")]
 //     fn trace_block(&self, Bytes, Option<RPCTraceConfig>)
 //                     -> Result<RPCBlockTrace, Error>;
-//     #[rpc(name = "debug_traceBlockByNumber")]
-//     fn trace_block_by_number(&self, usize, Option<RPCTraceConfig>)
-//                                 -> Result<RPCBlockTrace, Error>;
 //     #[rpc(name = "debug_traceBlockByHash")]
 //     fn trace_block_by_hash(&self, Hex<H256>, Option<RPCTraceConfig>)
 //                             -> Result<RPCBlockTrace, Error>;
@@ -489,18 +660,15 @@ pub mod bridge {
 // }
 
 impl RPCTransaction {
-    pub fn new_from_receipt(
-        receipt: evm_state::transactions::TransactionReceipt,
-        block_hash: H256,
-    ) -> Result<Self, crate::Error> {
-        let ref tx = receipt.transaction;
+    /// Transaction, that wasn't included in block yet.
+    pub fn new_pending(tx: &evm_state::transactions::Transaction) -> Result<Self, crate::Error> {
         let address = tx.address().map_err(|_| Error::InvalidParams)?.into();
 
         let (to, creates) = match tx.action {
             evm_state::transactions::TransactionAction::Call(_) => (Some(address), None),
             evm_state::transactions::TransactionAction::Create => (None, Some(address)),
         };
-        let hash = tx.signing_hash();
+        let hash = tx.tx_id_hash();
         Ok(RPCTransaction {
             from: Some(tx.caller().map_err(|_| Error::InvalidParams)?.into()),
             to,
@@ -511,9 +679,21 @@ impl RPCTransaction {
             data: Some(tx.input.clone().into()),
             nonce: Some(tx.nonce.into()),
             hash: Some(hash.into()),
+            transaction_index: None,
+            block_hash: None,
+            block_number: None,
+        })
+    }
+
+    pub fn new_from_receipt(
+        receipt: evm_state::transactions::TransactionReceipt,
+        block_hash: H256,
+    ) -> Result<Self, crate::Error> {
+        Ok(RPCTransaction {
             transaction_index: Some((receipt.index as usize).into()),
             block_hash: Some(block_hash.into()),
             block_number: Some(Hex(receipt.block_number.into())),
+            ..Self::new_pending(&receipt.transaction)?
         })
     }
 }
@@ -524,14 +704,14 @@ impl RPCReceipt {
         block_hash: H256,
     ) -> Result<Self, crate::Error> {
         let ref tx = receipt.transaction;
-        let address = tx.address().map_err(|_| Error::InvalidParams)?.into();
-        let (to, contract_address) = match tx.action {
-            evm_state::transactions::TransactionAction::Call(_) => (Some(address), None),
-            evm_state::transactions::TransactionAction::Create => (None, Some(address)),
+        let to = match tx.action {
+            evm_state::transactions::TransactionAction::Call(address) => Some(Hex(address)),
+            evm_state::transactions::TransactionAction::Create => None,
         };
-        let tx_hash = Hex(tx.signing_hash());
+        let tx_hash = Hex(tx.tx_id_hash());
         let tx_index: Hex<_> = (receipt.index as usize).into();
         let block_number = Hex(receipt.block_number.into());
+        let first_log_index = receipt.first_log_index as usize;
 
         let logs = receipt
             .logs
@@ -539,7 +719,7 @@ impl RPCReceipt {
             .enumerate()
             .map(|(id, log)| RPCLog {
                 removed: false,
-                log_index: Hex(id),
+                log_index: Hex(first_log_index + id),
                 transaction_hash: tx_hash.clone(),
                 transaction_index: tx_index.clone(),
                 block_hash: block_hash.into(),
@@ -550,25 +730,170 @@ impl RPCReceipt {
             })
             .collect();
 
+        let revert_reason = if receipt.revert_output.is_empty() {
+            None
+        } else {
+            Some(receipt.revert_output.into())
+        };
+
         Ok(RPCReceipt {
             to,
-            contract_address,
+            contract_address: receipt.contract_address.map(Hex),
             gas_used: receipt.used_gas.into(),
-            cumulative_gas_used: receipt.used_gas.into(),
+            cumulative_gas_used: receipt.cumulative_gas_used.into(),
             transaction_hash: tx_hash,
             transaction_index: tx_index,
             block_hash: block_hash.into(),
             block_number: block_number,
             logs,
+            logs_bloom: Hex(receipt.logs_bloom),
             status: Hex(if let evm_state::ExitReason::Succeed(_) = receipt.status {
                 1
             } else {
                 0
             }),
+            revert_reason,
         })
     }
 }
 
+impl RPCTopicFilter {
+    /// Converts positional topics of request into topics of `LogFilter`.
+    pub fn into_log_filter_topics(
+        topics: Option<Vec<Option<RPCTopicFilter>>>,
+    ) -> Result<Vec<Option<Vec<H256>>>, Error> {
+        let topics = topics.unwrap_or_default();
+        if topics.len() > LogFilter::MAX_TOPICS {
+            return Err(Error::InvalidParams);
+        }
+        Ok(topics
+            .into_iter()
+            .map(|topic| match topic? {
+                RPCTopicFilter::Single(topic) => Some(vec![topic.0]),
+                RPCTopicFilter::Or(topics) => {
+                    Some(topics.into_iter().map(|topic| topic.0).collect())
+                }
+            })
+            .collect())
+    }
+}
+
+impl RPCTraceConfig {
+    pub fn trace_config(&self) -> evm_state::tracing::TraceConfig {
+        evm_state::tracing::TraceConfig {
+            disable_memory: self.disable_memory,
+            disable_stack: self.disable_stack,
+            disable_storage: self.disable_storage,
+        }
+    }
+
+    /// Convert trace into format of requested tracer.
+    pub fn format_trace(
+        &self,
+        tx: &evm_state::transactions::Transaction,
+        trace: evm_state::tracing::Trace,
+    ) -> Result<Either<RPCTrace, RPCCallFrame>, Error> {
+        match self.tracer.as_deref() {
+            None => Ok(Either::Left(RPCTrace::from(trace))),
+            Some("callTracer") => Ok(Either::Right(RPCCallFrame::new_from_trace(tx, trace)?)),
+            Some(_) => Err(Error::InvalidParams),
+        }
+    }
+}
+
+impl From<evm_state::tracing::Trace> for RPCTrace {
+    fn from(trace: evm_state::tracing::Trace) -> Self {
+        RPCTrace {
+            gas: trace.used_gas,
+            failed: !matches!(trace.exit_reason, evm_state::ExitReason::Succeed(_)),
+            return_value: trace.return_value.into(),
+            struct_logs: trace.struct_logs.into_iter().map(RPCStep::from).collect(),
+        }
+    }
+}
+
+impl From<evm_state::tracing::StructLog> for RPCStep {
+    fn from(log: evm_state::tracing::StructLog) -> Self {
+        RPCStep {
+            pc: log.pc,
+            op: evm_state::tracing::opcode_name(log.op),
+            gas: log.gas,
+            gas_cost: log.gas_cost,
+            depth: log.depth,
+            error: log.error,
+            stack: log.stack.map(|stack| stack.into_iter().map(Hex).collect()),
+            memory: log
+                .memory
+                .map(|memory| memory.iter().map(hex::encode).collect()),
+            storage: log.storage.map(|storage| {
+                storage
+                    .iter()
+                    .map(|(key, value)| (hex::encode(key), hex::encode(value)))
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl RPCCallFrame {
+    /// Call tree of transaction, with gas of root call including intrinsic cost, like in geth.
+    pub fn new_from_trace(
+        tx: &evm_state::transactions::Transaction,
+        trace: evm_state::tracing::Trace,
+    ) -> Result<Self, Error> {
+        let frame = match trace.call {
+            Some(frame) => RPCCallFrame::from(frame),
+            // Transaction failed before execution, on intrinsic gas check.
+            None => RPCCallFrame {
+                call_type: match tx.action {
+                    evm_state::transactions::TransactionAction::Call(_) => "CALL",
+                    evm_state::transactions::TransactionAction::Create => "CREATE",
+                }
+                .to_string(),
+                from: Hex(tx.caller().map_err(|_| Error::InvalidParams)?),
+                to: Hex(tx.address().map_err(|_| Error::InvalidParams)?),
+                value: Hex(tx.value),
+                gas: Hex(0),
+                gas_used: Hex(0),
+                input: tx.input.clone().into(),
+                output: Bytes(vec![]),
+                error: Some(format!("{:?}", trace.exit_reason)),
+                calls: vec![],
+            },
+        };
+        Ok(RPCCallFrame {
+            gas: Hex(tx.gas_limit.low_u64()),
+            gas_used: Hex(trace.used_gas),
+            ..frame
+        })
+    }
+}
+
+impl From<evm_state::tracing::CallFrame> for RPCCallFrame {
+    fn from(frame: evm_state::tracing::CallFrame) -> Self {
+        use evm_state::tracing::CallType;
+        let call_type = match frame.call_type {
+            CallType::Call => "CALL",
+            CallType::CallCode => "CALLCODE",
+            CallType::DelegateCall => "DELEGATECALL",
+            CallType::StaticCall => "STATICCALL",
+            CallType::Create => "CREATE",
+        };
+        RPCCallFrame {
+            call_type: call_type.to_string(),
+            from: Hex(frame.from),
+            to: Hex(frame.to),
+            value: Hex(frame.value),
+            gas: Hex(frame.gas),
+            gas_used: Hex(frame.gas_used),
+            input: frame.input.into(),
+            output: frame.output.into(),
+            error: frame.error,
+            calls: frame.calls.into_iter().map(RPCCallFrame::from).collect(),
+        }
+    }
+}
+
 // #[derive(Serialize, Deserialize, Debug, Clone)]
 // #[serde(rename_all = "camelCase")]
 // pub struct RPCLog {
@@ -591,7 +916,7 @@ impl From<LogWithLocation> for RPCLog {
             transaction_index: (log.transaction_id as usize).into(),
             block_number: Hex(log.block_num.into()),
             block_hash: Hex(H256::zero()),
-            log_index: Hex(0),
+            log_index: Hex(log.log_index as usize),
             address: Hex(log.address),
             topics: log.topics.into_iter().map(Hex).collect(),
             data: Bytes(log.data),
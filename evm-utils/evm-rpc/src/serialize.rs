@@ -1,3 +1,4 @@
+use evm_state::Bloom;
 use primitive_types::{H160, H256, H512, U128, U256, U512};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, LowerHex};
@@ -121,6 +122,15 @@ impl FormatHex for H256 {
     }
 }
 
+impl FormatHex for Bloom {
+    fn format_hex(&self) -> String {
+        format!("0x{:x}", self)
+    }
+    fn from_hex(s: &str) -> Result<Self, Error> {
+        FromStr::from_str(&s).map_err(|_| ())
+    }
+}
+
 impl FormatHex for H160 {
     fn format_hex(&self) -> String {
         format!("0x{:x}", self)
@@ -279,6 +289,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hex_bloom_keeps_leading_zeros() {
+        let bloom = Bloom::from_low_u64_be(1);
+        let json = serde_json::to_string(&Hex(bloom)).unwrap();
+        assert_eq!(json.len(), 2 + 2 + 512);
+        assert_eq!(serde_json::from_str::<Hex<Bloom>>(&json).unwrap().0, bloom);
+    }
+
     #[test]
     fn bytes_single_digit() {
         assert_eq!("\"0x01\"", serde_json::to_string(&Bytes(vec![1])).unwrap());
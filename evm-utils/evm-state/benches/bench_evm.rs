@@ -7,6 +7,8 @@ use evm_state::*;
 use primitive_types::{H160 as Address, H256, U256};
 use sha3::{Digest, Keccak256};
 
+const CHAIN_ID: u64 = 0x77;
+
 fn name_to_key<S: AsRef<str>>(name: S) -> H160 {
     H256::from_slice(Keccak256::digest(name.as_ref().as_bytes()).as_slice()).into()
 }
@@ -43,8 +45,13 @@ fn criterion_benchmark(c: &mut Criterion) {
             state.set_account(address, AccountState::default());
         }
 
-        let mut executor =
-            Executor::with_config(state, evm::Config::istanbul(), u64::max_value(), 0);
+        let mut executor = Executor::with_config(
+            state,
+            evm::Config::istanbul(),
+            u64::max_value(),
+            CHAIN_ID,
+            0,
+        );
 
         let exit_reason = executor.with_executor(|executor| {
             executor.transact_create(
@@ -88,6 +95,7 @@ fn criterion_benchmark(c: &mut Criterion) {
             state.clone(),
             evm::Config::istanbul(),
             u64::max_value(),
+            CHAIN_ID,
             0,
         );
 
@@ -105,8 +113,13 @@ fn criterion_benchmark(c: &mut Criterion) {
         let contract_address = TransactionAction::Create.address(contract, U256::zero());
         let mut idx = 0;
         b.iter(|| {
-            let mut executor =
-                Executor::with_config(state.clone(), evm::Config::istanbul(), u64::max_value(), 0);
+            let mut executor = Executor::with_config(
+                state.clone(),
+                evm::Config::istanbul(),
+                u64::max_value(),
+                CHAIN_ID,
+                0,
+            );
 
             let exit_reason = black_box(executor.with_executor(|executor| {
                 executor.transact_call(
@@ -139,8 +152,13 @@ fn criterion_benchmark(c: &mut Criterion) {
                     state.set_account(address, AccountState::default());
                 }
 
-                let mut executor =
-                    Executor::with_config(state.clone(), evm::Config::istanbul(), u64::max_value(), 0);
+                let mut executor = Executor::with_config(
+                    state.clone(),
+                    evm::Config::istanbul(),
+                    u64::max_value(),
+                    CHAIN_ID,
+                    0,
+                );
                 let create_transaction_result = executor.with_executor(|executor| {
                     executor.transact_create(contract, U256::zero(), code.clone(), u64::max_value())
                 });
@@ -168,6 +186,7 @@ fn criterion_benchmark(c: &mut Criterion) {
                         state.clone(),
                         evm::Config::istanbul(),
                         u64::max_value(),
+                        CHAIN_ID,
                         0,
                     );
 
@@ -206,8 +225,13 @@ fn criterion_benchmark(c: &mut Criterion) {
 
         state.freeze();
 
-        let mut executor =
-            Executor::with_config(state.clone(), evm::Config::istanbul(), u64::max_value(), 0);
+        let mut executor = Executor::with_config(
+            state.clone(),
+            evm::Config::istanbul(),
+            u64::max_value(),
+            CHAIN_ID,
+            0,
+        );
 
         let exit_reason = executor.with_executor(|executor| {
             executor.transact_create(contract, U256::zero(), code.clone(), u64::max_value())
@@ -225,8 +249,13 @@ fn criterion_benchmark(c: &mut Criterion) {
         let contract_address = TransactionAction::Create.address(contract, U256::zero());
         let mut idx = 0;
         b.iter(|| {
-            let mut executor =
-                Executor::with_config(state.clone(), evm::Config::istanbul(), u64::max_value(), 0);
+            let mut executor = Executor::with_config(
+                state.clone(),
+                evm::Config::istanbul(),
+                u64::max_value(),
+                CHAIN_ID,
+                0,
+            );
 
             let exit_reason = executor.with_executor(|executor| {
                 executor.transact_call(
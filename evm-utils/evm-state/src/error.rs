@@ -1,7 +1,7 @@
 use snafu::{Backtrace, Snafu};
 
 use evm::ExitFatal;
-use primitive_types::H256;
+use primitive_types::{H256, U256};
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility = "pub(crate)")]
@@ -54,4 +54,78 @@ pub enum Error {
         size: u64,
         backtrace: Backtrace,
     },
+
+    #[snafu(display(
+        "Transaction {:x} signature is malformed or has high S value",
+        transaction_hash
+    ))]
+    InvalidSignature { transaction_hash: H256 },
+
+    #[snafu(display(
+        "Transaction {:x} is signed for chain {}, but current chain is {}",
+        transaction_hash,
+        tx_chain_id,
+        chain_id
+    ))]
+    WrongChainId {
+        transaction_hash: H256,
+        tx_chain_id: u64,
+        chain_id: u64,
+    },
+
+    #[snafu(display(
+        "Invalid nonce of transaction {:x}: state_nonce={}, tx_nonce={}",
+        transaction_hash,
+        state_nonce,
+        tx_nonce
+    ))]
+    NonceNotEqual {
+        transaction_hash: H256,
+        tx_nonce: U256,
+        state_nonce: U256,
+    },
+
+    #[snafu(display(
+        "Gas limit {} of transaction {:x} is out of bounds",
+        gas_limit,
+        transaction_hash
+    ))]
+    GasLimitOutOfBounds {
+        transaction_hash: H256,
+        gas_limit: U256,
+    },
+
+    #[snafu(display(
+        "Gas price {} of transaction {:x} is out of bounds",
+        gas_price,
+        transaction_hash
+    ))]
+    GasPriceOutOfBounds {
+        transaction_hash: H256,
+        gas_price: U256,
+    },
+
+    #[snafu(display(
+        "Gas price {} of transaction {:x} is lower than minimal gas price {}",
+        gas_price,
+        transaction_hash,
+        min_gas_price
+    ))]
+    GasPriceTooLow {
+        transaction_hash: H256,
+        gas_price: U256,
+        min_gas_price: U256,
+    },
+
+    #[snafu(display(
+        "Caller of transaction {:x} can't pay fee {}, balance={}",
+        transaction_hash,
+        max_fee,
+        balance
+    ))]
+    CantPayFee {
+        transaction_hash: H256,
+        max_fee: U256,
+        balance: U256,
+    },
 }
@@ -1,24 +1,40 @@
-use crate::EvmState;
+use crate::{EvmState, StateKey};
 use evm::backend::{Apply, Backend, Basic};
 use primitive_types::{H160, H256, U256};
+use std::{cell::RefCell, collections::BTreeSet};
 
 use crate::types::MemoryVicinity;
 
 pub struct EvmBackend {
     pub(crate) evm_state: EvmState,
     pub(crate) tx_info: MemoryVicinity,
+    // Keys of state, that was read by evm.
+    pub(crate) accessed: RefCell<BTreeSet<StateKey>>,
 }
 
 impl EvmBackend {
     pub fn new_from_state(evm_state: EvmState, tx_info: MemoryVicinity) -> Self {
-        Self { evm_state, tx_info }
+        Self {
+            evm_state,
+            tx_info,
+            accessed: RefCell::new(BTreeSet::new()),
+        }
     }
 
     fn tx_info(&self) -> &MemoryVicinity {
         &self.tx_info
     }
 
-    pub fn apply<A, I>(&mut self, values: A, delete_empty: bool)
+    pub(crate) fn access(&self, key: StateKey) {
+        if key.is_tracked() {
+            self.accessed.borrow_mut().insert(key);
+        }
+    }
+
+    /// Apply changes of execution to state.
+    /// Storage of destroyed and recreated accounts is wiped only if `wipe_storage` is set,
+    /// otherwise it's kept, as it was before storage wiping was activated.
+    pub fn apply<A, I>(&mut self, values: A, delete_empty: bool, wipe_storage: bool)
     where
         A: IntoIterator<Item = Apply<I>>,
         I: IntoIterator<Item = (H256, H256)>,
@@ -30,11 +46,10 @@ impl EvmBackend {
                     basic,
                     code,
                     storage,
-                    reset_storage: _,
+                    reset_storage,
                 } => {
                     log::debug!("Apply::Modify address = {}, basic = {:?}", address, basic);
                     // TODO: rollback on insert fail.
-                    // TODO: clear account storage on delete.
                     let is_empty = {
                         let mut account = self.evm_state.get_account(address).unwrap_or_default();
                         account.balance = basic.balance;
@@ -48,21 +63,12 @@ impl EvmBackend {
 
                         self.evm_state.accounts.insert(address, account);
 
-                        // TODO: Clear storage on reset_storage = true
-                        // if reset_storage {
-                        // 	account.storage = BTreeMap::new();
-                        // }
-
-                        // TODO: Clear zeros data (H256::default())
+                        if reset_storage && wipe_storage {
+                            self.evm_state.reset_storage(address);
+                        }
 
                         for (index, value) in storage {
-                            if value == H256::default() {
-                                self.evm_state.accounts_storage.remove((address, index));
-                            } else {
-                                self.evm_state
-                                    .accounts_storage
-                                    .insert((address, index), value);
-                            }
+                            self.evm_state.set_storage(address, index, value);
                         }
 
                         is_empty_state
@@ -70,10 +76,16 @@ impl EvmBackend {
 
                     if is_empty && delete_empty {
                         self.evm_state.accounts.remove(address);
+                        if wipe_storage {
+                            self.evm_state.reset_storage(address);
+                        }
                     }
                 }
                 Apply::Delete { address } => {
                     self.evm_state.accounts.remove(address);
+                    if wipe_storage {
+                        self.evm_state.reset_storage(address);
+                    }
                 }
             }
         }
@@ -119,10 +131,12 @@ impl Backend for EvmBackend {
     }
 
     fn exists(&self, address: H160) -> bool {
+        self.access(StateKey::Account(address));
         self.evm_state.get_account(address).is_some()
     }
 
     fn basic(&self, address: H160) -> Basic {
+        self.access(StateKey::Account(address));
         let a = self.evm_state.get_account(address).unwrap_or_default();
         Basic {
             balance: a.balance,
@@ -131,6 +145,7 @@ impl Backend for EvmBackend {
     }
 
     fn code(&self, address: H160) -> Vec<u8> {
+        self.access(StateKey::Account(address));
         self.evm_state
             .get_account(address)
             .map(|v| v.code)
@@ -138,6 +153,7 @@ impl Backend for EvmBackend {
     }
 
     fn storage(&self, address: H160, index: H256) -> H256 {
+        self.access(StateKey::Account(address));
         self.evm_state
             .get_storage(address, index)
             .unwrap_or_default()
@@ -1,6 +1,12 @@
 use std::{
-    any::type_name, borrow::Cow, collections::BTreeMap, fmt::Debug, fs, marker::PhantomData,
-    ops::Deref, path::Path,
+    any::type_name,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    fs,
+    marker::PhantomData,
+    ops::Deref,
+    path::Path,
 };
 
 use log::*;
@@ -8,9 +14,12 @@ use log::*;
 use crate::{
     mb_value::MaybeValue,
     persistent_types,
+    precompiles::NATIVE_TRANSFER_ADDR,
     storage::{PersistentAssoc, Result as StorageResult, VersionedStorage},
-    transactions::TransactionReceipt,
+    transactions::{LegacyTransactionReceipt, TransactionReceipt},
+    trie::{keccak, NodeStore, NodesDiff, Trie, EMPTY_TRIE_HASH},
     types::*,
+    BIG_TX_LIFETIME,
 };
 use serde::{Deserialize, Serialize};
 
@@ -20,10 +29,34 @@ pub type Storage = VersionedStorage<Slot>;
 // This allows us to save only changed data.
 persistent_types! {
     Accounts in "accounts" => H160 : AccountState,
-    AccountsStorage in "accounts_storage" => (H160, H256) : H256,
-    TransactionReceipts in "txs_receipts" => H256 : TransactionReceipt,
+    // Storage is keyed by generation of account storage, so it can be wiped without iteration.
+    AccountsStorage in "accounts_storage_by_generation" => (H160, u64, H256) : H256,
+    // Storage written before generations were introduced, read only, belongs to generation 0.
+    LegacyAccountsStorage in "accounts_storage" => (H160, H256) : H256,
+    StorageGenerations in "storage_generations" => H160 : u64,
+    // Receipts are keyed by canonical hash of transaction.
+    TransactionReceipts in "tx_receipts" => H256 : TransactionReceipt,
+    // Signing hash of transaction to canonical hash, keeps old index readable.
+    TransactionHashes in "tx_signing_hashes" => H256 : H256,
+    // Receipts of old blocks keyed by signing hash, read only.
+    LegacyTransactionReceipts in "txs_receipts" => H256 : LegacyTransactionReceipt,
+    // Canonical hash of transaction to signing hash of its legacy receipt, built on load.
+    LegacyTransactionHashes in "txs_legacy_hashes" => H256 : H256,
     TransactionsInBlock in "txs_in_block" => Slot : Vec<H256>, // TODO: Key is Slot or U256?
+    LogsBlooms in "logs_blooms" => Slot : Bloom,
+    // Native blockhash of frozen slot.
+    BlockSlots in "block_slots" => H256 : Slot,
     BigTransactions in "big_tx_storage" => H256 : BigTransactionStorage,
+    // Slot, at which unfinished big transaction is removed from storage.
+    BigTransactionsExpiresAt in "big_tx_expires_at" => H256 : Slot,
+    // Keys of big transactions, that expire at slot.
+    BigTransactionsExpiry in "big_tx_expiry" => Slot : Vec<H256>,
+    // Merkle Patricia Trie nodes by hash, shared between state and accounts storage tries.
+    TrieNodes in "trie_nodes" => H256 : Vec<u8>,
+    AccountsStorageRoots in "accounts_storage_roots" => H160 : H256,
+    StateRoot in "state_root" => () : H256,
+    // Slot, at which state trie was built from the whole state, visible on its forks.
+    StateRootBuiltAt in "state_root_built_at" => () : Slot,
 }
 
 #[derive(Debug)]
@@ -32,6 +65,8 @@ where
     M::Key: Ord,
 {
     map: BTreeMap<M::Key, MaybeValue<M::Value>>,
+    // Keys changed since layer was created or cloned.
+    changed: BTreeSet<M::Key>,
     is_frozen: bool,
     _type: PhantomData<M>,
 }
@@ -44,6 +79,7 @@ where
     pub fn empty() -> Self {
         Self {
             map: BTreeMap::new(),
+            changed: BTreeSet::new(),
             is_frozen: false,
             _type: PhantomData,
         }
@@ -51,7 +87,7 @@ where
 
     pub fn insert(&mut self, key: M::Key, value: M::Value)
     where
-        M::Key: Debug,
+        M::Key: Copy + Debug,
         M::Value: Debug,
     {
         assert!(
@@ -64,12 +100,13 @@ where
             key,
             value
         );
+        self.changed.insert(key);
         self.map.insert(key, MaybeValue::Value(value));
     }
 
     pub fn remove(&mut self, key: M::Key)
     where
-        M::Key: Debug,
+        M::Key: Copy + Debug,
     {
         assert!(
             !self.is_frozen,
@@ -77,9 +114,23 @@ where
         );
 
         trace!("layer :: {} removes {:?}", type_name::<M>(), key);
+        self.changed.insert(key);
         self.map.insert(key, MaybeValue::Removed);
     }
 
+    /// Copies values of keys, that was changed in `patch`.
+    fn merge_changes(&mut self, patch: &Self)
+    where
+        M::Key: Copy,
+        M::Value: Clone,
+    {
+        for key in &patch.changed {
+            if let Some(value) = patch.map.get(key) {
+                self.map.insert(*key, value.clone());
+            }
+        }
+    }
+
     fn freeze(&mut self) {
         self.is_frozen = true;
     }
@@ -116,16 +167,57 @@ where
     fn clone(&self) -> Self {
         Self {
             map: self.map.clone(),
+            changed: BTreeSet::new(),
             is_frozen: false,
             _type: PhantomData,
         }
     }
 }
+
+impl<M: PersistentAssoc> Layer<M>
+where
+    M::Key: Clone + Ord,
+    M::Value: Clone,
+{
+    // Unlike clone, keeps keys changed since layer was created, so snapshot can replace it.
+    fn snapshot(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            changed: self.changed.clone(),
+            is_frozen: self.is_frozen,
+            _type: PhantomData,
+        }
+    }
+}
 #[derive(Default, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct BigTransactionStorage {
     pub tx_chunks: Vec<u8>,
 }
 
+/// Part of evm state, that is tracked to detect conflicts between concurrent executors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StateKey {
+    /// Account with its code and storage.
+    Account(H160),
+    BigTransaction(H256),
+}
+
+impl StateKey {
+    // Balance of native transfer address is burned by the end of each transaction,
+    // so its state is the same between transactions, and it can't cause conflicts.
+    pub(crate) fn is_tracked(&self) -> bool {
+        !matches!(self, StateKey::Account(address) if *address == NATIVE_TRANSFER_ADDR)
+    }
+}
+
+/// Changes made by executor on top of state it was created from,
+/// with keys of state it accessed during execution.
+#[derive(Debug)]
+pub struct EvmPatch {
+    pub(crate) state: EvmState,
+    pub(crate) accessed: BTreeSet<StateKey>,
+}
+
 #[derive(Clone, Debug)] // TODO: Debug
 pub struct EvmState {
     pub(crate) current_slot: Slot,
@@ -133,10 +225,20 @@ pub struct EvmState {
 
     pub(crate) accounts: Layer<Accounts>,
     pub(crate) accounts_storage: Layer<AccountsStorage>,
+    pub(crate) storage_generations: Layer<StorageGenerations>,
     pub(crate) txs_receipts: Layer<TransactionReceipts>,
+    pub(crate) txs_hashes: Layer<TransactionHashes>,
     pub(crate) txs_in_block: Layer<TransactionsInBlock>,
+    pub(crate) logs_blooms: Layer<LogsBlooms>,
+    pub(crate) block_slots: Layer<BlockSlots>,
 
     pub(crate) big_transactions: Layer<BigTransactions>,
+    pub(crate) big_transactions_expires_at: Layer<BigTransactionsExpiresAt>,
+    pub(crate) big_transactions_expiry: Layer<BigTransactionsExpiry>,
+
+    // Count of patches merged during current slot, and number of the last patch that changed key.
+    pub(crate) merged_patches: u64,
+    pub(crate) changed_by_patch: BTreeMap<StateKey, u64>,
 
     pub storage: Storage,
 }
@@ -161,9 +263,17 @@ impl Default for EvmState {
 
             accounts: Layer::empty(),
             accounts_storage: Layer::empty(),
+            storage_generations: Layer::empty(),
             txs_receipts: Layer::empty(),
+            txs_hashes: Layer::empty(),
             txs_in_block: Layer::empty(),
+            logs_blooms: Layer::empty(),
+            block_slots: Layer::empty(),
             big_transactions: Layer::empty(),
+            big_transactions_expires_at: Layer::empty(),
+            big_transactions_expiry: Layer::empty(),
+            merged_patches: 0,
+            changed_by_patch: BTreeMap::new(),
 
             storage,
         }
@@ -171,16 +281,53 @@ impl Default for EvmState {
 }
 
 impl EvmState {
+    /// Copy of state with all tracked changes, that can be restored to revert later changes.
+    pub(crate) fn snapshot(&self) -> Self {
+        Self {
+            current_slot: self.current_slot,
+            previous_slot: self.previous_slot,
+            accounts: self.accounts.snapshot(),
+            accounts_storage: self.accounts_storage.snapshot(),
+            storage_generations: self.storage_generations.snapshot(),
+            txs_receipts: self.txs_receipts.snapshot(),
+            txs_hashes: self.txs_hashes.snapshot(),
+            txs_in_block: self.txs_in_block.snapshot(),
+            logs_blooms: self.logs_blooms.snapshot(),
+            block_slots: self.block_slots.snapshot(),
+            big_transactions: self.big_transactions.snapshot(),
+            big_transactions_expires_at: self.big_transactions_expires_at.snapshot(),
+            big_transactions_expiry: self.big_transactions_expiry.snapshot(),
+            merged_patches: self.merged_patches,
+            changed_by_patch: self.changed_by_patch.clone(),
+            storage: self.storage.clone(),
+        }
+    }
+
     pub fn freeze(&mut self) {
         debug!("freezing evm state (slot {})", self.current_slot);
+        self.update_state_root()
+            .expect("Unable to update EVM state root");
+        // State without parent is built from empty trie, so its root covers the whole state.
+        if self.previous_slot.is_none() {
+            self.storage
+                .typed::<StateRootBuiltAt>()
+                .insert_with(self.current_slot, (), self.current_slot.into())
+                .expect("Unable to mark EVM state root as built");
+        }
         self.dump_all()
             .expect("Unable to dump EVM state layers into storage");
 
         self.accounts.freeze();
         self.accounts_storage.freeze();
+        self.storage_generations.freeze();
         self.txs_receipts.freeze();
+        self.txs_hashes.freeze();
         self.txs_in_block.freeze();
+        self.logs_blooms.freeze();
+        self.block_slots.freeze();
         self.big_transactions.freeze();
+        self.big_transactions_expires_at.freeze();
+        self.big_transactions_expiry.freeze();
 
         debug!(
             "new slot {} with previous {:?}",
@@ -193,6 +340,7 @@ impl EvmState {
     }
 
     pub fn squash(&mut self) {
+        let _maintenance_lock = self.storage.maintenance_lock();
         let track: Vec<Slot> = self.storage.track_of(self.current_slot).collect();
         debug!("track of slot {} is {:?}", self.current_slot, track);
 
@@ -200,22 +348,6 @@ impl EvmState {
             info!("squashing evm state into slot {}", track[0]);
             squash_state(&self.storage, &track).expect("Unable to squash stored state");
         }
-
-        #[rustfmt::skip]
-        fn squash_state(storage: &Storage, track: &[Slot]) -> anyhow::Result<()> {
-            assert!(track.len() >= 2); // two versions at least
-            assert_eq!(storage.previous_of(track[track.len()-1])?, None);
-
-            storage.typed::<Accounts>().squash_into_rev_pass(&track)?;
-            storage.typed::<AccountsStorage>().squash_into_rev_pass(&track)?;
-            storage.typed::<TransactionReceipts>().squash_into_rev_pass(&track)?;
-            storage.typed::<TransactionsInBlock>().squash_into_rev_pass(&track)?;
-            storage.typed::<BigTransactions>().squash_into_rev_pass(&track)?;
-            // Mark current version as the first one
-            storage.stomp(track[0])?;
-            storage.flush()?;
-            Ok(())
-        }
     }
 
     // TODO: dump all
@@ -228,30 +360,75 @@ impl EvmState {
         // TODO: assert that all these maps are empty
         let accounts = self.accounts.clone();
         let accounts_storage = self.accounts_storage.clone();
+        let storage_generations = self.storage_generations.clone();
         let txs_receipts = self.txs_receipts.clone();
+        let txs_hashes = self.txs_hashes.clone();
         let txs_in_block = self.txs_in_block.clone();
+        let logs_blooms = self.logs_blooms.clone();
+        let block_slots = self.block_slots.clone();
         let big_transactions = self.big_transactions.clone();
+        let big_transactions_expires_at = self.big_transactions_expires_at.clone();
+        let big_transactions_expiry = self.big_transactions_expiry.clone();
 
-        Some(Self {
+        let mut state = Self {
             current_slot: new_slot,
             previous_slot: Some(self.current_slot),
 
             accounts,
             accounts_storage,
+            storage_generations,
             txs_receipts,
+            txs_hashes,
             txs_in_block,
+            logs_blooms,
+            block_slots,
             big_transactions,
+            big_transactions_expires_at,
+            big_transactions_expiry,
+            merged_patches: 0,
+            changed_by_patch: BTreeMap::new(),
             storage: self.storage.clone(),
-        })
+        };
+        state.remove_expired_big_txs(self.current_slot);
+        Some(state)
+    }
+
+    /// Removes big transactions, that expired in slots after `parent_slot`.
+    fn remove_expired_big_txs(&mut self, parent_slot: Slot) {
+        // Allocations made before parent slot was frozen can't expire later than this.
+        let last_slot = self
+            .current_slot
+            .min(parent_slot.saturating_add(BIG_TX_LIFETIME));
+        for slot in parent_slot.saturating_add(1)..=last_slot {
+            let keys = match self.get_big_txs_expiring_at(slot) {
+                Some(keys) => keys,
+                None => continue,
+            };
+            for key in keys {
+                // Transaction could be already executed, or reclaimed and allocated again.
+                if self.get_big_tx_expires_at(key) == Some(slot) {
+                    debug!("big transaction {:?} expired at slot {}", key, slot);
+                    self.big_transactions.remove(key);
+                    self.big_transactions_expires_at.remove(key);
+                }
+            }
+            self.big_transactions_expiry.remove(slot);
+        }
     }
 
     #[rustfmt::skip]
     fn dump_all(&mut self) -> anyhow::Result<()> {
         self.accounts.dump_into(&self.storage, self.current_slot)?;
         self.accounts_storage.dump_into(&self.storage, self.current_slot)?;
+        self.storage_generations.dump_into(&self.storage, self.current_slot)?;
         self.txs_receipts.dump_into(&self.storage, self.current_slot)?;
+        self.txs_hashes.dump_into(&self.storage, self.current_slot)?;
         self.txs_in_block.dump_into(&self.storage, self.current_slot)?;
+        self.logs_blooms.dump_into(&self.storage, self.current_slot)?;
+        self.block_slots.dump_into(&self.storage, self.current_slot)?;
         self.big_transactions.dump_into(&self.storage, self.current_slot)?;
+        self.big_transactions_expires_at.dump_into(&self.storage, self.current_slot)?;
+        self.big_transactions_expiry.dump_into(&self.storage, self.current_slot)?;
         Ok(())
     }
 
@@ -269,47 +446,415 @@ impl EvmState {
         if let Some(mb_value) = layer.map.get(&key) {
             Option::from(mb_value.by_ref()).map(Cow::Borrowed)
         } else {
-            let lookup_slot = if self.storage.is_exists(self.current_slot).unwrap() {
-                Some(self.current_slot)
+            self.lookup_storage::<M>(key).map(Cow::Owned)
+        }
+    }
+
+    /// Value of key in storage, at version of current slot or its parent.
+    /// Panics if storage can't be read, missing data would silently change execution result.
+    fn lookup_storage<M: PersistentAssoc>(&self, key: M::Key) -> Option<M::Value>
+    where
+        M::Key: Copy + Ord + Debug,
+        M::Value: Clone + Debug,
+    {
+        let lookup_slot = if self
+            .storage
+            .is_exists(self.current_slot)
+            .unwrap_or_else(|err| {
+                panic!(
+                    "Storage version {} lookup error: {:?}",
+                    self.current_slot, err
+                )
+            }) {
+            Some(self.current_slot)
+        } else {
+            self.previous_slot
+        };
+        let slot = lookup_slot?;
+
+        let mb_value = self
+            .storage
+            .typed::<M>()
+            .get_for(slot, key)
+            .unwrap_or_else(|err| {
+                panic!(
+                    "Storage ({} :: Key {} => Value {}) lookup error for key {:?}: {:?}",
+                    type_name::<M>(),
+                    type_name::<M::Key>(),
+                    type_name::<M::Value>(),
+                    key,
+                    err
+                )
+            });
+        match mb_value {
+            Some(mb_value) => {
+                debug!(
+                    "{}: key {:?} was found in storage, value: {:?}",
+                    type_name::<M>(),
+                    key,
+                    &mb_value
+                );
+                Option::from(mb_value)
+            }
+            None => {
+                debug!(
+                    "{}: key {:?} was not found in storage",
+                    type_name::<M>(),
+                    key
+                );
+                None
+            }
+        }
+    }
+
+    /// Applies accounts and storage changes of current layers to state trie,
+    /// and saves new trie nodes and roots for current slot.
+    fn update_state_root(&mut self) -> anyhow::Result<()> {
+        if self.accounts.map.is_empty()
+            && self.accounts_storage.map.is_empty()
+            && self.storage_generations.map.is_empty()
+        {
+            return Ok(());
+        }
+
+        // Storage of wiped accounts is rebuilt from empty trie.
+        let wiped: BTreeSet<H160> = self.storage_generations.map.keys().copied().collect();
+        let mut changed_storages: BTreeMap<H160, Vec<(H256, Option<H256>)>> =
+            wiped.iter().map(|address| (*address, vec![])).collect();
+        let mut generations = BTreeMap::new();
+        for (&(address, generation, index), value) in &self.accounts_storage.map {
+            let current_generation = *generations
+                .entry(address)
+                .or_insert_with(|| self.storage_generation(address));
+            // Storage was wiped after this change.
+            if generation != current_generation {
+                continue;
+            }
+            changed_storages
+                .entry(address)
+                .or_default()
+                .push((index, Option::from(*value)));
+        }
+
+        let mut nodes = NodesDiff::new();
+        let mut storage_roots = BTreeMap::new();
+
+        for (address, changes) in changed_storages {
+            let root = if wiped.contains(&address) {
+                EMPTY_TRIE_HASH
             } else {
-                self.previous_slot
+                self.storage_root(address)
             };
+            let mut trie = Trie::new(self, root);
+            for (index, value) in changes {
+                let key = keccak(index.as_bytes());
+                match value {
+                    Some(value) if !value.is_zero() => trie.insert(
+                        key.as_bytes(),
+                        rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec(),
+                    ),
+                    _ => trie.remove(key.as_bytes()),
+                }
+            }
+            let (root, diff) = trie.commit();
+            nodes.extend(diff);
+            storage_roots.insert(address, MaybeValue::Value(root));
+        }
 
-            if let Some(slot) = lookup_slot {
-                if let Some(mb_value) =
-                    self.storage
-                        .typed::<M>()
-                        .get_for(slot, key)
-                        .unwrap_or_else(|err| {
-                            panic!(
-                                "Storage ({} :: Key {} => Value {}) lookup error: {:?}",
-                                type_name::<M>(),
-                                type_name::<M::Key>(),
-                                type_name::<M::Value>(),
-                                err
-                            );
-                        })
-                {
-                    debug!(
-                        "{}: key {:?} was found in storage, value: {:?}",
-                        type_name::<M>(),
-                        key,
-                        &mb_value
-                    );
-                    Option::from(mb_value).map(Cow::Owned)
-                } else {
-                    debug!(
-                        "{}: key {:?} was not found in storage",
-                        type_name::<M>(),
-                        key
-                    );
-                    None
+        let touched: BTreeSet<H160> = self
+            .accounts
+            .map
+            .keys()
+            .chain(storage_roots.keys())
+            .copied()
+            .collect();
+
+        let mut trie = Trie::new(self, self.state_root());
+        for address in touched {
+            let key = keccak(address.as_bytes());
+            match self.get_account(address) {
+                Some(account) => {
+                    let storage_root = match storage_roots.get(&address) {
+                        Some(MaybeValue::Value(root)) => *root,
+                        _ => self.storage_root(address),
+                    };
+                    trie.insert(key.as_bytes(), account_rlp(&account, storage_root));
                 }
-            } else {
-                None
+                None => {
+                    trie.remove(key.as_bytes());
+                    storage_roots.insert(address, MaybeValue::Removed);
+                }
+            }
+        }
+        let (state_root, diff) = trie.commit();
+        nodes.extend(diff);
+
+        debug!(
+            "state root of slot {} is {:?}, {} new trie nodes",
+            self.current_slot,
+            state_root,
+            nodes.len()
+        );
+
+        let trie_nodes = self.storage.typed::<TrieNodes>();
+        for (hash, node) in nodes {
+            trie_nodes.insert_with(self.current_slot, hash, node.into())?;
+        }
+        let accounts_storage_roots = self.storage.typed::<AccountsStorageRoots>();
+        for (address, root) in storage_roots {
+            accounts_storage_roots.insert_with(self.current_slot, address, root)?;
+        }
+        self.storage
+            .typed::<StateRoot>()
+            .insert_with(self.current_slot, (), state_root.into())?;
+
+        Ok(())
+    }
+}
+
+/// Indexes receipts stored in legacy format by canonical hash of their transactions.
+/// Entries are written to the version of receipt, so they are visible in the same forks.
+fn index_legacy_receipts(storage: &Storage) -> anyhow::Result<()> {
+    let versions: Vec<Slot> = storage.versions().map(|(version, _)| version).collect();
+    let receipts = storage.typed::<LegacyTransactionReceipts>();
+    let hashes = storage.typed::<LegacyTransactionHashes>();
+    let mut indexed = 0;
+    for version in versions {
+        // Version was indexed on previous load.
+        if hashes.prefix_iter_for(version)?.next().is_some() {
+            continue;
+        }
+        for (signing_hash, receipt) in receipts.prefix_iter_for(version)? {
+            if let MaybeValue::Value(receipt) = receipt {
+                hashes.insert_with(
+                    version,
+                    receipt.transaction.tx_id_hash(),
+                    MaybeValue::Value(signing_hash),
+                )?;
+                indexed += 1;
+            }
+        }
+    }
+    if indexed > 0 {
+        info!("{} legacy receipts were indexed by canonical hash", indexed);
+    }
+    Ok(())
+}
+
+/// Values of column, visible at version `slot`.
+fn visible_at<M: PersistentAssoc>(
+    storage: &Storage,
+    slot: Slot,
+) -> anyhow::Result<BTreeMap<M::Key, M::Value>>
+where
+    M::Key: Copy + Ord,
+{
+    let column = storage.typed::<M>();
+    let mut values = BTreeMap::new();
+    for version in storage.track_of(slot) {
+        for (key, value) in column.prefix_iter_for(version)? {
+            values.entry(key).or_insert(value);
+        }
+    }
+    Ok(values
+        .into_iter()
+        .filter_map(|(key, value)| Option::from(value).map(|value| (key, value)))
+        .collect())
+}
+
+/// Builds state trie of the whole state at version `slot`, if its root wasn't built yet.
+///
+/// State root is updated incrementally, from accounts changed in slot,
+/// so state written before trie was introduced is added to the trie once, on load.
+fn build_state_root(storage: &Storage, slot: Slot) -> anyhow::Result<()> {
+    if !storage.is_exists(slot)?
+        || storage
+            .typed::<StateRootBuiltAt>()
+            .get_for(slot, ())?
+            .is_some()
+    {
+        return Ok(());
+    }
+    info!("building evm state trie at slot {}", slot);
+
+    let accounts = visible_at::<Accounts>(storage, slot)?;
+    let generations = visible_at::<StorageGenerations>(storage, slot)?;
+    let mut accounts_storage: BTreeMap<H160, BTreeMap<H256, H256>> = BTreeMap::new();
+    for ((address, index), value) in visible_at::<LegacyAccountsStorage>(storage, slot)? {
+        if !generations.contains_key(&address) {
+            accounts_storage
+                .entry(address)
+                .or_default()
+                .insert(index, value);
+        }
+    }
+    for ((address, generation, index), value) in visible_at::<AccountsStorage>(storage, slot)? {
+        if generations.get(&address).copied().unwrap_or_default() == generation {
+            // Values of generation 0 hide legacy ones.
+            accounts_storage
+                .entry(address)
+                .or_default()
+                .insert(index, value);
+        }
+    }
+
+    let store = NodesDiff::new();
+    let mut nodes = NodesDiff::new();
+    let mut storage_roots: BTreeMap<H160, MaybeValue<H256>> =
+        visible_at::<AccountsStorageRoots>(storage, slot)?
+            .into_iter()
+            .map(|(address, _)| (address, MaybeValue::Removed))
+            .collect();
+    let mut state_trie = Trie::new(&store, EMPTY_TRIE_HASH);
+    for (address, account) in &accounts {
+        let mut storage_trie = Trie::new(&store, EMPTY_TRIE_HASH);
+        for (index, value) in accounts_storage.remove(address).unwrap_or_default() {
+            if !value.is_zero() {
+                storage_trie.insert(
+                    keccak(index.as_bytes()).as_bytes(),
+                    rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec(),
+                );
+            }
+        }
+        let (storage_root, diff) = storage_trie.commit();
+        nodes.extend(diff);
+        storage_roots.insert(*address, MaybeValue::Value(storage_root));
+        state_trie.insert(
+            keccak(address.as_bytes()).as_bytes(),
+            account_rlp(account, storage_root),
+        );
+    }
+    let (state_root, diff) = state_trie.commit();
+    nodes.extend(diff);
+
+    let trie_nodes = storage.typed::<TrieNodes>();
+    for (hash, node) in nodes {
+        trie_nodes.insert_with(slot, hash, node.into())?;
+    }
+    let accounts_storage_roots = storage.typed::<AccountsStorageRoots>();
+    for (address, root) in storage_roots {
+        accounts_storage_roots.insert_with(slot, address, root)?;
+    }
+    storage
+        .typed::<StateRoot>()
+        .insert_with(slot, (), state_root.into())?;
+    storage
+        .typed::<StateRootBuiltAt>()
+        .insert_with(slot, (), slot.into())?;
+    storage.flush()?;
+    info!(
+        "evm state trie at slot {} is built, {} accounts, root {:?}",
+        slot,
+        accounts.len(),
+        state_root
+    );
+    Ok(())
+}
+
+#[rustfmt::skip]
+fn squash_state(storage: &Storage, track: &[Slot]) -> anyhow::Result<()> {
+    assert!(track.len() >= 2); // two versions at least
+    assert_eq!(storage.previous_of(track[track.len()-1])?, None);
+
+    storage.typed::<Accounts>().squash_into_rev_pass(&track)?;
+    storage.typed::<AccountsStorage>().squash_into_rev_pass(&track)?;
+    storage.typed::<LegacyAccountsStorage>().squash_into_rev_pass(&track)?;
+    storage.typed::<StorageGenerations>().squash_into_rev_pass(&track)?;
+    storage.typed::<TransactionReceipts>().squash_into_rev_pass(&track)?;
+    storage.typed::<TransactionHashes>().squash_into_rev_pass(&track)?;
+    storage.typed::<LegacyTransactionReceipts>().squash_into_rev_pass(&track)?;
+    storage.typed::<LegacyTransactionHashes>().squash_into_rev_pass(&track)?;
+    storage.typed::<TransactionsInBlock>().squash_into_rev_pass(&track)?;
+    storage.typed::<LogsBlooms>().squash_into_rev_pass(&track)?;
+    storage.typed::<BlockSlots>().squash_into_rev_pass(&track)?;
+    storage.typed::<BigTransactions>().squash_into_rev_pass(&track)?;
+    storage.typed::<BigTransactionsExpiresAt>().squash_into_rev_pass(&track)?;
+    storage.typed::<BigTransactionsExpiry>().squash_into_rev_pass(&track)?;
+    storage.typed::<TrieNodes>().squash_into_rev_pass(&track)?;
+    storage.typed::<AccountsStorageRoots>().squash_into_rev_pass(&track)?;
+    storage.typed::<StateRoot>().squash_into_rev_pass(&track)?;
+    storage.typed::<StateRootBuiltAt>().squash_into_rev_pass(&track)?;
+    // Mark current version as the first one
+    storage.stomp(track[0])?;
+    storage.flush()?;
+    Ok(())
+}
+
+/// Result of `prune_storage`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PruneStats {
+    /// Versions of forks, that don't descend from root.
+    pub dead_versions: usize,
+    /// Rooted versions, that were squashed into the oldest retained one.
+    pub squashed_versions: usize,
+    pub sst_files_size: u64,
+    pub live_data_size: u64,
+}
+
+/// Drops versions of forks abandoned by `root`,
+/// and squashes rooted versions older than `retention` slots before `root`.
+///
+/// Data of squashed slots stays readable on the fork of root,
+/// but state of these slots can't be requested anymore.
+pub fn prune_storage(storage: &Storage, root: Slot, retention: Slot) -> anyhow::Result<PruneStats> {
+    let mut stats = PruneStats::default();
+    let maintenance_lock = storage.maintenance_lock();
+    if storage.is_exists(root)? {
+        let rooted: Vec<Slot> = storage.track_of(root).collect();
+        let is_rooted: BTreeSet<Slot> = rooted.iter().copied().collect();
+
+        // Every descendant of root passes through it, as parent slot is always lower than child slot.
+        // Versions without known ancestor at or below root are kept.
+        let dead: Vec<Slot> = storage
+            .versions()
+            .map(|(version, _)| version)
+            .filter(|version| !is_rooted.contains(version))
+            .filter(|version| {
+                storage
+                    .track_of(*version)
+                    .find(|ancestor| *ancestor <= root)
+                    .map_or(false, |ancestor| ancestor != root)
+            })
+            .collect();
+        if !dead.is_empty() {
+            debug!("removing evm state versions of dead forks: {:?}", dead);
+            storage.remove_versions(COLUMN_NAMES, &dead)?;
+            stats.dead_versions = dead.len();
+        }
+
+        let oldest_retained = root.saturating_sub(retention);
+        if let Some(target) = rooted.iter().rev().find(|slot| **slot >= oldest_retained) {
+            let track: Vec<Slot> = storage.track_of(*target).collect();
+            if track.len() > 1 {
+                info!("squashing evm state history into slot {}", target);
+                squash_state(storage, &track)?;
+                storage.remove_versions(COLUMN_NAMES, &track[1..])?;
+                stats.squashed_versions = track.len() - 1;
             }
         }
     }
+    drop(maintenance_lock);
+
+    let (sst_files_size, live_data_size) = storage.columns_size(COLUMN_NAMES)?;
+    stats.sst_files_size = sst_files_size;
+    stats.live_data_size = live_data_size;
+    Ok(stats)
+}
+
+impl NodeStore for EvmState {
+    fn get_node(&self, hash: H256) -> Option<Vec<u8>> {
+        self.lookup_storage::<TrieNodes>(hash)
+    }
+}
+
+/// Account representation in state trie: `rlp([nonce, balance, storage_root, code_hash])`.
+fn account_rlp(account: &AccountState, storage_root: H256) -> Vec<u8> {
+    let mut stream = rlp::RlpStream::new_list(4);
+    stream.append(&account.nonce);
+    stream.append(&account.balance);
+    stream.append(&storage_root);
+    stream.append(&keccak(&account.code));
+    stream.out().to_vec()
 }
 
 impl EvmState {
@@ -330,6 +875,8 @@ impl EvmState {
             slot
         );
         let storage = Storage::open_persistent(path, COLUMN_NAMES)?;
+        index_legacy_receipts(&storage)?;
+        build_state_root(&storage, slot)?;
         let previous_slot = storage.previous_of(slot)?;
         debug!(
             "storage reports: previous of {} is {:?}",
@@ -342,9 +889,17 @@ impl EvmState {
 
             accounts: Layer::empty(),
             accounts_storage: Layer::empty(),
+            storage_generations: Layer::empty(),
             txs_receipts: Layer::empty(),
+            txs_hashes: Layer::empty(),
             txs_in_block: Layer::empty(),
+            logs_blooms: Layer::empty(),
+            block_slots: Layer::empty(),
             big_transactions: Layer::empty(),
+            big_transactions_expires_at: Layer::empty(),
+            big_transactions_expiry: Layer::empty(),
+            merged_patches: 0,
+            changed_by_patch: BTreeMap::new(),
             storage,
         })
     }
@@ -354,18 +909,199 @@ impl EvmState {
         self.current_slot
     }
 
+    /// Root of state trie, as it was calculated on last freeze.
+    pub fn state_root(&self) -> H256 {
+        self.lookup_storage::<StateRoot>(())
+            .unwrap_or(EMPTY_TRIE_HASH)
+    }
+
+    /// Root of state trie at frozen slot, `None` if slot is unknown to storage.
+    pub fn get_state_root(&self, slot: Slot) -> Option<H256> {
+        if !self.storage.is_exists(slot).unwrap() {
+            return None;
+        }
+        let root = self
+            .storage
+            .typed::<StateRoot>()
+            .get_for(slot, ())
+            .expect("Unable to retrieve state root from storage")
+            .and_then(Option::from);
+        Some(root.unwrap_or(EMPTY_TRIE_HASH))
+    }
+
+    /// Read-only view of state, as it was before execution of transactions in `slot`.
+    /// Returns `None` if slot or its parent is unknown to storage.
+    pub fn state_before_slot(&self, slot: Slot) -> Option<Self> {
+        let slot_is_frozen = self
+            .storage
+            .is_exists(slot)
+            .expect("Unable to retrieve version info from storage");
+        let parent = if slot_is_frozen {
+            self.storage
+                .previous_of(slot)
+                .expect("Unable to retrieve version info from storage")?
+        } else if slot == self.current_slot {
+            self.previous_slot?
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            current_slot: parent,
+            previous_slot: self
+                .storage
+                .previous_of(parent)
+                .expect("Unable to retrieve version info from storage"),
+
+            accounts: Layer::empty(),
+            accounts_storage: Layer::empty(),
+            storage_generations: Layer::empty(),
+            txs_receipts: Layer::empty(),
+            txs_hashes: Layer::empty(),
+            txs_in_block: Layer::empty(),
+            logs_blooms: Layer::empty(),
+            block_slots: Layer::empty(),
+            big_transactions: Layer::empty(),
+            big_transactions_expires_at: Layer::empty(),
+            big_transactions_expiry: Layer::empty(),
+            merged_patches: 0,
+            changed_by_patch: BTreeMap::new(),
+
+            storage: self.storage.clone(),
+        })
+    }
+
+    /// Read-only view of state, as it was after execution of transactions in `slot`,
+    /// on the fork of current state. Skipped slot resolves to its nearest ancestor.
+    /// Returns `None` if `slot` is older than any version kept in storage.
+    pub fn state_at_slot(&self, slot: Slot) -> Option<Self> {
+        if slot >= self.current_slot {
+            return Some(self.clone());
+        }
+        let last_frozen = if self
+            .storage
+            .is_exists(self.current_slot)
+            .expect("Unable to retrieve version info from storage")
+        {
+            self.current_slot
+        } else {
+            self.previous_slot?
+        };
+        let version = self
+            .storage
+            .track_of(last_frozen)
+            .find(|version| *version <= slot)?;
+
+        Some(Self {
+            current_slot: version,
+            previous_slot: self
+                .storage
+                .previous_of(version)
+                .expect("Unable to retrieve version info from storage"),
+
+            accounts: Layer::empty(),
+            accounts_storage: Layer::empty(),
+            storage_generations: Layer::empty(),
+            txs_receipts: Layer::empty(),
+            txs_hashes: Layer::empty(),
+            txs_in_block: Layer::empty(),
+            logs_blooms: Layer::empty(),
+            block_slots: Layer::empty(),
+            big_transactions: Layer::empty(),
+            big_transactions_expires_at: Layer::empty(),
+            big_transactions_expiry: Layer::empty(),
+            merged_patches: 0,
+            changed_by_patch: BTreeMap::new(),
+
+            storage: self.storage.clone(),
+        })
+    }
+
+    pub fn storage_root(&self, address: H160) -> H256 {
+        self.lookup_storage::<AccountsStorageRoots>(address)
+            .unwrap_or(EMPTY_TRIE_HASH)
+    }
+
     pub fn get_account(&self, address: H160) -> Option<AccountState> {
         self.lookup(&self.accounts, address).map(Cow::into_owned)
     }
 
     pub fn get_storage(&self, address: H160, index: H256) -> Option<H256> {
-        self.lookup(&self.accounts_storage, (address, index))
+        let generation = self.storage_generation(address);
+        match self.lookup(&self.accounts_storage, (address, generation, index)) {
+            Some(value) => Some(value.into_owned()).filter(|value| !value.is_zero()),
+            None if generation == 0 => {
+                self.lookup_storage::<LegacyAccountsStorage>((address, index))
+            }
+            None => None,
+        }
+    }
+
+    /// Generation of account storage, it's incremented each time storage is wiped.
+    pub fn storage_generation(&self, address: H160) -> u64 {
+        self.lookup(&self.storage_generations, address)
             .map(Cow::into_owned)
+            .unwrap_or_default()
     }
 
+    /// Sets value of account storage, zero value removes it.
+    /// In generation 0 zero is kept as value, to hide value of legacy storage.
+    pub fn set_storage(&mut self, address: H160, index: H256, value: H256) {
+        let generation = self.storage_generation(address);
+        if value.is_zero() && generation != 0 {
+            self.accounts_storage.remove((address, generation, index));
+        } else {
+            self.accounts_storage
+                .insert((address, generation, index), value);
+        }
+    }
+
+    /// Wipes whole storage of account, by switching it to the next generation.
+    pub fn reset_storage(&mut self, address: H160) {
+        let generation = self.storage_generation(address) + 1;
+        self.storage_generations.insert(address, generation);
+    }
+
+    /// Receipt by canonical hash of transaction, or by signing hash for compatibility.
     pub fn get_tx_receipt_by_hash(&self, tx_hash: H256) -> Option<TransactionReceipt> {
-        self.lookup(&self.txs_receipts, tx_hash)
-            .map(Cow::into_owned)
+        if let Some(receipt) = self.lookup(&self.txs_receipts, tx_hash) {
+            return Some(receipt.into_owned());
+        }
+        if let Some(tx_id_hash) = self.lookup(&self.txs_hashes, tx_hash) {
+            return self
+                .lookup(&self.txs_receipts, *tx_id_hash)
+                .map(Cow::into_owned);
+        }
+        if let Some(receipt) = self.get_legacy_tx_receipt(tx_hash) {
+            return Some(receipt);
+        }
+        let signing_hash = self.lookup_storage::<LegacyTransactionHashes>(tx_hash)?;
+        self.get_legacy_tx_receipt(signing_hash)
+    }
+
+    /// Converts receipt stored in legacy format, and restores its position in block.
+    fn get_legacy_tx_receipt(&self, tx_hash: H256) -> Option<TransactionReceipt> {
+        let legacy_receipt = |hash| {
+            self.lookup_storage::<LegacyTransactionReceipts>(hash)
+                .map(TransactionReceipt::from)
+        };
+        let mut receipt = legacy_receipt(tx_hash)?;
+        let txs = self
+            .get_txs_in_block(receipt.block_number)
+            .unwrap_or_default();
+
+        let mut previous: Option<TransactionReceipt> = None;
+        for hash in txs.into_iter().take(receipt.index as usize) {
+            let mut current = legacy_receipt(hash)?;
+            if let Some(previous) = &previous {
+                current.follow(previous);
+            }
+            previous = Some(current);
+        }
+        if let Some(previous) = &previous {
+            receipt.follow(previous);
+        }
+        Some(receipt)
     }
 
     pub fn get_txs_in_block(&self, block_num: Slot) -> Option<Vec<H256>> {
@@ -378,6 +1114,18 @@ impl EvmState {
             .map(Cow::into_owned)
     }
 
+    /// Slot, at which big transaction expires, `None` for ones allocated before expiration was introduced.
+    pub fn get_big_tx_expires_at(&self, key: H256) -> Option<Slot> {
+        self.lookup(&self.big_transactions_expires_at, key)
+            .map(Cow::into_owned)
+    }
+
+    /// Keys of big transactions, that was allocated to expire at `slot`.
+    pub fn get_big_txs_expiring_at(&self, slot: Slot) -> Option<Vec<H256>> {
+        self.lookup(&self.big_transactions_expiry, slot)
+            .map(Cow::into_owned)
+    }
+
     // TODO: currently used in benches only, remove
     pub fn set_account(&mut self, address: H160, state: AccountState) {
         self.accounts.insert(address, state);
@@ -390,37 +1138,57 @@ impl EvmState {
         self.big_transactions.insert(hash, data);
     }
 
-    // TODO: Optimize, using bloom filters.
-    // TODO: Check topics query limits <= 4.
-    // TODO: Filter by address, topics
+    /// Bloom of all logs emitted in block, `None` if block has no transactions.
+    pub fn get_logs_bloom(&self, block_num: Slot) -> Option<Bloom> {
+        self.lookup(&self.logs_blooms, block_num)
+            .map(Cow::into_owned)
+    }
+
+    /// Remembers native blockhash of current slot, should be called before freeze.
+    pub fn register_block_hash(&mut self, block_hash: H256) {
+        self.block_slots.insert(block_hash, self.current_slot);
+    }
+
+    /// Slot of block with `block_hash` on the fork of current state.
+    pub fn get_slot_by_block_hash(&self, block_hash: H256) -> Option<Slot> {
+        self.lookup(&self.block_slots, block_hash)
+            .map(Cow::into_owned)
+    }
+
+    /// Returns logs matching filter, blocks which bloom doesn't match filter are skipped.
+    /// Blocks without stored bloom are scanned fully.
     pub fn get_logs(&self, logs_filter: LogFilter) -> Vec<LogWithLocation> {
         let mut result = Vec::new();
 
-        for (block_id, txs) in (logs_filter.from_block..=logs_filter.to_block)
-            .filter_map(|b| self.get_txs_in_block(b).map(|k| (b, k)))
-        {
-            let txs_in_block = txs
-                .into_iter()
-                .map(|tx_hash| {
-                    (
-                        tx_hash,
-                        self.get_tx_receipt_by_hash(tx_hash)
-                            .expect("Transacton not found by hash, while exist by number"),
-                    )
-                })
-                .enumerate();
+        for block_num in logs_filter.from_block..=logs_filter.to_block {
+            if let Some(bloom) = self.get_logs_bloom(block_num) {
+                if !logs_filter.is_bloom_match(&bloom) {
+                    continue;
+                }
+            }
+            let txs = match self.get_txs_in_block(block_num) {
+                Some(txs) => txs,
+                None => continue,
+            };
 
-            for (tx_id, (tx_hash, receipt)) in txs_in_block {
+            let mut log_index = 0;
+            for (tx_id, tx_hash) in txs.into_iter().enumerate() {
+                let receipt = self
+                    .get_tx_receipt_by_hash(tx_hash)
+                    .expect("Transacton not found by hash, while exist by number");
                 for log in receipt.logs {
-                    let log_entry = LogWithLocation {
-                        transaction_hash: tx_hash,
-                        transaction_id: tx_id as u64,
-                        block_num: block_id,
-                        data: log.data,
-                        topics: log.topics,
-                        address: log.address,
-                    };
-                    result.push(log_entry)
+                    if logs_filter.is_log_match(&log.address, &log.topics) {
+                        result.push(LogWithLocation {
+                            transaction_hash: tx_hash,
+                            transaction_id: tx_id as u64,
+                            block_num,
+                            log_index,
+                            data: log.data,
+                            topics: log.topics,
+                            address: log.address,
+                        });
+                    }
+                    log_index += 1;
                 }
             }
         }
@@ -442,6 +1210,123 @@ impl EvmState {
 
         std::mem::swap(self, &mut updated);
     }
+
+    /// Checks if state accessed by patch was changed by other patches,
+    /// merged after patch was created.
+    pub fn is_conflicting(&self, patch: &EvmPatch) -> bool {
+        self.is_changed_after(&patch.state, &patch.accessed)
+    }
+
+    /// Checks if state accessed or changed in `forked` was changed by patches,
+    /// merged after `forked` was cloned from this state.
+    pub(crate) fn is_changed_after(
+        &self,
+        forked: &EvmState,
+        accessed: &BTreeSet<StateKey>,
+    ) -> bool {
+        let created_after = forked.merged_patches;
+        accessed
+            .iter()
+            .chain(forked.changed_keys().iter())
+            .any(|key| {
+                self.changed_by_patch
+                    .get(key)
+                    .map_or(false, |merged| *merged > created_after)
+            })
+    }
+
+    /// Keys of state, that was changed since state was cloned.
+    fn changed_keys(&self) -> BTreeSet<StateKey> {
+        let accounts = self
+            .accounts
+            .changed
+            .iter()
+            .chain(self.storage_generations.changed.iter())
+            .copied()
+            .chain(self.accounts_storage.changed.iter().map(|key| key.0))
+            .map(StateKey::Account);
+        let big_txs = self
+            .big_transactions
+            .changed
+            .iter()
+            .copied()
+            .map(StateKey::BigTransaction);
+        accounts
+            .chain(big_txs)
+            .filter(StateKey::is_tracked)
+            .collect()
+    }
+
+    /// Merges changes of patch, transactions of patch are placed after already merged ones.
+    /// Patch should be checked with `is_conflicting` first, otherwise changes of other patches may be lost.
+    pub fn merge(&mut self, patch: EvmPatch) {
+        assert_eq!(
+            self.current_slot, patch.state.current_slot,
+            "Patch should be created from state of the same slot"
+        );
+        self.merged_patches += 1;
+        for key in patch.state.changed_keys() {
+            self.changed_by_patch.insert(key, self.merged_patches);
+        }
+
+        let patch = patch.state;
+        self.accounts.merge_changes(&patch.accounts);
+        self.accounts_storage.merge_changes(&patch.accounts_storage);
+        self.storage_generations
+            .merge_changes(&patch.storage_generations);
+        self.txs_hashes.merge_changes(&patch.txs_hashes);
+        self.big_transactions.merge_changes(&patch.big_transactions);
+        self.big_transactions_expires_at
+            .merge_changes(&patch.big_transactions_expires_at);
+
+        for slot in &patch.big_transactions_expiry.changed {
+            let mut keys = self.get_big_txs_expiring_at(*slot).unwrap_or_default();
+            for key in patch.get_big_txs_expiring_at(*slot).unwrap_or_default() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            self.big_transactions_expiry.insert(*slot, keys);
+        }
+
+        // Receipts of patch are placed at the end of block.
+        for block_num in &patch.txs_in_block.changed {
+            let new_txs: Vec<H256> = patch
+                .get_txs_in_block(*block_num)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|hash| patch.txs_receipts.changed.contains(hash))
+                .collect();
+            if new_txs.is_empty() {
+                continue;
+            }
+
+            let mut hashes = self.get_txs_in_block(*block_num).unwrap_or_default();
+            let mut bloom = self.get_logs_bloom(*block_num).unwrap_or_default();
+            let mut previous = hashes
+                .last()
+                .and_then(|hash| self.get_tx_receipt_by_hash(*hash));
+            for hash in new_txs {
+                let mut receipt = patch
+                    .get_tx_receipt_by_hash(hash)
+                    .expect("Receipt of transaction in block should exist");
+                receipt.index = hashes.len() as u64;
+                match &previous {
+                    Some(previous) => receipt.follow(previous),
+                    None => {
+                        receipt.cumulative_gas_used = receipt.used_gas;
+                        receipt.first_log_index = 0;
+                    }
+                }
+                bloom.accrue_bloom(&receipt.logs_bloom);
+                hashes.push(hash);
+                self.txs_receipts.insert(hash, receipt.clone());
+                previous = Some(receipt);
+            }
+            self.txs_in_block.insert(*block_num, hashes);
+            self.logs_blooms.insert(*block_num, bloom);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -565,11 +1450,8 @@ mod tests {
             }
         }
 
-        for s in storage {
-            match &s.1 {
-                Some(v) => state.accounts_storage.insert(*s.0, *v),
-                None => state.accounts_storage.remove(*s.0),
-            }
+        for (&(address, index), value) in storage {
+            state.set_storage(address, index, value.unwrap_or_default());
         }
     }
 
@@ -670,13 +1552,11 @@ mod tests {
                     .accounts
                     .insert(*account, accounts_state[account].clone());
 
-                for (account_with_index, data) in accounts_storage.range((
+                for (&(address, index), data) in accounts_storage.range((
                     Included((*account, H256::zero())),
                     Included((*account, H256::repeat_byte(u8::MAX))),
                 )) {
-                    evm_state
-                        .accounts_storage
-                        .insert(*account_with_index, *data);
+                    evm_state.set_storage(address, index, *data);
                 }
             }
 
@@ -696,6 +1576,164 @@ mod tests {
         Ok(())
     }
 
+    fn expected_state_root(
+        accounts: &BTreeMap<H160, AccountState>,
+        storage: &BTreeMap<(H160, H256), H256>,
+    ) -> H256 {
+        let store = NodesDiff::new();
+        let mut state_trie = Trie::new(&store, EMPTY_TRIE_HASH);
+        for (address, account) in accounts {
+            let mut storage_trie = Trie::new(&store, EMPTY_TRIE_HASH);
+            for ((_, index), value) in
+                storage.range((*address, H256::zero())..=(*address, H256::repeat_byte(u8::MAX)))
+            {
+                storage_trie.insert(
+                    keccak(index.as_bytes()).as_bytes(),
+                    rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec(),
+                );
+            }
+            let storage_root = storage_trie.commit().0;
+            state_trie.insert(
+                keccak(address.as_bytes()).as_bytes(),
+                account_rlp(account, storage_root),
+            );
+        }
+        state_trie.commit().0
+    }
+
+    #[test]
+    fn state_root_matches_rebuilt_trie() {
+        const N_VERSIONS: u64 = 5;
+
+        let tmp_dir = tempdir().unwrap();
+        let mut state = EvmState::load_from(tmp_dir, 0).unwrap();
+        assert_eq!(state.state_root(), EMPTY_TRIE_HASH);
+
+        let mut accounts_state = BTreeMap::new();
+        let mut accounts_storage = BTreeMap::new();
+        let mut roots = vec![];
+
+        for version in 0..N_VERSIONS {
+            let accounts = generate_accounts_addresses(SEED + version, 4);
+            let new_accounts_state = generate_accounts_state(SEED + version, &accounts);
+            let new_storage = generate_storage(SEED + version, &accounts);
+
+            // Remove one of previously inserted accounts
+            let removed: BTreeSet<_> = accounts_state.keys().take(1).copied().collect();
+            for address in &removed {
+                accounts_state.remove(address);
+            }
+            accounts_state.extend(new_accounts_state.clone());
+            accounts_storage.extend(new_storage.clone());
+            accounts_storage.retain(|(address, _), _| accounts_state.contains_key(address));
+
+            save_state(
+                &mut state,
+                &to_state_diff(new_accounts_state, removed),
+                &to_state_diff(new_storage, BTreeSet::new()),
+            );
+            state.freeze();
+
+            let root = state.state_root();
+            assert_eq!(
+                root,
+                expected_state_root(&accounts_state, &accounts_storage)
+            );
+            roots.push((state.current_slot, root));
+
+            let next_slot = state.current_slot + 1;
+            state = state.try_fork(next_slot).unwrap();
+            assert_eq!(state.state_root(), root);
+        }
+
+        for (slot, root) in roots {
+            assert_eq!(state.get_state_root(slot), Some(root));
+        }
+        assert_eq!(state.get_state_root(N_VERSIONS + 1), None);
+    }
+
+    /// Writes state of slot, as it was written before state trie was introduced.
+    fn save_state_without_trie(
+        storage: &Storage,
+        slot: Slot,
+        previous: Option<Slot>,
+        accounts: &BTreeMap<H160, AccountState>,
+        accounts_storage: &BTreeMap<(H160, H256), H256>,
+    ) {
+        for (address, account) in accounts {
+            storage
+                .typed::<Accounts>()
+                .insert_with(slot, *address, account.clone().into())
+                .unwrap();
+        }
+        for (key, value) in accounts_storage {
+            storage
+                .typed::<LegacyAccountsStorage>()
+                .insert_with(slot, *key, (*value).into())
+                .unwrap();
+        }
+        storage.new_version(slot, previous).unwrap();
+    }
+
+    #[test]
+    fn state_root_of_upgraded_node_matches_rebuilt_one() {
+        let accounts = generate_accounts_addresses(SEED, 4);
+        let accounts_state = generate_accounts_state(SEED, &accounts);
+        let accounts_storage = generate_storage(SEED, &accounts);
+        let new_accounts = generate_accounts_addresses(SEED + 1, 2);
+        let new_accounts_state = generate_accounts_state(SEED + 1, &new_accounts);
+        let new_storage = generate_storage(SEED + 1, &new_accounts);
+
+        // Node, that was upgraded at slot 0, executes slot 1 with state trie.
+        let upgraded_dir = tempdir().unwrap();
+        {
+            let storage = Storage::open_persistent(&upgraded_dir, COLUMN_NAMES).unwrap();
+            save_state_without_trie(&storage, 0, None, &accounts_state, &accounts_storage);
+        }
+        let mut upgraded = EvmState::load_from(&upgraded_dir, 0).unwrap();
+        upgraded = upgraded.try_fork(1).unwrap();
+        save_state(
+            &mut upgraded,
+            &to_state_diff(new_accounts_state.clone(), BTreeSet::new()),
+            &to_state_diff(new_storage.clone(), BTreeSet::new()),
+        );
+        upgraded.freeze();
+
+        // Node, that was upgraded after slot 1.
+        let rebuilt_dir = tempdir().unwrap();
+        {
+            let storage = Storage::open_persistent(&rebuilt_dir, COLUMN_NAMES).unwrap();
+            save_state_without_trie(&storage, 0, None, &accounts_state, &accounts_storage);
+            save_state_without_trie(&storage, 1, Some(0), &new_accounts_state, &new_storage);
+        }
+        let mut rebuilt = EvmState::load_from(&rebuilt_dir, 1).unwrap();
+
+        let mut all_accounts = accounts_state.clone();
+        all_accounts.extend(new_accounts_state);
+        let mut all_storage = accounts_storage.clone();
+        all_storage.extend(new_storage);
+        let root = expected_state_root(&all_accounts, &all_storage);
+        assert_ne!(root, EMPTY_TRIE_HASH);
+        assert_eq!(upgraded.state_root(), root);
+        assert_eq!(rebuilt.state_root(), root);
+
+        // Storage written before upgrade is kept in roots of changed accounts.
+        let changed = (accounts[0], H256::repeat_byte(0x42), H256::repeat_byte(1));
+        all_storage.insert((changed.0, changed.1), changed.2);
+        let root = expected_state_root(&all_accounts, &all_storage);
+        for state in vec![&mut upgraded, &mut rebuilt] {
+            *state = state.try_fork(2).unwrap();
+            state.set_storage(changed.0, changed.1, changed.2);
+            state.freeze();
+            assert_eq!(state.state_root(), root);
+        }
+
+        // Built trie is reused after restart.
+        drop(rebuilt);
+        let rebuilt = EvmState::load_from(&rebuilt_dir, 2).unwrap();
+        assert_eq!(rebuilt.state_root(), root);
+    }
+
     #[test]
     fn lookups_thru_forks() {
         let _ = simple_logger::SimpleLogger::new().init();
@@ -720,4 +1758,348 @@ mod tests {
 
         assert_eq!(state.get_account(account), Some(account_state));
     }
+
+    #[test]
+    fn prune_drops_dead_forks_and_squashes_history() {
+        let account = |nonce: u64| AccountState {
+            nonce: nonce.into(),
+            ..Default::default()
+        };
+        let (a, b, c) = (
+            H160::repeat_byte(1),
+            H160::repeat_byte(2),
+            H160::repeat_byte(3),
+        );
+
+        let mut state = EvmState::default();
+        state.accounts.insert(a, account(1));
+        state.freeze();
+        let mut state = state.try_fork(1).unwrap();
+        state.accounts.insert(b, account(1));
+        state.freeze();
+
+        let mut dead = state.try_fork(2).unwrap();
+        dead.accounts.insert(c, account(1));
+        dead.freeze();
+
+        let mut state = state.try_fork(3).unwrap();
+        state.accounts.insert(a, account(3));
+        state.freeze();
+        let mut state = state.try_fork(4).unwrap();
+        state.freeze();
+
+        let stats = prune_storage(&state.storage, 3, 1).unwrap();
+        assert_eq!(stats.dead_versions, 1);
+        assert_eq!(stats.squashed_versions, 2);
+        for slot in 0..=2 {
+            assert!(!state.storage.is_exists(slot).unwrap());
+        }
+        assert_eq!(state.storage.track_of(4).collect::<Vec<_>>(), vec![4, 3]);
+
+        assert_eq!(state.get_account(a), Some(account(3)));
+        assert_eq!(state.get_account(b), Some(account(1)));
+        assert_eq!(state.get_account(c), None);
+        assert!(state.state_at_slot(1).is_none());
+        assert!(state.state_at_slot(3).is_some());
+
+        // Nothing left to prune.
+        let stats = prune_storage(&state.storage, 4, 1).unwrap();
+        assert_eq!((stats.dead_versions, stats.squashed_versions), (0, 0));
+    }
+
+    #[test]
+    fn prune_and_squash_concurrently() {
+        let account = |nonce: u64| AccountState {
+            nonce: nonce.into(),
+            ..Default::default()
+        };
+        let (a, b) = (H160::repeat_byte(1), H160::repeat_byte(2));
+
+        let mut state = EvmState::default();
+        state.accounts.insert(b, account(1));
+        for slot in 0..10 {
+            if slot > 0 {
+                state = state.try_fork(slot).unwrap();
+            }
+            state.accounts.insert(a, account(slot));
+            state.freeze();
+        }
+        let storage = state.storage.clone();
+
+        let pruner = {
+            let storage = storage.clone();
+            std::thread::spawn(move || prune_storage(&storage, 9, 2).unwrap())
+        };
+        let squasher = std::thread::spawn(move || state.squash());
+        let reader = {
+            let storage = storage.clone();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let accounts = storage.typed::<Accounts>();
+                    assert!(accounts.get_for(9, b).unwrap().is_some());
+                    assert!(accounts.get_for(9, a).unwrap().is_some());
+                }
+            })
+        };
+        pruner.join().unwrap();
+        squasher.join().unwrap();
+        reader.join().unwrap();
+
+        // Whichever finished first, history is squashed into slot 9, and nothing is lost.
+        assert_eq!(storage.track_of(9).collect::<Vec<_>>(), vec![9]);
+        let accounts = storage.typed::<Accounts>();
+        assert_eq!(
+            accounts.get_for(9, a).unwrap(),
+            Some(MaybeValue::Value(account(9)))
+        );
+        assert_eq!(
+            accounts.get_for(9, b).unwrap(),
+            Some(MaybeValue::Value(account(1)))
+        );
+    }
+
+    #[test]
+    fn state_before_slot() {
+        let tmp_dir = tempdir().unwrap();
+        let mut state = EvmState::load_from(tmp_dir, 0).unwrap();
+
+        let accounts = generate_accounts_addresses(SEED, 1);
+        let account = accounts.first().copied().unwrap();
+        let account_states: Vec<_> = (0..3)
+            .map(|seed| generate_accounts_state(seed, &accounts)[&account].clone())
+            .collect();
+
+        state.accounts.insert(account, account_states[0].clone());
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+        state.accounts.insert(account, account_states[1].clone());
+        state.freeze();
+        state = state.try_fork(2).unwrap();
+        state.accounts.insert(account, account_states[2].clone());
+
+        // Current slot is not frozen yet.
+        let before = state.state_before_slot(2).unwrap();
+        assert_eq!(before.get_account(account), Some(account_states[1].clone()));
+
+        let before = state.state_before_slot(1).unwrap();
+        assert_eq!(before.get_account(account), Some(account_states[0].clone()));
+
+        assert!(state.state_before_slot(0).is_none());
+        assert!(state.state_before_slot(3).is_none());
+        assert_eq!(state.get_account(account), Some(account_states[2].clone()));
+    }
+
+    #[test]
+    fn state_at_slot() {
+        let tmp_dir = tempdir().unwrap();
+        let mut state = EvmState::load_from(tmp_dir, 0).unwrap();
+
+        let accounts = generate_accounts_addresses(SEED, 1);
+        let account = accounts.first().copied().unwrap();
+        let account_states: Vec<_> = (0..4)
+            .map(|seed| generate_accounts_state(seed, &accounts)[&account].clone())
+            .collect();
+
+        state.accounts.insert(account, account_states[0].clone());
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+        state.accounts.insert(account, account_states[1].clone());
+        state.freeze();
+
+        // Sibling fork, that is not an ancestor of current state.
+        let mut sibling = state.try_fork(2).unwrap();
+        sibling.accounts.insert(account, account_states[3].clone());
+        sibling.freeze();
+
+        // Slot 2 is skipped on current fork.
+        state = state.try_fork(3).unwrap();
+        state.accounts.insert(account, account_states[2].clone());
+
+        let at = |slot| {
+            state
+                .state_at_slot(slot)
+                .unwrap()
+                .get_account(account)
+                .unwrap()
+        };
+        assert_eq!(at(0), account_states[0]);
+        assert_eq!(at(1), account_states[1]);
+        assert_eq!(at(2), account_states[1]);
+        assert_eq!(at(3), account_states[2]);
+        assert_eq!(at(10), account_states[2]);
+        assert_eq!(
+            sibling.state_at_slot(2).unwrap().get_account(account),
+            Some(account_states[3].clone())
+        );
+    }
+
+    #[test]
+    fn block_hash_index_follows_fork() {
+        let tmp_dir = tempdir().unwrap();
+        let mut state = EvmState::load_from(tmp_dir, 0).unwrap();
+        let hashes: Vec<_> = (0..3).map(H256::repeat_byte).collect();
+
+        state.register_block_hash(hashes[0]);
+        state.freeze();
+        let mut sibling = state.try_fork(1).unwrap();
+        sibling.register_block_hash(hashes[1]);
+        sibling.freeze();
+
+        state = state.try_fork(2).unwrap();
+        state.register_block_hash(hashes[2]);
+        assert_eq!(state.get_slot_by_block_hash(hashes[2]), Some(2));
+        state.freeze();
+
+        assert_eq!(state.get_slot_by_block_hash(hashes[0]), Some(0));
+        assert_eq!(state.get_slot_by_block_hash(hashes[1]), None);
+        assert_eq!(state.get_slot_by_block_hash(hashes[2]), Some(2));
+        assert_eq!(sibling.get_slot_by_block_hash(hashes[1]), Some(1));
+    }
+
+    #[test]
+    fn legacy_receipts_stay_readable() {
+        use crate::transactions::{TransactionAction, UnsignedTransaction};
+        use evm::{backend::Log, ExitReason, ExitSucceed};
+
+        let mut state = EvmState::default();
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let log = Log {
+            address: H160::repeat_byte(1),
+            topics: vec![],
+            data: vec![],
+        };
+        let receipts: Vec<_> = (0..2)
+            .map(|nonce| LegacyTransactionReceipt {
+                transaction: UnsignedTransaction {
+                    nonce: nonce.into(),
+                    gas_price: 0.into(),
+                    gas_limit: 0.into(),
+                    action: TransactionAction::Create,
+                    value: 0.into(),
+                    input: vec![],
+                }
+                .sign(&key, None),
+                status: ExitReason::Succeed(ExitSucceed::Stopped),
+                block_number: 0,
+                index: nonce,
+                used_gas: 21000.into(),
+                logs: vec![log.clone(), log.clone()],
+            })
+            .collect();
+        let hashes: Vec<_> = receipts
+            .iter()
+            .map(|receipt| receipt.transaction.signing_hash())
+            .collect();
+
+        let mut legacy = Layer::<LegacyTransactionReceipts>::empty();
+        for (hash, receipt) in hashes.iter().zip(&receipts) {
+            legacy.insert(*hash, receipt.clone());
+        }
+        legacy
+            .dump_into(&state.storage, state.current_slot)
+            .unwrap();
+        state.txs_in_block.insert(0, hashes.clone());
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+        assert!(state
+            .get_tx_receipt_by_hash(receipts[1].transaction.tx_id_hash())
+            .is_none());
+        index_legacy_receipts(&state.storage).unwrap();
+        // Repeated indexing skips indexed versions.
+        index_legacy_receipts(&state.storage).unwrap();
+
+        let receipt = state.get_tx_receipt_by_hash(hashes[1]).unwrap();
+        assert_eq!(receipt.transaction, receipts[1].transaction);
+        assert_eq!(receipt.cumulative_gas_used, 42000.into());
+        assert_eq!(receipt.first_log_index, 2);
+        assert_eq!(
+            receipt.contract_address,
+            Some(receipts[1].transaction.address().unwrap())
+        );
+        assert_eq!(
+            state.get_tx_receipt_by_hash(receipts[1].transaction.tx_id_hash()),
+            Some(receipt)
+        );
+    }
+
+    #[test]
+    fn get_logs_filters_by_topics_and_bloom() {
+        use crate::transactions::{TransactionAction, UnsignedTransaction};
+        use evm::{backend::Log, ExitReason, ExitSucceed};
+
+        let mut state = EvmState::default();
+        let key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let address = H160::repeat_byte(1);
+        let topic1 = H256::repeat_byte(2);
+        let topic2 = H256::repeat_byte(3);
+        let log = |topics: Vec<H256>| Log {
+            address,
+            topics,
+            data: vec![],
+        };
+
+        let mut insert_tx = |block: Slot, nonce: u64, logs: Vec<Log>| {
+            let tx = UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 0.into(),
+                gas_limit: 0.into(),
+                action: TransactionAction::Create,
+                value: 0.into(),
+                input: vec![],
+            }
+            .sign(&key, None);
+            let tx_hash = tx.tx_id_hash();
+            let mut hashes = state.get_txs_in_block(block).unwrap_or_default();
+            let receipt = TransactionReceipt::new(
+                tx,
+                0.into(),
+                block,
+                hashes.len() as u64,
+                logs,
+                (ExitReason::Succeed(ExitSucceed::Stopped), vec![]),
+            );
+            let mut bloom = state.get_logs_bloom(block).unwrap_or_default();
+            bloom.accrue_bloom(&receipt.logs_bloom);
+            hashes.push(tx_hash);
+            state.txs_in_block.insert(block, hashes);
+            state.logs_blooms.insert(block, bloom);
+            state.txs_receipts.insert(tx_hash, receipt);
+        };
+        insert_tx(1, 0, vec![log(vec![topic1]), log(vec![topic1, topic2])]);
+        insert_tx(1, 1, vec![log(vec![topic2])]);
+        insert_tx(2, 2, vec![log(vec![topic1])]);
+
+        let logs = |topics| {
+            state
+                .get_logs(LogFilter {
+                    from_block: 0,
+                    to_block: 3,
+                    address: Some(address),
+                    topics,
+                })
+                .into_iter()
+                .map(|log| (log.block_num, log.transaction_id, log.log_index))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            logs(vec![]),
+            vec![(1, 0, 0), (1, 0, 1), (1, 1, 2), (2, 0, 0)]
+        );
+        assert_eq!(logs(vec![None, Some(vec![topic2])]), vec![(1, 0, 1)]);
+        assert_eq!(
+            logs(vec![Some(vec![topic1, topic2])]),
+            vec![(1, 0, 0), (1, 0, 1), (1, 1, 2), (2, 0, 0)]
+        );
+        assert_eq!(logs(vec![Some(vec![topic2])]), vec![(1, 1, 2)]);
+        assert!(logs(vec![Some(vec![H256::repeat_byte(4)])]).is_empty());
+
+        assert!(!LogFilter {
+            from_block: 2,
+            to_block: 2,
+            address: None,
+            topics: vec![Some(vec![topic2])],
+        }
+        .is_bloom_match(&state.get_logs_bloom(2).unwrap()));
+        assert_eq!(state.get_logs_bloom(3), None);
+    }
 }
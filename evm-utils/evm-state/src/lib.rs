@@ -1,4 +1,4 @@
-use evm::executor::{MemoryStackState, StackSubstateMetadata};
+use evm::executor::{MemoryStackState, StackState, StackSubstateMetadata};
 pub use evm::{
     backend::{Apply, ApplyBackend, Backend, Log},
     executor::StackExecutor,
@@ -9,10 +9,13 @@ use log::{debug, error};
 pub use primitive_types::{H256, U256};
 pub use secp256k1::rand;
 
-mod error;
+pub mod error;
 mod layered_backend;
+pub mod precompiles;
+pub mod tracing;
 
 pub mod transactions;
+pub mod trie;
 pub mod types;
 
 use error::*;
@@ -24,12 +27,27 @@ pub use types::*;
 
 mod evm_backend;
 mod mb_value;
+#[cfg(test)]
+mod state_tests;
 mod storage;
 
+use precompiles::{NativeAccount, NativeStackState, NativeTransfer};
+use std::collections::BTreeMap;
 use std::fmt;
 
 pub const MAX_TX_LEN: u64 = 3 * 1024 * 1024; // Limit size to 3 MB
 pub const TX_MTU: u64 = 920;
+/// Count of slots, during which allocated big transaction should be written and executed.
+pub const BIG_TX_LIFETIME: Slot = 9000; // ~1 hour
+/// Count of wei in one lamport, lamports are 1/10^9 of native token, while wei are 1/10^18.
+pub const WEI_PER_LAMPORT: u64 = 1_000_000_000;
+/// Gas used by a plain value transfer.
+pub const TRANSFER_GAS: u64 = 21_000;
+/// Gas limit of EVM block, transactions are limited only by their own gas limit.
+pub const BLOCK_GAS_LIMIT: u64 = u64::MAX;
+
+#[cfg(test)]
+const TEST_CHAIN_ID: u64 = 0x77;
 
 pub trait FromKey {
     fn to_public_key(&self) -> secp256k1::PublicKey;
@@ -48,12 +66,149 @@ impl FromKey for secp256k1::SecretKey {
     }
 }
 
+/// Changes of execution rules, that are activated by native features.
+/// `Executor::with_config` uses latest rules, runtime should set them from its feature set,
+/// so blocks produced before activation are replayed with the rules of their time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutorFeatures {
+    /// Reject transactions with invalid signature, foreign chain id or wrong nonce,
+    /// and return chain id from CHAINID opcode, which returns 0 before.
+    pub validate_tx: bool,
+    /// Execute calls to native addresses with `precompiles::velas_precompiles`.
+    pub native_precompiles: bool,
+    /// Allow instructions, that don't move lamports of evm state account, to lock it read-only.
+    pub readonly_state_account: bool,
+    /// Big transactions are encoded in RLP instead of bincode, written only by signer of allocation,
+    /// can be reclaimed by owner, and expire after `BIG_TX_LIFETIME` slots.
+    pub rlp_big_transactions: bool,
+    /// Charge gas fee from caller, and reject transactions priced below minimal gas price.
+    pub charge_fee: bool,
+    /// Wipe storage of accounts, that are destroyed or recreated at the same address.
+    pub wipe_storage: bool,
+}
+
+impl ExecutorFeatures {
+    pub fn latest() -> Self {
+        ExecutorFeatures {
+            validate_tx: true,
+            native_precompiles: true,
+            readonly_state_account: true,
+            rlp_big_transactions: true,
+            charge_fee: true,
+            wipe_storage: true,
+        }
+    }
+}
+
+/// Minimal gas price in wei, a plain transfer at this price costs as much
+/// as a single signature at `target_lamports_per_signature`.
+pub fn min_gas_price(target_lamports_per_signature: u64) -> U256 {
+    U256::from(target_lamports_per_signature) * U256::from(WEI_PER_LAMPORT)
+        / U256::from(TRANSFER_GAS)
+}
+
+/// Fee is moved to native fee collector, so sub-lamport remainder of it is not charged,
+/// otherwise evm balances would drift from lamports of evm state account.
+fn fee_in_whole_lamports(fee: U256) -> U256 {
+    fee - fee % U256::from(WEI_PER_LAMPORT)
+}
+
 pub struct Executor {
     evm: EvmBackend,
     config: Config,
+    chain_id: u64,
+    features: ExecutorFeatures,
+    used_gas: u64,
+    min_gas_price: U256,
+    fee_collected: U256,
+    epoch: u64,
+    checkpoint: Option<Box<Checkpoint>>,
+}
+
+// Changes of executor, that can be reverted to.
+struct Checkpoint {
+    evm_state: EvmState,
     used_gas: u64,
+    fee_collected: U256,
+}
+
+/// Outcome of transaction, that was executed by `Executor::transact`.
+struct ExecutedTransaction {
+    result: (evm::ExitReason, Vec<u8>),
+    native_transfers: Vec<NativeTransfer>,
+    used_gas: u64,
+    fee: U256,
+}
+
+/// Stack executor, that runs transactions: `StackExecutor` of evm in blocks,
+/// or its copy with tracing hooks in debug rpc.
+trait TransactionExecutor {
+    fn transact_call(
+        &mut self,
+        caller: H160,
+        address: H160,
+        value: U256,
+        data: Vec<u8>,
+        gas_limit: u64,
+    ) -> (ExitReason, Vec<u8>);
+    fn transact_create(
+        &mut self,
+        caller: H160,
+        value: U256,
+        init_code: Vec<u8>,
+        gas_limit: u64,
+    ) -> ExitReason;
+    fn used_gas(&self) -> u64;
+    fn fee(&self, price: U256) -> U256;
+    fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError>;
+    fn deposit(&mut self, address: H160, value: U256);
+}
+
+macro_rules! impl_transaction_executor {
+    ($executor: ty) => {
+        impl<'backend, 'config> TransactionExecutor for $executor {
+            fn transact_call(
+                &mut self,
+                caller: H160,
+                address: H160,
+                value: U256,
+                data: Vec<u8>,
+                gas_limit: u64,
+            ) -> (ExitReason, Vec<u8>) {
+                self.transact_call(caller, address, value, data, gas_limit)
+            }
+            fn transact_create(
+                &mut self,
+                caller: H160,
+                value: U256,
+                init_code: Vec<u8>,
+                gas_limit: u64,
+            ) -> ExitReason {
+                self.transact_create(caller, value, init_code, gas_limit)
+            }
+            fn used_gas(&self) -> u64 {
+                self.used_gas()
+            }
+            fn fee(&self, price: U256) -> U256 {
+                self.fee(price)
+            }
+            fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError> {
+                self.state_mut().withdraw(address, value)
+            }
+            fn deposit(&mut self, address: H160, value: U256) {
+                self.state_mut().deposit(address, value)
+            }
+        }
+    };
 }
 
+impl_transaction_executor!(
+    StackExecutor<'config, NativeStackState<MemoryStackState<'backend, 'config, EvmBackend>>>
+);
+impl_transaction_executor!(
+    tracing::TracingExecutor<'config, tracing::MemoryStackState<'backend, 'config, EvmBackend>>
+);
+
 impl fmt::Debug for Executor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Executor")
@@ -63,71 +218,365 @@ impl fmt::Debug for Executor {
 }
 
 impl Executor {
-    pub fn with_config(state: EvmState, config: Config, gas_limit: u64, block_number: u64) -> Self {
+    /// Creates executor of block `block_number`, `config` defines rules of hardfork.
+    ///
+    /// Runtime and RPC execute every block with `Config::istanbul()`. Later hardforks aren't
+    /// supported by evm engine: its gasometer charges state access by constant costs of `Config`,
+    /// so warm and cold accesses of EIP-2929 and refund cap of EIP-3529 can't be expressed,
+    /// and typed transactions of EIP-2930 and EIP-1559 can't be decoded.
+    pub fn with_config(
+        state: EvmState,
+        config: Config,
+        gas_limit: u64,
+        chain_id: u64,
+        block_number: u64,
+    ) -> Self {
         let vicinity = MemoryVicinity {
             block_gas_limit: gas_limit.into(),
             block_number: block_number.into(),
+            chain_id: chain_id.into(),
             ..Default::default()
         };
         Executor {
             evm: EvmBackend::new_from_state(state, vicinity),
             config,
+            chain_id,
+            features: ExecutorFeatures::latest(),
             used_gas: 0,
+            min_gas_price: U256::zero(),
+            fee_collected: U256::zero(),
+            epoch: 0,
+            checkpoint: None,
+        }
+    }
+
+    pub fn set_features(&mut self, features: ExecutorFeatures) {
+        self.features = features;
+        self.evm.tx_info.chain_id = if features.validate_tx {
+            self.chain_id.into()
+        } else {
+            U256::zero()
+        };
+    }
+
+    pub fn features(&self) -> ExecutorFeatures {
+        self.features
+    }
+
+    /// Remember current changes, so ones made after can be reverted with `rollback_to_checkpoint`.
+    /// If checkpoint is already set, it's kept, so all changes since the first one are reverted.
+    pub fn set_checkpoint(&mut self) {
+        if self.checkpoint.is_none() {
+            self.checkpoint = Some(Box::new(Checkpoint {
+                evm_state: self.evm.evm_state.snapshot(),
+                used_gas: self.used_gas,
+                fee_collected: self.fee_collected,
+            }));
+        }
+    }
+
+    /// Revert changes made after checkpoint, state that was read since is still reported as accessed.
+    pub fn rollback_to_checkpoint(&mut self) {
+        if let Some(checkpoint) = self.checkpoint.take() {
+            self.evm.evm_state = checkpoint.evm_state;
+            self.used_gas = checkpoint.used_gas;
+            self.fee_collected = checkpoint.fee_collected;
         }
     }
 
+    /// Keep changes made after checkpoint.
+    pub fn discard_checkpoint(&mut self) {
+        self.checkpoint = None;
+    }
+
+    /// Set native epoch, that is visible to evm contracts through precompiles.
+    pub fn set_epoch(&mut self, epoch: u64) {
+        self.epoch = epoch;
+    }
+
+    /// Set minimal gas price that transactions should pay, in wei.
+    pub fn set_min_gas_price(&mut self, min_gas_price: U256) {
+        self.min_gas_price = min_gas_price;
+    }
+
     pub fn transaction_execute(
         &mut self,
         evm_tx: Transaction,
     ) -> Result<(evm::ExitReason, Vec<u8>), Error> {
+        self.transaction_execute_with_native_accounts(evm_tx, BTreeMap::new())
+            .map(|(reason, output, _)| (reason, output))
+    }
+
+    /// Execute transaction, giving precompiles access to native accounts.
+    /// Returns lamports that should be transfered from evm state account to native accounts.
+    pub fn transaction_execute_with_native_accounts(
+        &mut self,
+        evm_tx: Transaction,
+        native_accounts: BTreeMap<H256, NativeAccount>,
+    ) -> Result<(evm::ExitReason, Vec<u8>, Vec<NativeTransfer>), Error> {
+        let (caller, max_fee, gas_limit) = self.prepare_tx(&evm_tx)?;
+        let metadata = StackSubstateMetadata::new(gas_limit, &self.config);
+        let state = NativeStackState::new(MemoryStackState::new(metadata, &self.evm));
+        let mut executor =
+            StackExecutor::new_with_precompile(state, &self.config, self.precompiles());
+        let executed = self.transact(&mut executor, &evm_tx, caller, max_fee, &native_accounts);
+
+        let (updates, logs) = executor.into_state().into_inner().deconstruct();
+        let ExecutedTransaction {
+            result,
+            native_transfers,
+            ..
+        } = self.commit_tx(evm_tx, updates, logs, executed, native_accounts);
+        Ok((result.0, result.1, native_transfers))
+    }
+
+    /// Execute transaction the same way as `transaction_execute`, recording trace of execution.
+    pub fn transaction_trace(
+        &mut self,
+        evm_tx: Transaction,
+        trace_config: tracing::TraceConfig,
+    ) -> Result<tracing::Trace, Error> {
+        self.transaction_trace_with_native_accounts(evm_tx, BTreeMap::new(), trace_config)
+    }
+
+    /// Execute transaction the same way as `transaction_execute_with_native_accounts`,
+    /// recording trace of execution. Native accounts of receipt replay what transaction saw.
+    pub fn transaction_trace_with_native_accounts(
+        &mut self,
+        evm_tx: Transaction,
+        native_accounts: BTreeMap<H256, NativeAccount>,
+        trace_config: tracing::TraceConfig,
+    ) -> Result<tracing::Trace, Error> {
+        let (caller, max_fee, gas_limit) = self.prepare_tx(&evm_tx)?;
+        let metadata = tracing::StackSubstateMetadata::new(gas_limit, &self.config);
+        let state = tracing::MemoryStackState::new(metadata, &self.evm);
+        let mut executor = tracing::TracingExecutor::new_with_precompile(
+            state,
+            &self.config,
+            self.precompiles(),
+            trace_config,
+        );
+        let executed = self.transact(&mut executor, &evm_tx, caller, max_fee, &native_accounts);
+
+        let (state, tracer) = executor.into_parts();
+        let (updates, logs) = state.deconstruct();
+        let ExecutedTransaction {
+            result, used_gas, ..
+        } = self.commit_tx(evm_tx, updates, logs, executed, native_accounts);
+
+        let (struct_logs, call) = tracer.into_trace();
+        Ok(tracing::Trace {
+            used_gas,
+            exit_reason: result.0,
+            return_value: result.1,
+            struct_logs,
+            call,
+        })
+    }
+
+    /// Runs prepared transaction: charges `max_fee` up front, executes transaction,
+    /// burns value of native transfers and refunds unused gas to caller.
+    fn transact<E: TransactionExecutor>(
+        &self,
+        executor: &mut E,
+        evm_tx: &Transaction,
+        caller: H160,
+        max_fee: U256,
+        native_accounts: &BTreeMap<H256, NativeAccount>,
+    ) -> ExecutedTransaction {
+        executor
+            .withdraw(caller, max_fee)
+            .expect("Caller balance was checked before execution");
+        let slot = self.evm.tx_info.block_number.as_u64();
+        let (result, native_transfers) =
+            precompiles::with_native_context(slot, self.epoch, native_accounts.clone(), || {
+                match evm_tx.action {
+                    TransactionAction::Call(addr) => {
+                        debug!(
+                            "TransactionAction::Call caller  = {}, to = {}.",
+                            caller, addr
+                        );
+                        executor.transact_call(
+                            caller,
+                            addr,
+                            evm_tx.value,
+                            evm_tx.input.clone(),
+                            evm_tx.gas_limit.as_u64(),
+                        )
+                    }
+                    TransactionAction::Create => {
+                        let addr = evm_tx.address();
+                        debug!(
+                            "TransactionAction::Create caller  = {}, to = {:?}.",
+                            caller, addr
+                        );
+                        (
+                            executor.transact_create(
+                                caller,
+                                evm_tx.value,
+                                evm_tx.input.clone(),
+                                evm_tx.gas_limit.as_u64(),
+                            ),
+                            vec![],
+                        )
+                    }
+                }
+            });
+        let used_gas = executor.used_gas();
+        let fee = self.fee(executor.fee(evm_tx.gas_price));
+
+        // Value of native transfers is burned, lamports are moved from evm state account instead.
+        // Transfers of reverted call frames are dropped together with value that was sent to them.
+        let transferred = native_transfers.iter().fold(U256::zero(), |sum, transfer| {
+            sum + U256::from(transfer.lamports) * U256::from(WEI_PER_LAMPORT)
+        });
+        executor
+            .withdraw(precompiles::NATIVE_TRANSFER_ADDR, transferred)
+            .expect("Native transfer address should receive value of transfers");
+        executor.deposit(caller, max_fee - fee);
+
+        ExecutedTransaction {
+            result,
+            native_transfers,
+            used_gas,
+            fee,
+        }
+    }
+
+    /// Applies changes of executed transaction, and registers its receipt.
+    fn commit_tx<A, I, L>(
+        &mut self,
+        evm_tx: Transaction,
+        updates: A,
+        logs: L,
+        executed: ExecutedTransaction,
+        native_accounts: BTreeMap<H256, NativeAccount>,
+    ) -> ExecutedTransaction
+    where
+        A: IntoIterator<Item = Apply<I>>,
+        I: IntoIterator<Item = (H256, H256)>,
+        L: IntoIterator<Item = Log>,
+    {
+        assert!(executed.used_gas + self.used_gas <= self.evm.tx_info.block_gas_limit.as_u64());
+        self.evm.apply(updates, false, self.features.wipe_storage);
+        self.register_tx_receipt(
+            evm_tx,
+            executed.used_gas.into(),
+            logs,
+            executed.result.clone(),
+            native_accounts,
+        );
+        self.used_gas += executed.used_gas;
+        self.fee_collected += executed.fee;
+        executed
+    }
+
+    /// Check transaction against current state, and set transaction info for execution.
+    /// Returns caller, fee that should be paid up front, and gas limit of execution.
+    fn prepare_tx(&mut self, evm_tx: &Transaction) -> Result<(H160, U256, u64), Error> {
+        self.validate_tx(evm_tx)?;
         let caller = evm_tx.caller()?;
 
+        let state_nonce = self.evm.basic(caller).nonce;
+        if self.features.validate_tx && state_nonce != evm_tx.nonce {
+            return NonceNotEqual {
+                transaction_hash: evm_tx.tx_id_hash(),
+                tx_nonce: evm_tx.nonce,
+                state_nonce,
+            }
+            .fail();
+        }
+
+        // Gas is paid up front, unused gas is refunded after execution.
+        let max_fee = if self.features.charge_fee {
+            evm_tx.gas_limit * evm_tx.gas_price
+        } else {
+            U256::zero()
+        };
+        let balance = self.evm.basic(caller).balance;
+        if balance < max_fee {
+            return CantPayFee {
+                transaction_hash: evm_tx.tx_id_hash(),
+                max_fee,
+                balance,
+            }
+            .fail();
+        }
+
         self.evm.tx_info.origin = caller;
         self.evm.tx_info.gas_price = evm_tx.gas_price;
-        let gas_limit = self.evm.block_gas_limit().as_u64() - self.used_gas;
-        let metadata = StackSubstateMetadata::new(gas_limit, &self.config);
-        let state = MemoryStackState::new(metadata, &self.evm);
-        let mut executor = StackExecutor::new(state, &self.config);
-        let result = match evm_tx.action {
-            TransactionAction::Call(addr) => {
-                debug!(
-                    "TransactionAction::Call caller  = {}, to = {}.",
-                    caller, addr
-                );
-                executor.transact_call(
-                    caller,
-                    addr,
-                    evm_tx.value,
-                    evm_tx.input.clone(),
-                    evm_tx.gas_limit.as_u64(),
-                )
+        let gas_limit = std::cmp::min(
+            evm_tx.gas_limit.as_u64(),
+            self.evm.block_gas_limit().as_u64() - self.used_gas,
+        );
+        Ok((caller, max_fee, gas_limit))
+    }
+
+    /// Part of `fee` of executed transaction, that is charged from caller.
+    fn fee(&self, fee: U256) -> U256 {
+        if self.features.charge_fee {
+            fee_in_whole_lamports(fee)
+        } else {
+            U256::zero()
+        }
+    }
+
+    fn precompiles(&self) -> precompiles::PrecompileSet {
+        if self.features.native_precompiles {
+            precompiles::velas_precompiles
+        } else {
+            precompiles::no_precompiles
+        }
+    }
+
+    /// Stateless checks of transaction signature and gas.
+    fn validate_tx(&self, evm_tx: &Transaction) -> Result<(), Error> {
+        let transaction_hash = evm_tx.tx_id_hash();
+        let signature = &evm_tx.signature;
+
+        if self.features.validate_tx {
+            if !signature.is_valid() || !signature.is_low_s() {
+                return InvalidSignature { transaction_hash }.fail();
             }
-            TransactionAction::Create => {
-                let addr = evm_tx.address();
-                debug!(
-                    "TransactionAction::Create caller  = {}, to = {:?}.",
-                    caller, addr
-                );
-                (
-                    executor.transact_create(
-                        caller,
-                        evm_tx.value,
-                        evm_tx.input.clone(),
-                        evm_tx.gas_limit.as_u64(),
-                    ),
-                    vec![],
-                )
+
+            if let Some(tx_chain_id) = signature.chain_id() {
+                if self.chain_id != tx_chain_id {
+                    return WrongChainId {
+                        transaction_hash,
+                        tx_chain_id,
+                        chain_id: self.chain_id,
+                    }
+                    .fail();
+                }
             }
-        };
-        let used_gas = executor.used_gas();
+        }
+
+        if evm_tx.gas_limit > u64::MAX.into() {
+            return GasLimitOutOfBounds {
+                transaction_hash,
+                gas_limit: evm_tx.gas_limit,
+            }
+            .fail();
+        }
 
-        assert!(used_gas + self.used_gas <= self.evm.tx_info.block_gas_limit.as_u64());
-        let (updates, logs) = executor.into_state().deconstruct();
-        self.evm.apply(updates, false);
-        self.register_tx_receipt(evm_tx, used_gas.into(), logs, result.clone());
-        self.used_gas += used_gas;
+        if evm_tx.gas_limit.checked_mul(evm_tx.gas_price).is_none() {
+            return GasPriceOutOfBounds {
+                transaction_hash,
+                gas_price: evm_tx.gas_price,
+            }
+            .fail();
+        }
+
+        if self.features.charge_fee && evm_tx.gas_price < self.min_gas_price {
+            return GasPriceTooLow {
+                transaction_hash,
+                gas_price: evm_tx.gas_price,
+                min_gas_price: self.min_gas_price,
+            }
+            .fail();
+        }
 
-        Ok(result)
+        Ok(())
     }
 
     /// Do lowlevel operation with executor, without storing transaction into logs.
@@ -140,13 +589,18 @@ impl Executor {
             let gas_limit = self.evm.block_gas_limit().as_u64() - self.used_gas;
             let metadata = StackSubstateMetadata::new(gas_limit, &self.config);
             let state = MemoryStackState::new(metadata, &self.evm);
-            let mut executor = StackExecutor::new(state, &self.config);
-            let result = func(&mut executor);
+            let mut executor =
+                StackExecutor::new_with_precompile(state, &self.config, self.precompiles());
+            let slot = self.evm.tx_info.block_number.as_u64();
+            let (result, _) =
+                precompiles::with_native_context(slot, self.epoch, BTreeMap::new(), || {
+                    func(&mut executor)
+                });
             // let used_gas = executor.used_gas();
             let state = executor.into_state();
             (state.deconstruct(), result)
         };
-        self.evm.apply(updates, false);
+        self.evm.apply(updates, false, self.features.wipe_storage);
         result
     }
 
@@ -154,11 +608,17 @@ impl Executor {
         self.used_gas
     }
 
+    /// Total fee in wei, that was charged from callers of executed transactions.
+    pub fn fee_collected(&self) -> U256 {
+        self.fee_collected
+    }
+
     pub fn get_tx_receipt_by_hash(&mut self, tx: H256) -> Option<TransactionReceipt> {
         self.evm.evm_state.get_tx_receipt_by_hash(tx)
     }
 
     pub fn take_big_tx(&mut self, key: H256) -> Result<Vec<u8>, Error> {
+        self.evm.access(StateKey::BigTransaction(key));
         let big_tx_storage = if let Some(big_tx_storage) = self.evm.evm_state.get_big_tx(key) {
             debug!("data at get = {:?}", big_tx_storage.tx_chunks);
             big_tx_storage
@@ -166,11 +626,24 @@ impl Executor {
             return DataNotFound { key }.fail();
         };
         self.evm.evm_state.big_transactions.remove(key);
+        self.evm.evm_state.big_transactions_expires_at.remove(key);
 
         Ok(big_tx_storage.tx_chunks)
     }
 
+    /// Removes allocated big transaction, that will not be executed.
+    pub fn free_big_tx(&mut self, key: H256) -> Result<(), Error> {
+        self.evm.access(StateKey::BigTransaction(key));
+        if self.evm.evm_state.get_big_tx(key).is_none() {
+            return DataNotFound { key }.fail();
+        }
+        self.evm.evm_state.big_transactions.remove(key);
+        self.evm.evm_state.big_transactions_expires_at.remove(key);
+        Ok(())
+    }
+
     pub fn allocate_store(&mut self, key: H256, size: u64) -> Result<(), Error> {
+        self.evm.access(StateKey::BigTransaction(key));
         if self.evm.evm_state.get_big_tx(key).is_some() || size > MAX_TX_LEN {
             error!("Double allocation for key = {:?}", key);
             return AllocationError { key, size }.fail();
@@ -185,10 +658,34 @@ impl Executor {
             .big_transactions
             .insert(key, big_tx_storage);
 
+        if !self.features.rlp_big_transactions {
+            return Ok(());
+        }
+        let expires_at = self
+            .evm
+            .evm_state
+            .current_slot
+            .saturating_add(BIG_TX_LIFETIME);
+        self.evm
+            .evm_state
+            .big_transactions_expires_at
+            .insert(key, expires_at);
+        let mut expiring = self
+            .evm
+            .evm_state
+            .get_big_txs_expiring_at(expires_at)
+            .unwrap_or_default();
+        expiring.push(key);
+        self.evm
+            .evm_state
+            .big_transactions_expiry
+            .insert(expires_at, expiring);
+
         Ok(())
     }
 
     pub fn publish_data(&mut self, key: H256, offset: u64, data: &[u8]) -> Result<(), Error> {
+        self.evm.access(StateKey::BigTransaction(key));
         let mut big_tx_storage = if let Some(big_tx_storage) = self.evm.evm_state.get_big_tx(key) {
             let max_len = big_tx_storage.tx_chunks.len() as u64;
             let data_end = offset.saturating_add(data.len() as u64);
@@ -218,18 +715,20 @@ impl Executor {
         Ok(())
     }
 
-    // TODO: Handle duplicates, statuses.
+    // TODO: Handle duplicates.
     fn register_tx_receipt<I>(
         &mut self,
         tx: transactions::Transaction,
         used_gas: U256,
         logs: I,
         result: (evm::ExitReason, Vec<u8>),
+        native_accounts: BTreeMap<H256, NativeAccount>,
     ) where
         I: IntoIterator<Item = Log>,
     {
         let block_num = self.evm.tx_info.block_number.as_u64();
-        let tx_hash = tx.signing_hash();
+        let tx_hash = tx.tx_id_hash();
+        let signing_hash = tx.signing_hash();
 
         debug!("Register tx in evm block={}, tx= {}", block_num, tx_hash);
         // TODO: replace by Entry-like api
@@ -238,12 +737,9 @@ impl Executor {
             .evm_state
             .get_txs_in_block(block_num)
             .unwrap_or_default();
-        hashes.push(tx_hash);
-
         let index = hashes.len() as u64;
-        self.evm.evm_state.txs_in_block.insert(block_num, hashes);
 
-        let tx_receipt = TransactionReceipt::new(
+        let mut tx_receipt = TransactionReceipt::new(
             tx,
             used_gas,
             block_num,
@@ -251,12 +747,51 @@ impl Executor {
             logs.into_iter().collect(),
             result,
         );
+        tx_receipt.native_accounts = native_accounts;
+        if let Some(previous) = hashes
+            .last()
+            .and_then(|hash| self.evm.evm_state.get_tx_receipt_by_hash(*hash))
+        {
+            tx_receipt.follow(&previous);
+        }
+
+        hashes.push(tx_hash);
+        self.evm.evm_state.txs_in_block.insert(block_num, hashes);
+
+        let mut bloom = self
+            .evm
+            .evm_state
+            .get_logs_bloom(block_num)
+            .unwrap_or_default();
+        bloom.accrue_bloom(&tx_receipt.logs_bloom);
+        self.evm.evm_state.logs_blooms.insert(block_num, bloom);
+
+        self.evm.evm_state.txs_hashes.insert(signing_hash, tx_hash);
         self.evm.evm_state.txs_receipts.insert(tx_hash, tx_receipt);
     }
 
     pub fn deconstruct(self) -> EvmState {
         self.evm.evm_state
     }
+
+    /// Returns true if executor read or changed evm state.
+    pub fn has_accessed_state(&self) -> bool {
+        !self.evm.accessed.borrow().is_empty()
+    }
+
+    /// Checks if state accessed by executor was changed by patches,
+    /// merged into `state` after executor was created.
+    pub fn is_conflicting(&self, state: &EvmState) -> bool {
+        state.is_changed_after(&self.evm.evm_state, &self.evm.accessed.borrow())
+    }
+
+    /// Changes made by executor, that can be merged into state, concurrently changed by other executors.
+    pub fn into_patch(self) -> EvmPatch {
+        EvmPatch {
+            state: self.evm.evm_state,
+            accessed: self.evm.accessed.into_inner(),
+        }
+    }
 }
 
 pub const HELLO_WORLD_CODE:&str = "608060405234801561001057600080fd5b5061011e806100206000396000f3fe6080604052348015600f57600080fd5b506004361060285760003560e01c8063942ae0a714602d575b600080fd5b603360ab565b6040518080602001828103825283818151815260200191508051906020019080838360005b8381101560715780820151818401526020810190506058565b50505050905090810190601f168015609d5780820380516001836020036101000a031916815260200191505b509250505060405180910390f35b60606040518060400160405280600a81526020017f68656c6c6f576f726c640000000000000000000000000000000000000000000081525090509056fea2646970667358221220fa787b95ca91ffe90fdb780b8ee8cb11c474bc63cb8217112c88bc465f7ea7d364736f6c63430007020033";
@@ -280,6 +815,16 @@ mod tests {
         hash.into()
     }
 
+    #[test]
+    fn min_gas_price_matches_signature_fee() {
+        assert_eq!(min_gas_price(0), U256::zero());
+        assert_eq!(min_gas_price(21), U256::from(WEI_PER_LAMPORT / 1000));
+        assert_eq!(
+            min_gas_price(21) * U256::from(TRANSFER_GAS),
+            U256::from(21) * U256::from(WEI_PER_LAMPORT)
+        );
+    }
+
     #[test]
     fn test_evm_bytecode() {
         let _logger_error = simple_logger::SimpleLogger::new().init();
@@ -302,7 +847,8 @@ mod tests {
         backend.freeze();
 
         let config = evm::Config::istanbul();
-        let mut executor = Executor::with_config(backend.clone(), config, u64::max_value(), 0);
+        let mut executor =
+            Executor::with_config(backend.clone(), config, u64::max_value(), TEST_CHAIN_ID, 0);
 
         let exit_reason = match executor.with_executor(|e| {
             e.create(
@@ -366,7 +912,8 @@ mod tests {
         state = state.try_fork(slot).expect("Unable to fork EVM state");
 
         let config = evm::Config::istanbul();
-        let mut executor = Executor::with_config(state.clone(), config, u64::max_value(), 0);
+        let mut executor =
+            Executor::with_config(state.clone(), config, u64::max_value(), TEST_CHAIN_ID, 0);
         let key = H256::random();
         let size = 100;
         let data = vec![0, 1, 2, 3];
@@ -379,7 +926,8 @@ mod tests {
         state = state.try_fork(slot).expect("Unable to fork EVM state");
 
         let config = evm::Config::istanbul();
-        let mut executor = Executor::with_config(state.clone(), config, u64::max_value(), 0);
+        let mut executor =
+            Executor::with_config(state.clone(), config, u64::max_value(), TEST_CHAIN_ID, 0);
         executor.publish_data(key, 0, &data).unwrap();
 
         let patch = executor.deconstruct();
@@ -389,7 +937,8 @@ mod tests {
         state = state.try_fork(slot).expect("Unable to fork EVM state");
 
         let config = evm::Config::istanbul();
-        let mut executor = Executor::with_config(state.clone(), config, u64::max_value(), 0);
+        let mut executor =
+            Executor::with_config(state.clone(), config, u64::max_value(), TEST_CHAIN_ID, 0);
         executor
             .publish_data(key, data.len() as u64, &data)
             .unwrap();
@@ -402,10 +951,626 @@ mod tests {
         state = state.try_fork(slot).expect("Unable to fork EVM state");
 
         let config = evm::Config::istanbul();
-        let mut executor = Executor::with_config(state, config, u64::max_value(), 0);
+        let mut executor = Executor::with_config(state, config, u64::max_value(), TEST_CHAIN_ID, 0);
         let result = executor.take_big_tx(key).unwrap();
         assert_eq!(&result[..data.len()], &*data);
 
         assert_eq!(&result[data.len()..2 * data.len()], &*data)
     }
+
+    #[test]
+    fn selfdestruct_and_recreation_wipe_storage() {
+        let caller = name_to_key("caller");
+        let contract = name_to_key("contract");
+        let mut state = EvmState::default();
+        state.accounts.insert(
+            contract,
+            AccountState {
+                // CALLER SELFDESTRUCT
+                code: vec![0x33, 0xff],
+                ..Default::default()
+            },
+        );
+        state.set_storage(contract, H256::from_low_u64_be(0), H256::repeat_byte(1));
+        state.set_storage(contract, H256::from_low_u64_be(1), H256::repeat_byte(2));
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+        assert_ne!(state.storage_root(contract), trie::EMPTY_TRIE_HASH);
+
+        let config = evm::Config::istanbul();
+        let mut executor = Executor::with_config(
+            state.clone(),
+            config.clone(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            1,
+        );
+        let (exit_reason, _) = executor
+            .with_executor(|e| e.transact_call(caller, contract, U256::zero(), vec![], 100000));
+        assert!(matches!(exit_reason, ExitReason::Succeed(_)));
+        state.swap_commit(executor.deconstruct());
+
+        assert!(state.get_account(contract).is_none());
+        assert!(state
+            .get_storage(contract, H256::from_low_u64_be(0))
+            .is_none());
+        state.freeze();
+        state = state.try_fork(2).unwrap();
+        assert!(state
+            .get_storage(contract, H256::from_low_u64_be(1))
+            .is_none());
+
+        // Recreate contract at the same address, init code: SSTORE(1, 3) STOP
+        let mut executor =
+            Executor::with_config(state.clone(), config, u64::max_value(), TEST_CHAIN_ID, 2);
+        let exit_reason = match executor.with_executor(|e| {
+            e.create(
+                caller,
+                CreateScheme::Fixed(contract),
+                U256::zero(),
+                vec![0x60, 0x03, 0x60, 0x01, 0x55, 0x00],
+                None,
+            )
+        }) {
+            Capture::Exit((reason, _, _)) => reason,
+            Capture::Trap(_) => unreachable!(),
+        };
+        assert!(matches!(exit_reason, ExitReason::Succeed(_)));
+        state.swap_commit(executor.deconstruct());
+
+        assert!(state
+            .get_storage(contract, H256::from_low_u64_be(0))
+            .is_none());
+        assert_eq!(
+            state.get_storage(contract, H256::from_low_u64_be(1)),
+            Some(H256::from_low_u64_be(3))
+        );
+        state.freeze();
+
+        let mut expected = EvmState::default();
+        expected.set_storage(contract, H256::from_low_u64_be(1), H256::from_low_u64_be(3));
+        expected
+            .accounts
+            .insert(contract, state.get_account(contract).unwrap());
+        expected.freeze();
+        assert_eq!(
+            state.storage_root(contract),
+            expected.storage_root(contract)
+        );
+    }
+
+    #[test]
+    fn selfdestruct_keeps_storage_before_activation() {
+        let caller = name_to_key("caller");
+        let contract = name_to_key("contract");
+        let mut state = EvmState::default();
+        state.accounts.insert(
+            contract,
+            AccountState {
+                // CALLER SELFDESTRUCT
+                code: vec![0x33, 0xff],
+                ..Default::default()
+            },
+        );
+        state.set_storage(contract, H256::from_low_u64_be(0), H256::repeat_byte(1));
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+
+        let mut executor = Executor::with_config(
+            state.clone(),
+            evm::Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            1,
+        );
+        executor.set_features(ExecutorFeatures {
+            wipe_storage: false,
+            ..ExecutorFeatures::latest()
+        });
+        let (exit_reason, _) = executor
+            .with_executor(|e| e.transact_call(caller, contract, U256::zero(), vec![], 100000));
+        assert!(matches!(exit_reason, ExitReason::Succeed(_)));
+        state.swap_commit(executor.deconstruct());
+
+        assert!(state.get_account(contract).is_none());
+        assert_eq!(
+            state.get_storage(contract, H256::from_low_u64_be(0)),
+            Some(H256::repeat_byte(1))
+        );
+    }
+
+    // Steps of state test, each transaction is committed before the next one.
+    enum StateTestStep {
+        Call { input: Vec<u8> },
+        Create { init_code: Vec<u8> },
+        NextBlock,
+    }
+
+    struct StateTestCase {
+        name: &'static str,
+        pre_storage: Vec<(u64, u64)>,
+        steps: Vec<StateTestStep>,
+        post_storage: Vec<(u64, u64)>,
+    }
+
+    // Calldata for destructible contract, that sets `key` to `value`.
+    fn sstore_input(key: u64, value: u64) -> Vec<u8> {
+        let mut input = H256::from_low_u64_be(key).as_bytes().to_vec();
+        input.extend_from_slice(H256::from_low_u64_be(value).as_bytes());
+        input
+    }
+
+    // Runs case in the manner of GeneralStateTests, with pre storage of contract
+    // written in current or legacy storage layout.
+    fn run_state_test(case: &StateTestCase, legacy_pre_storage: bool) {
+        use crate::mb_value::MaybeValue;
+
+        let caller = name_to_key("caller");
+        let contract = name_to_key("contract");
+        let config = evm::Config::istanbul();
+        let slot_key = |key: u64| H256::from_low_u64_be(key);
+
+        let mut state = EvmState::default();
+        state.accounts.insert(
+            contract,
+            AccountState {
+                // if calldata is empty SELFDESTRUCT(CALLER), else SSTORE(calldata[0..32], calldata[32..64])
+                code: vec![
+                    0x36, 0x15, 0x60, 0x0d, 0x57, 0x60, 0x20, 0x35, 0x60, 0x00, 0x35, 0x55, 0x00,
+                    0x5b, 0x33, 0xff,
+                ],
+                ..Default::default()
+            },
+        );
+        for &(key, value) in &case.pre_storage {
+            if legacy_pre_storage {
+                state
+                    .storage
+                    .typed::<LegacyAccountsStorage>()
+                    .insert_with(
+                        state.current_slot,
+                        (contract, slot_key(key)),
+                        MaybeValue::Value(H256::from_low_u64_be(value)),
+                    )
+                    .unwrap();
+            } else {
+                state.set_storage(contract, slot_key(key), H256::from_low_u64_be(value));
+            }
+        }
+        state.freeze();
+        let mut slot = 1;
+        state = state.try_fork(slot).unwrap();
+
+        for step in &case.steps {
+            let mut executor = Executor::with_config(
+                state.clone(),
+                config.clone(),
+                u64::max_value(),
+                TEST_CHAIN_ID,
+                slot,
+            );
+            let exit_reason = match step {
+                StateTestStep::Call { input } => {
+                    executor
+                        .with_executor(|e| {
+                            e.transact_call(caller, contract, U256::zero(), input.clone(), 100000)
+                        })
+                        .0
+                }
+                StateTestStep::Create { init_code } => match executor.with_executor(|e| {
+                    e.create(
+                        caller,
+                        CreateScheme::Fixed(contract),
+                        U256::zero(),
+                        init_code.clone(),
+                        None,
+                    )
+                }) {
+                    Capture::Exit((reason, _, _)) => reason,
+                    Capture::Trap(_) => unreachable!(),
+                },
+                StateTestStep::NextBlock => {
+                    state.freeze();
+                    slot += 1;
+                    state = state.try_fork(slot).unwrap();
+                    continue;
+                }
+            };
+            assert!(
+                matches!(exit_reason, ExitReason::Succeed(_)),
+                "{}: step failed with {:?}",
+                case.name,
+                exit_reason
+            );
+            state.swap_commit(executor.deconstruct());
+        }
+        state.freeze();
+        state = state.try_fork(slot + 1).unwrap();
+
+        let keys: std::collections::BTreeSet<u64> = case
+            .pre_storage
+            .iter()
+            .chain(&case.post_storage)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in keys {
+            let expected = case
+                .post_storage
+                .iter()
+                .find(|(post_key, _)| *post_key == key)
+                .map(|(_, value)| H256::from_low_u64_be(*value));
+            assert_eq!(
+                state.get_storage(contract, slot_key(key)),
+                expected,
+                "{}: storage key {} with legacy pre storage: {}",
+                case.name,
+                key,
+                legacy_pre_storage
+            );
+        }
+
+        // Legacy storage has no trie, so only storage root of current layout is comparable.
+        if !legacy_pre_storage {
+            let mut expected = EvmState::default();
+            for &(key, value) in &case.post_storage {
+                expected.set_storage(contract, slot_key(key), H256::from_low_u64_be(value));
+            }
+            if let Some(account) = state.get_account(contract) {
+                expected.accounts.insert(contract, account);
+            }
+            expected.freeze();
+            assert_eq!(
+                state.storage_root(contract),
+                expected.storage_root(contract),
+                "{}: storage root",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn state_tests_selfdestruct_and_recreate() {
+        use StateTestStep::*;
+        // init code: SSTORE(1, 3) STOP
+        let recreate = || Create {
+            init_code: vec![0x60, 0x03, 0x60, 0x01, 0x55, 0x00],
+        };
+        let selfdestruct = || Call { input: vec![] };
+        let cases = vec![
+            StateTestCase {
+                name: "sstore_keeps_other_keys",
+                pre_storage: vec![(0, 1), (1, 2)],
+                steps: vec![Call {
+                    input: sstore_input(1, 7),
+                }],
+                post_storage: vec![(0, 1), (1, 7)],
+            },
+            StateTestCase {
+                name: "sstore_zero_clears_key",
+                pre_storage: vec![(0, 1), (1, 2)],
+                steps: vec![
+                    Call {
+                        input: sstore_input(0, 0),
+                    },
+                    NextBlock,
+                ],
+                post_storage: vec![(1, 2)],
+            },
+            StateTestCase {
+                name: "suicide_storage_check",
+                pre_storage: vec![(0, 1), (1, 2)],
+                steps: vec![selfdestruct()],
+                post_storage: vec![],
+            },
+            StateTestCase {
+                name: "sstore_then_suicide_in_same_block",
+                pre_storage: vec![(0, 1)],
+                steps: vec![
+                    Call {
+                        input: sstore_input(2, 5),
+                    },
+                    selfdestruct(),
+                ],
+                post_storage: vec![],
+            },
+            StateTestCase {
+                name: "suicide_then_recreate_in_same_block",
+                pre_storage: vec![(0, 1), (1, 2)],
+                steps: vec![selfdestruct(), recreate()],
+                post_storage: vec![(1, 3)],
+            },
+            StateTestCase {
+                name: "suicide_then_recreate_in_next_block",
+                pre_storage: vec![(0, 1), (1, 2)],
+                steps: vec![selfdestruct(), NextBlock, recreate()],
+                post_storage: vec![(1, 3)],
+            },
+        ];
+
+        for case in &cases {
+            run_state_test(case, false);
+            run_state_test(case, true);
+        }
+    }
+
+    #[test]
+    fn receipts_are_indexed_by_canonical_hash() {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        let tx = |nonce: u64, action: TransactionAction, input: Vec<u8>| {
+            UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 0.into(),
+                gas_limit: 100000.into(),
+                action,
+                value: 0.into(),
+                input,
+            }
+            .sign(&key, Some(TEST_CHAIN_ID))
+        };
+        let contract = name_to_key("contract");
+        // LOG0(0, 0) STOP
+        let logging = tx(
+            0,
+            TransactionAction::Create,
+            vec![0x60, 0x00, 0x60, 0x00, 0xa0, 0x00],
+        );
+        let reverting = tx(1, TransactionAction::Call(contract), vec![]);
+
+        let mut state = EvmState::default();
+        state.accounts.insert(
+            contract,
+            AccountState {
+                // MSTORE(0, 42) REVERT(0, 32)
+                code: vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xfd],
+                ..Default::default()
+            },
+        );
+        let mut executor = Executor::with_config(
+            state.clone(),
+            evm::Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        executor.transaction_execute(logging.clone()).unwrap();
+        executor.transaction_execute(reverting.clone()).unwrap();
+        state.swap_commit(executor.deconstruct());
+        state.freeze();
+        state = state.try_fork(1).unwrap();
+
+        assert_eq!(
+            state.get_txs_in_block(0),
+            Some(vec![logging.tx_id_hash(), reverting.tx_id_hash()])
+        );
+        let first = state.get_tx_receipt_by_hash(logging.tx_id_hash()).unwrap();
+        assert!(first.status.is_succeed());
+        assert_eq!(first.contract_address, Some(logging.address().unwrap()));
+        assert_eq!(first.cumulative_gas_used, first.used_gas);
+        assert_eq!(first.first_log_index, 0);
+        assert_eq!(first.logs_bloom, logs_bloom(&first.logs));
+        assert!(first.revert_output.is_empty());
+
+        let second = state
+            .get_tx_receipt_by_hash(reverting.tx_id_hash())
+            .unwrap();
+        assert_eq!(
+            state.get_tx_receipt_by_hash(reverting.signing_hash()),
+            Some(second.clone())
+        );
+        assert!(matches!(second.status, ExitReason::Revert(_)));
+        assert_eq!(second.contract_address, None);
+        assert_eq!(second.cumulative_gas_used, first.used_gas + second.used_gas);
+        assert_eq!(second.first_log_index, 1);
+        assert_eq!(second.revert_output, H256::from_low_u64_be(42).as_bytes());
+    }
+
+    #[test]
+    fn concurrent_patches_are_merged_or_conflict() {
+        let first_key = SecretKey::new(&mut rand::thread_rng());
+        let second_key = SecretKey::new(&mut rand::thread_rng());
+        let (first_receiver, second_receiver) = (name_to_key("first"), name_to_key("second"));
+        let transfer = |key: &SecretKey, nonce: u64, receiver: H160| {
+            UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 0.into(),
+                gas_limit: 30000.into(),
+                action: TransactionAction::Call(receiver),
+                value: 1.into(),
+                input: vec![],
+            }
+            .sign(key, Some(TEST_CHAIN_ID))
+        };
+        let executor = |state: &EvmState| {
+            Executor::with_config(
+                state.clone(),
+                evm::Config::istanbul(),
+                u64::max_value(),
+                TEST_CHAIN_ID,
+                0,
+            )
+        };
+
+        let mut state = EvmState::default();
+        for key in &[first_key, second_key] {
+            state.accounts.insert(
+                key.to_address(),
+                AccountState {
+                    balance: 10.into(),
+                    ..Default::default()
+                },
+            );
+        }
+        let base = state.clone();
+
+        let first = transfer(&first_key, 0, first_receiver);
+        let second = transfer(&second_key, 0, second_receiver);
+        let mut first_executor = executor(&base);
+        first_executor.transaction_execute(first.clone()).unwrap();
+        let mut second_executor = executor(&base);
+        second_executor.transaction_execute(second.clone()).unwrap();
+        // Next transaction of the first caller is executed on stale state.
+        let next = transfer(&first_key, 1, first_receiver);
+        let mut stale_executor = executor(&base);
+        assert!(stale_executor.transaction_execute(next.clone()).is_err());
+
+        for patch in vec![first_executor.into_patch(), second_executor.into_patch()] {
+            assert!(!state.is_conflicting(&patch));
+            state.merge(patch);
+        }
+        assert!(stale_executor.has_accessed_state());
+        assert!(stale_executor.is_conflicting(&state));
+        assert!(state.is_conflicting(&stale_executor.into_patch()));
+
+        let mut next_executor = executor(&state);
+        next_executor.transaction_execute(next.clone()).unwrap();
+        let patch = next_executor.into_patch();
+        assert!(!state.is_conflicting(&patch));
+        state.merge(patch);
+
+        assert_eq!(state.get_account(first_receiver).unwrap().balance, 2.into());
+        assert_eq!(
+            state.get_account(second_receiver).unwrap().balance,
+            1.into()
+        );
+        assert_eq!(
+            state.get_account(first_key.to_address()).unwrap().balance,
+            8.into()
+        );
+        assert_eq!(
+            state.get_txs_in_block(0),
+            Some(vec![
+                first.tx_id_hash(),
+                second.tx_id_hash(),
+                next.tx_id_hash()
+            ])
+        );
+        let first = state.get_tx_receipt_by_hash(first.tx_id_hash()).unwrap();
+        let next = state.get_tx_receipt_by_hash(next.tx_id_hash()).unwrap();
+        assert_eq!(next.index, 2);
+        assert_eq!(next.cumulative_gas_used, first.used_gas * 3);
+    }
+
+    #[test]
+    fn rollback_to_checkpoint_reverts_later_changes() {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        let receiver = name_to_key("receiver");
+        let transfer = |nonce: u64| {
+            UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 0.into(),
+                gas_limit: 30000.into(),
+                action: TransactionAction::Call(receiver),
+                value: 1.into(),
+                input: vec![],
+            }
+            .sign(&key, Some(TEST_CHAIN_ID))
+        };
+
+        let mut state = EvmState::default();
+        state.accounts.insert(
+            key.to_address(),
+            AccountState {
+                balance: 10.into(),
+                ..Default::default()
+            },
+        );
+        let mut executor = Executor::with_config(
+            state.clone(),
+            evm::Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        let kept = transfer(0);
+        executor.transaction_execute(kept.clone()).unwrap();
+        let used_gas = executor.used_gas();
+
+        executor.set_checkpoint();
+        executor.transaction_execute(transfer(1)).unwrap();
+        // the first checkpoint is kept
+        executor.set_checkpoint();
+        executor.transaction_execute(transfer(2)).unwrap();
+        executor.rollback_to_checkpoint();
+        assert_eq!(executor.used_gas(), used_gas);
+        assert_eq!(executor.with_executor(|e| e.balance(receiver)), 1.into());
+        // reverted transaction can be executed again
+        let next = transfer(1);
+        executor.set_checkpoint();
+        executor.transaction_execute(next.clone()).unwrap();
+        executor.discard_checkpoint();
+        executor.rollback_to_checkpoint();
+
+        state.merge(executor.into_patch());
+        assert_eq!(state.get_account(receiver).unwrap().balance, 2.into());
+        assert_eq!(
+            state.get_txs_in_block(0),
+            Some(vec![kept.tx_id_hash(), next.tx_id_hash()])
+        );
+    }
+
+    #[test]
+    fn chain_id_opcode_returns_zero_before_validation() {
+        let contract = name_to_key("contract");
+        // MSTORE(0, CHAINID), RETURN(0, 32)
+        let code = vec![0x46, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+        let mut executor = Executor::with_config(
+            EvmState::default(),
+            evm::Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        executor.with_executor(|e| e.state_mut().set_code(contract, code));
+        let chain_id = |executor: &mut Executor| {
+            let (_, output) = executor.with_executor(|e| {
+                e.transact_call(H160::zero(), contract, U256::zero(), vec![], 30000)
+            });
+            U256::from_big_endian(&output)
+        };
+
+        assert_eq!(chain_id(&mut executor), U256::from(TEST_CHAIN_ID));
+        executor.set_features(ExecutorFeatures::default());
+        assert_eq!(chain_id(&mut executor), U256::zero());
+    }
+
+    #[test]
+    fn big_tx_expires_after_lifetime() {
+        let config = evm::Config::istanbul();
+        let mut state = EvmState::default();
+        let mut executor = Executor::with_config(
+            state.clone(),
+            config.clone(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        let (expired, freed) = (H256::random(), H256::random());
+        executor.allocate_store(expired, 100).unwrap();
+        executor.allocate_store(freed, 100).unwrap();
+        executor.free_big_tx(freed).unwrap();
+        assert!(executor.free_big_tx(freed).is_err());
+        // Allocations made before activation of feature don't expire.
+        let legacy = H256::random();
+        executor.set_features(ExecutorFeatures::default());
+        executor.allocate_store(legacy, 100).unwrap();
+        state.swap_commit(executor.deconstruct());
+        assert!(state.get_big_tx(freed).is_none());
+        assert_eq!(state.get_big_tx_expires_at(expired), Some(BIG_TX_LIFETIME));
+        assert_eq!(state.get_big_tx_expires_at(legacy), None);
+
+        state.freeze();
+        state = state.try_fork(BIG_TX_LIFETIME - 1).unwrap();
+        assert!(state.get_big_tx(expired).is_some());
+
+        // Slot of expiration is skipped.
+        state.freeze();
+        state = state.try_fork(BIG_TX_LIFETIME + 1).unwrap();
+        assert!(state.get_big_tx(expired).is_none());
+        assert!(state.get_big_txs_expiring_at(BIG_TX_LIFETIME).is_none());
+        assert!(state.get_big_tx(legacy).is_some());
+
+        // Key can be allocated again after expiration.
+        let mut executor = Executor::with_config(state, config, u64::max_value(), TEST_CHAIN_ID, 0);
+        executor.allocate_store(expired, 100).unwrap();
+    }
 }
@@ -0,0 +1,765 @@
+//! Precompiled contracts that allow evm contracts to interact with native chain.
+//!
+//! Evm precompiles are plain functions, so native context (current slot, epoch, accounts of
+//! the instruction) is passed to them through thread local storage, see `with_native_context`.
+//! Native transfers are journaled per call frame by `NativeStackState`, so reverted frame
+//! drops its transfers, like any other changes of evm state.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ops::{Deref, DerefMut};
+
+use evm::backend::{Backend, Basic};
+use evm::executor::{StackState, StackSubstateMetadata};
+use evm::{Context, ExitError, ExitSucceed, Transfer};
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::WEI_PER_LAMPORT;
+
+// "VELAS-CHAIN" prefixed addresses.
+const fn native_address(id: u8) -> H160 {
+    H160([
+        0x56, 0x45, 0x4c, 0x41, 0x53, 0x2d, 0x43, 0x48, 0x41, 0x49, 0x4e, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, id,
+    ])
+}
+
+/// Transfer call value to native account.
+/// Input: 32 bytes pubkey of receiver, value should be convertible to lamports without remainder.
+pub const NATIVE_TRANSFER_ADDR: H160 = native_address(0x01);
+/// Read lamports of native account.
+/// Input: 32 bytes pubkey. Output: 32 bytes lamports count.
+pub const NATIVE_BALANCE_ADDR: H160 = native_address(0x02);
+/// Verify ed25519 signature.
+/// Input: 32 bytes pubkey, 64 bytes signature, message. Output: 32 bytes, 1 if signature is valid, 0 otherwise.
+pub const ED25519_VERIFY_ADDR: H160 = native_address(0x03);
+/// Read current native slot and epoch.
+/// Output: 32 bytes slot, 32 bytes epoch.
+pub const NATIVE_CLOCK_ADDR: H160 = native_address(0x04);
+
+pub const NATIVE_TRANSFER_GAS: u64 = 7_000;
+pub const NATIVE_BALANCE_GAS: u64 = 700;
+pub const ED25519_VERIFY_BASE_GAS: u64 = 3_000;
+pub const ED25519_VERIFY_WORD_GAS: u64 = 12;
+pub const NATIVE_CLOCK_GAS: u64 = 40;
+
+/// Native account, that is available to evm contracts during execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeAccount {
+    pub lamports: u64,
+    pub is_writable: bool,
+}
+
+/// Lamports that should be moved from evm state account to native account, after execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativeTransfer {
+    pub receiver: H256,
+    pub lamports: u64,
+}
+
+#[derive(Debug, Default)]
+struct NativeContext {
+    slot: u64,
+    epoch: u64,
+    accounts: BTreeMap<H256, NativeAccount>,
+    /// Transfers of each call frame, that is not yet committed to parent one.
+    frames: Vec<Vec<NativeTransfer>>,
+}
+
+impl NativeContext {
+    /// Lamports of account, including transfers of frames that wasn't reverted.
+    fn lamports(&self, pubkey: &H256) -> Option<u64> {
+        let account = self.accounts.get(pubkey)?;
+        let transferred: u64 = self
+            .frames
+            .iter()
+            .flatten()
+            .filter(|transfer| transfer.receiver == *pubkey)
+            .map(|transfer| transfer.lamports)
+            .sum();
+        Some(account.lamports + transferred)
+    }
+}
+
+thread_local! {
+    static NATIVE_CONTEXT: RefCell<NativeContext> = RefCell::new(NativeContext::default());
+}
+
+/// Run `func` with native context available to precompiles.
+/// Returns result of `func` and native transfers that was requested during execution.
+pub(crate) fn with_native_context<F, R>(
+    slot: u64,
+    epoch: u64,
+    accounts: BTreeMap<H256, NativeAccount>,
+    func: F,
+) -> (R, Vec<NativeTransfer>)
+where
+    F: FnOnce() -> R,
+{
+    NATIVE_CONTEXT.with(|context| {
+        *context.borrow_mut() = NativeContext {
+            slot,
+            epoch,
+            accounts,
+            frames: vec![vec![]],
+        }
+    });
+    let result = func();
+    let context = NATIVE_CONTEXT.with(|context| context.replace(NativeContext::default()));
+    (result, context.frames.into_iter().flatten().collect())
+}
+
+pub(crate) fn enter_frame() {
+    NATIVE_CONTEXT.with(|context| context.borrow_mut().frames.push(vec![]));
+}
+
+pub(crate) fn exit_frame(commit: bool) {
+    NATIVE_CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        let transfers = context.frames.pop().unwrap_or_default();
+        if commit {
+            if let Some(parent) = context.frames.last_mut() {
+                parent.extend(transfers);
+            }
+        }
+    });
+}
+
+/// Stack state, that keeps native transfers in sync with call frames of evm.
+pub struct NativeStackState<S>(S);
+
+impl<S> NativeStackState<S> {
+    pub fn new(state: S) -> Self {
+        NativeStackState(state)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S> Deref for NativeStackState<S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S> DerefMut for NativeStackState<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.0
+    }
+}
+
+impl<S: Backend> Backend for NativeStackState<S> {
+    fn gas_price(&self) -> U256 {
+        self.0.gas_price()
+    }
+    fn origin(&self) -> H160 {
+        self.0.origin()
+    }
+    fn block_hash(&self, number: U256) -> H256 {
+        self.0.block_hash(number)
+    }
+    fn block_number(&self) -> U256 {
+        self.0.block_number()
+    }
+    fn block_coinbase(&self) -> H160 {
+        self.0.block_coinbase()
+    }
+    fn block_timestamp(&self) -> U256 {
+        self.0.block_timestamp()
+    }
+    fn block_difficulty(&self) -> U256 {
+        self.0.block_difficulty()
+    }
+    fn block_gas_limit(&self) -> U256 {
+        self.0.block_gas_limit()
+    }
+    fn chain_id(&self) -> U256 {
+        self.0.chain_id()
+    }
+    fn exists(&self, address: H160) -> bool {
+        self.0.exists(address)
+    }
+    fn basic(&self, address: H160) -> Basic {
+        self.0.basic(address)
+    }
+    fn code(&self, address: H160) -> Vec<u8> {
+        self.0.code(address)
+    }
+    fn storage(&self, address: H160, index: H256) -> H256 {
+        self.0.storage(address, index)
+    }
+    fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+        self.0.original_storage(address, index)
+    }
+}
+
+impl<'config, S: StackState<'config>> StackState<'config> for NativeStackState<S> {
+    fn metadata(&self) -> &StackSubstateMetadata<'config> {
+        self.0.metadata()
+    }
+    fn metadata_mut(&mut self) -> &mut StackSubstateMetadata<'config> {
+        self.0.metadata_mut()
+    }
+
+    fn enter(&mut self, gas_limit: u64, is_static: bool) {
+        self.0.enter(gas_limit, is_static);
+        enter_frame();
+    }
+    fn exit_commit(&mut self) -> Result<(), ExitError> {
+        exit_frame(true);
+        self.0.exit_commit()
+    }
+    fn exit_revert(&mut self) -> Result<(), ExitError> {
+        exit_frame(false);
+        self.0.exit_revert()
+    }
+    fn exit_discard(&mut self) -> Result<(), ExitError> {
+        exit_frame(false);
+        self.0.exit_discard()
+    }
+
+    fn is_empty(&self, address: H160) -> bool {
+        self.0.is_empty(address)
+    }
+    fn deleted(&self, address: H160) -> bool {
+        self.0.deleted(address)
+    }
+
+    fn inc_nonce(&mut self, address: H160) {
+        self.0.inc_nonce(address)
+    }
+    fn set_storage(&mut self, address: H160, key: H256, value: H256) {
+        self.0.set_storage(address, key, value)
+    }
+    fn reset_storage(&mut self, address: H160) {
+        self.0.reset_storage(address)
+    }
+    fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) {
+        self.0.log(address, topics, data)
+    }
+    fn set_deleted(&mut self, address: H160) {
+        self.0.set_deleted(address)
+    }
+    fn set_code(&mut self, address: H160, code: Vec<u8>) {
+        self.0.set_code(address, code)
+    }
+    fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+        self.0.transfer(transfer)
+    }
+    fn reset_balance(&mut self, address: H160) {
+        self.0.reset_balance(address)
+    }
+    fn touch(&mut self, address: H160) {
+        self.0.touch(address)
+    }
+}
+
+pub type PrecompileResult = Result<(ExitSucceed, Vec<u8>, u64), ExitError>;
+pub type PrecompileSet = fn(H160, &[u8], Option<u64>, &Context) -> Option<PrecompileResult>;
+
+fn error(message: &'static str) -> ExitError {
+    ExitError::Other(Cow::Borrowed(message))
+}
+
+fn charge(cost: u64, target_gas: Option<u64>) -> Result<u64, ExitError> {
+    match target_gas {
+        Some(gas) if gas < cost => Err(ExitError::OutOfGas),
+        _ => Ok(cost),
+    }
+}
+
+fn u256_output(values: &[U256]) -> Vec<u8> {
+    let mut output = vec![0; 32 * values.len()];
+    for (value, chunk) in values.iter().zip(output.chunks_mut(32)) {
+        value.to_big_endian(chunk);
+    }
+    output
+}
+
+fn pubkey_input(input: &[u8]) -> Result<H256, ExitError> {
+    if input.len() != 32 {
+        return Err(error("Input should be 32 bytes of native pubkey"));
+    }
+    Ok(H256::from_slice(input))
+}
+
+/// Precompile set that is used before activation of native precompiles, all addresses are plain accounts.
+pub fn no_precompiles(
+    _address: H160,
+    _input: &[u8],
+    _target_gas: Option<u64>,
+    _context: &Context,
+) -> Option<PrecompileResult> {
+    None
+}
+
+/// Precompile set of velas evm, that is used instead of the default one.
+pub fn velas_precompiles(
+    address: H160,
+    input: &[u8],
+    target_gas: Option<u64>,
+    context: &Context,
+) -> Option<PrecompileResult> {
+    let result = if address == NATIVE_TRANSFER_ADDR {
+        native_transfer(input, target_gas, context)
+    } else if address == NATIVE_BALANCE_ADDR {
+        native_balance(input, target_gas)
+    } else if address == ED25519_VERIFY_ADDR {
+        ed25519_verify(input, target_gas)
+    } else if address == NATIVE_CLOCK_ADDR {
+        native_clock(target_gas)
+    } else {
+        return None;
+    };
+    Some(result)
+}
+
+fn native_transfer(input: &[u8], target_gas: Option<u64>, context: &Context) -> PrecompileResult {
+    let cost = charge(NATIVE_TRANSFER_GAS, target_gas)?;
+    // Delegatecall keeps value of parent call, without transfering it to precompile.
+    if context.address != NATIVE_TRANSFER_ADDR {
+        return Err(error("Native transfer should be called directly"));
+    }
+    let receiver = pubkey_input(input)?;
+    let wei_per_lamport = U256::from(WEI_PER_LAMPORT);
+    if !(context.apparent_value % wei_per_lamport).is_zero() {
+        return Err(error("Transfer value is not convertible to lamports"));
+    }
+    let lamports = context.apparent_value / wei_per_lamport;
+    if lamports > U256::from(u64::MAX) {
+        return Err(error("Transfer value is out of bounds"));
+    }
+    let lamports = lamports.as_u64();
+
+    NATIVE_CONTEXT.with(|native| {
+        let mut native = native.borrow_mut();
+        match native.accounts.get(&receiver) {
+            Some(account) if account.is_writable => {}
+            _ => return Err(error("Receiver is not writable account of instruction")),
+        };
+        native
+            .lamports(&receiver)
+            .and_then(|current| current.checked_add(lamports))
+            .ok_or_else(|| error("Receiver lamports overflow"))?;
+        native
+            .frames
+            .last_mut()
+            .ok_or_else(|| error("Native transfer outside of evm execution"))?
+            .push(NativeTransfer { receiver, lamports });
+        Ok(())
+    })?;
+    Ok((ExitSucceed::Returned, vec![], cost))
+}
+
+fn native_balance(input: &[u8], target_gas: Option<u64>) -> PrecompileResult {
+    let cost = charge(NATIVE_BALANCE_GAS, target_gas)?;
+    let pubkey = pubkey_input(input)?;
+    let lamports = NATIVE_CONTEXT
+        .with(|native| native.borrow().lamports(&pubkey))
+        .ok_or_else(|| error("Account is not passed to instruction"))?;
+    Ok((ExitSucceed::Returned, u256_output(&[lamports.into()]), cost))
+}
+
+fn ed25519_verify(input: &[u8], target_gas: Option<u64>) -> PrecompileResult {
+    let words = (input.len() as u64 + 31) / 32;
+    let cost = charge(
+        ED25519_VERIFY_BASE_GAS + ED25519_VERIFY_WORD_GAS * words,
+        target_gas,
+    )?;
+    if input.len() < 96 {
+        return Err(error("Input should contain pubkey and signature"));
+    }
+    let (pubkey, rest) = input.split_at(32);
+    let (signature, message) = rest.split_at(64);
+
+    let is_valid = match (
+        ed25519_dalek::PublicKey::from_bytes(pubkey),
+        ed25519_dalek::Signature::try_from(signature),
+    ) {
+        (Ok(pubkey), Ok(signature)) => pubkey.verify_strict(message, &signature).is_ok(),
+        _ => false,
+    };
+    Ok((
+        ExitSucceed::Returned,
+        u256_output(&[U256::from(is_valid as u8)]),
+        cost,
+    ))
+}
+
+fn native_clock(target_gas: Option<u64>) -> PrecompileResult {
+    let cost = charge(NATIVE_CLOCK_GAS, target_gas)?;
+    let (slot, epoch) = NATIVE_CONTEXT.with(|native| {
+        let native = native.borrow();
+        (native.slot, native.epoch)
+    });
+    Ok((
+        ExitSucceed::Returned,
+        u256_output(&[slot.into(), epoch.into()]),
+        cost,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use ed25519_dalek::Signer;
+
+    const LAMPORT: u64 = WEI_PER_LAMPORT;
+
+    fn executor() -> (Executor, secp256k1::SecretKey) {
+        let mut executor = Executor::with_config(
+            EvmState::default(),
+            Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            5,
+        );
+        executor.set_epoch(7);
+        let secret_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let caller = secret_key.to_address();
+        executor.with_executor(|e| e.state_mut().deposit(caller, U256::from(100 * LAMPORT)));
+        (executor, secret_key)
+    }
+
+    fn call(nonce: u64, address: H160, value: u64, input: Vec<u8>) -> UnsignedTransaction {
+        UnsignedTransaction {
+            nonce: nonce.into(),
+            gas_price: 0.into(),
+            gas_limit: 300_000.into(),
+            action: TransactionAction::Call(address),
+            value: value.into(),
+            input,
+        }
+    }
+
+    /// Contract that forwards call with value and data to `address`, and reverts after call if `revert` is set.
+    fn forwarder_code(address: H160, revert: bool) -> Vec<u8> {
+        let mut code = vec![0x36, 0x60, 0x00, 0x60, 0x00, 0x37]; // CALLDATACOPY(0, 0, CALLDATASIZE)
+        code.extend(&[0x60, 0x40, 0x60, 0x00, 0x36, 0x60, 0x00, 0x34, 0x73]);
+        code.extend(address.as_bytes());
+        code.extend(&[0x5a, 0xf1]); // CALL(GAS, address, CALLVALUE, 0, CALLDATASIZE, 0, 0x40)
+        if revert {
+            code.extend(&[0x60, 0x00, 0x80, 0xfd]); // REVERT(0, 0)
+        } else {
+            code.extend(&[0x50, 0x3d, 0x60, 0x00, 0x60, 0x00, 0x3e]); // RETURNDATACOPY(0, 0, RETURNDATASIZE)
+            code.extend(&[0x3d, 0x60, 0x00, 0xf3]); // RETURN(0, RETURNDATASIZE)
+        }
+        code
+    }
+
+    fn writable(lamports: u64) -> NativeAccount {
+        NativeAccount {
+            lamports,
+            is_writable: true,
+        }
+    }
+
+    #[test]
+    fn native_clock() {
+        let (mut executor, secret_key) = executor();
+        let tx = call(0, NATIVE_CLOCK_ADDR, 0, vec![]).sign(&secret_key, None);
+        let (reason, output) = executor.transaction_execute(tx).unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(output, u256_output(&[5.into(), 7.into()]));
+    }
+
+    #[test]
+    fn native_addresses_are_plain_accounts_before_activation() {
+        let (mut executor, secret_key) = executor();
+        executor.set_features(ExecutorFeatures {
+            native_precompiles: false,
+            ..ExecutorFeatures::latest()
+        });
+        let tx = call(0, NATIVE_CLOCK_ADDR, LAMPORT, vec![]).sign(&secret_key, None);
+        let (reason, output) = executor.transaction_execute(tx).unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert!(output.is_empty());
+        executor.with_executor(|e| assert_eq!(e.balance(NATIVE_CLOCK_ADDR), U256::from(LAMPORT)));
+    }
+
+    #[test]
+    fn native_balance() {
+        let (mut executor, secret_key) = executor();
+        let pubkey = H256::repeat_byte(0x11);
+        let accounts = vec![(
+            pubkey,
+            NativeAccount {
+                lamports: 42,
+                is_writable: false,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let tx =
+            call(0, NATIVE_BALANCE_ADDR, 0, pubkey.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, output, _) = executor
+            .transaction_execute_with_native_accounts(tx, accounts)
+            .unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(output, u256_output(&[42.into()]));
+
+        let tx =
+            call(1, NATIVE_BALANCE_ADDR, 0, pubkey.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, _) = executor.transaction_execute(tx).unwrap();
+        assert!(matches!(reason, ExitReason::Error(_)));
+    }
+
+    #[test]
+    fn ed25519_verify() {
+        let (mut executor, secret_key) = executor();
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let keypair = ed25519_dalek::Keypair { secret, public };
+        let message = b"hello native chain";
+
+        let mut input = public.to_bytes().to_vec();
+        input.extend(keypair.sign(message).to_bytes().iter());
+        input.extend(message);
+        let tx = call(0, ED25519_VERIFY_ADDR, 0, input.clone()).sign(&secret_key, None);
+        let (reason, output) = executor.transaction_execute(tx).unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(output, u256_output(&[1.into()]));
+
+        *input.last_mut().unwrap() ^= 1;
+        let tx = call(1, ED25519_VERIFY_ADDR, 0, input).sign(&secret_key, None);
+        let (reason, output) = executor.transaction_execute(tx).unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(output, u256_output(&[0.into()]));
+    }
+
+    #[test]
+    fn native_transfer() {
+        let (mut executor, secret_key) = executor();
+        let caller = secret_key.to_address();
+        let receiver = H256::repeat_byte(0x11);
+        let accounts: BTreeMap<_, _> = vec![(receiver, writable(0))].into_iter().collect();
+
+        let tx = call(
+            0,
+            NATIVE_TRANSFER_ADDR,
+            3 * LAMPORT,
+            receiver.as_bytes().to_vec(),
+        )
+        .sign(&secret_key, None);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts.clone())
+            .unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(
+            transfers,
+            vec![NativeTransfer {
+                receiver,
+                lamports: 3
+            }]
+        );
+
+        // through contract
+        let contract = H160::repeat_byte(0x22);
+        let code = forwarder_code(NATIVE_TRANSFER_ADDR, false);
+        executor.with_executor(|e| e.state_mut().set_code(contract, code));
+        let tx =
+            call(1, contract, 2 * LAMPORT, receiver.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts)
+            .unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(
+            transfers,
+            vec![NativeTransfer {
+                receiver,
+                lamports: 2
+            }]
+        );
+
+        // transfered value is burned
+        executor.with_executor(|e| {
+            assert_eq!(e.balance(caller), U256::from(95 * LAMPORT));
+            assert_eq!(e.balance(NATIVE_TRANSFER_ADDR), U256::zero());
+            assert_eq!(e.balance(contract), U256::zero());
+        });
+    }
+
+    #[test]
+    fn native_transfer_is_traced_with_accounts_of_receipt() {
+        let (mut executor, secret_key) = executor();
+        let caller = secret_key.to_address();
+        let receiver = H256::repeat_byte(0x11);
+        let accounts: BTreeMap<_, _> = vec![(receiver, writable(0))].into_iter().collect();
+        let contract = H160::repeat_byte(0x22);
+        let code = forwarder_code(NATIVE_TRANSFER_ADDR, false);
+        executor.with_executor(|e| e.state_mut().set_code(contract, code));
+        let state = executor.evm.evm_state.clone();
+
+        let tx =
+            call(0, contract, 2 * LAMPORT, receiver.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, output, _) = executor
+            .transaction_execute_with_native_accounts(tx.clone(), accounts.clone())
+            .unwrap();
+        let receipt = executor.get_tx_receipt_by_hash(tx.tx_id_hash()).unwrap();
+        assert_eq!(receipt.native_accounts, accounts);
+
+        let mut replay = Executor::with_config(
+            state,
+            Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            5,
+        );
+        replay.set_epoch(7);
+        let trace = replay
+            .transaction_trace_with_native_accounts(
+                tx,
+                receipt.native_accounts,
+                tracing::TraceConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(trace.exit_reason, reason);
+        assert_eq!(trace.return_value, output);
+        assert_eq!(trace.used_gas, executor.used_gas());
+        replay.with_executor(|e| {
+            assert_eq!(e.balance(caller), U256::from(98 * LAMPORT));
+            assert_eq!(e.balance(NATIVE_TRANSFER_ADDR), U256::zero());
+        });
+    }
+
+    #[test]
+    fn native_transfer_rejected() {
+        let (mut executor, secret_key) = executor();
+        let caller = secret_key.to_address();
+        let receiver = H256::repeat_byte(0x11);
+        let accounts: BTreeMap<_, _> = vec![(receiver, writable(0))].into_iter().collect();
+        let transfer = |nonce, value, receiver: H256| {
+            call(
+                nonce,
+                NATIVE_TRANSFER_ADDR,
+                value,
+                receiver.as_bytes().to_vec(),
+            )
+            .sign(&secret_key, None)
+        };
+
+        // fraction of lamport
+        let tx = transfer(0, LAMPORT + 1, receiver);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts.clone())
+            .unwrap();
+        assert!(matches!(reason, ExitReason::Error(_)));
+        assert!(transfers.is_empty());
+
+        // account is not passed
+        let tx = transfer(1, LAMPORT, H256::repeat_byte(0x33));
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts.clone())
+            .unwrap();
+        assert!(matches!(reason, ExitReason::Error(_)));
+        assert!(transfers.is_empty());
+
+        // account is readonly
+        let readonly = vec![(
+            receiver,
+            NativeAccount {
+                lamports: 0,
+                is_writable: false,
+            },
+        )]
+        .into_iter()
+        .collect();
+        let tx = transfer(2, LAMPORT, receiver);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, readonly)
+            .unwrap();
+        assert!(matches!(reason, ExitReason::Error(_)));
+        assert!(transfers.is_empty());
+
+        // reverted by contract
+        let contract = H160::repeat_byte(0x22);
+        let code = forwarder_code(NATIVE_TRANSFER_ADDR, true);
+        executor.with_executor(|e| e.state_mut().set_code(contract, code));
+        let tx = call(3, contract, LAMPORT, receiver.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts.clone())
+            .unwrap();
+        assert!(matches!(reason, ExitReason::Revert(_)));
+        assert!(transfers.is_empty());
+
+        // reverted by inner call, while transaction succeed, value stays with outer contract
+        let outer_contract = H160::repeat_byte(0x33);
+        let code = forwarder_code(contract, false);
+        executor.with_executor(|e| e.state_mut().set_code(outer_contract, code));
+        let tx =
+            call(4, outer_contract, LAMPORT, receiver.as_bytes().to_vec()).sign(&secret_key, None);
+        let (reason, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts.clone())
+            .unwrap();
+        assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+        assert!(transfers.is_empty());
+        executor.with_executor(|e| assert_eq!(e.balance(outer_contract), U256::from(LAMPORT)));
+
+        // delegatecall is not allowed
+        let contract = H160::repeat_byte(0x44);
+        let mut code = vec![0x36, 0x60, 0x00, 0x60, 0x00, 0x37];
+        code.extend(&[0x60, 0x40, 0x60, 0x00, 0x36, 0x60, 0x00, 0x73]);
+        code.extend(NATIVE_TRANSFER_ADDR.as_bytes());
+        code.extend(&[0x5a, 0xf4, 0x00]); // DELEGATECALL(GAS, address, 0, CALLDATASIZE, 0, 0x40)
+        executor.with_executor(|e| e.state_mut().set_code(contract, code));
+        let tx = call(5, contract, LAMPORT, receiver.as_bytes().to_vec()).sign(&secret_key, None);
+        let (_, _, transfers) = executor
+            .transaction_execute_with_native_accounts(tx, accounts)
+            .unwrap();
+        assert!(transfers.is_empty());
+
+        executor.with_executor(|e| {
+            assert_eq!(e.balance(caller), U256::from(98 * LAMPORT));
+            assert_eq!(e.balance(NATIVE_TRANSFER_ADDR), U256::zero());
+        });
+    }
+
+    #[test]
+    fn native_transfers_are_journaled_by_frames() {
+        let receiver = H256::repeat_byte(0x11);
+        let accounts = vec![(receiver, writable(10))].into_iter().collect();
+        let context = |value: u64| Context {
+            address: NATIVE_TRANSFER_ADDR,
+            caller: H160::repeat_byte(0x22),
+            apparent_value: U256::from(value * LAMPORT),
+        };
+        let balance = || super::native_balance(receiver.as_bytes(), None).unwrap().1;
+
+        let (_, transfers) = with_native_context(0, 0, accounts, || {
+            enter_frame();
+            super::native_transfer(receiver.as_bytes(), None, &context(1)).unwrap();
+            enter_frame();
+            super::native_transfer(receiver.as_bytes(), None, &context(2)).unwrap();
+            assert_eq!(balance(), u256_output(&[13.into()]));
+            exit_frame(false);
+            // reverted transfer is not visible to balance
+            assert_eq!(balance(), u256_output(&[11.into()]));
+            enter_frame();
+            super::native_transfer(receiver.as_bytes(), None, &context(4)).unwrap();
+            exit_frame(true);
+            exit_frame(true);
+            assert_eq!(balance(), u256_output(&[15.into()]));
+        });
+        assert_eq!(
+            transfers,
+            vec![
+                NativeTransfer {
+                    receiver,
+                    lamports: 1
+                },
+                NativeTransfer {
+                    receiver,
+                    lamports: 4
+                },
+            ]
+        );
+    }
+}
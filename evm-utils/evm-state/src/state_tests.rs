@@ -0,0 +1,221 @@
+//! Runner of `GeneralStateTests` from https://github.com/ethereum/tests.
+//!
+//! Fixtures aren't stored in repository, `tests/fetch-general-state-tests.sh` copies filled ones
+//! into `tests/fixtures/GeneralStateTests`, keeping directory of each test suite, so the test
+//! is ignored by default. Every post state of Istanbul, the only hardfork supported by executor,
+//! is checked by state root and hash of logs. Unused gas is refunded and fee goes to coinbase,
+//! as in Ethereum.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use evm::executor::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+use primitive_types::{H160, H256, U256};
+use serde_json::Value;
+
+use crate::{
+    precompiles, trie::keccak, types::MemoryVicinity, AccountState, EvmBackend, EvmState, FromKey,
+    Log,
+};
+
+const HARDFORK: &str = "Istanbul";
+
+// Fixtures use both even and odd length hex.
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() % 2 == 1 {
+        hex::decode(format!("0{}", hex))
+    } else {
+        hex::decode(hex)
+    }
+    .unwrap_or_else(|err| panic!("Invalid hex {}: {}", hex, err))
+}
+
+fn bytes(value: &Value) -> Vec<u8> {
+    hex_bytes(value.as_str().expect("Fixture value should be hex string"))
+}
+
+fn u256(value: &Value) -> U256 {
+    U256::from_big_endian(&bytes(value))
+}
+
+fn h256(value: U256) -> H256 {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+// Consensus hash of logs, that is stored in post state of fixture.
+fn logs_hash(logs: &[Log]) -> H256 {
+    let mut stream = rlp::RlpStream::new_list(logs.len());
+    for log in logs {
+        stream.begin_list(3);
+        stream.append(&log.address);
+        stream.append_list(&log.topics);
+        stream.append(&log.data);
+    }
+    keccak(&stream.out())
+}
+
+fn pre_state(pre: &Value) -> EvmState {
+    let mut state = EvmState::default();
+    for (address, account) in pre.as_object().expect("Pre state should be object") {
+        let address = H160::from_slice(&hex_bytes(address));
+        state.accounts.insert(
+            address,
+            AccountState {
+                nonce: u256(&account["nonce"]),
+                balance: u256(&account["balance"]),
+                code: bytes(&account["code"]),
+            },
+        );
+        for (index, value) in account["storage"]
+            .as_object()
+            .expect("Storage should be object")
+        {
+            let index = h256(U256::from_big_endian(&hex_bytes(index)));
+            state.set_storage(address, index, h256(u256(value)));
+        }
+    }
+    state.freeze();
+    state.try_fork(1).expect("Unable to fork pre state")
+}
+
+fn run_case(name: &str, test: &Value, post: &Value) {
+    let env = &test["env"];
+    let tx = &test["transaction"];
+    let indexed = |field: &str, index: &str| {
+        let index = post["indexes"][index]
+            .as_u64()
+            .expect("Index should be number");
+        &tx[field][index as usize]
+    };
+
+    let secret_key = secp256k1::SecretKey::from_slice(&bytes(&tx["secretKey"]))
+        .expect("Fixture secret key should be valid");
+    let caller = secret_key.to_address();
+    let gas_price = u256(&tx["gasPrice"]);
+    let gas_limit = u256(indexed("gasLimit", "gas"));
+    let value = u256(indexed("value", "value"));
+    let data = bytes(indexed("data", "data"));
+    let coinbase = H160::from_slice(&bytes(&env["currentCoinbase"]));
+
+    let vicinity = MemoryVicinity {
+        gas_price,
+        origin: caller,
+        chain_id: U256::one(),
+        block_hashes: vec![],
+        block_number: u256(&env["currentNumber"]),
+        block_coinbase: coinbase,
+        block_timestamp: u256(&env["currentTimestamp"]),
+        block_difficulty: u256(&env["currentDifficulty"]),
+        block_gas_limit: u256(&env["currentGasLimit"]),
+    };
+    let mut backend = EvmBackend::new_from_state(pre_state(&test["pre"]), vicinity);
+
+    // Invalid transaction is not included in block, so post state equals to pre state.
+    let sender = backend.evm_state.get_account(caller).unwrap_or_default();
+    let max_fee = gas_limit.saturating_mul(gas_price);
+    let is_valid = sender.nonce == u256(&tx["nonce"])
+        && gas_limit <= u64::MAX.into()
+        && max_fee
+            .checked_add(value)
+            .map_or(false, |cost| sender.balance >= cost);
+
+    let logs = if is_valid {
+        let config = evm::Config::istanbul();
+        let gas_limit = gas_limit.as_u64();
+        let metadata = StackSubstateMetadata::new(gas_limit, &config);
+        let state = MemoryStackState::new(metadata, &backend);
+        let mut executor =
+            StackExecutor::new_with_precompile(state, &config, precompiles::no_precompiles);
+        executor
+            .state_mut()
+            .withdraw(caller, max_fee)
+            .expect("Sender balance was checked");
+        let to = bytes(&tx["to"]);
+        if to.is_empty() {
+            executor.transact_create(caller, value, data, gas_limit);
+        } else {
+            executor.transact_call(caller, H160::from_slice(&to), value, data, gas_limit);
+        }
+        let fee = executor.fee(gas_price);
+        executor.state_mut().deposit(coinbase, fee);
+        executor.state_mut().deposit(caller, max_fee - fee);
+
+        let (updates, logs) = executor.into_state().deconstruct();
+        let logs: Vec<Log> = logs.into_iter().collect();
+        backend.apply(updates, true, true);
+        logs
+    } else {
+        vec![]
+    };
+
+    let mut state = backend.evm_state;
+    state.freeze();
+    assert_eq!(
+        state.state_root(),
+        H256::from_slice(&bytes(&post["hash"])),
+        "{}: state root of post state {}",
+        name,
+        post["indexes"]
+    );
+    assert_eq!(
+        logs_hash(&logs),
+        H256::from_slice(&bytes(&post["logs"])),
+        "{}: logs of post state {}",
+        name,
+        post["indexes"]
+    );
+}
+
+// Runs all post states of fixture file, returns count of checked post states.
+fn run_fixture(path: &Path) -> usize {
+    let content = fs::read(path).unwrap_or_else(|err| panic!("Can't read {:?}: {}", path, err));
+    let fixture: Value = serde_json::from_slice(&content)
+        .unwrap_or_else(|err| panic!("Can't parse {:?}: {}", path, err));
+
+    let mut checked = 0;
+    for (name, test) in fixture.as_object().expect("Fixture should be object") {
+        let posts = match test["post"].get(HARDFORK) {
+            Some(posts) => posts.as_array().expect("Post states should be array"),
+            None => continue,
+        };
+        for post in posts {
+            if post.get("expectException").is_some() {
+                continue;
+            }
+            run_case(name, test, post);
+            checked += 1;
+        }
+    }
+    checked
+}
+
+fn fixture_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap_or_else(|err| panic!("Can't read {:?}: {}", dir, err)) {
+        let path = entry.expect("Unable to read directory entry").path();
+        if path.is_dir() {
+            fixture_files(&path, files);
+        } else if path.extension().map_or(false, |ext| ext == "json") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+#[ignore]
+fn general_state_tests() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/GeneralStateTests");
+    let mut files = vec![];
+    fixture_files(&dir, &mut files);
+    files.sort();
+
+    let checked: usize = files.iter().map(|path| run_fixture(path)).sum();
+    assert!(
+        checked > 0,
+        "No post states of {} in {:?}, run tests/fetch-general-state-tests.sh",
+        HARDFORK,
+        dir
+    );
+}
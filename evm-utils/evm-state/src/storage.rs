@@ -8,7 +8,7 @@ use std::{
     mem::size_of,
     ops::{Deref, Sub},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use bincode::config::{BigEndian, DefaultOptions, Options as _, WithOtherEndian};
@@ -66,6 +66,9 @@ impl AsRef<Path> for Location {
 pub struct VersionedStorage<V> {
     db: Arc<DB>,
     location: Location,
+    // Shared by all clones, squash and removal of versions hold it exclusively,
+    // so lookups that walk through track of versions don't observe them half done.
+    maintenance: Arc<RwLock<()>>,
     _version: PhantomData<V>,
 }
 
@@ -74,11 +77,28 @@ impl<V> Clone for VersionedStorage<V> {
         Self {
             db: Arc::clone(&self.db),
             location: self.location.clone(),
+            maintenance: Arc::clone(&self.maintenance),
             _version: PhantomData,
         }
     }
 }
 
+impl<V> VersionedStorage<V> {
+    /// Exclusive access for changes of version history, like squash or removal of versions.
+    /// Lookups by track of versions wait until it's released.
+    pub fn maintenance_lock(&self) -> RwLockWriteGuard<'_, ()> {
+        self.maintenance
+            .write()
+            .expect("storage maintenance lock was poisoned")
+    }
+
+    fn lookup_lock(&self) -> RwLockReadGuard<'_, ()> {
+        self.maintenance
+            .read()
+            .expect("storage maintenance lock was poisoned")
+    }
+}
+
 type Previous<V> = Option<V>; // TODO: Vec<V>
 
 trait BincodeResultExt<T> {
@@ -175,6 +195,66 @@ where
         self.db.flush_opt(&opts)?;
         Ok(())
     }
+
+    /// Removes `versions` with all their data in `columns`, and compacts freed key range.
+    pub fn remove_versions<S: AsRef<str>>(
+        &self,
+        columns: impl IntoIterator<Item = S>,
+        versions: &[V],
+    ) -> Result<()>
+    where
+        V: AsBytePrefix + Ord,
+    {
+        let (first, last) = match (versions.iter().min(), versions.iter().max()) {
+            (Some(first), Some(last)) => (first.to_bytes(), last.to_bytes()),
+            _ => return Ok(()),
+        };
+
+        for version in versions {
+            self.db.delete(CODER.serialize(version).typed_ctx()?)?;
+        }
+
+        for column in columns {
+            let cf = self
+                .db
+                .cf_handle(column.as_ref())
+                .expect("Column family is opened with storage");
+            let mut batch = WriteBatch::default();
+            for version in versions {
+                for (key, _) in self.db.prefix_iterator_cf(cf, version.to_bytes()) {
+                    batch.delete_cf(cf, key);
+                }
+            }
+            self.db.write(batch)?;
+            self.db
+                .compact_range_cf(cf, Some(first.as_ref()), Some(last.as_ref()));
+        }
+        Ok(())
+    }
+
+    /// Size of sst files and estimated size of live data in `columns`, in bytes.
+    pub fn columns_size<S: AsRef<str>>(
+        &self,
+        columns: impl IntoIterator<Item = S>,
+    ) -> Result<(u64, u64)> {
+        let mut sst_files_size = 0;
+        let mut live_data_size = 0;
+        for column in columns {
+            let cf = self
+                .db
+                .cf_handle(column.as_ref())
+                .expect("Column family is opened with storage");
+            sst_files_size += self
+                .db
+                .property_int_value_cf(cf, "rocksdb.total-sst-files-size")?
+                .unwrap_or_default();
+            live_data_size += self
+                .db
+                .property_int_value_cf(cf, "rocksdb.estimate-live-data-size")?
+                .unwrap_or_default();
+        }
+        Ok((sst_files_size, live_data_size))
+    }
 }
 
 const KEEP_N_BACKUPS: usize = 12; // TODO: tweak it
@@ -283,6 +363,7 @@ where
         Ok(Self {
             db,
             location,
+            maintenance: Arc::new(RwLock::new(())),
             _version: PhantomData,
         })
     }
@@ -599,6 +680,7 @@ where
         M::Key: Debug,
         M::Value: Debug,
     {
+        let _lookup_lock = self.storage.lookup_lock();
         let mut next_version = Some(version);
         let mut previous: Option<V>; // TOOD: simplify assignment flow
 
@@ -848,10 +930,18 @@ primitive_type_has_max! {
     H160, H256, H512
 }
 
+impl HasMax for () {
+    const MAX: () = ();
+}
+
 impl<A: HasMax, B: HasMax> HasMax for (A, B) {
     const MAX: (A, B) = (A::MAX, B::MAX);
 }
 
+impl<A: HasMax, B: HasMax, C: HasMax> HasMax for (A, B, C) {
+    const MAX: (A, B, C) = (A::MAX, B::MAX, C::MAX);
+}
+
 impl<V> Debug for VersionedStorage<V>
 where
     V: 'static,
@@ -0,0 +1,536 @@
+//! Execution tracing, used by debug rpc to re-execute transactions.
+//!
+//! `evm` doesn't provide hooks into interpreter loop, so tracing uses its own copy of stack executor.
+//! The copy follows `evm` version pinned in `Cargo.toml`, and `trace_matches_execution` checks
+//! that traced transactions produce the same result, gas and state as executed ones.
+
+mod executor;
+mod state;
+
+pub(crate) use executor::{StackSubstateMetadata, TracingExecutor};
+pub(crate) use state::MemoryStackState;
+
+use std::collections::{BTreeMap, HashMap};
+
+use evm::{ExitReason, Machine, Opcode, Stack};
+use primitive_types::{H160, H256, U256};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraceConfig {
+    pub disable_memory: bool,
+    pub disable_stack: bool,
+    pub disable_storage: bool,
+}
+
+/// Single executed opcode, in the same format as geth `structLogs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: Opcode,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: usize,
+    pub error: Option<String>,
+    pub stack: Option<Vec<U256>>,
+    pub memory: Option<Vec<H256>>,
+    pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallType {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+}
+
+/// Call of contract, in the same format as geth `callTracer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallFrame {
+    pub call_type: CallType,
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+    pub gas: u64,
+    pub gas_used: u64,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    pub error: Option<String>,
+    pub calls: Vec<CallFrame>,
+}
+
+/// Trace of transaction execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub used_gas: u64,
+    pub exit_reason: ExitReason,
+    pub return_value: Vec<u8>,
+    pub struct_logs: Vec<StructLog>,
+    pub call: Option<CallFrame>,
+}
+
+/// Collects struct logs and call frames during execution.
+#[derive(Debug, Default)]
+pub(crate) struct Tracer {
+    config: TraceConfig,
+    struct_logs: Vec<StructLog>,
+    // Storage that was accessed by contract during execution.
+    storages: HashMap<H160, BTreeMap<H256, H256>>,
+    frames: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl Tracer {
+    pub fn new(config: TraceConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn enter_frame(&mut self, frame: CallFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn exit_frame(&mut self, gas_used: u64, reason: &ExitReason, output: &[u8]) {
+        let mut frame = self
+            .frames
+            .pop()
+            .expect("Frame should be entered before exit");
+        frame.gas_used = gas_used;
+        frame.output = output.to_vec();
+        frame.error = exit_error(reason);
+        match self.frames.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+
+    /// Record opcode before execution, returns index of recorded log.
+    pub fn record_step(
+        &mut self,
+        machine: &Machine,
+        address: H160,
+        pc: usize,
+        gas: u64,
+        storage_access: Option<(H256, H256)>,
+    ) -> Option<usize> {
+        let (op, stack) = machine.inspect()?;
+        if let Some((index, value)) = storage_access {
+            self.storages
+                .entry(address)
+                .or_default()
+                .insert(index, value);
+        }
+
+        let stack = if self.config.disable_stack {
+            None
+        } else {
+            Some(
+                (0..stack.len())
+                    .rev()
+                    .map(|i| {
+                        let value = stack.peek(i).expect("Index is in stack bounds");
+                        U256::from_big_endian(value.as_bytes())
+                    })
+                    .collect(),
+            )
+        };
+        let memory = if self.config.disable_memory {
+            None
+        } else {
+            let memory = machine.memory();
+            Some(
+                memory
+                    .get(0, memory.len())
+                    .chunks(32)
+                    .map(H256::from_slice)
+                    .collect(),
+            )
+        };
+        let storage = if self.config.disable_storage {
+            None
+        } else {
+            Some(self.storages.get(&address).cloned().unwrap_or_default())
+        };
+
+        self.struct_logs.push(StructLog {
+            pc,
+            op,
+            gas,
+            gas_cost: 0,
+            depth: self.frames.len(),
+            error: None,
+            stack,
+            memory,
+            storage,
+        });
+        Some(self.struct_logs.len() - 1)
+    }
+
+    pub fn finish_step(&mut self, index: usize, gas_cost: u64, reason: Option<&ExitReason>) {
+        let log = &mut self.struct_logs[index];
+        log.gas_cost = gas_cost;
+        log.error = reason.and_then(exit_error);
+    }
+
+    pub fn into_trace(self) -> (Vec<StructLog>, Option<CallFrame>) {
+        (self.struct_logs, self.root)
+    }
+}
+
+fn exit_error(reason: &ExitReason) -> Option<String> {
+    match reason {
+        ExitReason::Succeed(_) => None,
+        ExitReason::Revert(_) => Some("execution reverted".to_string()),
+        ExitReason::Error(error) => Some(format!("{:?}", error)),
+        ExitReason::Fatal(error) => Some(format!("{:?}", error)),
+    }
+}
+
+/// Program counter of next opcode, `stack` is the state before opcode execution.
+pub(crate) fn next_pc(pc: usize, opcode: Opcode, stack: &Stack) -> usize {
+    let peek = |n| {
+        stack
+            .peek(n)
+            .map(|value| U256::from_big_endian(value.as_bytes()))
+            .unwrap_or_default()
+    };
+    match opcode.as_u8() {
+        // JUMP
+        0x56 => peek(0).low_u64() as usize,
+        // JUMPI
+        0x57 if !peek(1).is_zero() => peek(0).low_u64() as usize,
+        // PUSH1..PUSH32
+        op @ 0x60..=0x7f => pc + 1 + (op - 0x5f) as usize,
+        _ => pc + 1,
+    }
+}
+
+/// Name of opcode, as it used by geth.
+pub fn opcode_name(opcode: Opcode) -> String {
+    let name = match opcode.as_u8() {
+        0x00 => "STOP",
+        0x01 => "ADD",
+        0x02 => "MUL",
+        0x03 => "SUB",
+        0x04 => "DIV",
+        0x05 => "SDIV",
+        0x06 => "MOD",
+        0x07 => "SMOD",
+        0x08 => "ADDMOD",
+        0x09 => "MULMOD",
+        0x0a => "EXP",
+        0x0b => "SIGNEXTEND",
+        0x10 => "LT",
+        0x11 => "GT",
+        0x12 => "SLT",
+        0x13 => "SGT",
+        0x14 => "EQ",
+        0x15 => "ISZERO",
+        0x16 => "AND",
+        0x17 => "OR",
+        0x18 => "XOR",
+        0x19 => "NOT",
+        0x1a => "BYTE",
+        0x1b => "SHL",
+        0x1c => "SHR",
+        0x1d => "SAR",
+        0x20 => "SHA3",
+        0x30 => "ADDRESS",
+        0x31 => "BALANCE",
+        0x32 => "ORIGIN",
+        0x33 => "CALLER",
+        0x34 => "CALLVALUE",
+        0x35 => "CALLDATALOAD",
+        0x36 => "CALLDATASIZE",
+        0x37 => "CALLDATACOPY",
+        0x38 => "CODESIZE",
+        0x39 => "CODECOPY",
+        0x3a => "GASPRICE",
+        0x3b => "EXTCODESIZE",
+        0x3c => "EXTCODECOPY",
+        0x3d => "RETURNDATASIZE",
+        0x3e => "RETURNDATACOPY",
+        0x3f => "EXTCODEHASH",
+        0x40 => "BLOCKHASH",
+        0x41 => "COINBASE",
+        0x42 => "TIMESTAMP",
+        0x43 => "NUMBER",
+        0x44 => "DIFFICULTY",
+        0x45 => "GASLIMIT",
+        0x46 => "CHAINID",
+        0x47 => "SELFBALANCE",
+        0x50 => "POP",
+        0x51 => "MLOAD",
+        0x52 => "MSTORE",
+        0x53 => "MSTORE8",
+        0x54 => "SLOAD",
+        0x55 => "SSTORE",
+        0x56 => "JUMP",
+        0x57 => "JUMPI",
+        0x58 => "PC",
+        0x59 => "MSIZE",
+        0x5a => "GAS",
+        0x5b => "JUMPDEST",
+        op @ 0x60..=0x7f => return format!("PUSH{}", op - 0x5f),
+        op @ 0x80..=0x8f => return format!("DUP{}", op - 0x7f),
+        op @ 0x90..=0x9f => return format!("SWAP{}", op - 0x8f),
+        op @ 0xa0..=0xa4 => return format!("LOG{}", op - 0xa0),
+        0xf0 => "CREATE",
+        0xf1 => "CALL",
+        0xf2 => "CALLCODE",
+        0xf3 => "RETURN",
+        0xf4 => "DELEGATECALL",
+        0xf5 => "CREATE2",
+        0xfa => "STATICCALL",
+        0xfd => "REVERT",
+        0xfe => "INVALID",
+        0xff => "SELFDESTRUCT",
+        op => return format!("opcode 0x{:x} not defined", op),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn executor(contracts: &[(H160, Vec<u8>)]) -> (Executor, secp256k1::SecretKey) {
+        let mut executor = Executor::with_config(
+            EvmState::default(),
+            Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        let secret_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let caller = secret_key.to_address();
+        executor.with_executor(|e| {
+            for (address, code) in contracts {
+                e.state_mut().set_code(*address, code.clone());
+            }
+            e.state_mut().deposit(caller, U256::from(1_000_000_000))
+        });
+        (executor, secret_key)
+    }
+
+    fn call(address: H160) -> UnsignedTransaction {
+        UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: 0.into(),
+            gas_limit: 300_000.into(),
+            action: TransactionAction::Call(address),
+            value: 0.into(),
+            input: vec![],
+        }
+    }
+
+    #[test]
+    fn trace_matches_execution() {
+        let contract = H160::repeat_byte(0x11);
+        let callee = H160::repeat_byte(0x22);
+        let callee_code = vec![0x60, 0x00, 0x80, 0xfd]; // REVERT(0, 0)
+        let mut code = vec![0x60, 0x2a, 0x60, 0x01, 0x55]; // SSTORE(1, 42)
+        code.extend(&[0x60, 0x00, 0x80, 0x80, 0x80, 0x80, 0x73]);
+        code.extend(callee.as_bytes());
+        code.extend(&[0x5a, 0xf1, 0x00]); // CALL(GAS, callee, 0, 0, 0, 0, 0)
+
+        // Deploys SSTORE(0, 42).
+        let init_code = vec![
+            0x60, 0x06, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x06, 0x60, 0x00, 0xf3, 0x60, 0x2a,
+            0x60, 0x00, 0x55, 0x00,
+        ];
+        let (mut traced, secret_key) = executor(&[(contract, code), (callee, callee_code)]);
+        let mut executed = Executor::with_config(
+            traced.evm.evm_state.clone(),
+            Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+
+        let txs = vec![
+            call(contract),
+            UnsignedTransaction {
+                action: TransactionAction::Create,
+                input: init_code,
+                ..call(contract)
+            },
+            UnsignedTransaction {
+                value: 1000.into(),
+                ..call(H160::repeat_byte(0x33))
+            },
+        ];
+        for (nonce, tx) in txs.into_iter().enumerate() {
+            let tx = UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 1.into(),
+                ..tx
+            }
+            .sign(&secret_key, Some(TEST_CHAIN_ID));
+            let hash = tx.tx_id_hash();
+
+            let (reason, output) = executed.transaction_execute(tx.clone()).unwrap();
+            let trace = traced
+                .transaction_trace(tx, TraceConfig::default())
+                .unwrap();
+            assert!(matches!(reason, ExitReason::Succeed(_)));
+            assert_eq!(trace.exit_reason, reason);
+            assert_eq!(trace.return_value, output);
+            assert_eq!(
+                traced.get_tx_receipt_by_hash(hash),
+                executed.get_tx_receipt_by_hash(hash)
+            );
+        }
+        assert_eq!(traced.used_gas(), executed.used_gas());
+        assert_eq!(traced.fee_collected(), executed.fee_collected());
+
+        let mut traced = traced.deconstruct();
+        let mut executed = executed.deconstruct();
+        traced.freeze();
+        executed.freeze();
+        assert_eq!(traced.state_root(), executed.state_root());
+    }
+
+    #[test]
+    fn struct_logs_follow_execution() {
+        let contract = H160::repeat_byte(0x11);
+        let code = vec![
+            0x60, 0x04, 0x56, 0xfe, 0x5b, // JUMP(4) over INVALID
+            0x60, 0x2a, 0x60, 0x01, 0x55, // SSTORE(1, 42)
+            0x60, 0x01, 0x54, 0x60, 0x00, 0x52, // MSTORE(0, SLOAD(1))
+            0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN(0, 32)
+        ];
+        let (mut executor, secret_key) = executor(&[(contract, code)]);
+        let tx = call(contract).sign(&secret_key, Some(TEST_CHAIN_ID));
+
+        let mut expected = Executor::with_config(
+            executor.evm.evm_state.clone(),
+            Config::istanbul(),
+            u64::max_value(),
+            TEST_CHAIN_ID,
+            0,
+        );
+        let (reason, output) = expected.transaction_execute(tx.clone()).unwrap();
+
+        let trace = executor
+            .transaction_trace(tx, TraceConfig::default())
+            .unwrap();
+        assert_eq!(trace.exit_reason, reason);
+        assert_eq!(trace.return_value, output);
+        assert_eq!(trace.used_gas, expected.used_gas());
+        assert_eq!(executor.used_gas(), expected.used_gas());
+
+        let ops: Vec<_> = trace
+            .struct_logs
+            .iter()
+            .map(|log| (log.pc, opcode_name(log.op)))
+            .collect();
+        let expected_ops = vec![
+            (0, "PUSH1"),
+            (2, "JUMP"),
+            (4, "JUMPDEST"),
+            (5, "PUSH1"),
+            (7, "PUSH1"),
+            (9, "SSTORE"),
+            (10, "PUSH1"),
+            (12, "SLOAD"),
+            (13, "PUSH1"),
+            (15, "MSTORE"),
+            (16, "PUSH1"),
+            (18, "PUSH1"),
+            (20, "RETURN"),
+        ];
+        let expected_ops: Vec<_> = expected_ops
+            .into_iter()
+            .map(|(pc, op)| (pc, op.to_string()))
+            .collect();
+        assert_eq!(ops, expected_ops);
+
+        let first = &trace.struct_logs[0];
+        assert_eq!(first.depth, 1);
+        assert_eq!(first.gas_cost, 3);
+        assert_eq!(first.stack, Some(vec![]));
+        assert_eq!(trace.struct_logs[1].stack, Some(vec![4.into()]));
+        assert_eq!(trace.struct_logs[1].gas, first.gas - 3);
+
+        let sstore = &trace.struct_logs[5];
+        let storage: BTreeMap<_, _> = vec![(H256::from_low_u64_be(1), H256::from_low_u64_be(42))]
+            .into_iter()
+            .collect();
+        assert_eq!(sstore.storage, Some(storage));
+        assert_eq!(sstore.stack, Some(vec![42.into(), 1.into()]));
+
+        let ret = trace.struct_logs.last().unwrap();
+        assert_eq!(ret.memory, Some(vec![H256::from_low_u64_be(42)]));
+        assert_eq!(ret.error, None);
+
+        let frame = trace.call.unwrap();
+        assert_eq!(frame.call_type, CallType::Call);
+        assert_eq!(frame.to, contract);
+        assert_eq!(frame.output, output);
+        assert!(frame.calls.is_empty());
+    }
+
+    #[test]
+    fn trace_config_disables_fields() {
+        let contract = H160::repeat_byte(0x11);
+        let code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]; // SSTORE(0, 1)
+        let (mut executor, secret_key) = executor(&[(contract, code)]);
+        let tx = call(contract).sign(&secret_key, Some(TEST_CHAIN_ID));
+
+        let config = TraceConfig {
+            disable_memory: true,
+            disable_stack: true,
+            disable_storage: true,
+        };
+        let trace = executor.transaction_trace(tx, config).unwrap();
+        assert_eq!(trace.struct_logs.len(), 4);
+        for log in trace.struct_logs {
+            assert_eq!(log.stack, None);
+            assert_eq!(log.memory, None);
+            assert_eq!(log.storage, None);
+        }
+    }
+
+    #[test]
+    fn call_tree_with_reverted_subcall() {
+        let inner = H160::repeat_byte(0x22);
+        let inner_code = vec![0x60, 0x00, 0x80, 0xfd]; // REVERT(0, 0)
+        let outer = H160::repeat_byte(0x11);
+        let mut outer_code = vec![0x60, 0x00, 0x80, 0x80, 0x80, 0x80, 0x73];
+        outer_code.extend(inner.as_bytes());
+        outer_code.extend(&[0x5a, 0xf1, 0x00]); // CALL(GAS, inner, 0, 0, 0, 0, 0)
+        let (mut executor, secret_key) = executor(&[(outer, outer_code), (inner, inner_code)]);
+        let tx = call(outer).sign(&secret_key, Some(TEST_CHAIN_ID));
+
+        let trace = executor
+            .transaction_trace(tx, TraceConfig::default())
+            .unwrap();
+        assert_eq!(trace.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+        let frame = trace.call.unwrap();
+        assert_eq!(frame.to, outer);
+        assert_eq!(frame.error, None);
+        assert_eq!(frame.calls.len(), 1);
+        let subcall = &frame.calls[0];
+        assert_eq!(subcall.call_type, CallType::Call);
+        assert_eq!(subcall.from, outer);
+        assert_eq!(subcall.to, inner);
+        assert_eq!(subcall.error, Some("execution reverted".to_string()));
+        assert!(subcall.gas_used > 0 && subcall.gas_used < frame.gas_used);
+
+        let depths: Vec<_> = trace.struct_logs.iter().map(|log| log.depth).collect();
+        assert_eq!(depths.iter().filter(|depth| **depth == 2).count(), 3);
+        let revert = trace
+            .struct_logs
+            .iter()
+            .find(|log| opcode_name(log.op) == "REVERT")
+            .unwrap();
+        assert_eq!(revert.depth, 2);
+        assert_eq!(revert.error, Some("execution reverted".to_string()));
+    }
+}
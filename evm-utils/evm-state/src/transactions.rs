@@ -3,9 +3,13 @@ use primitive_types::{H160, H256, U256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use crate::error::*;
+use crate::precompiles::NativeAccount;
+use crate::trie::ordered_trie_root;
+use ethbloom::{Bloom, Input as BloomInput};
 use secp256k1::{
     recovery::{RecoverableSignature, RecoveryId},
     Message,
@@ -72,6 +76,11 @@ impl Transaction {
         self.signing_rlp_append(&mut stream, chain_id);
         H256::from_slice(Keccak256::digest(&stream.as_raw()).as_slice())
     }
+
+    /// Canonical hash of signed transaction `keccak(rlp(tx))`, that is used by wallets and explorers.
+    pub fn tx_id_hash(&self) -> H256 {
+        H256::from_slice(Keccak256::digest(&rlp::encode(self)).as_slice())
+    }
 }
 
 #[derive(Clone)]
@@ -291,7 +300,7 @@ impl From<Transaction> for UnsignedTransaction {
     }
 }
 
-// TODO: Work on logs and state_root.
+// TODO: Work on state_root.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TransactionReceipt {
     pub transaction: Transaction,
@@ -300,11 +309,23 @@ pub struct TransactionReceipt {
     pub index: u64,
     // pub state_root: H256,
     pub used_gas: Gas,
-    // pub logs_bloom: LogsBloom,
+    /// Gas used by this and all previous transactions in block.
+    pub cumulative_gas_used: Gas,
+    pub logs_bloom: Bloom,
     pub logs: Vec<Log>,
+    /// Index in block of the first log emitted by transaction.
+    pub first_log_index: u64,
+    /// Address of contract created by transaction.
+    pub contract_address: Option<Address>,
+    /// Data returned by reverted transaction, usually encoded revert reason.
+    pub revert_output: Vec<u8>,
+    /// Native accounts, that were available to precompiles, used to replay transaction.
+    pub native_accounts: BTreeMap<H256, NativeAccount>,
 }
 
 impl TransactionReceipt {
+    /// Receipt of the first transaction in block,
+    /// use `follow` to place it after previous one.
     pub fn new(
         transaction: Transaction,
         used_gas: Gas,
@@ -313,15 +334,104 @@ impl TransactionReceipt {
         logs: Vec<Log>,
         result: (evm::ExitReason, Vec<u8>),
     ) -> TransactionReceipt {
+        let contract_address = match transaction.action {
+            TransactionAction::Create => transaction.address().ok(),
+            _ => None,
+        };
+        let revert_output = match result.0 {
+            evm::ExitReason::Revert(_) => result.1,
+            _ => vec![],
+        };
         TransactionReceipt {
             status: result.0,
             transaction,
             used_gas,
+            cumulative_gas_used: used_gas,
+            logs_bloom: logs_bloom(&logs),
             block_number,
             index,
             logs,
+            first_log_index: 0,
+            contract_address,
+            revert_output,
+            native_accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Accounts gas and logs of previous transaction in block.
+    pub fn follow(&mut self, previous: &TransactionReceipt) {
+        self.cumulative_gas_used = previous.cumulative_gas_used + self.used_gas;
+        self.first_log_index = previous.first_log_index + previous.logs.len() as u64;
+    }
+
+    /// Consensus encoding of receipt: `rlp([status, cumulative_gas, logs_bloom, logs])`.
+    fn rlp_append_with_gas(&self, s: &mut RlpStream, cumulative_gas: Gas) {
+        s.begin_list(4);
+        s.append(&(self.status.is_succeed() as u8));
+        s.append(&cumulative_gas);
+        s.append(&self.logs_bloom);
+        s.begin_list(self.logs.len());
+        for log in &self.logs {
+            s.begin_list(3);
+            s.append(&log.address);
+            s.append_list(&log.topics);
+            s.append(&log.data);
+        }
+    }
+}
+
+/// Receipt format, that was stored before canonical hashes were introduced.
+/// Such receipts are keyed by `Transaction::signing_hash`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LegacyTransactionReceipt {
+    pub transaction: Transaction,
+    pub status: evm::ExitReason,
+    pub block_number: u64,
+    pub index: u64,
+    pub used_gas: Gas,
+    pub logs: Vec<Log>,
+}
+
+impl From<LegacyTransactionReceipt> for TransactionReceipt {
+    /// Block position (`cumulative_gas_used`, `first_log_index`) should be set with `follow`,
+    /// revert output wasn't stored and is lost.
+    fn from(receipt: LegacyTransactionReceipt) -> Self {
+        TransactionReceipt::new(
+            receipt.transaction,
+            receipt.used_gas,
+            receipt.block_number,
+            receipt.index,
+            receipt.logs,
+            (receipt.status, vec![]),
+        )
+    }
+}
+
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = Bloom::default();
+    for log in logs {
+        bloom.accrue(BloomInput::Raw(log.address.as_bytes()));
+        for topic in &log.topics {
+            bloom.accrue(BloomInput::Raw(topic.as_bytes()));
         }
     }
+    bloom
+}
+
+/// Root of block transactions trie, transactions should be in order of execution.
+pub fn transactions_root<'a>(transactions: impl IntoIterator<Item = &'a Transaction>) -> H256 {
+    ordered_trie_root(transactions.into_iter().map(|tx| rlp::encode(tx).to_vec()))
+}
+
+/// Root of block receipts trie, receipts should be in order of execution.
+pub fn receipts_root<'a>(receipts: impl IntoIterator<Item = &'a TransactionReceipt>) -> H256 {
+    let mut cumulative_gas = Gas::zero();
+    ordered_trie_root(receipts.into_iter().map(|receipt| {
+        cumulative_gas += receipt.used_gas;
+        let mut stream = RlpStream::new();
+        receipt.rlp_append_with_gas(&mut stream, cumulative_gas);
+        stream.out().to_vec()
+    }))
 }
 
 pub fn addr_from_public_key(key: &PublicKey) -> H160 {
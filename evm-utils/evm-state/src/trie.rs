@@ -0,0 +1,656 @@
+//! Ethereum compatible Merkle Patricia Trie.
+//!
+//! Nodes are addressed by keccak of their rlp encoding and are loaded lazily from
+//! any `NodeStore`. Modified nodes are kept in memory until `Trie::commit`,
+//! which returns new root with all nodes that should be persisted.
+
+use std::collections::HashMap;
+
+use primitive_types::H256;
+use rlp::{DecoderError, Rlp, RlpStream};
+use sha3::{Digest, Keccak256};
+
+/// Root of trie without any key, `keccak(rlp(""))`.
+pub const EMPTY_TRIE_HASH: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// Nodes created during trie modification, by their hashes.
+pub type NodesDiff = HashMap<H256, Vec<u8>>;
+
+pub trait NodeStore {
+    /// Returns rlp encoded node by its hash.
+    fn get_node(&self, hash: H256) -> Option<Vec<u8>>;
+}
+
+impl NodeStore for NodesDiff {
+    fn get_node(&self, hash: H256) -> Option<Vec<u8>> {
+        self.get(&hash).cloned()
+    }
+}
+
+pub fn keccak(bytes: &[u8]) -> H256 {
+    H256::from_slice(Keccak256::digest(bytes).as_slice())
+}
+
+/// Root of trie with `rlp(index) => item` entries, used for block transactions and receipts.
+pub fn ordered_trie_root<I, T>(items: I) -> H256
+where
+    I: IntoIterator<Item = T>,
+    T: Into<Vec<u8>>,
+{
+    let store = NodesDiff::new();
+    let mut trie = Trie::new(&store, EMPTY_TRIE_HASH);
+    for (index, item) in items.into_iter().enumerate() {
+        trie.insert(&rlp::encode(&index), item.into());
+    }
+    trie.commit().0
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Child,
+    },
+    Branch {
+        children: Box<[Child; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Child {
+    Empty,
+    /// Node that is stored separately, referenced by hash.
+    Hash(H256),
+    /// Node that was loaded or modified, or is inlined into parent (rlp shorter than 32 bytes).
+    Node(Box<Node>),
+}
+
+impl Default for Child {
+    fn default() -> Self {
+        Child::Empty
+    }
+}
+
+impl From<Node> for Child {
+    fn from(node: Node) -> Self {
+        Child::Node(Box::new(node))
+    }
+}
+
+pub struct Trie<'s, S: ?Sized> {
+    store: &'s S,
+    root: Child,
+}
+
+impl<'s, S: NodeStore + ?Sized> Trie<'s, S> {
+    pub fn new(store: &'s S, root: H256) -> Self {
+        let root = if root == EMPTY_TRIE_HASH {
+            Child::Empty
+        } else {
+            Child::Hash(root)
+        };
+        Self { store, root }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.get_at(&self.root, &to_nibbles(key))
+    }
+
+    /// Inserts value by key, empty value is treated as removal.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        if value.is_empty() {
+            return self.remove(key);
+        }
+        let root = std::mem::take(&mut self.root);
+        let root = self.take(root);
+        self.root = self.insert_at(root, &to_nibbles(key), value).into();
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        let root = std::mem::take(&mut self.root);
+        let root = self.take(root);
+        self.root = self
+            .remove_at(root, &to_nibbles(key))
+            .map(Child::from)
+            .unwrap_or_default();
+    }
+
+    /// Calculates root hash, and collects all new nodes.
+    pub fn commit(self) -> (H256, NodesDiff) {
+        let mut diff = NodesDiff::new();
+        let root = match self.root {
+            Child::Empty => EMPTY_TRIE_HASH,
+            Child::Hash(hash) => hash,
+            Child::Node(node) => {
+                let encoded = encode_node(&node, &mut diff);
+                let hash = keccak(&encoded);
+                diff.insert(hash, encoded);
+                hash
+            }
+        };
+        (root, diff)
+    }
+
+    fn load(&self, hash: H256) -> Node {
+        let bytes = self
+            .store
+            .get_node(hash)
+            .unwrap_or_else(|| panic!("Trie node {:x} is missing in store", hash));
+        decode_node(&Rlp::new(&bytes))
+            .unwrap_or_else(|err| panic!("Unable to decode trie node {:x}: {:?}", hash, err))
+    }
+
+    fn take(&self, child: Child) -> Option<Node> {
+        match child {
+            Child::Empty => None,
+            Child::Hash(hash) => Some(self.load(hash)),
+            Child::Node(node) => Some(*node),
+        }
+    }
+
+    fn get_at(&self, child: &Child, path: &[u8]) -> Option<Vec<u8>> {
+        match child {
+            Child::Empty => None,
+            Child::Hash(hash) => self.get_in_node(&self.load(*hash), path),
+            Child::Node(node) => self.get_in_node(node, path),
+        }
+    }
+
+    fn get_in_node(&self, node: &Node, path: &[u8]) -> Option<Vec<u8>> {
+        match node {
+            Node::Leaf {
+                path: leaf_path,
+                value,
+            } if leaf_path.as_slice() == path => Some(value.clone()),
+            Node::Leaf { .. } => None,
+            Node::Extension {
+                path: ext_path,
+                child,
+            } if path.starts_with(ext_path) => self.get_at(child, &path[ext_path.len()..]),
+            Node::Extension { .. } => None,
+            Node::Branch { value, .. } if path.is_empty() => value.clone(),
+            Node::Branch { children, .. } => self.get_at(&children[path[0] as usize], &path[1..]),
+        }
+    }
+
+    fn insert_at(&self, node: Option<Node>, path: &[u8], value: Vec<u8>) -> Node {
+        match node {
+            None => Node::Leaf {
+                path: path.to_vec(),
+                value,
+            },
+            Some(Node::Leaf {
+                path: leaf_path,
+                value: leaf_value,
+            }) => {
+                let common = common_prefix(&leaf_path, path);
+                if common == leaf_path.len() && common == path.len() {
+                    return Node::Leaf {
+                        path: leaf_path,
+                        value,
+                    };
+                }
+
+                let mut children = Box::new(<[Child; 16]>::default());
+                let mut branch_value = None;
+                put_leaf(
+                    &mut children,
+                    &mut branch_value,
+                    &leaf_path[common..],
+                    leaf_value,
+                );
+                put_leaf(&mut children, &mut branch_value, &path[common..], value);
+
+                with_prefix(
+                    &path[..common],
+                    Node::Branch {
+                        children,
+                        value: branch_value,
+                    },
+                )
+            }
+            Some(Node::Extension {
+                path: ext_path,
+                child,
+            }) => {
+                let common = common_prefix(&ext_path, path);
+                if common == ext_path.len() {
+                    let child = self.insert_at(self.take(child), &path[common..], value);
+                    return Node::Extension {
+                        path: ext_path,
+                        child: child.into(),
+                    };
+                }
+
+                let mut children = Box::new(<[Child; 16]>::default());
+                let mut branch_value = None;
+                children[ext_path[common] as usize] = if ext_path.len() == common + 1 {
+                    child
+                } else {
+                    Node::Extension {
+                        path: ext_path[common + 1..].to_vec(),
+                        child,
+                    }
+                    .into()
+                };
+                put_leaf(&mut children, &mut branch_value, &path[common..], value);
+
+                with_prefix(
+                    &path[..common],
+                    Node::Branch {
+                        children,
+                        value: branch_value,
+                    },
+                )
+            }
+            Some(Node::Branch { children, value: _ }) if path.is_empty() => Node::Branch {
+                children,
+                value: Some(value),
+            },
+            Some(Node::Branch {
+                mut children,
+                value: branch_value,
+            }) => {
+                let index = path[0] as usize;
+                let child = self.take(std::mem::take(&mut children[index]));
+                children[index] = self.insert_at(child, &path[1..], value).into();
+                Node::Branch {
+                    children,
+                    value: branch_value,
+                }
+            }
+        }
+    }
+
+    fn remove_at(&self, node: Option<Node>, path: &[u8]) -> Option<Node> {
+        match node {
+            None => None,
+            Some(Node::Leaf {
+                path: leaf_path, ..
+            }) if leaf_path.as_slice() == path => None,
+            Some(leaf @ Node::Leaf { .. }) => Some(leaf),
+            Some(Node::Extension {
+                path: ext_path,
+                child,
+            }) if path.starts_with(&ext_path) => self
+                .remove_at(self.take(child), &path[ext_path.len()..])
+                .map(|child| with_prefix(&ext_path, child)),
+            Some(extension @ Node::Extension { .. }) => Some(extension),
+            Some(Node::Branch { children, value: _ }) if path.is_empty() => {
+                self.normalize_branch(children, None)
+            }
+            Some(Node::Branch {
+                mut children,
+                value,
+            }) => {
+                let index = path[0] as usize;
+                let child = self.take(std::mem::take(&mut children[index]));
+                children[index] = self
+                    .remove_at(child, &path[1..])
+                    .map(Child::from)
+                    .unwrap_or_default();
+                self.normalize_branch(children, value)
+            }
+        }
+    }
+
+    /// Collapses branch that has less than two entries.
+    fn normalize_branch(
+        &self,
+        mut children: Box<[Child; 16]>,
+        value: Option<Vec<u8>>,
+    ) -> Option<Node> {
+        let used: Vec<usize> = children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| !matches!(child, Child::Empty))
+            .map(|(index, _)| index)
+            .collect();
+
+        match (used.as_slice(), value) {
+            ([], None) => None,
+            ([], Some(value)) => Some(Node::Leaf {
+                path: vec![],
+                value,
+            }),
+            ([index], None) => {
+                let child = self
+                    .take(std::mem::take(&mut children[*index]))
+                    .expect("Branch child is not empty");
+                Some(with_prefix(&[*index as u8], child))
+            }
+            (_, value) => Some(Node::Branch { children, value }),
+        }
+    }
+}
+
+fn put_leaf(
+    children: &mut [Child; 16],
+    branch_value: &mut Option<Vec<u8>>,
+    path: &[u8],
+    value: Vec<u8>,
+) {
+    if path.is_empty() {
+        *branch_value = Some(value);
+    } else {
+        children[path[0] as usize] = Node::Leaf {
+            path: path[1..].to_vec(),
+            value,
+        }
+        .into();
+    }
+}
+
+/// Prepends path to node, joining it with paths of leafs and extensions.
+fn with_prefix(prefix: &[u8], node: Node) -> Node {
+    if prefix.is_empty() {
+        return node;
+    }
+    match node {
+        Node::Leaf { path, value } => Node::Leaf {
+            path: [prefix, &path].concat(),
+            value,
+        },
+        Node::Extension { path, child } => Node::Extension {
+            path: [prefix, &path].concat(),
+            child,
+        },
+        branch @ Node::Branch { .. } => Node::Extension {
+            path: prefix.to_vec(),
+            child: branch.into(),
+        },
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+}
+
+fn hex_prefix_encode(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 0x20 } else { 0x00 };
+    let mut bytes = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        bytes.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        bytes.push(flag);
+        nibbles
+    };
+    bytes.extend(rest.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+    bytes
+}
+
+fn hex_prefix_decode(bytes: &[u8]) -> Result<(Vec<u8>, bool), DecoderError> {
+    let first = *bytes.first().ok_or(DecoderError::RlpIsTooShort)?;
+    let is_leaf = first & 0x20 != 0;
+    let mut nibbles = Vec::with_capacity(bytes.len() * 2);
+    if first & 0x10 != 0 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&bytes[1..]));
+    Ok((nibbles, is_leaf))
+}
+
+fn encode_node(node: &Node, diff: &mut NodesDiff) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    match node {
+        Node::Leaf { path, value } => {
+            stream.begin_list(2);
+            stream.append(&hex_prefix_encode(path, true));
+            stream.append(value);
+        }
+        Node::Extension { path, child } => {
+            stream.begin_list(2);
+            stream.append(&hex_prefix_encode(path, false));
+            append_child(&mut stream, child, diff);
+        }
+        Node::Branch { children, value } => {
+            stream.begin_list(17);
+            for child in children.iter() {
+                append_child(&mut stream, child, diff);
+            }
+            match value {
+                Some(value) => stream.append(value),
+                None => stream.append_empty_data(),
+            };
+        }
+    }
+    stream.out().to_vec()
+}
+
+fn append_child(stream: &mut RlpStream, child: &Child, diff: &mut NodesDiff) {
+    match child {
+        Child::Empty => {
+            stream.append_empty_data();
+        }
+        Child::Hash(hash) => {
+            stream.append(hash);
+        }
+        Child::Node(node) => {
+            let encoded = encode_node(node, diff);
+            if encoded.len() < 32 {
+                stream.append_raw(&encoded, 1);
+            } else {
+                let hash = keccak(&encoded);
+                diff.insert(hash, encoded);
+                stream.append(&hash);
+            }
+        }
+    }
+}
+
+fn decode_node(rlp: &Rlp<'_>) -> Result<Node, DecoderError> {
+    match rlp.item_count()? {
+        2 => {
+            let (path, is_leaf) = hex_prefix_decode(rlp.at(0)?.data()?)?;
+            if is_leaf {
+                Ok(Node::Leaf {
+                    path,
+                    value: rlp.val_at(1)?,
+                })
+            } else {
+                Ok(Node::Extension {
+                    path,
+                    child: decode_child(&rlp.at(1)?)?,
+                })
+            }
+        }
+        17 => {
+            let mut children = Box::new(<[Child; 16]>::default());
+            for (index, child) in children.iter_mut().enumerate() {
+                *child = decode_child(&rlp.at(index)?)?;
+            }
+            let value = rlp.at(16)?;
+            let value = if value.is_empty() {
+                None
+            } else {
+                Some(value.data()?.to_vec())
+            };
+            Ok(Node::Branch { children, value })
+        }
+        _ => Err(DecoderError::Custom("Unexpected items count in trie node")),
+    }
+}
+
+fn decode_child(rlp: &Rlp<'_>) -> Result<Child, DecoderError> {
+    if rlp.is_list() {
+        return Ok(decode_node(rlp)?.into());
+    }
+    let data = rlp.data()?;
+    match data.len() {
+        0 => Ok(Child::Empty),
+        32 => Ok(Child::Hash(H256::from_slice(data))),
+        _ => Err(DecoderError::RlpInvalidLength),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    use rand::rngs::mock::StepRng;
+    use rand::Rng;
+
+    use super::*;
+
+    fn root_of(entries: &[(&str, &str)]) -> H256 {
+        let store = NodesDiff::new();
+        let mut trie = Trie::new(&store, EMPTY_TRIE_HASH);
+        for (key, value) in entries {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        trie.commit().0
+    }
+
+    #[test]
+    fn empty_trie_root() {
+        assert_eq!(EMPTY_TRIE_HASH, keccak(&rlp::NULL_RLP));
+        assert_eq!(root_of(&[]), EMPTY_TRIE_HASH);
+    }
+
+    #[test]
+    fn it_agrees_with_ethereum_test_vectors() {
+        assert_eq!(
+            root_of(&[
+                ("doe", "reindeer"),
+                ("dog", "puppy"),
+                ("dogglesworth", "cat")
+            ]),
+            H256::from_str("8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+                .unwrap()
+        );
+        assert_eq!(
+            root_of(&[
+                ("do", "verb"),
+                ("horse", "stallion"),
+                ("doge", "coin"),
+                ("dog", "puppy")
+            ]),
+            H256::from_str("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84")
+                .unwrap()
+        );
+        assert_eq!(
+            root_of(&[("foo", "bar"), ("food", "bass")]),
+            H256::from_str("17beaa1648bafa633cda809c90c04af50fc8aed3cb40d16efbddee6fdf63c4c3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn it_reads_committed_nodes() {
+        let mut store = NodesDiff::new();
+        let mut trie = Trie::new(&store, EMPTY_TRIE_HASH);
+        trie.insert(b"do", b"verb".to_vec());
+        trie.insert(b"dog", b"puppy".to_vec());
+        let (root, diff) = trie.commit();
+        store.extend(diff);
+
+        let mut trie = Trie::new(&store, root);
+        assert_eq!(trie.get(b"do"), Some(b"verb".to_vec()));
+        assert_eq!(trie.get(b"dog"), Some(b"puppy".to_vec()));
+        assert_eq!(trie.get(b"doge"), None);
+
+        trie.insert(b"doge", b"coin".to_vec());
+        trie.insert(b"horse", b"stallion".to_vec());
+        let (root, diff) = trie.commit();
+        store.extend(diff);
+
+        assert_eq!(
+            root,
+            root_of(&[
+                ("do", "verb"),
+                ("horse", "stallion"),
+                ("doge", "coin"),
+                ("dog", "puppy")
+            ])
+        );
+        assert_eq!(Trie::new(&store, root).get(b"doge"), Some(b"coin".to_vec()));
+    }
+
+    #[test]
+    fn removal_restores_previous_root() {
+        let entries = [
+            ("doe", "reindeer"),
+            ("dog", "puppy"),
+            ("dogglesworth", "cat"),
+        ];
+
+        let mut store = NodesDiff::new();
+        let mut trie = Trie::new(&store, EMPTY_TRIE_HASH);
+        for (key, value) in &entries {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        let (root, diff) = trie.commit();
+        store.extend(diff);
+        assert_eq!(root, root_of(&entries));
+
+        let mut trie = Trie::new(&store, root);
+        trie.insert(b"do", b"verb".to_vec());
+        let (extended_root, diff) = trie.commit();
+        store.extend(diff);
+
+        let mut trie = Trie::new(&store, extended_root);
+        trie.remove(b"do");
+        trie.remove(b"unknown");
+        assert_eq!(trie.commit().0, root);
+
+        let mut trie = Trie::new(&store, extended_root);
+        for key in &["do", "doe", "dog", "dogglesworth"] {
+            trie.remove(key.as_bytes());
+        }
+        assert_eq!(trie.commit().0, EMPTY_TRIE_HASH);
+    }
+
+    #[test]
+    fn incremental_updates_match_rebuilt_trie() {
+        const N_ROUNDS: usize = 8;
+        const N_KEYS: usize = 64;
+
+        let mut rng = StepRng::new(7, 0x9e37_79b9_7f4a_7c15);
+        let mut store = NodesDiff::new();
+        let mut root = EMPTY_TRIE_HASH;
+        let mut expected = BTreeMap::new();
+
+        for _ in 0..N_ROUNDS {
+            let mut trie = Trie::new(&store, root);
+            for _ in 0..N_KEYS {
+                let key = keccak(&[rng.gen_range(0, 96u8)]);
+                if rng.gen_range(0, 4) == 0 {
+                    trie.remove(key.as_bytes());
+                    expected.remove(&key);
+                } else {
+                    let value = rng.gen::<[u8; 4]>().to_vec();
+                    trie.insert(key.as_bytes(), value.clone());
+                    expected.insert(key, value);
+                }
+            }
+            let (new_root, diff) = trie.commit();
+            store.extend(diff);
+            root = new_root;
+
+            let mut rebuilt = Trie::new(&store, EMPTY_TRIE_HASH);
+            for (key, value) in &expected {
+                rebuilt.insert(key.as_bytes(), value.clone());
+            }
+            assert_eq!(rebuilt.commit().0, root);
+
+            let trie = Trie::new(&store, root);
+            for (key, value) in &expected {
+                assert_eq!(trie.get(key.as_bytes()).as_ref(), Some(value));
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub use ethbloom::Bloom;
+use ethbloom::Input as BloomInput;
 pub use primitive_types::{H160, H256, U256};
 use serde::{Deserialize, Serialize};
 
@@ -56,6 +58,8 @@ pub struct LogWithLocation {
     pub transaction_hash: H256,
     pub transaction_id: u64,
     pub block_num: u64,
+    /// Index of log in block.
+    pub log_index: u64,
     pub address: H160,
     pub data: Vec<u8>,
     pub topics: Vec<H256>,
@@ -65,5 +69,92 @@ pub struct LogFilter {
     pub from_block: u64,
     pub to_block: u64,
     pub address: Option<H160>,
-    pub topics: Vec<H256>,
+    /// Positional topics, `None` matches any topic, otherwise any of listed.
+    pub topics: Vec<Option<Vec<H256>>>,
+}
+
+impl LogFilter {
+    /// Maximum number of topics in log.
+    pub const MAX_TOPICS: usize = 4;
+
+    /// Topics are matched by position, so log with less topics than filter never matches.
+    pub fn is_log_match(&self, address: &H160, topics: &[H256]) -> bool {
+        if matches!(self.address, Some(ref expected) if expected != address) {
+            return false;
+        }
+        if self.topics.len() > topics.len() {
+            return false;
+        }
+        self.topics
+            .iter()
+            .zip(topics)
+            .all(|(expected, topic)| match expected {
+                Some(expected) if !expected.is_empty() => expected.contains(topic),
+                _ => true,
+            })
+    }
+
+    /// Returns `false` if block with `bloom` has no logs, that match filter.
+    pub fn is_bloom_match(&self, bloom: &Bloom) -> bool {
+        if let Some(address) = &self.address {
+            if !bloom.contains_input(BloomInput::Raw(address.as_bytes())) {
+                return false;
+            }
+        }
+        self.topics.iter().all(|expected| match expected {
+            Some(expected) if !expected.is_empty() => expected
+                .iter()
+                .any(|topic| bloom.contains_input(BloomInput::Raw(topic.as_bytes()))),
+            _ => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(address: Option<H160>, topics: Vec<Option<Vec<H256>>>) -> LogFilter {
+        LogFilter {
+            from_block: 0,
+            to_block: 0,
+            address,
+            topics,
+        }
+    }
+
+    #[test]
+    fn log_filter_match() {
+        let address = H160::repeat_byte(1);
+        let topic1 = H256::repeat_byte(2);
+        let topic2 = H256::repeat_byte(3);
+        let other = H256::repeat_byte(4);
+        let topics = [topic1, topic2];
+
+        assert!(filter(None, vec![]).is_log_match(&address, &topics));
+        assert!(filter(Some(address), vec![]).is_log_match(&address, &topics));
+        assert!(!filter(Some(H160::repeat_byte(5)), vec![]).is_log_match(&address, &topics));
+
+        assert!(filter(None, vec![Some(vec![topic1])]).is_log_match(&address, &topics));
+        assert!(filter(None, vec![None, Some(vec![topic2])]).is_log_match(&address, &topics));
+        assert!(filter(None, vec![Some(vec![other, topic1])]).is_log_match(&address, &topics));
+        assert!(!filter(None, vec![Some(vec![topic2])]).is_log_match(&address, &topics));
+        assert!(!filter(None, vec![None, None, Some(vec![other])]).is_log_match(&address, &topics));
+        assert!(!filter(None, vec![None, None, None]).is_log_match(&address, &topics));
+    }
+
+    #[test]
+    fn log_filter_bloom_match() {
+        let address = H160::repeat_byte(1);
+        let topic = H256::repeat_byte(2);
+        let mut bloom = Bloom::default();
+        bloom.accrue(BloomInput::Raw(address.as_bytes()));
+        bloom.accrue(BloomInput::Raw(topic.as_bytes()));
+
+        assert!(filter(None, vec![]).is_bloom_match(&bloom));
+        assert!(filter(Some(address), vec![None, Some(vec![topic])]).is_bloom_match(&bloom));
+        assert!(filter(None, vec![Some(vec![H256::repeat_byte(3), topic])]).is_bloom_match(&bloom));
+        assert!(!filter(Some(H160::repeat_byte(3)), vec![]).is_bloom_match(&bloom));
+        assert!(!filter(None, vec![Some(vec![H256::repeat_byte(3)])]).is_bloom_match(&bloom));
+    }
 }
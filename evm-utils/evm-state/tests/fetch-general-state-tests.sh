@@ -0,0 +1,32 @@
+#!/usr/bin/env bash
+#
+# Copies filled GeneralStateTests fixtures from https://github.com/ethereum/tests
+# into tests/fixtures/GeneralStateTests, that are checked by `general_state_tests`:
+#
+#   tests/fetch-general-state-tests.sh
+#   cargo test -p evm-state general_state_tests -- --ignored
+#
+
+set -e
+cd "$(dirname "$0")"
+
+ref=${ETHEREUM_TESTS_REF:-v7.0.0}
+# SSTORE gas and refunds, CREATE2 collisions with self-destructed accounts,
+# SELFBALANCE, SELFDESTRUCT and recreation of accounts.
+suites=(
+  stSStoreTest
+  stCreate2
+  stSelfBalance
+  stSystemOperationsTest
+  stExtCodeHash
+)
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+git clone --depth 1 --branch "$ref" https://github.com/ethereum/tests.git "$tmp"/tests
+
+for suite in "${suites[@]}"; do
+  rm -rf fixtures/GeneralStateTests/"$suite"
+  cp -r "$tmp"/tests/GeneralStateTests/"$suite" fixtures/GeneralStateTests/
+done
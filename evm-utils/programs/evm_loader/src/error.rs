@@ -0,0 +1,59 @@
+use num_derive::{FromPrimitive, ToPrimitive};
+use solana_sdk::decode_error::DecodeError;
+use thiserror::Error;
+
+use crate::scope::evm;
+
+/// Reasons the evm transaction might be rejected
+#[derive(Error, Debug, Clone, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum EvmError {
+    #[error("transaction signature is malformed or has high S value")]
+    InvalidSignature,
+
+    #[error("transaction is signed for another chain id")]
+    WrongChainId,
+
+    #[error("transaction nonce doesn't match nonce of the caller account")]
+    NonceNotEqual,
+
+    #[error("transaction gas limit is out of bounds")]
+    GasLimitOutOfBounds,
+
+    #[error("transaction gas price is out of bounds")]
+    GasPriceOutOfBounds,
+
+    #[error("transaction gas price is lower than minimal gas price")]
+    GasPriceTooLow,
+
+    #[error("caller balance is not enough to pay transaction fee")]
+    CantPayFee,
+
+    #[error("evm is invoked by another program, but cross-program invocation is not enabled")]
+    CrossExecutionNotEnabled,
+
+    #[error("evm executor is not available in this context")]
+    ExecutorNotAvailable,
+}
+
+impl<E> DecodeError<E> for EvmError {
+    fn type_of() -> &'static str {
+        "EvmError"
+    }
+}
+
+impl EvmError {
+    /// Returns evm error for errors that can be caused by invalid transaction.
+    pub fn from_execution_error(error: &evm::error::Error) -> Option<Self> {
+        use evm::error::Error::*;
+        Some(match error {
+            UnrecoverableCaller { .. } | InvalidSignature { .. } => Self::InvalidSignature,
+            WrongChainId { .. } => Self::WrongChainId,
+            NonceNotEqual { .. } => Self::NonceNotEqual,
+            GasLimitOutOfBounds { .. } => Self::GasLimitOutOfBounds,
+            GasPriceOutOfBounds { .. } => Self::GasPriceOutOfBounds,
+            GasPriceTooLow { .. } => Self::GasPriceTooLow,
+            CantPayFee { .. } => Self::CantPayFee,
+            _ => return None,
+        })
+    }
+}
@@ -9,6 +9,7 @@ use sha3::{Digest, Keccak256};
 #[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum EvmBigTransaction {
     /// Allocate data in storage, pay fee should be taken from evm.
+    /// Allocation is removed, if it's not executed in `BIG_TX_LIFETIME` slots.
     EvmTransactionAllocate {
         seed: H256,
         len: u64,
@@ -23,7 +24,13 @@ pub enum EvmBigTransaction {
     },
 
     /// Execute merged transaction, in order to do this, user should make sure that transaction is successfully writed.
+    /// Data should contain RLP encoded evm transaction, or bincode encoded one,
+    /// until `evm_rlp_big_transactions` feature is activated.
     EvmTransactionExecute { seed: H256 },
+
+    /// Free allocated data, if transaction will not be executed.
+    /// Available after `evm_rlp_big_transactions` feature is activated.
+    EvmTransactionReclaim { seed: H256 },
 }
 
 impl EvmBigTransaction {
@@ -32,6 +39,7 @@ impl EvmBigTransaction {
             EvmBigTransaction::EvmTransactionAllocate { seed, .. } => seed,
             EvmBigTransaction::EvmTransactionWrite { seed, .. } => seed,
             EvmBigTransaction::EvmTransactionExecute { seed } => seed,
+            EvmBigTransaction::EvmTransactionReclaim { seed } => seed,
         }
     }
 
@@ -47,6 +55,14 @@ impl EvmBigTransaction {
 pub enum EvmInstruction {
     /// Execute native evm transaction.
     ///
+    /// Outer args:
+    /// account_key[0] - `[]`. Evm state account, should be writable only if lamports are
+    /// transferred to native accounts.
+    /// account_key[1..] - Native accounts that are available to evm precompiles,
+    /// writable accounts can receive lamports.
+    ///
+    /// Can be invoked by other programs, in that case used gas is billed against compute units
+    /// of the caller, and transaction output is available to it through `get_return_data`.
     ///
     EvmTransaction {
         evm_tx: evm::Transaction,
@@ -74,20 +90,19 @@ pub enum EvmInstruction {
     FreeOwnership {},
 
     EvmBigTransaction(EvmBigTransaction),
-    // / Transfer gweis to solana lamports.
-    // /
-    // / Outer args:
-    // / account_key[0] - `[writable]. Evm state account, used for lock.
-    // / account_key[1] - `[writable, signer]`. Owner account that's allowed to manage withdrawal of his account by transfering ownership.
-    // /
-    // / Inner args:
-    // / amount - count of lamports to be transfered.
-    // / ether_key - recevier etherium address.
-    // /
-    // SwapEtherToNative {
-    //     amount: u64,
-    //     ether_key: evm::Address,
-    //     account_key: solana::Address,
-    //     ether_signature: evm::Signature,
-    // }
+
+    /// Transfer gweis from ethereum back to native lamports.
+    ///
+    /// Outer args:
+    /// account_key[0] - `[writable]`. Evm state account, used for lock.
+    /// account_key[1] - `[writable]`. Receiver of lamports.
+    ///
+    /// Inner args:
+    /// evm_tx - evm transaction that sends gweis to `ETH_TO_VLX_ADDR`, with receiver pubkey as input.
+    /// Amount of gweis should be convertible to lamports without remainder.
+    /// Available only with native precompiles, the call is executed by native transfer precompile.
+    ///
+    SwapEtherToNative {
+        evm_tx: evm::Transaction,
+    },
 }
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod instructions;
 pub mod processor;
 
@@ -12,10 +13,24 @@ pub mod scope {
         pub use evm_state::*;
         pub use primitive_types::H160 as Address;
 
-        const LAMPORTS_TO_GWEI_PRICE: u64 = 1_000_000_000; // Lamports is 1/10^9 of SOLs while GWEI is 1/10^18
+        /// Address that swaps gweis back to native lamports, same as native transfer precompile.
+        pub const ETH_TO_VLX_ADDR: Address = precompiles::NATIVE_TRANSFER_ADDR;
 
         pub fn lamports_to_gwei(lamports: u64) -> U256 {
-            U256::from(lamports) * U256::from(LAMPORTS_TO_GWEI_PRICE)
+            U256::from(lamports) * U256::from(WEI_PER_LAMPORT)
+        }
+
+        /// Converts gweis back to lamports, returning remainder that is less than one lamport.
+        pub fn gweis_to_lamports(gweis: U256) -> (u64, U256) {
+            let lamports = gweis / U256::from(WEI_PER_LAMPORT);
+            let change = gweis % U256::from(WEI_PER_LAMPORT);
+            if lamports > U256::from(u64::MAX) {
+                return (
+                    u64::MAX,
+                    gweis - U256::from(u64::MAX) * U256::from(WEI_PER_LAMPORT),
+                );
+            }
+            (lamports.as_u64(), change)
         }
     }
     pub mod solana {
@@ -42,6 +57,30 @@ pub fn send_raw_tx(signer: solana::Address, evm_tx: evm::Transaction) -> solana:
     )
 }
 
+/// Send evm transaction, evm state account is locked read-only,
+/// so transaction can be executed in parallel with other evm transactions.
+/// Should be used only after `evm_readonly_state_account` feature activation,
+/// before it instruction fails with `MissingAccount`.
+pub fn send_raw_tx_readonly_state(
+    signer: solana::Address,
+    evm_tx: evm::Transaction,
+) -> solana::Instruction {
+    let mut instruction = send_raw_tx(signer, evm_tx);
+    instruction.accounts[0] = AccountMeta::new_readonly(solana::evm_state::ID, false);
+    instruction
+}
+
+/// Send evm transaction, with native accounts that are available to evm precompiles.
+pub fn send_raw_tx_with_native_accounts(
+    signer: solana::Address,
+    evm_tx: evm::Transaction,
+    native_accounts: Vec<AccountMeta>,
+) -> solana::Instruction {
+    let mut instruction = send_raw_tx(signer, evm_tx);
+    instruction.accounts.extend(native_accounts);
+    instruction
+}
+
 pub(crate) fn transfer_native_to_eth(
     owner: solana::Address,
     lamports: u64,
@@ -71,6 +110,41 @@ pub(crate) fn free_ownership(owner: solana::Address) -> solana::Instruction {
     Instruction::new(crate::ID, &EvmInstruction::FreeOwnership {}, account_metas)
 }
 
+/// Create evm transaction that transfers `lamports` from evm caller to native `receiver`.
+/// Should be signed and sent with `transfer_eth_to_native`.
+pub fn transfer_eth_to_native_tx(
+    nonce: evm::U256,
+    gas_price: evm::U256,
+    lamports: u64,
+    receiver: solana::Address,
+) -> evm::UnsignedTransaction {
+    evm::UnsignedTransaction {
+        nonce,
+        gas_price,
+        // plain transfer with 32 bytes of input, unused gas is refunded.
+        gas_limit: evm::U256::from(30_000),
+        action: evm::TransactionAction::Call(evm::ETH_TO_VLX_ADDR),
+        value: evm::lamports_to_gwei(lamports),
+        input: receiver.to_bytes().to_vec(),
+    }
+}
+
+pub fn transfer_eth_to_native(
+    receiver: solana::Address,
+    evm_tx: evm::Transaction,
+) -> solana::Instruction {
+    let account_metas = vec![
+        AccountMeta::new(solana::evm_state::ID, false),
+        AccountMeta::new(receiver, false),
+    ];
+
+    Instruction::new(
+        crate::ID,
+        &EvmInstruction::SwapEtherToNative { evm_tx },
+        account_metas,
+    )
+}
+
 pub fn big_tx_allocate(owner: &solana::Address, seed: evm::H256, len: u64) -> solana::Instruction {
     let account_metas = vec![
         AccountMeta::new(solana::evm_state::ID, false),
@@ -123,6 +197,19 @@ pub fn big_tx_execute(owner: &solana::Address, seed: evm::H256) -> solana::Instr
     )
 }
 
+pub fn big_tx_reclaim(owner: &solana::Address, seed: evm::H256) -> solana::Instruction {
+    let account_metas = vec![
+        AccountMeta::new(solana::evm_state::ID, false),
+        AccountMeta::new(*owner, true),
+    ];
+    let big_tx = EvmBigTransaction::EvmTransactionReclaim { seed };
+    Instruction::new(
+        crate::ID,
+        &EvmInstruction::EvmBigTransaction(big_tx),
+        account_metas,
+    )
+}
+
 pub fn transfer_native_to_eth_ixs(
     owner: solana::Address,
     lamports: u64,
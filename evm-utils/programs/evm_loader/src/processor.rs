@@ -1,9 +1,15 @@
+use super::error::EvmError;
 use super::instructions::{EvmBigTransaction, EvmInstruction};
 use super::scope::*;
 use log::*;
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
+use evm::precompiles::{NativeAccount, NativeTransfer};
 use evm::{Executor, ExitReason};
+use solana_sdk::account::Account;
+use solana_sdk::feature_set::evm_cross_execution;
 use solana_sdk::instruction::InstructionError;
+use solana_sdk::process_instruction::{ComputeMeter, InvokeContext};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::{keyed_account::KeyedAccount, program_utils::limited_deserialize};
 
@@ -14,9 +20,13 @@ pub fn next_account_info<'a, 'b, I: Iterator<Item = &'a KeyedAccount<'b>>>(
     iter.next().ok_or(InstructionError::NotEnoughAccountKeys)
 }
 
-/// Ensure that first account is program itself, and it's locked for writes.
+/// Ensure that first account is program itself.
+/// It should be locked for writes, only if instruction moves lamports between evm and native accounts,
+/// read-only evm state allows parallel execution of evm transactions.
+/// Until `readonly_state_account` feature is activated, it should always be locked for writes.
 fn check_evm_account<'a, 'b>(
     keyed_accounts: &'a [KeyedAccount<'b>],
+    features: evm::ExecutorFeatures,
 ) -> Result<(&'a KeyedAccount<'b>, &'a [KeyedAccount<'b>]), InstructionError> {
     let first = keyed_accounts
         .first()
@@ -24,8 +34,12 @@ fn check_evm_account<'a, 'b>(
 
     trace!("first = {:?}", first);
     trace!("all = {:?}", keyed_accounts);
-    if first.unsigned_key() != &solana::evm_state::id() || !first.is_writable() {
-        error!("First account is not evm, or not writable");
+    if first.unsigned_key() != &solana::evm_state::id() {
+        error!("First account is not evm");
+        return Err(InstructionError::MissingAccount);
+    }
+    if !features.readonly_state_account && !first.is_writable() {
+        error!("First account is not writable");
         return Err(InstructionError::MissingAccount);
     }
 
@@ -33,34 +47,192 @@ fn check_evm_account<'a, 'b>(
     Ok((first, keyed_accounts))
 }
 
+/// Instructions that move lamports of evm state account should lock it for writes.
+fn check_evm_account_writable(evm_state_account: &KeyedAccount) -> Result<(), InstructionError> {
+    if !evm_state_account.is_writable() {
+        debug!("Evm state account is not writable");
+        return Err(InstructionError::ReadonlyLamportChange);
+    }
+    Ok(())
+}
+
+fn execution_error(error: evm::error::Error) -> InstructionError {
+    debug!("Evm transaction rejected: {}", error);
+    EvmError::from_execution_error(&error)
+        .map(InstructionError::from)
+        .unwrap_or(InstructionError::InvalidArgument)
+}
+
+/// Accounts of instruction, that are visible to evm precompiles.
+/// Lamports can be transferred to them, only if evm state account is writable.
+fn native_accounts(
+    keyed_accounts: &[KeyedAccount],
+    evm_state_is_writable: bool,
+) -> Result<BTreeMap<evm::H256, NativeAccount>, InstructionError> {
+    keyed_accounts
+        .iter()
+        .map(|account| {
+            let native = NativeAccount {
+                lamports: account.lamports()?,
+                is_writable: evm_state_is_writable && account.is_writable(),
+            };
+            Ok((evm::H256(account.unsigned_key().to_bytes()), native))
+        })
+        .collect()
+}
+
+/// Move lamports of gweis, that was burned by evm, from evm state account to receivers.
+fn apply_native_transfers(
+    evm_state_account: &mut Account,
+    keyed_accounts: &[KeyedAccount],
+    native_transfers: Vec<NativeTransfer>,
+) -> Result<(), InstructionError> {
+    for transfer in native_transfers {
+        let receiver = keyed_accounts
+            .iter()
+            .find(|account| account.unsigned_key().to_bytes() == transfer.receiver.0)
+            .ok_or(InstructionError::MissingAccount)?;
+        debug!(
+            "Native transfer of {} lamports to {}",
+            transfer.lamports,
+            receiver.unsigned_key()
+        );
+        evm_state_account.lamports = evm_state_account
+            .lamports
+            .checked_sub(transfer.lamports)
+            .ok_or(InstructionError::InsufficientFunds)?;
+        receiver.try_account_ref_mut()?.lamports += transfer.lamports;
+    }
+    Ok(())
+}
+
+/// Evm execution, invoked by another program.
+/// Gas of executed transactions is billed against compute units of the caller,
+/// and output of the last transaction is returned to it.
+/// Compute units only bound the work of caller's instruction and aren't paid in lamports,
+/// so gas fee is still charged from evm caller, as for transactions sent directly.
+struct CrossExecution {
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    gas_per_unit: u64,
+    output: Vec<u8>,
+}
+
+impl CrossExecution {
+    fn check_gas_limit(&self, evm_tx: &evm::Transaction) -> Result<(), InstructionError> {
+        let remaining = self.compute_meter.borrow().get_remaining();
+        let gas_budget = evm::U256::from(remaining) * evm::U256::from(self.gas_per_unit);
+        if evm_tx.gas_limit > gas_budget {
+            debug!(
+                "Transaction gas limit {} exceeds remaining compute budget {}",
+                evm_tx.gas_limit, gas_budget
+            );
+            return Err(InstructionError::ComputationalBudgetExceeded);
+        }
+        Ok(())
+    }
+
+    fn consume_gas(&mut self, gas: u64) -> Result<(), InstructionError> {
+        let units = (gas + self.gas_per_unit - 1) / self.gas_per_unit;
+        self.compute_meter.borrow_mut().consume(units)
+    }
+}
+
+/// Execute transaction, if evm was invoked by another program, bill it for used gas and
+/// record transaction output.
+fn execute_tx(
+    executor: &mut Executor,
+    evm_tx: evm::Transaction,
+    native_accounts: BTreeMap<evm::H256, NativeAccount>,
+    cross_execution: Option<&mut CrossExecution>,
+) -> Result<(ExitReason, Vec<u8>, Vec<NativeTransfer>), InstructionError> {
+    let cross_execution = match cross_execution {
+        Some(cross_execution) => cross_execution,
+        None => {
+            return executor
+                .transaction_execute_with_native_accounts(evm_tx, native_accounts)
+                .map_err(execution_error)
+        }
+    };
+    cross_execution.check_gas_limit(&evm_tx)?;
+    let used_gas = executor.used_gas();
+    let result = executor
+        .transaction_execute_with_native_accounts(evm_tx, native_accounts)
+        .map_err(execution_error)?;
+    cross_execution.consume_gas(executor.used_gas() - used_gas)?;
+    cross_execution.output = result.1.clone();
+    Ok(result)
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct EvmProcessor {}
 
 impl EvmProcessor {
     pub fn process_instruction(
+        &self,
+        program_id: &Pubkey,
+        keyed_accounts: &[KeyedAccount],
+        data: &[u8],
+        invoke_context: &mut dyn InvokeContext,
+    ) -> Result<(), InstructionError> {
+        // Evm program is the first one on invocation stack, if it's called directly by transaction.
+        if invoke_context.invoke_depth() <= 1 {
+            let executor = invoke_context.get_evm_executor();
+            return self.process_with_executor(program_id, keyed_accounts, data, executor, None);
+        }
+
+        if !invoke_context.is_feature_active(&evm_cross_execution::id()) {
+            debug!("Evm execution from crossprogram is not enabled.");
+            return Err(EvmError::CrossExecutionNotEnabled.into());
+        }
+        let mut cross_execution = CrossExecution {
+            compute_meter: invoke_context.get_compute_meter(),
+            gas_per_unit: invoke_context.get_bpf_compute_budget().evm_gas_per_unit,
+            output: vec![],
+        };
+        let executor = invoke_context.get_evm_executor();
+        self.process_with_executor(
+            program_id,
+            keyed_accounts,
+            data,
+            executor,
+            Some(&mut cross_execution),
+        )?;
+        invoke_context.set_return_data(*program_id, cross_execution.output);
+        Ok(())
+    }
+
+    fn process_with_executor(
         &self,
         _program_id: &Pubkey,
         keyed_accounts: &[KeyedAccount],
         data: &[u8],
         executor: Option<&mut Executor>,
+        mut cross_execution: Option<&mut CrossExecution>,
     ) -> Result<(), InstructionError> {
-        let executor = executor.expect("Evm execution from crossprogram is not allowed.");
+        let executor = executor.ok_or_else(|| {
+            error!("Evm executor is not available.");
+            InstructionError::from(EvmError::ExecutorNotAvailable)
+        })?;
 
-        let (evm_state_account, keyed_accounts) = check_evm_account(keyed_accounts)?;
-        let mut evm_state_account = evm_state_account.try_account_ref_mut()?;
+        let (evm_state_keyed_account, keyed_accounts) =
+            check_evm_account(keyed_accounts, executor.features())?;
+        let mut evm_state_account = evm_state_keyed_account.try_account_ref_mut()?;
 
         let ix = limited_deserialize(data)?;
         debug!("Run evm exec with ix = {:?}.", ix);
         match ix {
             EvmInstruction::EvmTransaction { evm_tx } => {
-                // TODO: Handle gas price
-                // TODO: Handle nonce
-                // TODO: validate tx signature
-                let result = executor
-                    .transaction_execute(evm_tx)
-                    .map_err(|_| InstructionError::InvalidArgument)?;
-                debug!("Exit status = {:?}", result);
-                if matches!(result.0, ExitReason::Fatal(_) | ExitReason::Error(_)) {
+                let native_accounts =
+                    native_accounts(keyed_accounts, evm_state_keyed_account.is_writable())?;
+                let (reason, output, native_transfers) = execute_tx(
+                    executor,
+                    evm_tx,
+                    native_accounts,
+                    cross_execution.as_deref_mut(),
+                )?;
+                debug!("Exit status = {:?}, output = {:?}", reason, output);
+                apply_native_transfers(&mut evm_state_account, keyed_accounts, native_transfers)?;
+                if matches!(reason, ExitReason::Fatal(_) | ExitReason::Error(_)) {
                     return Err(InstructionError::InvalidError);
                 }
             }
@@ -73,6 +245,7 @@ impl EvmProcessor {
                 lamports,
                 ether_address,
             } => {
+                check_evm_account_writable(evm_state_keyed_account)?;
                 let accounts_iter = &mut keyed_accounts.iter();
                 let signer_account = next_account_info(accounts_iter)?;
                 let gweis = evm::lamports_to_gwei(lamports);
@@ -108,10 +281,65 @@ impl EvmProcessor {
                 evm_state_account.lamports += lamports;
                 executor.with_executor(|e| e.state_mut().deposit(ether_address, gweis));
             }
+            EvmInstruction::SwapEtherToNative { evm_tx } => {
+                check_evm_account_writable(evm_state_keyed_account)?;
+                // Swap is a call of native transfer precompile, so it moves lamports the same way
+                // as any evm transaction with native accounts.
+                if !executor.features().native_precompiles {
+                    debug!("SwapEtherToNative: native precompiles are not enabled");
+                    return Err(InstructionError::InvalidInstructionData);
+                }
+                let receiver_accounts = keyed_accounts
+                    .get(..1)
+                    .ok_or(InstructionError::NotEnoughAccountKeys)?;
+                let receiver_account = &receiver_accounts[0];
+
+                if evm_tx.action != evm::TransactionAction::Call(evm::ETH_TO_VLX_ADDR) {
+                    debug!("SwapEtherToNative: transaction should call swap address");
+                    return Err(InstructionError::InvalidArgument);
+                }
+                if evm_tx.input != receiver_account.unsigned_key().to_bytes() {
+                    debug!("SwapEtherToNative: transaction input should be receiver pubkey");
+                    return Err(InstructionError::InvalidArgument);
+                }
+                // Only whole lamports can be swapped, so no gweis are lost on rounding.
+                let (lamports, change) = evm::gweis_to_lamports(evm_tx.value);
+                if !change.is_zero() {
+                    debug!(
+                        "SwapEtherToNative: amount {} is not convertible to lamports",
+                        evm_tx.value
+                    );
+                    return Err(InstructionError::InvalidArgument);
+                }
+                if lamports > evm_state_account.lamports {
+                    error!(
+                        "SwapEtherToNative: evm state has insufficient lamports ({}, need {})",
+                        evm_state_account.lamports, lamports
+                    );
+                    return Err(InstructionError::InsufficientFunds);
+                }
+
+                let native_accounts = native_accounts(receiver_accounts, true)?;
+                let (reason, output, native_transfers) = execute_tx(
+                    executor,
+                    evm_tx,
+                    native_accounts,
+                    cross_execution.as_deref_mut(),
+                )?;
+                debug!("Exit status = {:?}, output = {:?}", reason, output);
+                apply_native_transfers(
+                    &mut evm_state_account,
+                    receiver_accounts,
+                    native_transfers,
+                )?;
+                if matches!(reason, ExitReason::Fatal(_) | ExitReason::Error(_)) {
+                    return Err(InstructionError::InvalidError);
+                }
+            }
             EvmInstruction::EvmBigTransaction(big_tx) => {
                 let accounts_iter = &mut keyed_accounts.iter();
                 let signer_account = next_account_info(accounts_iter)?;
-                self.process_big_tx(signer_account, executor, big_tx)?
+                self.process_big_tx(signer_account, executor, big_tx, cross_execution)?
             }
         }
         Ok(())
@@ -122,7 +350,13 @@ impl EvmProcessor {
         signer_account: &KeyedAccount<'_>,
         executor: &mut Executor,
         big_tx: EvmBigTransaction,
+        cross_execution: Option<&mut CrossExecution>,
     ) -> Result<(), InstructionError> {
+        let rlp_big_transactions = executor.features().rlp_big_transactions;
+        if rlp_big_transactions && signer_account.signer_key().is_none() {
+            debug!("Big transaction: owner must sign");
+            return Err(InstructionError::MissingRequiredSignature);
+        }
         let key = big_tx.get_key(*signer_account.unsigned_key());
         debug!("executing big_tx = {:?}", big_tx);
         match big_tx {
@@ -150,15 +384,20 @@ impl EvmProcessor {
                 };
 
                 debug!("Trying to deserialize tx ={:?}", tx);
-                let tx: evm::Transaction = bincode::deserialize(&tx).map_err(|e| {
-                    debug!("real error = {:?}", e);
-                    InstructionError::InvalidArgument
-                })?;
+                let tx: evm::Transaction = if rlp_big_transactions {
+                    rlp::decode(&tx).map_err(|e| {
+                        debug!("real error = {:?}", e);
+                        InstructionError::InvalidArgument
+                    })?
+                } else {
+                    bincode::deserialize(&tx).map_err(|e| {
+                        debug!("real error = {:?}", e);
+                        InstructionError::InvalidArgument
+                    })?
+                };
 
                 debug!("Executing evm tx = {:?}.", tx);
-                let result = executor
-                    .transaction_execute(tx)
-                    .map_err(|_| InstructionError::InvalidArgument)?;
+                let result = execute_tx(executor, tx, BTreeMap::new(), cross_execution)?;
                 debug!("Exit status = {:?}", result);
                 match result.0 {
                     ExitReason::Fatal(_) | ExitReason::Error(_) => {
@@ -167,6 +406,16 @@ impl EvmProcessor {
                     _ => {}
                 }
             }
+            EvmBigTransaction::EvmTransactionReclaim { .. } => {
+                if !rlp_big_transactions {
+                    debug!("Big transaction reclaim is not activated");
+                    return Err(InstructionError::InvalidInstructionData);
+                }
+                if let Err(e) = executor.free_big_tx(key) {
+                    error!("Error reclaiming big transaction = {:?}", e);
+                    return Err(InstructionError::InvalidArgument);
+                }
+            }
         }
         Ok(())
     }
@@ -175,7 +424,7 @@ impl EvmProcessor {
 const SECRET_KEY_DUMMY: [u8; 32] = [1; 32];
 
 #[doc(hidden)]
-pub fn dummy_call() -> evm::Transaction {
+pub fn dummy_call(nonce: u64) -> evm::Transaction {
     let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
     let dummy_address = evm::addr_from_public_key(&evm::PublicKey::from_secret_key(
         &evm::SECP256K1,
@@ -183,8 +432,8 @@ pub fn dummy_call() -> evm::Transaction {
     ));
 
     let tx_call = evm::UnsignedTransaction {
-        nonce: 1.into(),
-        gas_price: 1.into(),
+        nonce: nonce.into(),
+        gas_price: 0.into(),
         gas_limit: 300000.into(),
         action: evm::TransactionAction::Call(dummy_address),
         value: 0.into(),
@@ -196,10 +445,12 @@ pub fn dummy_call() -> evm::Transaction {
 mod test {
     use super::*;
     use evm_state::transactions::{TransactionAction, TransactionSignature};
-    use evm_state::{ExitReason, ExitSucceed};
+    use evm_state::{ExitReason, ExitSucceed, FromKey, Handler};
     use primitive_types::{H160, H256, U256};
+    use solana_sdk::genesis_config::EVM_DEVELOP_CHAIN_ID;
     use solana_sdk::keyed_account::KeyedAccount;
     use solana_sdk::native_loader;
+    use solana_sdk::process_instruction::MockInvokeContext;
     use solana_sdk::program_utils::limited_deserialize;
     use solana_sdk::sysvar::rent::Rent;
 
@@ -222,6 +473,31 @@ mod test {
         }
     }
 
+    #[test]
+    fn cross_execution_without_executor_fails() {
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let keyed_accounts = [KeyedAccount::new(
+            &solana::evm_state::ID,
+            false,
+            &evm_account,
+        )];
+        let mut invoke_context = MockInvokeContext::default();
+        invoke_context.invoke_depth = 2;
+        assert_eq!(
+            processor.process_instruction(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmTransaction {
+                    evm_tx: dummy_call(0)
+                })
+                .unwrap(),
+                &mut invoke_context,
+            ),
+            Err(EvmError::ExecutorNotAvailable.into())
+        );
+    }
+
     #[test]
     fn serialize_deserialize_eth_ix() {
         let tx = dummy_eth_tx();
@@ -230,14 +506,22 @@ mod test {
         assert_eq!(sol_ix, limited_deserialize(&ser).unwrap());
     }
 
+    fn fund_dummy_caller(executor: &mut evm_state::Executor) {
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+        executor.with_executor(|e| e.state_mut().deposit(caller, U256::from(u64::MAX)));
+    }
+
     #[test]
     fn execute_tx() {
         let mut executor = evm_state::Executor::with_config(
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
+        fund_dummy_caller(&mut executor);
         let mut executor = Some(&mut executor);
         let processor = EvmProcessor::default();
         let evm_account = RefCell::new(crate::create_state_account());
@@ -255,20 +539,21 @@ mod test {
         let tx_create = tx_create.sign(&secret_key, None);
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmTransaction {
                     evm_tx: tx_create.clone()
                 })
                 .unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_ok());
         println!("cx = {:?}", executor);
         let tx_address = tx_create.address().unwrap();
         let tx_call = evm::UnsignedTransaction {
-            nonce: 0.into(),
+            nonce: 1.into(),
             gas_price: 1.into(),
             gas_limit: 300000.into(),
             action: TransactionAction::Call(tx_address),
@@ -280,11 +565,12 @@ mod test {
         let tx_call = tx_call.sign(&secret_key, None);
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmTransaction { evm_tx: tx_call }).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_ok());
         println!("cx = {:?}", executor);
@@ -295,6 +581,382 @@ mod test {
             .is_some())
     }
 
+    fn execute_evm_tx(
+        executor: &mut evm_state::Executor,
+        evm_tx: evm::Transaction,
+    ) -> Result<(), InstructionError> {
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let evm_keyed_account = KeyedAccount::new(&solana::evm_state::ID, false, &evm_account);
+        processor.process_with_executor(
+            &crate::ID,
+            &[evm_keyed_account],
+            &bincode::serialize(&EvmInstruction::EvmTransaction { evm_tx }).unwrap(),
+            Some(executor),
+            None,
+        )
+    }
+
+    fn hello_world_create(nonce: u64) -> evm::UnsignedTransaction {
+        evm::UnsignedTransaction {
+            nonce: nonce.into(),
+            gas_price: 1.into(),
+            gas_limit: 300000.into(),
+            action: TransactionAction::Create,
+            value: 0.into(),
+            input: hex::decode(evm_state::HELLO_WORLD_CODE).unwrap().to_vec(),
+        }
+    }
+
+    #[test]
+    fn execute_tx_rejects_invalid_nonce() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let tx_create = hello_world_create(0).sign(&secret_key, None);
+
+        assert_eq!(execute_evm_tx(&mut executor, tx_create.clone()), Ok(()));
+        // replay
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create),
+            Err(EvmError::NonceNotEqual.into())
+        );
+        // nonce from future
+        assert_eq!(
+            execute_evm_tx(&mut executor, hello_world_create(2).sign(&secret_key, None)),
+            Err(EvmError::NonceNotEqual.into())
+        );
+        assert_eq!(
+            execute_evm_tx(&mut executor, hello_world_create(1).sign(&secret_key, None)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn execute_tx_rejects_invalid_signature() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+
+        // Malleable counterpart of valid signature: (r, n - s) with flipped recovery id.
+        let curve_order = U256::from_big_endian(
+            &hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+                .unwrap(),
+        );
+        let mut tx_create = hello_world_create(0).sign(&secret_key, None);
+        let s = U256::from_big_endian(tx_create.signature.s.as_bytes());
+        let mut high_s = [0u8; 32];
+        (curve_order - s).to_big_endian(&mut high_s);
+        tx_create.signature.s = H256::from(high_s);
+        tx_create.signature.v = if tx_create.signature.v == 27 { 28 } else { 27 };
+        assert!(!tx_create.signature.is_low_s());
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create),
+            Err(EvmError::InvalidSignature.into())
+        );
+
+        let mut tx_create = hello_world_create(0).sign(&secret_key, None);
+        tx_create.signature.r = H256::zero();
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create),
+            Err(EvmError::InvalidSignature.into())
+        );
+    }
+
+    #[test]
+    fn execute_tx_rejects_wrong_chain_id() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+
+        let tx_create = hello_world_create(0).sign(&secret_key, Some(EVM_DEVELOP_CHAIN_ID + 1));
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create),
+            Err(EvmError::WrongChainId.into())
+        );
+
+        let tx_create = hello_world_create(0).sign(&secret_key, Some(EVM_DEVELOP_CHAIN_ID));
+        assert_eq!(execute_evm_tx(&mut executor, tx_create), Ok(()));
+    }
+
+    #[test]
+    fn execute_tx_without_validation_feature() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        executor.set_features(evm_state::ExecutorFeatures::default());
+        fund_dummy_caller(&mut executor);
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+
+        // Checks that was added later should not reject transactions of old blocks.
+        let tx_create = hello_world_create(5).sign(&secret_key, Some(EVM_DEVELOP_CHAIN_ID + 1));
+        assert_eq!(execute_evm_tx(&mut executor, tx_create), Ok(()));
+    }
+
+    #[test]
+    fn readonly_evm_state_requires_feature() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let processor = EvmProcessor::default();
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let keyed_accounts = [KeyedAccount::new_readonly(
+            &solana::evm_state::ID,
+            false,
+            &evm_account,
+        )];
+        let mut execute = |executor: &mut evm_state::Executor, nonce| {
+            let evm_tx = hello_world_create(nonce).sign(&secret_key, None);
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmTransaction { evm_tx }).unwrap(),
+                Some(executor),
+                None,
+            )
+        };
+
+        executor.set_features(evm_state::ExecutorFeatures {
+            readonly_state_account: false,
+            ..evm_state::ExecutorFeatures::latest()
+        });
+        assert_eq!(
+            execute(&mut executor, 0),
+            Err(InstructionError::MissingAccount)
+        );
+
+        executor.set_features(evm_state::ExecutorFeatures::latest());
+        assert_eq!(execute(&mut executor, 0), Ok(()));
+    }
+
+    #[test]
+    fn execute_tx_rejects_gas_out_of_bounds() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+
+        let mut tx_create = hello_world_create(0);
+        tx_create.gas_limit = U256::from(u64::MAX) + 1;
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create.sign(&secret_key, None)),
+            Err(EvmError::GasLimitOutOfBounds.into())
+        );
+
+        let mut tx_create = hello_world_create(0);
+        tx_create.gas_price = U256::MAX;
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create.sign(&secret_key, None)),
+            Err(EvmError::GasPriceOutOfBounds.into())
+        );
+    }
+
+    #[test]
+    fn execute_tx_charges_fee() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        // price is not multiple of lamport, so fee has sub-lamport remainder
+        let gas_price = U256::from(evm_state::WEI_PER_LAMPORT / 1000 + 1);
+        executor.set_min_gas_price(gas_price);
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+
+        let mut tx_create = hello_world_create(0);
+        tx_create.gas_price = gas_price;
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create.clone().sign(&secret_key, None)),
+            Err(EvmError::CantPayFee.into())
+        );
+
+        fund_dummy_caller(&mut executor);
+        assert_eq!(
+            execute_evm_tx(&mut executor, hello_world_create(0).sign(&secret_key, None)),
+            Err(EvmError::GasPriceTooLow.into())
+        );
+
+        let balance_before = executor.with_executor(|e| e.balance(caller));
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create.sign(&secret_key, None)),
+            Ok(())
+        );
+        let balance_after = executor.with_executor(|e| e.balance(caller));
+        let (lamports, change) =
+            evm::gweis_to_lamports(U256::from(executor.used_gas()) * gas_price);
+        assert_ne!(change, U256::zero());
+        assert_eq!(executor.fee_collected(), evm::lamports_to_gwei(lamports));
+        assert_eq!(balance_before - balance_after, executor.fee_collected());
+    }
+
+    #[test]
+    fn execute_tx_without_fee_before_activation() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        executor.set_features(evm_state::ExecutorFeatures {
+            charge_fee: false,
+            ..evm_state::ExecutorFeatures::latest()
+        });
+        executor.set_min_gas_price(U256::from(evm_state::WEI_PER_LAMPORT));
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+
+        // caller without balance can send transaction priced below minimal gas price
+        let mut tx_create = hello_world_create(0);
+        tx_create.gas_price = U256::from(evm_state::WEI_PER_LAMPORT / 2);
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx_create.sign(&secret_key, None)),
+            Ok(())
+        );
+        assert_eq!(executor.fee_collected(), U256::zero());
+        assert_eq!(executor.with_executor(|e| e.balance(caller)), U256::zero());
+    }
+
+    #[test]
+    fn execute_tx_with_native_transfer() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+        executor.with_executor(|e| e.state_mut().deposit(caller, evm::lamports_to_gwei(10)));
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        evm_account.borrow_mut().lamports = 10;
+        let receiver_account = RefCell::new(solana_sdk::account::Account::default());
+        let receiver_id = Pubkey::new_unique();
+        let transfer = |nonce: u64, receiver: Pubkey| {
+            evm::UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: 0.into(),
+                gas_limit: 300000.into(),
+                action: TransactionAction::Call(evm::precompiles::NATIVE_TRANSFER_ADDR),
+                value: evm::lamports_to_gwei(3),
+                input: receiver.to_bytes().to_vec(),
+            }
+            .sign(&secret_key, None)
+        };
+
+        let keyed_accounts = [
+            KeyedAccount::new(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&receiver_id, false, &receiver_account),
+        ];
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmTransaction {
+                    evm_tx: transfer(0, receiver_id)
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Ok(())
+        );
+        assert_eq!(receiver_account.borrow().lamports, 3);
+        assert_eq!(evm_account.borrow().lamports, 7);
+
+        // receiver is not passed to instruction
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmTransaction {
+                    evm_tx: transfer(1, Pubkey::new_unique())
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Err(InstructionError::InvalidError)
+        );
+        assert_eq!(receiver_account.borrow().lamports, 3);
+        assert_eq!(evm_account.borrow().lamports, 7);
+        assert_eq!(
+            executor.with_executor(|e| e.balance(caller)),
+            evm::lamports_to_gwei(7)
+        );
+
+        // read-only evm state account doesn't allow transfers to native accounts
+        let keyed_accounts = [
+            KeyedAccount::new_readonly(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&receiver_id, false, &receiver_account),
+        ];
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmTransaction {
+                    evm_tx: transfer(2, receiver_id)
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Err(InstructionError::InvalidError)
+        );
+        assert_eq!(receiver_account.borrow().lamports, 3);
+        assert_eq!(evm_account.borrow().lamports, 7);
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts[..1],
+                &bincode::serialize(&EvmInstruction::SwapNativeToEther {
+                    lamports: 1,
+                    ether_address: caller,
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Err(InstructionError::ReadonlyLamportChange)
+        );
+    }
+
     #[test]
     fn execute_tx_with_state_apply() {
         let state = RwLock::new(evm_state::EvmState::default());
@@ -340,18 +1002,21 @@ mod test {
                 locked.clone(),
                 evm_state::Config::istanbul(),
                 10000000,
+                EVM_DEVELOP_CHAIN_ID,
                 0,
             );
+            fund_dummy_caller(&mut executor_orig);
             let mut executor = Some(&mut executor_orig);
             assert!(processor
-                .process_instruction(
+                .process_with_executor(
                     &crate::ID,
                     &keyed_accounts,
                     &bincode::serialize(&EvmInstruction::EvmTransaction {
                         evm_tx: tx_create.clone()
                     })
                     .unwrap(),
-                    executor.as_deref_mut()
+                    executor.as_deref_mut(),
+                    None
                 )
                 .is_ok());
             println!("cx = {:?}", executor);
@@ -395,17 +1060,19 @@ mod test {
                 locked.clone(),
                 evm_state::Config::istanbul(),
                 10000000,
+                EVM_DEVELOP_CHAIN_ID,
                 0,
             );
             let mut executor = Some(&mut executor_orig);
 
             assert!(processor
-                .process_instruction(
+                .process_with_executor(
                     &crate::ID,
                     &keyed_accounts,
                     &bincode::serialize(&EvmInstruction::EvmTransaction { evm_tx: tx_call })
                         .unwrap(),
-                    executor.as_deref_mut()
+                    executor.as_deref_mut(),
+                    None
                 )
                 .is_ok());
             println!("cx = {:?}", executor);
@@ -434,6 +1101,7 @@ mod test {
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
         let mut executor = Some(&mut executor);
@@ -456,7 +1124,7 @@ mod test {
         let lamports_before = keyed_accounts[0].try_account_ref_mut().unwrap().lamports;
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::SwapNativeToEther {
@@ -464,7 +1132,8 @@ mod test {
                     ether_address: ether_dummy_address
                 })
                 .unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_ok());
         println!("cx = {:?}", executor);
@@ -475,11 +1144,12 @@ mod test {
         );
         assert_eq!(keyed_accounts[1].try_account_ref_mut().unwrap().lamports, 0);
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::FreeOwnership {}).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_ok());
         println!("cx = {:?}", executor);
@@ -489,14 +1159,233 @@ mod test {
         );
     }
 
+    fn swap_call(nonce: u64, lamports: u64, receiver: solana::Address) -> evm::Transaction {
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        crate::transfer_eth_to_native_tx(nonce.into(), 0.into(), lamports, receiver)
+            .sign(&secret_key, None)
+    }
+
+    #[test]
+    fn execute_swap_ether_to_native() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let processor = EvmProcessor::default();
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+
+        let user_account = RefCell::new(solana_sdk::account::Account {
+            lamports: 1000,
+            data: vec![],
+            owner: crate::ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+        let user_id = Pubkey::new_unique();
+        let receiver_account = RefCell::new(solana_sdk::account::Account::default());
+        let receiver_id = Pubkey::new_unique();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let total_lamports = 1000 + evm_account.borrow().lamports;
+
+        let keyed_accounts = [
+            KeyedAccount::new(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&user_id, true, &user_account),
+        ];
+        processor
+            .process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::SwapNativeToEther {
+                    lamports: 1000,
+                    ether_address: caller,
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            )
+            .unwrap();
+
+        let keyed_accounts = [
+            KeyedAccount::new(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&receiver_id, false, &receiver_account),
+        ];
+        processor
+            .process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::SwapEtherToNative {
+                    evm_tx: swap_call(0, 400, receiver_id),
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(receiver_account.borrow().lamports, 400);
+        assert_eq!(
+            evm_account.borrow().lamports + user_account.borrow().lamports + 400,
+            total_lamports
+        );
+        assert_eq!(
+            executor.with_executor(|e| e.balance(caller)),
+            evm::lamports_to_gwei(600)
+        );
+        assert_eq!(
+            executor.with_executor(|e| e.balance(evm::ETH_TO_VLX_ADDR)),
+            U256::zero()
+        );
+        // all gweis that are left in evm are still backed by lamports of evm state account.
+        assert_eq!(
+            evm::lamports_to_gwei(evm_account.borrow().lamports - 1),
+            executor.with_executor(|e| e.balance(caller))
+        );
+    }
+
+    #[test]
+    fn swap_ether_to_native_rejects_invalid_tx() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let processor = EvmProcessor::default();
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+
+        let receiver_account = RefCell::new(solana_sdk::account::Account::default());
+        let receiver_id = Pubkey::new_unique();
+        let evm_account = RefCell::new(crate::create_state_account());
+        evm_account.borrow_mut().lamports = 1000;
+        let keyed_accounts = [
+            KeyedAccount::new(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&receiver_id, false, &receiver_account),
+        ];
+        let mut swap = |evm_tx| {
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::SwapEtherToNative { evm_tx }).unwrap(),
+                Some(&mut executor),
+                None,
+            )
+        };
+
+        // amount with fraction of lamport
+        let mut tx = crate::transfer_eth_to_native_tx(0.into(), 0.into(), 1, receiver_id);
+        tx.value += U256::one();
+        assert_eq!(
+            swap(tx.sign(&secret_key, None)),
+            Err(InstructionError::InvalidArgument)
+        );
+
+        // receiver doesn't match
+        let tx = swap_call(0, 1, Pubkey::new_unique());
+        assert_eq!(swap(tx), Err(InstructionError::InvalidArgument));
+
+        // not a swap address
+        let mut tx = crate::transfer_eth_to_native_tx(0.into(), 0.into(), 1, receiver_id);
+        tx.action = TransactionAction::Call(caller);
+        assert_eq!(
+            swap(tx.sign(&secret_key, None)),
+            Err(InstructionError::InvalidArgument)
+        );
+
+        // not enough lamports locked in evm state
+        let tx = swap_call(0, 1001, receiver_id);
+        assert_eq!(swap(tx), Err(InstructionError::InsufficientFunds));
+
+        // rejected transactions don't consume nonce
+        assert_eq!(swap(swap_call(0, 1000, receiver_id)), Ok(()));
+        drop(swap);
+        assert_eq!(receiver_account.borrow().lamports, 1000);
+        assert_eq!(evm_account.borrow().lamports, 0);
+    }
+
+    #[test]
+    fn plain_transfer_to_swap_address_is_rejected() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let caller = secret_key.to_address();
+        let balance_before = executor.with_executor(|e| e.balance(caller));
+
+        // without writable receiver, value can't leave evm, so it stays with caller
+        let tx = swap_call(0, 1, Pubkey::new_unique());
+        assert_eq!(
+            execute_evm_tx(&mut executor, tx),
+            Err(InstructionError::InvalidError)
+        );
+        assert_eq!(
+            executor.with_executor(|e| e.balance(evm::ETH_TO_VLX_ADDR)),
+            U256::zero()
+        );
+        assert_eq!(
+            executor.with_executor(|e| e.balance(caller)),
+            balance_before
+        );
+    }
+
+    #[test]
+    fn swap_ether_to_native_requires_native_precompiles() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        executor.set_features(evm_state::ExecutorFeatures::default());
+        fund_dummy_caller(&mut executor);
+        let processor = EvmProcessor::default();
+
+        let receiver_account = RefCell::new(solana_sdk::account::Account::default());
+        let receiver_id = Pubkey::new_unique();
+        let evm_account = RefCell::new(crate::create_state_account());
+        evm_account.borrow_mut().lamports = 1000;
+        let keyed_accounts = [
+            KeyedAccount::new(&solana::evm_state::ID, false, &evm_account),
+            KeyedAccount::new(&receiver_id, false, &receiver_account),
+        ];
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::SwapEtherToNative {
+                    evm_tx: swap_call(0, 1, receiver_id)
+                })
+                .unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Err(InstructionError::InvalidInstructionData)
+        );
+        assert_eq!(receiver_account.borrow().lamports, 0);
+        assert_eq!(evm_account.borrow().lamports, 1000);
+    }
+
     fn all_ixs() -> Vec<solana_sdk::instruction::Instruction> {
-        let tx_call = dummy_call();
+        let tx_call = dummy_call(0);
 
         let signer = solana::Address::new_unique();
         vec![
             crate::transfer_native_to_eth(signer, 1, tx_call.address().unwrap()),
             crate::free_ownership(signer),
             crate::send_raw_tx(signer, tx_call),
+            crate::transfer_eth_to_native(signer, swap_call(1, 0, signer)),
         ]
     }
 
@@ -527,6 +1416,7 @@ mod test {
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
         let mut executor = Some(&mut executor);
@@ -562,21 +1452,23 @@ mod test {
 
             println!("Keyed accounts = {:?}", keyed_accounts);
             processor
-                .process_instruction(
+                .process_with_executor(
                     &crate::ID,
                     &keyed_accounts,
                     &bincode::serialize(&data).unwrap(),
                     executor.as_deref_mut(),
+                    None,
                 )
                 .unwrap();
             keyed_accounts.remove(0);
 
             let err = processor
-                .process_instruction(
+                .process_with_executor(
                     &crate::ID,
                     &keyed_accounts,
                     &bincode::serialize(&data).unwrap(),
                     executor.as_deref_mut(),
+                    None,
                 )
                 .unwrap_err();
             match err {
@@ -592,6 +1484,7 @@ mod test {
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
         let mut executor = Some(&mut executor);
@@ -617,11 +1510,12 @@ mod test {
             seed: H256::zero(),
         };
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_err());
         println!("cx = {:?}", executor);
@@ -633,11 +1527,12 @@ mod test {
         };
 
         processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
                 executor.as_deref_mut(),
+                None,
             )
             .unwrap();
         println!("cx = {:?}", executor);
@@ -649,6 +1544,7 @@ mod test {
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
         let mut executor = Some(&mut executor);
@@ -676,11 +1572,12 @@ mod test {
             seed: H256::zero(),
         };
         processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
                 executor.as_deref_mut(),
+                None,
             )
             .unwrap();
         println!("cx = {:?}", executor);
@@ -693,11 +1590,12 @@ mod test {
         };
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_err());
 
@@ -710,11 +1608,12 @@ mod test {
         };
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_err());
 
@@ -728,11 +1627,12 @@ mod test {
         };
 
         processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
                 executor.as_deref_mut(),
+                None,
             )
             .unwrap();
 
@@ -745,11 +1645,12 @@ mod test {
         };
 
         processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
                 executor.as_deref_mut(),
+                None,
             )
             .unwrap();
 
@@ -762,6 +1663,7 @@ mod test {
             evm_state::EvmState::default(),
             evm_state::Config::istanbul(),
             10000000,
+            EVM_DEVELOP_CHAIN_ID,
             0,
         );
         let mut executor = Some(&mut executor);
@@ -788,16 +1690,192 @@ mod test {
         };
 
         assert!(processor
-            .process_instruction(
+            .process_with_executor(
                 &crate::ID,
                 &keyed_accounts,
                 &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
-                executor.as_deref_mut()
+                executor.as_deref_mut(),
+                None
             )
             .is_err());
         println!("cx = {:?}", executor);
     }
 
+    #[test]
+    fn big_tx_execute_rlp_and_reclaim() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        fund_dummy_caller(&mut executor);
+        let mut executor = Some(&mut executor);
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let evm_keyed_account = KeyedAccount::new(&solana::evm_state::ID, false, &evm_account);
+
+        let user_account = RefCell::new(solana_sdk::account::Account {
+            lamports: 1000,
+            data: vec![],
+            owner: crate::ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+        let user_id = Pubkey::new_unique();
+        let user_keyed_account = KeyedAccount::new(&user_id, true, &user_account);
+
+        let keyed_accounts = [evm_keyed_account, user_keyed_account];
+        let mut process = |big_transaction| {
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
+                executor.as_deref_mut(),
+                None,
+            )
+        };
+
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let mut tx_create = hello_world_create(0);
+        // Constructor arguments are ignored, but make transaction bigger than MTU.
+        tx_create
+            .input
+            .extend_from_slice(&[0; evm::TX_MTU as usize]);
+        let tx_bytes = rlp::encode(&tx_create.sign(&secret_key, None)).to_vec();
+        assert!(tx_bytes.len() > evm::TX_MTU as usize);
+
+        let seed = H256::zero();
+        process(EvmBigTransaction::EvmTransactionAllocate {
+            seed,
+            len: tx_bytes.len() as u64,
+            _pay_for_data: None,
+        })
+        .unwrap();
+        for (i, chunk) in tx_bytes.chunks(evm::TX_MTU as usize).enumerate() {
+            process(EvmBigTransaction::EvmTransactionWrite {
+                seed,
+                offset: i as u64 * evm::TX_MTU,
+                data: chunk.to_vec(),
+            })
+            .unwrap();
+        }
+        process(EvmBigTransaction::EvmTransactionExecute { seed }).unwrap();
+        assert!(process(EvmBigTransaction::EvmTransactionExecute { seed }).is_err());
+
+        // Abandoned allocation can be reclaimed by owner.
+        let seed = H256::repeat_byte(1);
+        let allocate = || EvmBigTransaction::EvmTransactionAllocate {
+            seed,
+            len: 100,
+            _pay_for_data: None,
+        };
+        process(allocate()).unwrap();
+        assert!(process(allocate()).is_err());
+        process(EvmBigTransaction::EvmTransactionReclaim { seed }).unwrap();
+        assert!(process(EvmBigTransaction::EvmTransactionReclaim { seed }).is_err());
+        process(allocate()).unwrap();
+    }
+
+    #[test]
+    fn big_tx_requires_owner_signature() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let evm_keyed_account = KeyedAccount::new(&solana::evm_state::ID, false, &evm_account);
+
+        let user_account = RefCell::new(solana_sdk::account::Account {
+            lamports: 1000,
+            data: vec![],
+            owner: crate::ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+        let user_id = Pubkey::new_unique();
+        let user_keyed_account = KeyedAccount::new(&user_id, false, &user_account);
+
+        let big_transaction = EvmBigTransaction::EvmTransactionReclaim { seed: H256::zero() };
+        assert_eq!(
+            processor.process_with_executor(
+                &crate::ID,
+                &[evm_keyed_account, user_keyed_account],
+                &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
+                Some(&mut executor),
+                None,
+            ),
+            Err(InstructionError::MissingRequiredSignature)
+        );
+    }
+
+    #[test]
+    fn big_tx_in_bincode_until_rlp_feature() {
+        let mut executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            10000000,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        executor.set_features(evm_state::ExecutorFeatures::default());
+        fund_dummy_caller(&mut executor);
+        let mut executor = Some(&mut executor);
+        let processor = EvmProcessor::default();
+        let evm_account = RefCell::new(crate::create_state_account());
+        let evm_keyed_account = KeyedAccount::new(&solana::evm_state::ID, true, &evm_account);
+
+        let user_account = RefCell::new(solana_sdk::account::Account {
+            lamports: 1000,
+            data: vec![],
+            owner: crate::ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+        let user_id = Pubkey::new_unique();
+        // Owner signature is not required before activation.
+        let user_keyed_account = KeyedAccount::new(&user_id, false, &user_account);
+
+        let keyed_accounts = [evm_keyed_account, user_keyed_account];
+        let mut process = |big_transaction| {
+            processor.process_with_executor(
+                &crate::ID,
+                &keyed_accounts,
+                &bincode::serialize(&EvmInstruction::EvmBigTransaction(big_transaction)).unwrap(),
+                executor.as_deref_mut(),
+                None,
+            )
+        };
+
+        let secret_key = evm::SecretKey::from_slice(&SECRET_KEY_DUMMY).unwrap();
+        let tx_bytes = bincode::serialize(&hello_world_create(0).sign(&secret_key, None)).unwrap();
+
+        let seed = H256::zero();
+        process(EvmBigTransaction::EvmTransactionAllocate {
+            seed,
+            len: tx_bytes.len() as u64,
+            _pay_for_data: None,
+        })
+        .unwrap();
+        process(EvmBigTransaction::EvmTransactionWrite {
+            seed,
+            offset: 0,
+            data: tx_bytes,
+        })
+        .unwrap();
+        process(EvmBigTransaction::EvmTransactionExecute { seed }).unwrap();
+
+        assert_eq!(
+            process(EvmBigTransaction::EvmTransactionReclaim { seed }),
+            Err(InstructionError::InvalidInstructionData)
+        );
+    }
+
     #[test]
     fn check_tx_mtu_is_in_solanas_limit() {
         use solana_sdk::hash::hash;
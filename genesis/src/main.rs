@@ -271,6 +271,16 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 .help("percentage of collected fee to burn")
                 .validator(is_valid_percentage),
         )
+        .arg(
+            Arg::with_name("evm_min_gas_price")
+                .long("evm-min-gas-price")
+                .value_name("WEI")
+                .takes_value(true)
+                .help(
+                    "Minimal gas price of evm transactions \
+                     [default: cost of signature at target-lamports-per-signature per transfer gas]",
+                ),
+        )
         .arg(
             Arg::with_name("vote_commission_percentage")
                 .long("vote-commission-percentage")
@@ -354,6 +364,15 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                     "Selects the features that will be enabled for the cluster"
                 ),
         )
+        .arg(
+            Arg::with_name("evm_chain_id")
+                .long("evm-chain-id")
+                .value_name("CHAIN_ID")
+                .takes_value(true)
+                .help(
+                    "Chain id of evm transactions [default: depends on cluster type]"
+                ),
+        )
         .arg(
             Arg::with_name("max_genesis_archive_unpacked_size")
                 .long("max-genesis-archive-unpacked-size")
@@ -442,6 +461,11 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         value_t_or_exit!(matches, "target_signatures_per_slot", u64),
     );
     fee_rate_governor.burn_percent = value_t_or_exit!(matches, "fee_burn_percentage", u8);
+    let evm_min_gas_price = if matches.is_present("evm_min_gas_price") {
+        Some(value_t_or_exit!(matches, "evm_min_gas_price", u64))
+    } else {
+        None
+    };
 
     let mut poh_config = PohConfig {
         target_tick_duration: if matches.is_present("target_tick_duration") {
@@ -490,6 +514,12 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         matches.is_present("enable_warmup_epochs"),
     );
 
+    let evm_chain_id = if matches.value_of("evm_chain_id").is_some() {
+        value_t_or_exit!(matches, "evm_chain_id", u64)
+    } else {
+        cluster_type.default_evm_chain_id()
+    };
+
     let native_instruction_processors = if cluster_type == ClusterType::Development {
         vec![
             solana_vest_program!(),
@@ -508,6 +538,8 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         rent,
         poh_config,
         cluster_type,
+        evm_chain_id: Some(evm_chain_id),
+        evm_min_gas_price,
         ..GenesisConfig::default()
     };
 
@@ -18,8 +18,8 @@ use solana_rayon_threadlimit::get_thread_count;
 use solana_runtime::{
     accounts_index::AccountIndex,
     bank::{
-        Bank, InnerInstructionsList, TransactionBalancesSet, TransactionExecutionResult,
-        TransactionLogMessages, TransactionResults,
+        Bank, ExecutedBatch, InnerInstructionsList, TransactionBalancesSet,
+        TransactionExecutionResult, TransactionLogMessages, TransactionResults,
     },
     bank_forks::BankForks,
     bank_utils,
@@ -38,7 +38,7 @@ use solana_sdk::{
     transaction::{Result, Transaction, TransactionError},
 };
 use solana_transaction_status::token_balances::{
-    collect_token_balances, TransactionTokenBalancesSet,
+    collect_token_balances, TransactionTokenBalances, TransactionTokenBalancesSet,
 };
 
 use std::{
@@ -101,30 +101,51 @@ fn get_first_error(
     first_err
 }
 
-fn execute_batch(
-    batch: &TransactionBatch,
-    bank: &Arc<Bank>,
-    transaction_status_sender: Option<TransactionStatusSender>,
-    replay_vote_sender: Option<&ReplayVoteSender>,
-) -> Result<()> {
-    let record_token_balances = transaction_status_sender.is_some();
+// Batch executed in parallel with others, that is not committed yet.
+struct UncommittedBatch {
+    executed: ExecutedBatch,
+    pre_token_balances: TransactionTokenBalances,
+    mint_decimals: HashMap<Pubkey, u8>,
+}
 
+fn execute_batch(batch: &TransactionBatch, record_transaction_status: bool) -> UncommittedBatch {
+    let bank = batch.bank();
     let mut mint_decimals: HashMap<Pubkey, u8> = HashMap::new();
 
-    let pre_token_balances = if record_token_balances {
+    let pre_token_balances = if record_transaction_status {
         collect_token_balances(&bank, &batch, &mut mint_decimals)
     } else {
         vec![]
     };
 
+    let executed = bank.load_and_execute_batch(
+        batch,
+        MAX_PROCESSING_AGE,
+        record_transaction_status,
+        record_transaction_status,
+        record_transaction_status,
+    );
+    UncommittedBatch {
+        executed,
+        pre_token_balances,
+        mint_decimals,
+    }
+}
+
+fn commit_batch(
+    batch: &TransactionBatch,
+    bank: &Arc<Bank>,
+    executed: UncommittedBatch,
+    transaction_status_sender: Option<TransactionStatusSender>,
+    replay_vote_sender: Option<&ReplayVoteSender>,
+) -> Result<()> {
+    let UncommittedBatch {
+        executed,
+        pre_token_balances,
+        mut mint_decimals,
+    } = executed;
     let (tx_results, balances, inner_instructions, transaction_logs) =
-        batch.bank().load_execute_and_commit_transactions(
-            batch,
-            MAX_PROCESSING_AGE,
-            transaction_status_sender.is_some(),
-            transaction_status_sender.is_some(),
-            transaction_status_sender.is_some(),
-        );
+        batch.bank().commit_executed_batch(batch, executed);
 
     bank_utils::find_and_send_votes(batch.transactions(), &tx_results, replay_vote_sender);
 
@@ -135,11 +156,7 @@ fn execute_batch(
     } = tx_results;
 
     if let Some(sender) = transaction_status_sender {
-        let post_token_balances = if record_token_balances {
-            collect_token_balances(&bank, &batch, &mut mint_decimals)
-        } else {
-            vec![]
-        };
+        let post_token_balances = collect_token_balances(&bank, &batch, &mut mint_decimals);
 
         let token_balances =
             TransactionTokenBalancesSet::new(pre_token_balances, post_token_balances);
@@ -169,21 +186,36 @@ fn execute_batches(
     replay_vote_sender: Option<&ReplayVoteSender>,
 ) -> Result<()> {
     inc_new_counter_debug!("bank-par_execute_entries-count", batches.len());
-    let results: Vec<Result<()>> = PAR_THREAD_POOL.with(|thread_pool| {
+    let record_transaction_status = transaction_status_sender.is_some();
+    let executed: Vec<UncommittedBatch> = PAR_THREAD_POOL.with(|thread_pool| {
         thread_pool.borrow().install(|| {
             batches
                 .into_par_iter()
-                .map_with(transaction_status_sender, |sender, batch| {
-                    let result = execute_batch(batch, bank, sender.clone(), replay_vote_sender);
-                    if let Some(entry_callback) = entry_callback {
-                        entry_callback(bank);
-                    }
-                    result
-                })
+                .map(|batch| execute_batch(batch, record_transaction_status))
                 .collect()
         })
     });
 
+    // Batches don't lock evm state, so they are committed in order of entries,
+    // and ones that accessed evm state changed by previous batches are executed again.
+    let results: Vec<Result<()>> = batches
+        .iter()
+        .zip(executed)
+        .map(|(batch, executed)| {
+            let result = commit_batch(
+                batch,
+                bank,
+                executed,
+                transaction_status_sender.clone(),
+                replay_vote_sender,
+            );
+            if let Some(entry_callback) = entry_callback {
+                entry_callback(bank);
+            }
+            result
+        })
+        .collect();
+
     first_err(&results)
 }
 
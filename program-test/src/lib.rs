@@ -624,11 +624,10 @@ impl ProgramTest {
         }
 
         // Realistic fee_calculator part 1: Fake a single signature by calling
-        // `bank.commit_transactions()` so that the fee calculator in the child bank will be
+        // `bank.increment_signature_count()` so that the fee calculator in the child bank will be
         // initialized with a non-zero fee.
         assert_eq!(bank.signature_count(), 0);
-        let update = bank.evm_state.read().unwrap().clone();
-        bank.commit_transactions(&[], None, &mut [], &[], 0, 1, update);
+        bank.increment_signature_count(1);
         assert_eq!(bank.signature_count(), 1);
 
         // Advance beyond slot 0 for a slightly more realistic test environment
@@ -1061,10 +1061,13 @@ mod tests {
                 stack_frame_size: 4096,
                 log_pubkey_units: 100,
                 max_cpi_instruction_size: usize::MAX,
+                return_data_units: 100,
+                evm_gas_per_unit: 10,
             },
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::default()),
+            None,
         );
         assert_eq!(
             Err(InstructionError::ProgramFailedToComplete),
@@ -17,9 +17,10 @@ use solana_sdk::{
     bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     entrypoint::{MAX_PERMITTED_DATA_INCREASE, SUCCESS},
     feature_set::{
-        abort_on_all_cpi_failures, limit_cpi_loader_invoke, pubkey_log_syscall_enabled,
-        ristretto_mul_syscall_enabled, sha256_syscall_enabled, sol_log_compute_units_syscall,
-        try_find_program_address_syscall_enabled, use_loaded_program_accounts,
+        abort_on_all_cpi_failures, evm_cross_execution, limit_cpi_loader_invoke,
+        pubkey_log_syscall_enabled, ristretto_mul_syscall_enabled, sha256_syscall_enabled,
+        sol_log_compute_units_syscall, try_find_program_address_syscall_enabled,
+        use_loaded_program_accounts,
     },
     hash::{Hasher, HASH_BYTES},
     instruction::{AccountMeta, Instruction, InstructionError},
@@ -136,6 +137,10 @@ pub fn register_syscalls(
         .register_syscall_by_name(b"sol_invoke_signed_c", SyscallInvokeSignedC::call)?;
     syscall_registry
         .register_syscall_by_name(b"sol_invoke_signed_rust", SyscallInvokeSignedRust::call)?;
+    if invoke_context.is_feature_active(&evm_cross_execution::id()) {
+        syscall_registry
+            .register_syscall_by_name(b"sol_get_return_data", SyscallGetReturnData::call)?;
+    }
     syscall_registry.register_syscall_by_name(b"sol_alloc_free_", SyscallAllocFree::call)?;
 
     Ok(syscall_registry)
@@ -239,6 +244,10 @@ pub fn bind_syscall_context_objects<'a>(
 
     // Cross-program invocation syscalls
 
+    let is_evm_cross_execution_active =
+        invoke_context.is_feature_active(&evm_cross_execution::id());
+    let return_data_cost = bpf_compute_budget.return_data_units;
+    let compute_meter = invoke_context.get_compute_meter();
     let invoke_context = Rc::new(RefCell::new(invoke_context));
     vm.bind_syscall_context_object(
         Box::new(SyscallInvokeSignedC {
@@ -256,6 +265,17 @@ pub fn bind_syscall_context_objects<'a>(
         }),
         None,
     )?;
+    if is_evm_cross_execution_active {
+        vm.bind_syscall_context_object(
+            Box::new(SyscallGetReturnData {
+                cost: return_data_cost,
+                compute_meter,
+                invoke_context: invoke_context.clone(),
+                loader_id,
+            }),
+            None,
+        )?;
+    }
 
     // Memory allocator
 
@@ -824,6 +844,49 @@ impl<'a> SyscallObject<BPFError> for SyscallRistrettoMul<'a> {
     }
 }
 
+/// Get data returned by the last program invoked by cross-program invocation
+pub struct SyscallGetReturnData<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallGetReturnData<'a> {
+    fn call(
+        &mut self,
+        return_data_addr: u64,
+        len: u64,
+        program_id_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(self.compute_meter.consume(self.cost), result);
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        let (program_id, return_data) = invoke_context.get_return_data();
+        let length = len.min(return_data.len() as u64);
+        if length != 0 {
+            let data = question_mark!(
+                translate_slice_mut::<u8>(memory_mapping, return_data_addr, length, self.loader_id),
+                result
+            );
+            data.copy_from_slice(&return_data[..length as usize]);
+            let program_id_result = question_mark!(
+                translate_type_mut::<Pubkey>(memory_mapping, program_id_addr, self.loader_id),
+                result
+            );
+            *program_id_result = *program_id;
+        }
+        *result = Ok(return_data.len() as u64);
+    }
+}
+
 // Cross-program invocation syscalls
 
 struct AccountReferences<'a> {
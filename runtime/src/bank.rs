@@ -84,7 +84,7 @@ use std::{
     rc::Rc,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
-        LockResult, RwLockWriteGuard, {Arc, RwLock, RwLockReadGuard},
+        LockResult, Mutex, MutexGuard, RwLockWriteGuard, {Arc, RwLock, RwLockReadGuard},
     },
     time::Duration,
 };
@@ -366,6 +366,35 @@ pub struct OverwrittenVoteAccount {
     pub transaction_result_index: usize,
 }
 
+/// Batch, that was executed, but not committed yet.
+pub struct ExecutedBatch {
+    loaded_accounts: Vec<TransactionLoadResult>,
+    executed: Vec<TransactionExecutionResult>,
+    inner_instructions: Vec<Option<InnerInstructionsList>>,
+    transaction_logs: Vec<TransactionLogMessages>,
+    retryable_txs: Vec<usize>,
+    tx_count: u64,
+    signature_count: u64,
+    evm_executor: evm_state::Executor,
+    pre_balances: TransactionBalances,
+    // Batch is executed with the same options again, on conflict of evm changes.
+    max_age: usize,
+    collect_balances: bool,
+    enable_cpi_recording: bool,
+    enable_log_recording: bool,
+}
+
+impl ExecutedBatch {
+    pub fn execution_results(&self) -> &[TransactionExecutionResult] {
+        &self.executed
+    }
+
+    /// Indexes of transactions, that weren't executed, but can be retried later.
+    pub fn retryable_transactions(&self) -> &[usize] {
+        &self.retryable_txs
+    }
+}
+
 impl TransactionBalancesSet {
     pub fn new(pre_balances: TransactionBalances, post_balances: TransactionBalances) -> Self {
         assert_eq!(pre_balances.len(), post_balances.len());
@@ -721,6 +750,14 @@ pub struct Bank {
 
     pub evm_state: RwLock<evm_state::EvmState>,
 
+    /// Held from the check of evm conflicts of executed batch until its commit,
+    /// so evm changes are committed in the same order as batches are recorded.
+    evm_commit_lock: Mutex<()>,
+
+    /// Evm fees, that are already added to collector fees, but still held by evm state account.
+    /// Evm transactions lock evm state account read-only, so its lamports are changed once in `freeze`.
+    evm_fees: AtomicU64,
+
     /// Hash of this Bank's state. Only meaningful after freezing.
     hash: RwLock<Hash>,
 
@@ -829,6 +866,9 @@ pub struct Bank {
 
     pub cluster_type: Option<ClusterType>,
 
+    /// Chain id of evm transactions, taken from genesis
+    pub evm_chain_id: u64,
+
     pub lazy_rent_collection: AtomicBool,
 
     pub no_stake_rewrite: AtomicBool,
@@ -908,6 +948,7 @@ impl Bank {
         bank.ancestors.insert(bank.slot(), 0);
         bank.transaction_debug_keys = debug_keys;
         bank.cluster_type = Some(genesis_config.cluster_type);
+        bank.evm_chain_id = genesis_config.evm_chain_id();
 
         bank.rc.accounts = Arc::new(Accounts::new_with_config(
             paths,
@@ -999,6 +1040,8 @@ impl Bank {
             slot,
             epoch,
             evm_state: RwLock::new(evm_state),
+            evm_commit_lock: Mutex::new(()),
+            evm_fees: AtomicU64::new(0),
             blockhash_queue: RwLock::new(parent.blockhash_queue.read().unwrap().clone()),
 
             // TODO: clean this up, so much special-case copying...
@@ -1038,6 +1081,7 @@ impl Bank {
             rewards: RwLock::new(vec![]),
             skip_drop: AtomicBool::new(false),
             cluster_type: parent.cluster_type,
+            evm_chain_id: parent.evm_chain_id,
             lazy_rent_collection: AtomicBool::new(parent.lazy_rent_collection.load(Relaxed)),
             no_stake_rewrite: AtomicBool::new(parent.no_stake_rewrite.load(Relaxed)),
             rewards_pool_pubkeys: parent.rewards_pool_pubkeys.clone(),
@@ -1144,6 +1188,8 @@ impl Bank {
             rc: bank_rc,
             src: new(),
             evm_state: RwLock::new(evm_state),
+            evm_commit_lock: new(),
+            evm_fees: new(),
             blockhash_queue: RwLock::new(fields.blockhash_queue),
             ancestors: fields.ancestors,
             hash: RwLock::new(fields.hash),
@@ -1183,6 +1229,7 @@ impl Bank {
             rewards: new(),
             skip_drop: new(),
             cluster_type: Some(genesis_config.cluster_type),
+            evm_chain_id: genesis_config.evm_chain_id(),
             lazy_rent_collection: new(),
             no_stake_rewrite: new(),
             rewards_pool_pubkeys: new(),
@@ -1226,6 +1273,7 @@ impl Bank {
         assert_eq!(bank.epoch_schedule, genesis_config.epoch_schedule);
         assert_eq!(bank.epoch, bank.epoch_schedule.get_epoch(bank.slot));
         bank.fee_rate_governor.lamports_per_signature = bank.fee_calculator.lamports_per_signature;
+        bank.fee_rate_governor.evm_min_gas_price = genesis_config.evm_min_gas_price;
         assert_eq!(
             bank.fee_rate_governor.create_fee_calculator(),
             bank.fee_calculator
@@ -2031,13 +2079,19 @@ impl Bank {
         // committed before this write lock can be obtained here.
         let mut hash = self.hash.write().unwrap();
 
-        self.evm_state
-            .write()
-            .expect("evm state was poisoned")
-            .freeze();
+        {
+            let mut evm_state = self.evm_state.write().expect("evm state was poisoned");
+            if *hash == Hash::default() {
+                evm_state.register_block_hash(evm_state::H256::from_slice(
+                    self.last_blockhash().as_ref(),
+                ));
+            }
+            evm_state.freeze();
+        }
         if *hash == Hash::default() {
             // finish up any deferred changes to account state
             self.collect_rent_eagerly();
+            self.settle_evm_fees();
             self.collect_fees();
             self.distribute_rent();
             self.update_slot_history();
@@ -2127,6 +2181,7 @@ impl Bank {
     fn process_genesis_config(&mut self, genesis_config: &GenesisConfig) {
         // Bootstrap validator collects fees until `new_from_parent` is called.
         self.fee_rate_governor = genesis_config.fee_rate_governor.clone();
+        self.fee_rate_governor.evm_min_gas_price = genesis_config.evm_min_gas_price;
         self.fee_calculator = self.fee_rate_governor.create_fee_calculator();
 
         for (pubkey, account) in genesis_config.accounts.iter() {
@@ -2322,6 +2377,14 @@ impl Bank {
         &self.fee_rate_governor
     }
 
+    /// Minimal gas price of evm transactions in wei.
+    pub fn evm_min_gas_price(&self) -> evm_state::U256 {
+        match self.fee_rate_governor.evm_min_gas_price {
+            Some(min_gas_price) => min_gas_price.into(),
+            None => evm_state::min_gas_price(self.fee_rate_governor.target_lamports_per_signature),
+        }
+    }
+
     pub fn get_blockhash_last_valid_slot(&self, blockhash: &Hash) -> Option<Slot> {
         let blockhash_queue = self.blockhash_queue.read().unwrap();
         // This calculation will need to be updated to consider epoch boundaries if BlockhashQueue
@@ -2471,7 +2534,7 @@ impl Bank {
             _retryable_transactions,
             _transaction_count,
             _signature_count,
-            _patch,
+            _evm_executor,
         ) = self.load_and_execute_transactions(
             &batch,
             // After simulation, transactions will need to be forwarded to the leader
@@ -2898,7 +2961,7 @@ impl Bank {
         Vec<usize>,
         u64,
         u64,
-        evm_state::EvmState,
+        evm_state::Executor,
     ) {
         let txs = batch.transactions();
         debug!("processing transactions: {}", txs.len());
@@ -2945,9 +3008,13 @@ impl Bank {
         let mut evm_executor = evm_state::Executor::with_config(
             evm_state,
             evm_state::Config::istanbul(),
-            u64::max_value(),
+            evm_state::BLOCK_GAS_LIMIT,
+            self.evm_chain_id,
             self.slot(),
         );
+        evm_executor.set_features(self.evm_features());
+        evm_executor.set_min_gas_price(self.evm_min_gas_price());
+        evm_executor.set_epoch(self.epoch());
 
         let mut signature_count: u64 = 0;
         let mut inner_instructions: Vec<Option<InnerInstructionsList>> =
@@ -3130,7 +3197,7 @@ impl Bank {
             retryable_txs,
             tx_count,
             signature_count,
-            evm_executor.deconstruct(),
+            evm_executor,
         )
     }
 
@@ -3194,7 +3261,9 @@ impl Bank {
         results
     }
 
-    pub fn commit_transactions(
+    /// Commits results of execution. Evm changes of `evm_executor` shouldn't conflict
+    /// with committed ones, so batches are committed with `commit_executed_batch`.
+    fn commit_transactions(
         &self,
         txs: &[Transaction],
         iteration_order: Option<&[usize]>,
@@ -3202,7 +3271,7 @@ impl Bank {
         executed: &[TransactionExecutionResult],
         tx_count: u64,
         signature_count: u64,
-        patch: evm_state::EvmState,
+        evm_executor: evm_state::Executor,
     ) -> TransactionResults {
         assert!(
             !self.freeze_started(),
@@ -3239,10 +3308,17 @@ impl Bank {
         let overwritten_vote_accounts =
             self.update_cached_accounts(txs, iteration_order, executed, loaded_accounts);
 
-        self.evm_state
-            .write()
-            .expect("bank evm state was poisoned")
-            .swap_commit(patch);
+        let evm_fee = evm_executor.fee_collected();
+        {
+            let mut evm_state = self.evm_state.write().expect("bank evm state was poisoned");
+            let patch = evm_executor.into_patch();
+            assert!(
+                !evm_state.is_conflicting(&patch),
+                "evm changes of batch conflict with committed ones, batch should be executed again"
+            );
+            evm_state.merge(patch);
+        }
+        self.collect_evm_fee(evm_fee);
         // once committed there is no way to unroll
         write_time.stop();
         debug!("store: {}us txs_len={}", write_time.as_us(), txs.len(),);
@@ -3259,6 +3335,31 @@ impl Bank {
         }
     }
 
+    // Gas fees are already charged from evm balances, so take the same amount of lamports
+    // from the evm state account and distribute them with the rest of transaction fees.
+    fn collect_evm_fee(&self, fee: evm_state::U256) {
+        let (lamports, change) = solana_evm_loader_program::scope::evm::gweis_to_lamports(fee);
+        debug_assert!(
+            change.is_zero(),
+            "evm fee should be charged in whole lamports"
+        );
+        if lamports == 0 {
+            return;
+        }
+        self.evm_fees.fetch_add(lamports, Relaxed);
+        self.collector_fees.fetch_add(lamports, Relaxed);
+    }
+
+    // Move lamports of evm fees, collected during this slot, out of evm state account.
+    fn settle_evm_fees(&self) {
+        let lamports = self.evm_fees.load(Relaxed);
+        if lamports == 0 {
+            return;
+        }
+        self.withdraw(&solana_sdk::evm_state::id(), lamports)
+            .expect("evm state account should hold lamports for all evm balances");
+    }
+
     // Distribute collected rent fees for this slot to staked validators (excluding stakers)
     // according to stake.
     //
@@ -3836,27 +3937,126 @@ impl Bank {
         Vec<Option<InnerInstructionsList>>,
         Vec<TransactionLogMessages>,
     ) {
+        let executed = self.load_and_execute_batch(
+            batch,
+            max_age,
+            collect_balances,
+            enable_cpi_recording,
+            enable_log_recording,
+        );
+        self.commit_executed_batch(batch, executed)
+    }
+
+    /// Loads and executes batch, without committing its results.
+    /// Batches can be executed in parallel, and should be committed
+    /// with `commit_executed_batch` in order of their entries.
+    pub fn load_and_execute_batch(
+        &self,
+        batch: &TransactionBatch,
+        max_age: usize,
+        collect_balances: bool,
+        enable_cpi_recording: bool,
+        enable_log_recording: bool,
+    ) -> ExecutedBatch {
         let pre_balances = if collect_balances {
             self.collect_balances(batch)
         } else {
             vec![]
         };
         let (
-            mut loaded_accounts,
+            loaded_accounts,
             executed,
             inner_instructions,
             transaction_logs,
-            _,
+            retryable_txs,
             tx_count,
             signature_count,
-            patch,
+            evm_executor,
         ) = self.load_and_execute_transactions(
             batch,
             max_age,
             enable_cpi_recording,
             enable_log_recording,
         );
+        ExecutedBatch {
+            loaded_accounts,
+            executed,
+            inner_instructions,
+            transaction_logs,
+            retryable_txs,
+            tx_count,
+            signature_count,
+            evm_executor,
+            pre_balances,
+            max_age,
+            collect_balances,
+            enable_cpi_recording,
+            enable_log_recording,
+        }
+    }
+
+    /// Commits executed batch. Batch is executed again, if evm state it accessed
+    /// was changed by batches committed after its execution.
+    pub fn commit_executed_batch(
+        &self,
+        batch: &TransactionBatch,
+        executed: ExecutedBatch,
+    ) -> (
+        TransactionResults,
+        TransactionBalancesSet,
+        Vec<Option<InnerInstructionsList>>,
+        Vec<TransactionLogMessages>,
+    ) {
+        self.record_and_commit_executed_batch(batch, executed, |_| true)
+            .expect("batch is committed without recording")
+    }
+
+    /// Commits executed batch the same way as `commit_executed_batch`, if `record` of its final
+    /// execution results returns true. Evm changes should be recorded in the order of their commit,
+    /// otherwise replay could execute batch against state, that its leader didn't see.
+    /// So `record` is called under evm commit lock, after check of conflicts.
+    pub fn record_and_commit_executed_batch<F>(
+        &self,
+        batch: &TransactionBatch,
+        mut executed: ExecutedBatch,
+        record: F,
+    ) -> Option<(
+        TransactionResults,
+        TransactionBalancesSet,
+        Vec<Option<InnerInstructionsList>>,
+        Vec<TransactionLogMessages>,
+    )>
+    where
+        F: FnOnce(&ExecutedBatch) -> bool,
+    {
+        let mut evm_commit_lock_time = Measure::start("evm_commit_lock_time");
+        let evm_commit_lock = self.evm_commit_lock(&executed.evm_executor);
+        if self.is_evm_conflicting(&executed.evm_executor) {
+            inc_new_counter_info!("bank-evm_conflicting_batches", 1);
+            executed = self.load_and_execute_batch(
+                batch,
+                executed.max_age,
+                executed.collect_balances,
+                executed.enable_cpi_recording,
+                executed.enable_log_recording,
+            );
+        }
+        if !record(&executed) {
+            return None;
+        }
 
+        let ExecutedBatch {
+            mut loaded_accounts,
+            executed,
+            inner_instructions,
+            transaction_logs,
+            tx_count,
+            signature_count,
+            evm_executor,
+            pre_balances,
+            collect_balances,
+            ..
+        } = executed;
         let results = self.commit_transactions(
             batch.transactions(),
             batch.iteration_order(),
@@ -3864,19 +4064,48 @@ impl Bank {
             &executed,
             tx_count,
             signature_count,
-            patch,
+            evm_executor,
         );
+        if let Some(evm_commit_lock) = evm_commit_lock {
+            drop(evm_commit_lock);
+            evm_commit_lock_time.stop();
+            inc_new_counter_info!(
+                "bank-evm_commit_lock_us",
+                evm_commit_lock_time.as_us() as usize
+            );
+        }
+
         let post_balances = if collect_balances {
             self.collect_balances(batch)
         } else {
             vec![]
         };
-        (
+        Some((
             results,
             TransactionBalancesSet::new(pre_balances, post_balances),
             inner_instructions,
             transaction_logs,
-        )
+        ))
+    }
+
+    /// Lock, that should be held from the check of evm conflicts of executed batch until its commit.
+    /// Batches, that didn't access evm state, are committed without it.
+    pub fn evm_commit_lock(&self, evm_executor: &evm_state::Executor) -> Option<MutexGuard<()>> {
+        if evm_executor.has_accessed_state() {
+            Some(
+                self.evm_commit_lock
+                    .lock()
+                    .expect("evm commit lock was poisoned"),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Checks if evm state accessed by executor was changed by batches,
+    /// committed after its execution started.
+    pub fn is_evm_conflicting(&self, evm_executor: &evm_state::Executor) -> bool {
+        evm_executor.is_conflicting(&self.evm_state.read().expect("bank evm state was poisoned"))
     }
 
     #[must_use]
@@ -4184,7 +4413,7 @@ impl Bank {
         self.signature_count.load(Relaxed)
     }
 
-    fn increment_signature_count(&self, signature_count: u64) {
+    pub fn increment_signature_count(&self, signature_count: u64) {
         self.signature_count.fetch_add(signature_count, Relaxed);
     }
 
@@ -4249,6 +4478,16 @@ impl Bank {
             hash = extend_and_hash(&hash, &buf)
         }
 
+        if self.evm_state_root_in_bank_hash_enabled() {
+            let evm_state_root = self
+                .evm_state
+                .read()
+                .expect("evm state was poisoned")
+                .state_root();
+            info!("bank {} evm state root: {:?}", self.slot(), evm_state_root);
+            hash = extend_and_hash(&hash, evm_state_root.as_bytes())
+        }
+
         info!(
             "bank frozen: {} hash: {} accounts_delta: {} signature_count: {} last_blockhash: {} capitalization: {}",
             self.slot(),
@@ -4667,6 +4906,37 @@ impl Bank {
         self.feature_set.cumulative_rent_related_fixes_enabled()
     }
 
+    pub fn evm_state_root_in_bank_hash_enabled(&self) -> bool {
+        self.feature_set
+            .is_active(&feature_set::evm_state_root_in_bank_hash::id())
+    }
+
+    pub fn evm_features(&self) -> evm_state::ExecutorFeatures {
+        self.evm_features_at(self.slot())
+    }
+
+    /// Evm execution rules, that were active at `slot` of this bank's fork.
+    pub fn evm_features_at(&self, slot: Slot) -> evm_state::ExecutorFeatures {
+        evm_state::ExecutorFeatures {
+            validate_tx: self
+                .evm_feature_activated_at(&feature_set::evm_transaction_validation::id(), slot),
+            native_precompiles: self
+                .evm_feature_activated_at(&feature_set::evm_native_precompiles::id(), slot),
+            readonly_state_account: self
+                .evm_feature_activated_at(&feature_set::evm_readonly_state_account::id(), slot),
+            rlp_big_transactions: self
+                .evm_feature_activated_at(&feature_set::evm_rlp_big_transactions::id(), slot),
+            charge_fee: self.evm_feature_activated_at(&feature_set::evm_gas_fees::id(), slot),
+            wipe_storage: self.evm_feature_activated_at(&feature_set::evm_storage_wipe::id(), slot),
+        }
+    }
+
+    fn evm_feature_activated_at(&self, feature_id: &Pubkey, slot: Slot) -> bool {
+        self.feature_set
+            .activated_slot(feature_id)
+            .map_or(false, |activated_slot| activated_slot <= slot)
+    }
+
     pub fn stake_program_v2_enabled(&self) -> bool {
         self.feature_set
             .is_active(&feature_set::stake_program_v2::id())
@@ -7755,7 +8025,12 @@ pub(crate) mod tests {
 
     #[test]
     fn test_interleaving_locks_evm_tx() {
-        let (genesis_config, mint_keypair) = create_genesis_config(20000 * 3);
+        let (mut genesis_config, mint_keypair) = create_genesis_config(20000 * 3);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_readonly_state_account::id(),
+        );
         let bank = Bank::new(&genesis_config);
         let alice = Keypair::new();
         let bob = Keypair::new();
@@ -7763,17 +8038,17 @@ pub(crate) mod tests {
         assert!(bank.transfer(20000, &mint_keypair, &alice.pubkey()).is_ok());
         assert!(bank.transfer(20000, &mint_keypair, &bob.pubkey()).is_ok());
 
-        let create_tx = |from_keypair: &Keypair, hash: Hash| {
+        let create_tx = |from_keypair: &Keypair, hash: Hash, nonce: u64| {
             let from_pubkey = from_keypair.pubkey();
-            let instruction = solana_evm_loader_program::send_raw_tx(
+            let instruction = solana_evm_loader_program::send_raw_tx_readonly_state(
                 from_pubkey,
-                solana_evm_loader_program::processor::dummy_call(),
+                solana_evm_loader_program::processor::dummy_call(nonce),
             );
             let message = Message::new(&[instruction], Some(&from_pubkey));
             Transaction::new(&[from_keypair], message, hash)
         };
 
-        let tx1 = create_tx(&alice, genesis_config.hash());
+        let tx1 = create_tx(&alice, genesis_config.hash(), 0);
         let first_call = vec![tx1];
 
         let lock_result = bank.prepare_batch(&first_call, None);
@@ -7789,17 +8064,19 @@ pub(crate) mod tests {
             .fee_collection_results;
         assert_eq!(results_alice[0], Ok(()));
 
-        // try executing an evm transaction from other key, but while lock is active
+        // evm transactions lock evm state read-only, so they don't block each other
         let blockhash = bank.last_blockhash();
-        let tx = create_tx(&bob, blockhash);
-        assert_eq!(
-            bank.process_transaction(&tx),
-            Err(TransactionError::AccountInUse)
+        let tx = create_tx(&bob, blockhash, 1);
+        assert!(bank.process_transaction(&tx).is_ok());
+
+        // while swap of lamports needs write lock
+        let instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            bob.pubkey(),
+            1,
+            evm_state::H160::zero(),
         );
-        // the second time should fail as well
-        // this verifies that `unlock_accounts` doesn't unlock `AccountInUse` accounts
-        let blockhash = bank.last_blockhash();
-        let tx = create_tx(&bob, blockhash);
+        let message = Message::new(&instructions, Some(&bob.pubkey()));
+        let tx = Transaction::new(&[&bob], message, bank.last_blockhash());
         assert_eq!(
             bank.process_transaction(&tx),
             Err(TransactionError::AccountInUse)
@@ -7807,11 +8084,392 @@ pub(crate) mod tests {
 
         drop(lock_result);
 
-        let blockhash = bank.last_blockhash();
-        let tx = create_tx(&bob, blockhash);
         assert!(bank.process_transaction(&tx).is_ok());
     }
 
+    fn activate_feature_at_genesis(genesis_config: &mut GenesisConfig, feature_id: Pubkey) {
+        genesis_config.accounts.insert(
+            feature_id,
+            feature::create_account(
+                &Feature {
+                    activated_at: Some(0),
+                },
+                42,
+            ),
+        );
+    }
+
+    #[test]
+    fn test_evm_conflicting_batch_executed_again() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(20000 * 3);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_transaction_validation::id(),
+        );
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_readonly_state_account::id(),
+        );
+        let bank = Bank::new(&genesis_config);
+        let alice = Keypair::new();
+        let bob = Keypair::new();
+
+        assert!(bank.transfer(20000, &mint_keypair, &alice.pubkey()).is_ok());
+        assert!(bank.transfer(20000, &mint_keypair, &bob.pubkey()).is_ok());
+
+        // both transactions are sent by the same evm caller
+        let create_tx = |from_keypair: &Keypair, nonce: u64| {
+            let from_pubkey = from_keypair.pubkey();
+            let instruction = solana_evm_loader_program::send_raw_tx_readonly_state(
+                from_pubkey,
+                solana_evm_loader_program::processor::dummy_call(nonce),
+            );
+            let message = Message::new(&[instruction], Some(&from_pubkey));
+            Transaction::new(&[from_keypair], message, bank.last_blockhash())
+        };
+        let first = vec![create_tx(&alice, 0)];
+        let second = vec![create_tx(&bob, 1)];
+
+        let first_batch = bank.prepare_batch(&first, None);
+        let second_batch = bank.prepare_batch(&second, None);
+        assert!(second_batch.lock_results().iter().all(Result::is_ok));
+
+        // second transaction is executed before first one is committed, so its nonce is invalid
+        let second_executed =
+            bank.load_and_execute_batch(&second_batch, MAX_PROCESSING_AGE, false, false, false);
+        assert!(second_executed.executed[0].0.is_err());
+        let first_executed =
+            bank.load_and_execute_batch(&first_batch, MAX_PROCESSING_AGE, false, false, false);
+
+        let (first_results, ..) = bank.commit_executed_batch(&first_batch, first_executed);
+        assert_eq!(first_results.fee_collection_results[0], Ok(()));
+        let (second_results, ..) = bank.commit_executed_batch(&second_batch, second_executed);
+        assert_eq!(second_results.fee_collection_results[0], Ok(()));
+
+        let hashes = vec![
+            solana_evm_loader_program::processor::dummy_call(0).tx_id_hash(),
+            solana_evm_loader_program::processor::dummy_call(1).tx_id_hash(),
+        ];
+        assert_eq!(
+            bank.evm_state.read().unwrap().get_txs_in_block(bank.slot()),
+            Some(hashes)
+        );
+    }
+
+    #[test]
+    fn test_evm_gas_fee_collected() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(1_000_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(21, 0);
+        activate_feature_at_genesis(&mut genesis_config, feature_set::evm_gas_fees::id());
+        let bank = Bank::new(&genesis_config);
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_address = evm_state::FromKey::to_address(&evm_secret_key);
+
+        let instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            mint_keypair.pubkey(),
+            100_000,
+            evm_address,
+        );
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+        // plain transfer costs 21000 gas, which is 21 lamports at minimal gas price
+        let min_gas_price = bank.evm_min_gas_price();
+        assert_eq!(min_gas_price, 1_000_000.into());
+        let evm_tx = evm_state::UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: min_gas_price,
+            gas_limit: 300_000.into(),
+            action: evm_state::TransactionAction::Call(evm_state::H160::repeat_byte(0x22)),
+            value: 0.into(),
+            input: vec![],
+        }
+        .sign(&evm_secret_key, None);
+
+        let collector_fees = bank.collector_fees.load(Relaxed);
+        let evm_state_lamports = bank.get_balance(&solana_sdk::evm_state::id());
+        let instruction = solana_evm_loader_program::send_raw_tx(mint_keypair.pubkey(), evm_tx);
+        let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+        assert_eq!(bank.collector_fees.load(Relaxed), collector_fees + 21 + 21);
+
+        // evm fee is moved out of evm state account once, when bank is frozen
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports
+        );
+        bank.freeze();
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports - 21
+        );
+        assert_eq!(
+            bank.evm_state
+                .read()
+                .unwrap()
+                .get_account(evm_address)
+                .unwrap()
+                .balance,
+            evm_state::U256::from(100_000 - 21) * evm_state::U256::from(1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_evm_min_gas_price_from_genesis() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(1_000_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(21, 0);
+        genesis_config.evm_chain_id = Some(solana_sdk::genesis_config::EVM_DEVELOP_CHAIN_ID);
+        genesis_config.evm_min_gas_price = Some(2_000_000);
+        activate_feature_at_genesis(&mut genesis_config, feature_set::evm_gas_fees::id());
+        let bank = Bank::new(&genesis_config);
+        assert_eq!(bank.evm_min_gas_price(), 2_000_000.into());
+
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            mint_keypair.pubkey(),
+            100_000,
+            evm_state::FromKey::to_address(&evm_secret_key),
+        );
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+        let create_tx = |nonce: u64, gas_price: u64| {
+            let evm_tx = evm_state::UnsignedTransaction {
+                nonce: nonce.into(),
+                gas_price: gas_price.into(),
+                gas_limit: 30_000.into(),
+                action: evm_state::TransactionAction::Call(evm_state::H160::repeat_byte(0x22)),
+                value: 0.into(),
+                input: vec![],
+            }
+            .sign(&evm_secret_key, None);
+            let instruction = solana_evm_loader_program::send_raw_tx(mint_keypair.pubkey(), evm_tx);
+            let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+            Transaction::new(&[&mint_keypair], message, bank.last_blockhash())
+        };
+
+        // price derived from target lamports per signature is not enough
+        assert_eq!(
+            bank.process_transaction(&create_tx(0, 1_000_000)),
+            Err(TransactionError::InstructionError(
+                0,
+                solana_evm_loader_program::error::EvmError::GasPriceTooLow.into()
+            ))
+        );
+        assert_eq!(bank.process_transaction(&create_tx(0, 2_000_000)), Ok(()));
+    }
+
+    #[test]
+    fn test_evm_swap_ether_to_native_conserves_supply() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(1_000_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_native_precompiles::id(),
+        );
+        let bank = Bank::new(&genesis_config);
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_address = evm_state::FromKey::to_address(&evm_secret_key);
+        let receiver = solana_sdk::pubkey::new_rand();
+        let capitalization = bank.capitalization();
+
+        let instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            mint_keypair.pubkey(),
+            100_000,
+            evm_address,
+        );
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+        let evm_state_lamports = bank.get_balance(&solana_sdk::evm_state::id());
+
+        let evm_tx = solana_evm_loader_program::transfer_eth_to_native_tx(
+            0.into(),
+            0.into(),
+            40_000,
+            receiver,
+        )
+        .sign(&evm_secret_key, None);
+        let instruction = solana_evm_loader_program::transfer_eth_to_native(receiver, evm_tx);
+        let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+        assert_eq!(bank.get_balance(&receiver), 40_000);
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports - 40_000
+        );
+        assert_eq!(
+            bank.evm_state
+                .read()
+                .unwrap()
+                .get_account(evm_address)
+                .unwrap()
+                .balance,
+            solana_evm_loader_program::scope::evm::lamports_to_gwei(60_000)
+        );
+        assert_eq!(bank.capitalization(), capitalization);
+    }
+
+    #[test]
+    fn test_evm_failed_message_conserves_supply() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(1_000_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_native_precompiles::id(),
+        );
+        let bank = Bank::new(&genesis_config);
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_address = evm_state::FromKey::to_address(&evm_secret_key);
+        let receiver = solana_sdk::pubkey::new_rand();
+        let evm_state_lamports = bank.get_balance(&solana_sdk::evm_state::id());
+
+        // swap is followed by failing instruction
+        let mut instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            mint_keypair.pubkey(),
+            100_000,
+            evm_address,
+        );
+        instructions.push(system_instruction::transfer(
+            &mint_keypair.pubkey(),
+            &solana_sdk::pubkey::new_rand(),
+            10_000_000,
+        ));
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert!(bank.process_transaction(&tx).is_err());
+
+        let evm_balance = |bank: &Bank| {
+            bank.evm_state
+                .read()
+                .unwrap()
+                .get_account(evm_address)
+                .unwrap_or_default()
+                .balance
+        };
+        assert_eq!(evm_balance(&bank), evm_state::U256::zero());
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports
+        );
+
+        // gweis of reverted swap can't be withdrawn
+        let evm_tx = solana_evm_loader_program::transfer_eth_to_native_tx(
+            0.into(),
+            0.into(),
+            40_000,
+            receiver,
+        )
+        .sign(&evm_secret_key, None);
+        let instruction = solana_evm_loader_program::transfer_eth_to_native(receiver, evm_tx);
+        let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert!(bank.process_transaction(&tx).is_err());
+        assert_eq!(bank.get_balance(&receiver), 0);
+        assert_eq!(evm_balance(&bank), evm_state::U256::zero());
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports
+        );
+    }
+
+    #[test]
+    fn test_evm_native_transfer_precompile() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(1_000_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_native_precompiles::id(),
+        );
+        let bank = Bank::new(&genesis_config);
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_address = evm_state::FromKey::to_address(&evm_secret_key);
+        let receiver = solana_sdk::pubkey::new_rand();
+        let capitalization = bank.capitalization();
+
+        let instructions = solana_evm_loader_program::transfer_native_to_eth_ixs(
+            mint_keypair.pubkey(),
+            100_000,
+            evm_address,
+        );
+        let message = Message::new(&instructions, Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+        let evm_state_lamports = bank.get_balance(&solana_sdk::evm_state::id());
+
+        let evm_tx = evm_state::UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: 0.into(),
+            gas_limit: 300_000.into(),
+            action: evm_state::TransactionAction::Call(
+                evm_state::precompiles::NATIVE_TRANSFER_ADDR,
+            ),
+            value: solana_evm_loader_program::scope::evm::lamports_to_gwei(30_000),
+            input: receiver.to_bytes().to_vec(),
+        }
+        .sign(&evm_secret_key, None);
+        let instruction = solana_evm_loader_program::send_raw_tx_with_native_accounts(
+            mint_keypair.pubkey(),
+            evm_tx,
+            vec![AccountMeta::new(receiver, false)],
+        );
+        let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+        let tx = Transaction::new(&[&mint_keypair], message, bank.last_blockhash());
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+        assert_eq!(bank.get_balance(&receiver), 30_000);
+        assert_eq!(
+            bank.get_balance(&solana_sdk::evm_state::id()),
+            evm_state_lamports - 30_000
+        );
+        assert_eq!(bank.capitalization(), capitalization);
+    }
+
+    #[test]
+    fn test_evm_chain_id_from_genesis() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(20000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(0, 0);
+        genesis_config.cluster_type = ClusterType::Testnet;
+        genesis_config.evm_chain_id = Some(0x1234);
+        activate_feature_at_genesis(
+            &mut genesis_config,
+            feature_set::evm_transaction_validation::id(),
+        );
+        let bank = Bank::new(&genesis_config);
+        assert_eq!(bank.evm_chain_id, 0x1234);
+
+        let evm_secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let create_tx = |chain_id: u64| {
+            let evm_tx = evm_state::UnsignedTransaction {
+                nonce: 0.into(),
+                gas_price: 0.into(),
+                gas_limit: 300_000.into(),
+                action: evm_state::TransactionAction::Call(evm_state::H160::repeat_byte(0x22)),
+                value: 0.into(),
+                input: vec![],
+            }
+            .sign(&evm_secret_key, Some(chain_id));
+            let instruction = solana_evm_loader_program::send_raw_tx(mint_keypair.pubkey(), evm_tx);
+            let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+            Transaction::new(&[&mint_keypair], message, bank.last_blockhash())
+        };
+
+        assert_eq!(
+            bank.process_transaction(&create_tx(solana_sdk::genesis_config::EVM_TESTNET_CHAIN_ID)),
+            Err(TransactionError::InstructionError(
+                0,
+                solana_evm_loader_program::error::EvmError::WrongChainId.into()
+            ))
+        );
+        assert_eq!(bank.process_transaction(&create_tx(0x1234)), Ok(()));
+    }
+
     #[test]
     fn test_readonly_relaxed_locks() {
         let (genesis_config, _) = create_genesis_config(3);
@@ -7991,6 +8649,103 @@ pub(crate) mod tests {
         assert!(bank2.verify_bank_hash());
     }
 
+    #[test]
+    fn test_bank_hash_evm_state_root() {
+        let (mut genesis_config, _mint_keypair) = create_genesis_config(2_000);
+        let evm_address = evm_state::H160::repeat_byte(0x11);
+        let evm_account = evm_state::AccountState {
+            balance: 42.into(),
+            ..Default::default()
+        };
+
+        let bank0 = Bank::new(&genesis_config);
+        let bank1 = Bank::new(&genesis_config);
+        assert!(!bank0.evm_state_root_in_bank_hash_enabled());
+        bank0
+            .evm_state
+            .write()
+            .unwrap()
+            .set_account(evm_address, evm_account.clone());
+        bank0.freeze();
+        bank1.freeze();
+        assert_eq!(bank0.hash(), bank1.hash());
+
+        activate_all_features(&mut genesis_config);
+        let bank0 = Bank::new(&genesis_config);
+        let bank1 = Bank::new(&genesis_config);
+        assert!(bank0.evm_state_root_in_bank_hash_enabled());
+        bank0
+            .evm_state
+            .write()
+            .unwrap()
+            .set_account(evm_address, evm_account);
+        bank0.freeze();
+        bank1.freeze();
+        assert_ne!(bank0.hash(), bank1.hash());
+    }
+
+    #[test]
+    fn test_evm_features_follow_feature_activation() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(2_000);
+        let mut bank = Bank::new(&genesis_config);
+        assert_eq!(bank.evm_features(), evm_state::ExecutorFeatures::default());
+
+        let mut features = FeatureSet::default();
+        features
+            .active
+            .insert(feature_set::evm_transaction_validation::id(), 10);
+        features
+            .active
+            .insert(feature_set::evm_native_precompiles::id(), 20);
+        features
+            .active
+            .insert(feature_set::evm_readonly_state_account::id(), 30);
+        features
+            .active
+            .insert(feature_set::evm_rlp_big_transactions::id(), 40);
+        features.active.insert(feature_set::evm_gas_fees::id(), 50);
+        features
+            .active
+            .insert(feature_set::evm_storage_wipe::id(), 60);
+        bank.feature_set = Arc::new(features);
+
+        assert!(!bank.evm_features_at(9).validate_tx);
+        assert!(bank.evm_features_at(10).validate_tx);
+        assert!(!bank.evm_features_at(19).native_precompiles);
+        assert!(bank.evm_features_at(20).native_precompiles);
+        assert!(!bank.evm_features_at(29).readonly_state_account);
+        assert!(bank.evm_features_at(30).readonly_state_account);
+        assert!(!bank.evm_features_at(39).rlp_big_transactions);
+        assert!(bank.evm_features_at(40).rlp_big_transactions);
+        assert!(!bank.evm_features_at(49).charge_fee);
+        assert!(bank.evm_features_at(50).charge_fee);
+        assert!(!bank.evm_features_at(59).wipe_storage);
+        assert!(bank.evm_features_at(60).wipe_storage);
+    }
+
+    #[test]
+    fn test_evm_block_hash_registered_on_freeze() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(2_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        bank0.freeze();
+        let mut bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        goto_end_of_slot(&mut bank1);
+        bank1.freeze();
+        // Repeated freeze keeps frozen evm state untouched.
+        bank1.freeze();
+
+        let evm_state = bank1.evm_state.read().unwrap();
+        let block_hash = |bank: &Bank| evm_state::H256::from_slice(bank.last_blockhash().as_ref());
+        assert_eq!(
+            evm_state.get_slot_by_block_hash(block_hash(&bank0)),
+            Some(0)
+        );
+        assert_eq!(
+            evm_state.get_slot_by_block_hash(block_hash(&bank1)),
+            Some(1)
+        );
+    }
+
     #[test]
     fn test_bank_hash_internal_state_verify() {
         solana_logger::setup();
@@ -0,0 +1,133 @@
+// Service to prune evm state storage
+//
+// Versions of abandoned forks are dropped, and rooted history older than retention window
+// is squashed into single version.
+
+use crate::{bank::Bank, bank_forks::BankForks};
+use log::*;
+use solana_measure::measure::Measure;
+use solana_sdk::clock::Slot;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::Duration,
+};
+
+const INTERVAL_MS: u64 = 1000;
+// Prune once in this number of rooted slots.
+const PRUNE_INTERVAL_SLOTS: Slot = 100;
+
+/// Default number of rooted slots, which state is kept queryable (~2 days).
+pub const DEFAULT_EVM_STATE_RETENTION: Slot = 432_000;
+
+pub struct EvmStatePrunerService {
+    t_pruner: JoinHandle<()>,
+}
+
+impl EvmStatePrunerService {
+    pub fn new(
+        bank_forks: Arc<RwLock<BankForks>>,
+        retention: Slot,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        info!(
+            "EvmStatePrunerService active, retention: {} slots",
+            retention
+        );
+        let exit = exit.clone();
+        let mut last_pruned_root = None;
+        let t_pruner = Builder::new()
+            .name("solana-evm-state-pruner".to_string())
+            .spawn(move || loop {
+                if exit.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let bank = bank_forks.read().unwrap().root_bank().clone();
+                Self::prune(&bank, retention, &mut last_pruned_root);
+
+                sleep(Duration::from_millis(INTERVAL_MS));
+            })
+            .unwrap();
+        Self { t_pruner }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_pruner.join()
+    }
+
+    fn prune(root_bank: &Bank, retention: Slot, last_pruned_root: &mut Option<Slot>) {
+        let root = root_bank.slot();
+        if matches!(*last_pruned_root, Some(last) if root < last + PRUNE_INTERVAL_SLOTS) {
+            return;
+        }
+
+        // Storage is shared by all banks, so bank lock is not held while pruning,
+        // concurrent squash and lookups are serialized by lock of storage itself.
+        let storage = root_bank
+            .evm_state
+            .read()
+            .expect("evm state was poisoned")
+            .storage
+            .clone();
+
+        let mut prune_time = Measure::start("prune_time");
+        let stats = match evm_state::prune_storage(&storage, root, retention) {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!("Unable to prune evm state at root {}: {:?}", root, e);
+                return;
+            }
+        };
+        prune_time.stop();
+        *last_pruned_root = Some(root);
+
+        datapoint_info!(
+            "evm_state_pruner",
+            ("root", root, i64),
+            ("dead_versions", stats.dead_versions, i64),
+            ("squashed_versions", stats.squashed_versions, i64),
+            ("prune_time", prune_time.as_us(), i64),
+            ("sst_files_size", stats.sst_files_size, i64),
+            ("live_data_size", stats.live_data_size, i64),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::genesis_utils::create_genesis_config;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_evm_state_pruner_prunes_by_interval() {
+        let genesis = create_genesis_config(10);
+        let bank0 = Arc::new(Bank::new(&genesis.genesis_config));
+        bank0.freeze();
+        let mut bank = bank0;
+        for slot in 1..=3 {
+            bank = Arc::new(Bank::new_from_parent(&bank, &Pubkey::default(), slot));
+            bank.freeze();
+        }
+        let storage = bank.evm_state.read().unwrap().storage.clone();
+        assert!(storage.is_exists(0).unwrap());
+
+        let mut last_pruned_root = None;
+        EvmStatePrunerService::prune(&bank, 1, &mut last_pruned_root);
+        assert_eq!(last_pruned_root, Some(3));
+        assert!(!storage.is_exists(0).unwrap());
+        assert!(!storage.is_exists(1).unwrap());
+        assert_eq!(storage.track_of(3).collect::<Vec<_>>(), vec![3, 2]);
+
+        // Next root is too close to previous one.
+        let bank = Arc::new(Bank::new_from_parent(&bank, &Pubkey::default(), 4));
+        bank.freeze();
+        EvmStatePrunerService::prune(&bank, 1, &mut last_pruned_root);
+        assert_eq!(last_pruned_root, Some(3));
+        assert!(storage.is_exists(2).unwrap());
+    }
+}
@@ -14,6 +14,7 @@ pub mod bloom;
 pub mod builtins;
 pub mod commitment;
 pub mod epoch_stakes;
+pub mod evm_state_pruner_service;
 pub mod genesis_utils;
 pub mod hardened_unpack;
 pub mod inline_spl_token_v2_0;
@@ -211,6 +211,8 @@ pub struct ThisInvokeContext<'a> {
     executors: Rc<RefCell<Executors>>,
     instruction_recorder: Option<InstructionRecorder>,
     feature_set: Arc<FeatureSet>,
+    evm_executor: Option<&'a mut evm_state::Executor>,
+    return_data: (Pubkey, Vec<u8>),
 }
 impl<'a> ThisInvokeContext<'a> {
     #[allow(clippy::too_many_arguments)]
@@ -225,6 +227,7 @@ impl<'a> ThisInvokeContext<'a> {
         executors: Rc<RefCell<Executors>>,
         instruction_recorder: Option<InstructionRecorder>,
         feature_set: Arc<FeatureSet>,
+        evm_executor: Option<&'a mut evm_state::Executor>,
     ) -> Self {
         let mut program_ids = Vec::with_capacity(bpf_compute_budget.max_invoke_depth);
         program_ids.push(*program_id);
@@ -242,6 +245,8 @@ impl<'a> ThisInvokeContext<'a> {
             executors,
             instruction_recorder,
             feature_set,
+            evm_executor,
+            return_data: (Pubkey::default(), vec![]),
         }
     }
 }
@@ -330,6 +335,15 @@ impl<'a> InvokeContext for ThisInvokeContext<'a> {
             }
         })
     }
+    fn get_evm_executor(&mut self) -> Option<&mut evm_state::Executor> {
+        self.evm_executor.as_deref_mut()
+    }
+    fn set_return_data(&mut self, program_id: Pubkey, data: Vec<u8>) {
+        self.return_data = (program_id, data);
+    }
+    fn get_return_data(&self) -> (&Pubkey, &[u8]) {
+        (&self.return_data.0, &self.return_data.1)
+    }
 }
 pub struct ThisLogger {
     log_collector: Option<Rc<LogCollector>>,
@@ -477,7 +491,6 @@ impl MessageProcessor {
         keyed_accounts: &[KeyedAccount],
         instruction_data: &[u8],
         invoke_context: &mut dyn InvokeContext,
-        evm_executor: Option<&mut evm_state::Executor>,
     ) -> Result<(), InstructionError> {
         if let Some(root_account) = keyed_accounts.iter().next() {
             let root_id = root_account.unsigned_key();
@@ -487,7 +500,7 @@ impl MessageProcessor {
                         &solana_sdk::evm_loader::id(),
                         &keyed_accounts[1..], // skip evm program_id
                         instruction_data,
-                        evm_executor,
+                        invoke_context,
                     );
                 }
                 for (id, process_instruction) in &self.programs {
@@ -684,6 +697,7 @@ impl MessageProcessor {
 
             // Invoke callee
             invoke_context.push(program_id)?;
+            invoke_context.set_return_data(*program_id, vec![]);
 
             let mut message_processor = MessageProcessor::default();
             for (program_id, process_instruction) in invoke_context.get_programs().iter() {
@@ -695,7 +709,6 @@ impl MessageProcessor {
                 &keyed_accounts,
                 &instruction.data,
                 invoke_context,
-                None,
             );
             if result.is_ok() {
                 // Verify the called program has not misbehaved
@@ -874,6 +887,7 @@ impl MessageProcessor {
             executors,
             instruction_recorder,
             feature_set,
+            evm_executor,
         );
         let keyed_accounts =
             Self::create_keyed_accounts(message, instruction, executable_accounts, accounts);
@@ -882,7 +896,6 @@ impl MessageProcessor {
             &keyed_accounts,
             &instruction.data,
             &mut invoke_context,
-            evm_executor,
         )?;
         Self::verify(
             message,
@@ -913,28 +926,44 @@ impl MessageProcessor {
         bpf_compute_budget: BpfComputeBudget,
         mut evm_executor: Option<&mut evm_state::Executor>,
     ) -> Result<(), TransactionError> {
-        for (instruction_index, instruction) in message.instructions.iter().enumerate() {
-            let instruction_recorder = instruction_recorders
-                .as_ref()
-                .map(|recorders| recorders[instruction_index].clone());
-            self.execute_instruction(
-                message,
-                instruction,
-                &loaders[instruction_index],
-                accounts,
-                account_deps,
-                rent_collector,
-                log_collector.clone(),
-                executors.clone(),
-                instruction_recorder,
-                instruction_index,
-                feature_set.clone(),
-                bpf_compute_budget,
-                evm_executor.as_deref_mut(),
-            )
-            .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
+        // Evm changes are reverted with accounts of failed message,
+        // whether evm was invoked directly or by another program.
+        if message.account_keys.contains(&solana_sdk::evm_state::id()) {
+            if let Some(evm_executor) = evm_executor.as_deref_mut() {
+                evm_executor.set_checkpoint();
+            }
         }
-        Ok(())
+        let result = message.instructions.iter().enumerate().try_for_each(
+            |(instruction_index, instruction)| {
+                let instruction_recorder = instruction_recorders
+                    .as_ref()
+                    .map(|recorders| recorders[instruction_index].clone());
+                self.execute_instruction(
+                    message,
+                    instruction,
+                    &loaders[instruction_index],
+                    accounts,
+                    account_deps,
+                    rent_collector,
+                    log_collector.clone(),
+                    executors.clone(),
+                    instruction_recorder,
+                    instruction_index,
+                    feature_set.clone(),
+                    bpf_compute_budget,
+                    evm_executor.as_deref_mut(),
+                )
+                .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))
+            },
+        );
+        if let Some(evm_executor) = evm_executor {
+            if result.is_err() {
+                evm_executor.rollback_to_checkpoint();
+            } else {
+                evm_executor.discard_checkpoint();
+            }
+        }
+        result
     }
 }
 
@@ -942,6 +971,7 @@ impl MessageProcessor {
 mod tests {
     use super::*;
     use solana_sdk::{
+        genesis_config::EVM_DEVELOP_CHAIN_ID,
         instruction::{AccountMeta, Instruction, InstructionError},
         message::Message,
         native_loader::create_loadable_account,
@@ -985,6 +1015,7 @@ mod tests {
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
 
         // Check call depth increases and has a limit
@@ -1862,6 +1893,7 @@ mod tests {
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            None,
         );
         let metas = vec![
             AccountMeta::new(owned_key, false),
@@ -1915,6 +1947,276 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_process_cross_program_evm() {
+        use evm_state::precompiles::NATIVE_CLOCK_ADDR;
+        use solana_evm_loader_program::{error::EvmError, instructions::EvmInstruction};
+
+        fn mock_caller_process_instruction(
+            _program_id: &Pubkey,
+            keyed_accounts: &[KeyedAccount],
+            data: &[u8],
+            invoke_context: &mut dyn InvokeContext,
+        ) -> Result<(), InstructionError> {
+            let instruction = Instruction {
+                program_id: solana_sdk::evm_loader::id(),
+                accounts: vec![AccountMeta::new_readonly(
+                    solana_sdk::evm_state::id(),
+                    false,
+                )],
+                data: data.to_vec(),
+            };
+            MessageProcessor::native_invoke(
+                invoke_context,
+                instruction,
+                &[&keyed_accounts[1], &keyed_accounts[2]],
+                &[],
+            )?;
+            let (_, return_data) = invoke_context.get_return_data();
+            let remaining = invoke_context.get_compute_meter().borrow().get_remaining();
+            let mut result = keyed_accounts[0].try_account_ref_mut()?;
+            result.data[..64].copy_from_slice(return_data);
+            result.data[64..].copy_from_slice(&remaining.to_le_bytes());
+            Ok(())
+        }
+
+        let caller_program_id = solana_sdk::pubkey::new_rand();
+        let result_key = solana_sdk::pubkey::new_rand();
+        let mut message_processor = MessageProcessor::default();
+        message_processor.add_program(caller_program_id, mock_caller_process_instruction);
+
+        let secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_tx = evm_state::UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: 0.into(),
+            gas_limit: 30_000.into(),
+            action: evm_state::TransactionAction::Call(NATIVE_CLOCK_ADDR),
+            value: 0.into(),
+            input: vec![],
+        }
+        .sign(&secret_key, None);
+        let message = Message::new(
+            &[Instruction::new(
+                caller_program_id,
+                &EvmInstruction::EvmTransaction { evm_tx },
+                vec![
+                    AccountMeta::new(result_key, false),
+                    AccountMeta::new_readonly(solana_sdk::evm_state::id(), false),
+                    AccountMeta::new_readonly(solana_sdk::evm_loader::id(), false),
+                ],
+            )],
+            None,
+        );
+        let accounts: Vec<_> = message
+            .account_keys
+            .iter()
+            .map(|key| {
+                if *key == result_key {
+                    Account::new_ref(1, 72, &caller_program_id)
+                } else if *key == solana_sdk::evm_state::id() {
+                    Rc::new(RefCell::new(
+                        solana_evm_loader_program::create_state_account(),
+                    ))
+                } else {
+                    Rc::new(RefCell::new(create_loadable_account("mock_program", 1)))
+                }
+            })
+            .collect();
+        let loaders = vec![vec![(
+            caller_program_id,
+            RefCell::new(create_loadable_account("mock_caller", 1)),
+        )]];
+
+        let mut evm_executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            evm_state::BLOCK_GAS_LIMIT,
+            EVM_DEVELOP_CHAIN_ID,
+            5,
+        );
+        evm_executor.set_epoch(7);
+        let process_message =
+            |message: &Message,
+             feature_set: FeatureSet,
+             bpf_compute_budget: BpfComputeBudget,
+             evm_executor: &mut evm_state::Executor| {
+                message_processor.process_message(
+                    message,
+                    &loaders,
+                    &accounts,
+                    &[],
+                    &RentCollector::default(),
+                    None,
+                    Rc::new(RefCell::new(Executors::default())),
+                    None,
+                    Arc::new(feature_set),
+                    bpf_compute_budget,
+                    Some(evm_executor),
+                )
+            };
+
+        // Cross-program invocation is feature gated
+        assert_eq!(
+            process_message(
+                &message,
+                FeatureSet::default(),
+                BpfComputeBudget::new(&FeatureSet::all_enabled()),
+                &mut evm_executor,
+            ),
+            Err(TransactionError::InstructionError(
+                0,
+                EvmError::CrossExecutionNotEnabled.into()
+            ))
+        );
+
+        // Gas limit of transaction exceeds compute budget of the caller
+        let bpf_compute_budget = BpfComputeBudget {
+            max_units: 1_000,
+            ..BpfComputeBudget::new(&FeatureSet::all_enabled())
+        };
+        assert_eq!(
+            process_message(
+                &message,
+                FeatureSet::all_enabled(),
+                bpf_compute_budget,
+                &mut evm_executor,
+            ),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ComputationalBudgetExceeded
+            ))
+        );
+        assert_eq!(evm_executor.used_gas(), 0);
+
+        // Output of evm transaction is returned to the caller, and gas is billed in compute units
+        let bpf_compute_budget = BpfComputeBudget::new(&FeatureSet::all_enabled());
+        assert_eq!(
+            process_message(
+                &message,
+                FeatureSet::all_enabled(),
+                bpf_compute_budget,
+                &mut evm_executor,
+            ),
+            Ok(())
+        );
+        let used_units = (evm_executor.used_gas() + bpf_compute_budget.evm_gas_per_unit - 1)
+            / bpf_compute_budget.evm_gas_per_unit;
+        let mut expected = vec![0; 64];
+        expected[31] = 5;
+        expected[63] = 7;
+        expected.extend_from_slice(&(bpf_compute_budget.max_units - used_units).to_le_bytes());
+        let result_index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == result_key)
+            .unwrap();
+        assert_eq!(accounts[result_index].borrow().data, expected);
+    }
+
+    #[test]
+    fn test_process_cross_program_evm_reverted_with_caller() {
+        use solana_evm_loader_program::instructions::EvmInstruction;
+
+        fn mock_failing_caller_process_instruction(
+            _program_id: &Pubkey,
+            keyed_accounts: &[KeyedAccount],
+            data: &[u8],
+            invoke_context: &mut dyn InvokeContext,
+        ) -> Result<(), InstructionError> {
+            let instruction = Instruction {
+                program_id: solana_sdk::evm_loader::id(),
+                accounts: vec![AccountMeta::new_readonly(
+                    solana_sdk::evm_state::id(),
+                    false,
+                )],
+                data: data.to_vec(),
+            };
+            MessageProcessor::native_invoke(
+                invoke_context,
+                instruction,
+                &[&keyed_accounts[0], &keyed_accounts[1]],
+                &[],
+            )?;
+            Err(InstructionError::Custom(0))
+        }
+
+        let caller_program_id = solana_sdk::pubkey::new_rand();
+        let mut message_processor = MessageProcessor::default();
+        message_processor.add_program(caller_program_id, mock_failing_caller_process_instruction);
+
+        let secret_key = evm_state::SecretKey::from_slice(&[1; 32]).unwrap();
+        let evm_tx = evm_state::UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: 0.into(),
+            gas_limit: 30_000.into(),
+            action: evm_state::TransactionAction::Call(evm_state::H160::repeat_byte(0x11)),
+            value: 0.into(),
+            input: vec![],
+        }
+        .sign(&secret_key, None);
+        let tx_hash = evm_tx.tx_id_hash();
+        let message = Message::new(
+            &[Instruction::new(
+                caller_program_id,
+                &EvmInstruction::EvmTransaction { evm_tx },
+                vec![
+                    AccountMeta::new_readonly(solana_sdk::evm_state::id(), false),
+                    AccountMeta::new_readonly(solana_sdk::evm_loader::id(), false),
+                ],
+            )],
+            None,
+        );
+        let accounts: Vec<_> = message
+            .account_keys
+            .iter()
+            .map(|key| {
+                if *key == solana_sdk::evm_state::id() {
+                    Rc::new(RefCell::new(
+                        solana_evm_loader_program::create_state_account(),
+                    ))
+                } else {
+                    Rc::new(RefCell::new(create_loadable_account("mock_program", 1)))
+                }
+            })
+            .collect();
+        let loaders = vec![vec![(
+            caller_program_id,
+            RefCell::new(create_loadable_account("mock_caller", 1)),
+        )]];
+
+        let mut evm_executor = evm_state::Executor::with_config(
+            evm_state::EvmState::default(),
+            evm_state::Config::istanbul(),
+            evm_state::BLOCK_GAS_LIMIT,
+            EVM_DEVELOP_CHAIN_ID,
+            0,
+        );
+        let result = message_processor.process_message(
+            &message,
+            &loaders,
+            &accounts,
+            &[],
+            &RentCollector::default(),
+            None,
+            Rc::new(RefCell::new(Executors::default())),
+            None,
+            Arc::new(FeatureSet::all_enabled()),
+            BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            Some(&mut evm_executor),
+        );
+        assert_eq!(
+            result,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(0)
+            ))
+        );
+
+        // Evm transaction was executed, but reverted with the rest of the message
+        assert_eq!(evm_executor.used_gas(), 0);
+        assert!(evm_executor.get_tx_receipt_by_hash(tx_hash).is_none());
+    }
+
     #[test]
     fn test_debug() {
         let mut message_processor = MessageProcessor::default();
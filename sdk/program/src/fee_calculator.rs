@@ -84,6 +84,12 @@ pub struct FeeRateGovernor {
 
     // What portion of collected fees are to be destroyed, as a fraction of std::u8::MAX
     pub burn_percent: u8,
+
+    // Minimal gas price of EVM transactions in wei, if not set it's derived from
+    // target_lamports_per_signature.  Genesis config stores it separately, to keep layout of
+    // existing genesis configs and snapshots
+    #[serde(skip)]
+    pub evm_min_gas_price: Option<u64>,
 }
 
 pub const DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE: u64 = 10_000;
@@ -102,6 +108,7 @@ impl Default for FeeRateGovernor {
             min_lamports_per_signature: 0,
             max_lamports_per_signature: 0,
             burn_percent: DEFAULT_BURN_PERCENT,
+            evm_min_gas_price: None,
         }
     }
 }
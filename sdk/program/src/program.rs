@@ -1,4 +1,6 @@
-use crate::{account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction};
+use crate::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
+};
 
 /// Invoke a cross-program instruction
 ///
@@ -54,8 +56,31 @@ pub fn invoke_signed(
     crate::program_stubs::sol_invoke_signed(instruction, account_infos, signers_seeds)
 }
 
+/// Get data returned by the last program invoked by cross-program invocation
+///
+/// Returns id of the program that set the data, or `None` if no data was returned.
+pub fn get_return_data() -> Option<(Pubkey, Vec<u8>)> {
+    #[cfg(target_arch = "bpf")]
+    {
+        let mut program_id = Pubkey::default();
+        let size =
+            unsafe { sol_get_return_data(std::ptr::null_mut(), 0, &mut program_id) } as usize;
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0; size];
+        unsafe { sol_get_return_data(data.as_mut_ptr(), size as u64, &mut program_id) };
+        Some((program_id, data))
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    crate::program_stubs::sol_get_return_data()
+}
+
 #[cfg(target_arch = "bpf")]
 extern "C" {
+    fn sol_get_return_data(data: *mut u8, length: u64, program_id: *mut Pubkey) -> u64;
+
     fn sol_invoke_signed_rust(
         instruction_addr: *const u8,
         account_infos_addr: *const u8,
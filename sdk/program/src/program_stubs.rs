@@ -2,7 +2,9 @@
 
 #![cfg(not(target_arch = "bpf"))]
 
-use crate::{account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction};
+use crate::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
+};
 use std::sync::{Arc, RwLock};
 
 lazy_static::lazy_static! {
@@ -31,6 +33,9 @@ pub trait SyscallStubs: Sync + Send {
         sol_log("SyscallStubs: sol_invoke_signed() not available");
         Ok(())
     }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        None
+    }
 }
 
 struct DefaultSyscallStubs {}
@@ -61,3 +66,7 @@ pub(crate) fn sol_invoke_signed(
         .unwrap()
         .sol_invoke_signed(instruction, account_infos, signers_seeds)
 }
+
+pub(crate) fn sol_get_return_data() -> Option<(Pubkey, Vec<u8>)> {
+    SYSCALL_STUBS.read().unwrap().sol_get_return_data()
+}
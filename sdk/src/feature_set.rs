@@ -134,6 +134,38 @@ pub mod abort_on_all_cpi_failures {
     solana_sdk::declare_id!("ED5D5a2hQaECHaMmKpnU48GdsfafdCjkb3pgAw5RKbb2");
 }
 
+pub mod evm_state_root_in_bank_hash {
+    solana_sdk::declare_id!("A5oRgvM3gCN87izjZ5PxuvcBETxX5kDwoVHNJqCmbB8q");
+}
+
+pub mod evm_cross_execution {
+    solana_sdk::declare_id!("8Tvejjc7keKTbMERpe9dMEVcCASYjkXDuZJSYNGJo8dP");
+}
+
+pub mod evm_transaction_validation {
+    solana_sdk::declare_id!("BfWnMEmvBnNLtYGRtmiTdCLMcquuhpUYj3XQThACHj4D");
+}
+
+pub mod evm_native_precompiles {
+    solana_sdk::declare_id!("4tVXCLLanVQPAokEFXTbP3zyxM3sPfqeYBJKoQLxiCWt");
+}
+
+pub mod evm_readonly_state_account {
+    solana_sdk::declare_id!("J4dwSonmmSrB5pzohYJaQAoy6hbzLLBk9BePvDEA57o6");
+}
+
+pub mod evm_rlp_big_transactions {
+    solana_sdk::declare_id!("35865CLtTviKhWyJNfGzThGoHjYku2Caz7tkYm1WHrcW");
+}
+
+pub mod evm_gas_fees {
+    solana_sdk::declare_id!("FRYqNmYfMDS6QppyRa1vw5c5r4fYPzHQM4gsUiHrqqFb");
+}
+
+pub mod evm_storage_wipe {
+    solana_sdk::declare_id!("GnHi92aYRiLXx3zwBPsYct69XYthnidkeim18QeDigV");
+}
+
 lazy_static! {
     /// Map of feature identifiers to user-visible description
     pub static ref FEATURE_NAMES: HashMap<Pubkey, &'static str> = [
@@ -169,6 +201,14 @@ lazy_static! {
         (limit_cpi_loader_invoke::id(), "Loader not authorized via CPI"),
         (use_loaded_program_accounts::id(), "Use loaded program accounts"),
         (abort_on_all_cpi_failures::id(), "Abort on all CPI failures"),
+        (evm_state_root_in_bank_hash::id(), "Include EVM state root in bank hash"),
+        (evm_cross_execution::id(), "EVM cross-program invocation"),
+        (evm_transaction_validation::id(), "EVM transaction nonce, signature and chain id checks"),
+        (evm_native_precompiles::id(), "EVM precompiles of native chain"),
+        (evm_readonly_state_account::id(), "EVM transactions with read-only EVM state account"),
+        (evm_rlp_big_transactions::id(), "EVM big transactions in RLP, signed by owner, with reclaim and expiration"),
+        (evm_gas_fees::id(), "EVM gas fees and minimal gas price"),
+        (evm_storage_wipe::id(), "EVM storage wiping on selfdestruct and recreation"),
         /*************** ADD NEW FEATURES HERE ***************/
     ]
     .iter()
@@ -5,6 +5,7 @@
 use crate::{
     account::Account,
     clock::{UnixTimestamp, DEFAULT_TICKS_PER_SLOT},
+    deserialize_utils::default_on_eof,
     epoch_schedule::EpochSchedule,
     fee_calculator::FeeRateGovernor,
     hash::{hash, Hash},
@@ -34,6 +35,11 @@ use std::{
 // deprecated default that is no longer used
 pub const UNUSED_DEFAULT: u64 = 1024;
 
+// EVM chain ids of public clusters
+pub const EVM_MAINNET_CHAIN_ID: u64 = 105;
+pub const EVM_TESTNET_CHAIN_ID: u64 = 111;
+pub const EVM_DEVELOP_CHAIN_ID: u64 = 0x77;
+
 // The order can't align with release lifecycle only to remain ABI-compatible...
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, AbiEnumVisitor, AbiExample)]
 pub enum ClusterType {
@@ -45,6 +51,15 @@ pub enum ClusterType {
 
 impl ClusterType {
     pub const STRINGS: [&'static str; 4] = ["development", "devnet", "testnet", "mainnet-beta"];
+
+    /// Chain id of evm transactions, for genesis configs that don't specify one.
+    pub fn default_evm_chain_id(&self) -> u64 {
+        match self {
+            ClusterType::MainnetBeta => EVM_MAINNET_CHAIN_ID,
+            ClusterType::Testnet => EVM_TESTNET_CHAIN_ID,
+            ClusterType::Devnet | ClusterType::Development => EVM_DEVELOP_CHAIN_ID,
+        }
+    }
 }
 
 impl FromStr for ClusterType {
@@ -89,6 +104,20 @@ pub struct GenesisConfig {
     pub epoch_schedule: EpochSchedule,
     /// network runlevel
     pub cluster_type: ClusterType,
+    /// chain id of evm transactions, not serialized if unset, to keep layout and hash of
+    /// genesis configs created before it was added
+    #[serde(
+        deserialize_with = "default_on_eof",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub evm_chain_id: Option<u64>,
+    /// minimal gas price of evm transactions in wei, that is set to `fee_rate_governor` by bank;
+    /// it's serialized after `evm_chain_id`, so it can be stored only together with it
+    #[serde(
+        deserialize_with = "default_on_eof",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub evm_min_gas_price: Option<u64>,
 }
 
 // useful for basic tests
@@ -125,6 +154,8 @@ impl Default for GenesisConfig {
             rent: Rent::default(),
             epoch_schedule: EpochSchedule::default(),
             cluster_type: ClusterType::Development,
+            evm_chain_id: None,
+            evm_min_gas_price: None,
         }
     }
 }
@@ -149,6 +180,11 @@ impl GenesisConfig {
         hash(&serialized)
     }
 
+    pub fn evm_chain_id(&self) -> u64 {
+        self.evm_chain_id
+            .unwrap_or_else(|| self.cluster_type.default_evm_chain_id())
+    }
+
     pub fn disable_cap_altering_features_for_preciseness(&mut self) {
         self.accounts
             .remove(&crate::feature_set::simple_capitalization::id());
@@ -237,6 +273,7 @@ impl fmt::Display for GenesisConfig {
             "\
              Creation time: {}\n\
              Cluster type: {:?}\n\
+             EVM chain id: {}\n\
              Genesis hash: {}\n\
              Shred version: {}\n\
              Ticks per slot: {:?}\n\
@@ -252,6 +289,7 @@ impl fmt::Display for GenesisConfig {
              ",
             Utc.timestamp(self.creation_time, 0).to_rfc3339(),
             self.cluster_type,
+            self.evm_chain_id(),
             self.hash(),
             compute_shred_version(&self.hash(), None),
             self.ticks_per_slot,
@@ -336,4 +374,43 @@ mod tests {
         assert_eq!(config.hash(), loaded_config.hash());
         let _ignored = std::fs::remove_file(&path);
     }
+
+    #[test]
+    fn test_evm_chain_id() {
+        let mut config = GenesisConfig::default();
+        assert_eq!(config.evm_chain_id(), EVM_DEVELOP_CHAIN_ID);
+        config.cluster_type = ClusterType::MainnetBeta;
+        assert_eq!(config.evm_chain_id(), EVM_MAINNET_CHAIN_ID);
+        config.cluster_type = ClusterType::Testnet;
+        assert_eq!(config.evm_chain_id(), EVM_TESTNET_CHAIN_ID);
+        config.evm_chain_id = Some(0x1234);
+        assert_eq!(config.evm_chain_id(), 0x1234);
+    }
+
+    #[test]
+    fn test_genesis_config_evm_fields_compatibility() {
+        let config = GenesisConfig::default();
+        let serialized = serialize(&config).unwrap();
+        let loaded_config: GenesisConfig = deserialize(&serialized).unwrap();
+        assert_eq!(loaded_config.evm_chain_id, None);
+        assert_eq!(config.hash(), loaded_config.hash());
+
+        let config = GenesisConfig {
+            evm_chain_id: Some(0x1234),
+            ..config
+        };
+        let serialized_with_chain_id = serialize(&config).unwrap();
+        assert_eq!(serialized_with_chain_id[..serialized.len()], serialized[..]);
+        let loaded_config: GenesisConfig = deserialize(&serialized_with_chain_id).unwrap();
+        assert_eq!(loaded_config.evm_chain_id, Some(0x1234));
+        assert_eq!(loaded_config.evm_min_gas_price, None);
+
+        let config = GenesisConfig {
+            evm_min_gas_price: Some(2_000_000_000),
+            ..config
+        };
+        let loaded_config: GenesisConfig = deserialize(&serialize(&config).unwrap()).unwrap();
+        assert_eq!(loaded_config.evm_chain_id, Some(0x1234));
+        assert_eq!(loaded_config.evm_min_gas_price, Some(2_000_000_000));
+    }
 }
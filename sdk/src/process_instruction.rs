@@ -1,8 +1,8 @@
 use solana_sdk::{
     account::Account,
     feature_set::{
-        bpf_compute_budget_balancing, max_cpi_instruction_size_ipv6_mtu, max_invoke_depth_4,
-        max_program_call_depth_64, pubkey_log_syscall_enabled, FeatureSet,
+        bpf_compute_budget_balancing, evm_cross_execution, max_cpi_instruction_size_ipv6_mtu,
+        max_invoke_depth_4, max_program_call_depth_64, pubkey_log_syscall_enabled, FeatureSet,
     },
     instruction::{CompiledInstruction, Instruction, InstructionError},
     keyed_account::KeyedAccount,
@@ -63,6 +63,12 @@ pub trait InvokeContext {
     fn is_feature_active(&self, feature_id: &Pubkey) -> bool;
     /// Get an account from a pre-account
     fn get_account(&self, pubkey: &Pubkey) -> Option<RefCell<Account>>;
+    /// Get evm executor of the current transaction, if evm is available
+    fn get_evm_executor(&mut self) -> Option<&mut evm_state::Executor>;
+    /// Set data returned by the currently executing program
+    fn set_return_data(&mut self, program_id: Pubkey, data: Vec<u8>);
+    /// Get data returned by the last invoked program
+    fn get_return_data(&self) -> (&Pubkey, &[u8]);
 }
 
 #[derive(Clone, Copy, Debug, AbiExample)]
@@ -93,6 +99,10 @@ pub struct BpfComputeBudget {
     pub log_pubkey_units: u64,
     /// Maximum cross-program invocation instruction size
     pub max_cpi_instruction_size: usize,
+    /// Number of compute units consumed by a get_return_data call
+    pub return_data_units: u64,
+    /// Amount of evm gas paid by one compute unit, when evm is invoked by another program
+    pub evm_gas_per_unit: u64,
 }
 impl Default for BpfComputeBudget {
     fn default() -> Self {
@@ -116,6 +126,8 @@ impl BpfComputeBudget {
             stack_frame_size: 4_096,
             log_pubkey_units: 0,
             max_cpi_instruction_size: std::usize::MAX,
+            return_data_units: 0,
+            evm_gas_per_unit: 1,
         };
 
         if feature_set.is_active(&bpf_compute_budget_balancing::id()) {
@@ -153,6 +165,13 @@ impl BpfComputeBudget {
                 ..bpf_compute_budget
             };
         }
+        if feature_set.is_active(&evm_cross_execution::id()) {
+            bpf_compute_budget = BpfComputeBudget {
+                return_data_units: 100,
+                evm_gas_per_unit: 10,
+                ..bpf_compute_budget
+            };
+        }
         bpf_compute_budget
     }
 }
@@ -295,6 +314,7 @@ pub struct MockInvokeContext {
     pub compute_meter: MockComputeMeter,
     pub programs: Vec<(Pubkey, ProcessInstructionWithContext)>,
     pub invoke_depth: usize,
+    pub return_data: (Pubkey, Vec<u8>),
 }
 impl Default for MockInvokeContext {
     fn default() -> Self {
@@ -307,6 +327,7 @@ impl Default for MockInvokeContext {
             },
             programs: vec![],
             invoke_depth: 0,
+            return_data: (Pubkey::default(), vec![]),
         }
     }
 }
@@ -355,4 +376,13 @@ impl InvokeContext for MockInvokeContext {
     fn get_account(&self, _pubkey: &Pubkey) -> Option<RefCell<Account>> {
         None
     }
+    fn get_evm_executor(&mut self) -> Option<&mut evm_state::Executor> {
+        None
+    }
+    fn set_return_data(&mut self, program_id: Pubkey, data: Vec<u8>) {
+        self.return_data = (program_id, data);
+    }
+    fn get_return_data(&self) -> (&Pubkey, &[u8]) {
+        (&self.return_data.0, &self.return_data.1)
+    }
 }
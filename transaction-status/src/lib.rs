@@ -5,6 +5,7 @@ extern crate serde_derive;
 
 pub mod parse_accounts;
 pub mod parse_bpf_loader;
+pub mod parse_evm;
 pub mod parse_instruction;
 pub mod parse_stake;
 pub mod parse_system;
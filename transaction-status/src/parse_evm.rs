@@ -0,0 +1,392 @@
+use crate::parse_instruction::{
+    check_num_accounts, ParsableProgram, ParseInstructionError, ParsedInstructionEnum,
+};
+use bincode::deserialize;
+use serde_json::{json, Value};
+use solana_evm_loader_program::{
+    instructions::{EvmBigTransaction, EvmInstruction},
+    scope::evm,
+};
+use solana_sdk::{instruction::CompiledInstruction, pubkey::Pubkey};
+
+pub fn parse_evm(
+    instruction: &CompiledInstruction,
+    account_keys: &[Pubkey],
+) -> Result<ParsedInstructionEnum, ParseInstructionError> {
+    let evm_instruction: EvmInstruction = deserialize(&instruction.data)
+        .map_err(|_| ParseInstructionError::InstructionNotParsable(ParsableProgram::EvmLoader))?;
+    match instruction.accounts.iter().max() {
+        Some(index) if (*index as usize) < account_keys.len() => {}
+        _ => {
+            // Runtime should prevent this from ever happening
+            return Err(ParseInstructionError::InstructionKeyMismatch(
+                ParsableProgram::EvmLoader,
+            ));
+        }
+    }
+    match evm_instruction {
+        EvmInstruction::EvmTransaction { evm_tx } => {
+            check_num_evm_accounts(&instruction.accounts, 1)?;
+            let native_accounts: Vec<_> = instruction.accounts[1..]
+                .iter()
+                .map(|index| account_keys[*index as usize].to_string())
+                .collect();
+            Ok(ParsedInstructionEnum {
+                instruction_type: "evmTransaction".to_string(),
+                info: json!({
+                    "evmStateAccount": account_keys[instruction.accounts[0] as usize].to_string(),
+                    "nativeAccounts": native_accounts,
+                    "transaction": parse_evm_transaction(&evm_tx),
+                }),
+            })
+        }
+        EvmInstruction::SwapNativeToEther {
+            lamports,
+            ether_address,
+        } => {
+            check_num_evm_accounts(&instruction.accounts, 2)?;
+            Ok(ParsedInstructionEnum {
+                instruction_type: "swapNativeToEther".to_string(),
+                info: json!({
+                    "evmStateAccount": account_keys[instruction.accounts[0] as usize].to_string(),
+                    "owner": account_keys[instruction.accounts[1] as usize].to_string(),
+                    "lamports": lamports,
+                    "etherAddress": format!("0x{:x}", ether_address),
+                }),
+            })
+        }
+        EvmInstruction::FreeOwnership {} => {
+            check_num_evm_accounts(&instruction.accounts, 2)?;
+            Ok(ParsedInstructionEnum {
+                instruction_type: "freeOwnership".to_string(),
+                info: json!({
+                    "evmStateAccount": account_keys[instruction.accounts[0] as usize].to_string(),
+                    "owner": account_keys[instruction.accounts[1] as usize].to_string(),
+                }),
+            })
+        }
+        EvmInstruction::EvmBigTransaction(big_tx) => {
+            check_num_evm_accounts(&instruction.accounts, 2)?;
+            let (instruction_type, mut info) = match big_tx {
+                EvmBigTransaction::EvmTransactionAllocate { seed, len, .. } => (
+                    "bigTransactionAllocate",
+                    json!({
+                        "seed": format!("0x{:x}", seed),
+                        "length": len,
+                    }),
+                ),
+                EvmBigTransaction::EvmTransactionWrite { seed, offset, data } => (
+                    "bigTransactionWrite",
+                    json!({
+                        "seed": format!("0x{:x}", seed),
+                        "offset": offset,
+                        "data": format!("0x{}", hex::encode(data)),
+                    }),
+                ),
+                EvmBigTransaction::EvmTransactionExecute { seed } => (
+                    "bigTransactionExecute",
+                    json!({ "seed": format!("0x{:x}", seed) }),
+                ),
+                EvmBigTransaction::EvmTransactionReclaim { seed } => (
+                    "bigTransactionReclaim",
+                    json!({ "seed": format!("0x{:x}", seed) }),
+                ),
+            };
+            let map = info.as_object_mut().unwrap();
+            map.insert(
+                "evmStateAccount".to_string(),
+                json!(account_keys[instruction.accounts[0] as usize].to_string()),
+            );
+            map.insert(
+                "owner".to_string(),
+                json!(account_keys[instruction.accounts[1] as usize].to_string()),
+            );
+            Ok(ParsedInstructionEnum {
+                instruction_type: instruction_type.to_string(),
+                info,
+            })
+        }
+        EvmInstruction::SwapEtherToNative { evm_tx } => {
+            check_num_evm_accounts(&instruction.accounts, 2)?;
+            Ok(ParsedInstructionEnum {
+                instruction_type: "swapEtherToNative".to_string(),
+                info: json!({
+                    "evmStateAccount": account_keys[instruction.accounts[0] as usize].to_string(),
+                    "receiver": account_keys[instruction.accounts[1] as usize].to_string(),
+                    "transaction": parse_evm_transaction(&evm_tx),
+                }),
+            })
+        }
+    }
+}
+
+/// Decode fields of embedded evm transaction, quantities are hex encoded as in ethereum rpc.
+fn parse_evm_transaction(evm_tx: &evm::Transaction) -> Value {
+    let mut value = json!({
+        "hash": format!("0x{:x}", evm_tx.tx_id_hash()),
+        "nonce": format!("0x{:x}", evm_tx.nonce),
+        "gasPrice": format!("0x{:x}", evm_tx.gas_price),
+        "gasLimit": format!("0x{:x}", evm_tx.gas_limit),
+        "value": format!("0x{:x}", evm_tx.value),
+    });
+    let map = value.as_object_mut().unwrap();
+    // Sender is recovered from signature, transaction with invalid signature has no sender.
+    if let Ok(from) = evm_tx.caller() {
+        map.insert("from".to_string(), json!(format!("0x{:x}", from)));
+    }
+    match evm_tx.action {
+        evm::TransactionAction::Call(to) => {
+            map.insert("to".to_string(), json!(format!("0x{:x}", to)));
+            if evm_tx.input.len() >= 4 {
+                map.insert(
+                    "inputSelector".to_string(),
+                    json!(format!("0x{}", hex::encode(&evm_tx.input[..4]))),
+                );
+            }
+        }
+        evm::TransactionAction::Create => {
+            map.insert("to".to_string(), Value::Null);
+            if let Ok(contract_address) = evm_tx.address() {
+                map.insert(
+                    "contractAddress".to_string(),
+                    json!(format!("0x{:x}", contract_address)),
+                );
+            }
+        }
+    }
+    map.insert("inputLength".to_string(), json!(evm_tx.input.len()));
+    value
+}
+
+fn check_num_evm_accounts(accounts: &[u8], num: usize) -> Result<(), ParseInstructionError> {
+    check_num_accounts(accounts, num, ParsableProgram::EvmLoader)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_sdk::{instruction::AccountMeta, message::Message};
+
+    fn sign_tx(tx: evm::UnsignedTransaction) -> (evm::Transaction, evm::Address) {
+        let secret_key = evm::SecretKey::from_slice(&[1; 32]).unwrap();
+        let from = evm::addr_from_public_key(&evm::PublicKey::from_secret_key(
+            &evm::SECP256K1,
+            &secret_key,
+        ));
+        (tx.sign(&secret_key, None), from)
+    }
+
+    #[test]
+    fn test_parse_evm_transaction() {
+        let mut keys: Vec<Pubkey> = vec![solana_sdk::evm_state::id()];
+        for _ in 0..2 {
+            keys.push(solana_sdk::pubkey::new_rand());
+        }
+
+        let to = evm::Address::repeat_byte(0x11);
+        let (evm_tx, from) = sign_tx(evm::UnsignedTransaction {
+            nonce: 3.into(),
+            gas_price: 1.into(),
+            gas_limit: 300_000.into(),
+            action: evm::TransactionAction::Call(to),
+            value: 16.into(),
+            input: vec![0x94, 0x2a, 0xe0, 0xa7, 0x01],
+        });
+        let instruction = solana_evm_loader_program::send_raw_tx_with_native_accounts(
+            keys[1],
+            evm_tx.clone(),
+            vec![AccountMeta::new_readonly(keys[2], false)],
+        );
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "evmTransaction".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "nativeAccounts": [keys[1].to_string(), keys[2].to_string()],
+                    "transaction": {
+                        "hash": format!("0x{:x}", evm_tx.tx_id_hash()),
+                        "from": format!("0x{:x}", from),
+                        "to": format!("0x{:x}", to),
+                        "value": "0x10",
+                        "nonce": "0x3",
+                        "gasPrice": "0x1",
+                        "gasLimit": "0x493e0",
+                        "inputSelector": "0x942ae0a7",
+                        "inputLength": 5,
+                    },
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+
+        let (evm_tx, from) = sign_tx(evm::UnsignedTransaction {
+            nonce: 0.into(),
+            gas_price: 1.into(),
+            gas_limit: 300_000.into(),
+            action: evm::TransactionAction::Create,
+            value: 0.into(),
+            input: vec![0x60, 0x80],
+        });
+        let instruction = solana_evm_loader_program::send_raw_tx(keys[1], evm_tx.clone());
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "evmTransaction".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "nativeAccounts": [keys[1].to_string()],
+                    "transaction": {
+                        "hash": format!("0x{:x}", evm_tx.tx_id_hash()),
+                        "from": format!("0x{:x}", from),
+                        "to": null,
+                        "contractAddress": format!("0x{:x}", evm_tx.address().unwrap()),
+                        "value": "0x0",
+                        "nonce": "0x0",
+                        "gasPrice": "0x1",
+                        "gasLimit": "0x493e0",
+                        "inputLength": 2,
+                    },
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_evm_swap_instructions() {
+        let keys: Vec<Pubkey> = vec![solana_sdk::evm_state::id(), solana_sdk::pubkey::new_rand()];
+
+        let ether_address = evm::Address::repeat_byte(0x22);
+        let instructions =
+            solana_evm_loader_program::transfer_native_to_eth_ixs(keys[1], 42, ether_address);
+        let message = Message::new(&instructions, None);
+        assert_eq!(
+            parse_evm(&message.instructions[1], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "swapNativeToEther".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                    "lamports": 42,
+                    "etherAddress": format!("0x{:x}", ether_address),
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[1], &message.account_keys[0..1]).is_err());
+        assert_eq!(
+            parse_evm(&message.instructions[2], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "freeOwnership".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[2], &message.account_keys[0..1]).is_err());
+
+        let (evm_tx, from) = sign_tx(solana_evm_loader_program::transfer_eth_to_native_tx(
+            1.into(),
+            0.into(),
+            1,
+            keys[1],
+        ));
+        let instruction =
+            solana_evm_loader_program::transfer_eth_to_native(keys[1], evm_tx.clone());
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "swapEtherToNative".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "receiver": keys[1].to_string(),
+                    "transaction": {
+                        "hash": format!("0x{:x}", evm_tx.tx_id_hash()),
+                        "from": format!("0x{:x}", from),
+                        "to": format!("0x{:x}", evm::ETH_TO_VLX_ADDR),
+                        "value": "0x3b9aca00",
+                        "nonce": "0x1",
+                        "gasPrice": "0x0",
+                        "gasLimit": "0x7530",
+                        "inputSelector": format!("0x{}", hex::encode(&keys[1].to_bytes()[..4])),
+                        "inputLength": 32,
+                    },
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_evm_big_transaction() {
+        let keys: Vec<Pubkey> = vec![solana_sdk::evm_state::id(), solana_sdk::pubkey::new_rand()];
+        let seed = evm::H256::repeat_byte(0x33);
+
+        let instruction = solana_evm_loader_program::big_tx_allocate(&keys[1], seed, 1024);
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "bigTransactionAllocate".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                    "seed": format!("0x{:x}", seed),
+                    "length": 1024,
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+
+        let instruction =
+            solana_evm_loader_program::big_tx_write(&keys[1], seed, 512, vec![0xde, 0xad]);
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "bigTransactionWrite".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                    "seed": format!("0x{:x}", seed),
+                    "offset": 512,
+                    "data": "0xdead",
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+
+        let instruction = solana_evm_loader_program::big_tx_execute(&keys[1], seed);
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "bigTransactionExecute".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                    "seed": format!("0x{:x}", seed),
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+
+        let instruction = solana_evm_loader_program::big_tx_reclaim(&keys[1], seed);
+        let message = Message::new(&[instruction], None);
+        assert_eq!(
+            parse_evm(&message.instructions[0], &message.account_keys).unwrap(),
+            ParsedInstructionEnum {
+                instruction_type: "bigTransactionReclaim".to_string(),
+                info: json!({
+                    "evmStateAccount": keys[0].to_string(),
+                    "owner": keys[1].to_string(),
+                    "seed": format!("0x{:x}", seed),
+                }),
+            }
+        );
+        assert!(parse_evm(&message.instructions[0], &message.account_keys[0..1]).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    parse_bpf_loader::parse_bpf_loader, parse_stake::parse_stake, parse_system::parse_system,
-    parse_token::parse_token, parse_vote::parse_vote,
+    parse_bpf_loader::parse_bpf_loader, parse_evm::parse_evm, parse_stake::parse_stake,
+    parse_system::parse_system, parse_token::parse_token, parse_vote::parse_vote,
 };
 use inflector::Inflector;
 use serde_json::Value;
@@ -14,6 +14,7 @@ use thiserror::Error;
 
 lazy_static! {
     static ref BPF_LOADER_PROGRAM_ID: Pubkey = solana_sdk::bpf_loader::id();
+    static ref EVM_LOADER_PROGRAM_ID: Pubkey = solana_sdk::evm_loader::id();
     static ref MEMO_PROGRAM_ID: Pubkey =
         Pubkey::from_str(&spl_memo_v1_0::id().to_string()).unwrap();
     static ref STAKE_PROGRAM_ID: Pubkey = solana_stake_program::id();
@@ -28,6 +29,7 @@ lazy_static! {
         m.insert(*STAKE_PROGRAM_ID, ParsableProgram::Stake);
         m.insert(*SYSTEM_PROGRAM_ID, ParsableProgram::System);
         m.insert(*VOTE_PROGRAM_ID, ParsableProgram::Vote);
+        m.insert(*EVM_LOADER_PROGRAM_ID, ParsableProgram::EvmLoader);
         m
     };
 }
@@ -73,6 +75,7 @@ pub enum ParsableProgram {
     Stake,
     System,
     Vote,
+    EvmLoader,
 }
 
 pub fn parse(
@@ -92,6 +95,7 @@ pub fn parse(
         ParsableProgram::Stake => serde_json::to_value(parse_stake(instruction, account_keys)?)?,
         ParsableProgram::System => serde_json::to_value(parse_system(instruction, account_keys)?)?,
         ParsableProgram::Vote => serde_json::to_value(parse_vote(instruction, account_keys)?)?,
+        ParsableProgram::EvmLoader => serde_json::to_value(parse_evm(instruction, account_keys)?)?,
     };
     Ok(ParsedInstruction {
         program: format!("{:?}", program_name).to_kebab_case(),
@@ -7,11 +7,15 @@ use rand::{seq::SliceRandom, thread_rng, Rng};
 use solana_clap_utils::{
     input_parsers::{keypair_of, keypairs_of, pubkey_of, value_of},
     input_validators::{
-        is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot,
+        is_keypair, is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair,
+        is_slot,
     },
     keypair::SKIP_SEED_PHRASE_VALIDATION_ARG,
 };
-use solana_client::{rpc_client::RpcClient, rpc_request::MAX_MULTIPLE_ACCOUNTS};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_request::{MAX_EVM_LOGS_BLOCK_RANGE, MAX_MULTIPLE_ACCOUNTS},
+};
 use solana_core::ledger_cleanup_service::{
     DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS,
 };
@@ -30,6 +34,7 @@ use solana_perf::recycler::enable_recycler_warming;
 use solana_runtime::{
     accounts_index::AccountIndex,
     bank_forks::{ArchiveFormat, SnapshotConfig, SnapshotVersion},
+    evm_state_pruner_service::DEFAULT_EVM_STATE_RETENTION,
     hardened_unpack::{unpack_genesis_archive, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_utils::get_highest_snapshot_archive_path,
 };
@@ -813,6 +818,8 @@ pub fn main() {
         &format!("{}-{}", VALIDATOR_PORT_RANGE.0, VALIDATOR_PORT_RANGE.1);
     let default_genesis_archive_unpacked_size = &MAX_GENESIS_ARCHIVE_UNPACKED_SIZE.to_string();
     let default_rpc_max_multiple_accounts = &MAX_MULTIPLE_ACCOUNTS.to_string();
+    let default_rpc_max_evm_logs_block_range = &MAX_EVM_LOGS_BLOCK_RANGE.to_string();
+    let default_evm_state_retention = &DEFAULT_EVM_STATE_RETENTION.to_string();
     let default_rpc_pubsub_max_connections = PubSubConfig::default().max_connections.to_string();
     let default_rpc_pubsub_max_fragment_size =
         PubSubConfig::default().max_fragment_size.to_string();
@@ -1007,6 +1014,24 @@ pub fn main() {
                 .help("Override the default maximum accounts accepted by \
                        the getMultipleAccounts JSON RPC method")
         )
+        .arg(
+            Arg::with_name("rpc_max_evm_logs_block_range")
+                .long("rpc-max-evm-logs-block-range")
+                .value_name("MAX BLOCKS")
+                .takes_value(true)
+                .default_value(default_rpc_max_evm_logs_block_range)
+                .help("Override the default maximum range of blocks accepted by \
+                       the eth_getLogs JSON RPC method")
+        )
+        .arg(
+            Arg::with_name("rpc_evm_fee_payer")
+                .long("rpc-evm-fee-payer")
+                .value_name("PATH")
+                .takes_value(true)
+                .validator(is_keypair)
+                .help("Enable the eth_sendRawTransaction JSON RPC method, \
+                       paying fees of relayed EVM transactions with this keypair")
+        )
         .arg(
             Arg::with_name("health_check_slot_distance")
                 .long("health-check-slot-distance")
@@ -1438,6 +1463,23 @@ pub fn main() {
                 .long("accounts-db-caching-enabled")
                 .help("Enable accounts caching"),
         )
+        .arg(
+            Arg::with_name("evm_state_retention")
+                .long("evm-state-retention")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<Slot>)
+                .default_value(default_evm_state_retention)
+                .help(
+                    "Number of rooted slots, which EVM state is kept. \
+                     Older state is squashed, and can't be queried by RPC",
+                ),
+        )
+        .arg(
+            Arg::with_name("evm_state_archive")
+                .long("evm-state-archive")
+                .help("Keep full history of EVM state, disables its pruning"),
+        )
         .get_matches();
 
     let identity_keypair = Arc::new(keypair_of(&matches, "identity").unwrap_or_else(Keypair::new));
@@ -1555,6 +1597,12 @@ pub fn main() {
                 "rpc_max_multiple_accounts",
                 usize
             )),
+            max_evm_logs_block_range: Some(value_t_or_exit!(
+                matches,
+                "rpc_max_evm_logs_block_range",
+                u64
+            )),
+            evm_fee_payer_keypair: matches.value_of("rpc_evm_fee_payer").map(PathBuf::from),
             health_check_slot_distance: value_t_or_exit!(
                 matches,
                 "health_check_slot_distance",
@@ -1610,6 +1658,11 @@ pub fn main() {
             .unwrap_or(poh_service::DEFAULT_PINNED_CPU_CORE),
         account_indexes,
         accounts_db_caching_enabled: matches.is_present("accounts_db_caching_enabled"),
+        evm_state_retention: if matches.is_present("evm_state_archive") {
+            None
+        } else {
+            Some(value_t_or_exit!(matches, "evm_state_retention", Slot))
+        },
         ..ValidatorConfig::default()
     };
 